@@ -0,0 +1,41 @@
+#![no_main]
+// Only the transformer is fuzzed, so most of the modules it depends on are unused
+#![allow(dead_code)]
+
+// code-context is a binary crate, so the transformer and the modules it depends on are
+// compiled into the target under the same names, keeping their `crate::` paths valid
+#[path = "../../src/endpoints.rs"]
+mod endpoints;
+#[path = "../../src/features.rs"]
+mod features;
+#[path = "../../src/fuzz.rs"]
+mod fuzz;
+#[path = "../../src/hierarchy.rs"]
+mod hierarchy;
+#[path = "../../src/module_path.rs"]
+mod module_path;
+#[path = "../../src/paths.rs"]
+mod paths;
+#[path = "../../src/preserve.rs"]
+mod preserve;
+#[path = "../../src/queries.rs"]
+mod queries;
+#[path = "../../src/removals.rs"]
+mod removals;
+#[path = "../../src/script.rs"]
+mod script;
+#[path = "../../src/summary.rs"]
+mod summary;
+#[path = "../../src/transformer.rs"]
+mod transformer;
+#[path = "../../src/type_usage.rs"]
+mod type_usage;
+#[path = "../../src/visibility.rs"]
+mod visibility;
+
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    // Invalid UTF-8 and invalid Rust are rejected before the transformer runs
+    if let Ok(source) = std::str::from_utf8(data) {
+        let _ = fuzz::transform(source);
+    }
+});
@@ -0,0 +1,452 @@
+use crate::script;
+use crate::summary::{self, type_string, SourceFile};
+use anyhow::Result;
+use clap::ValueEnum;
+use quote::ToTokens;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+use syn::{GenericArgument, GenericParam, ImplItem, Item, PathArguments, TraitItem, Type};
+
+/// How uses of simple type aliases are expanded
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AliasExpansion {
+    /// Replace uses of the aliases with the aliased types
+    Inline,
+    /// Append the expansion of the aliases a signature uses to its line as a comment
+    Annotate,
+}
+
+/// A type alias whose generic parameters are all plain type parameters
+struct Alias {
+    params: Vec<String>,
+    /// Tokens of the aliased type, kept as text so the processor can be shared by threads
+    ty: String,
+    /// Name of the defining module, `None` at the crate root
+    module: Option<String>,
+}
+
+impl Alias {
+    fn ty(&self) -> Type {
+        syn::parse_str(&self.ty).expect("aliased types are printed from parsed types")
+    }
+
+    /// Renders the definition of the alias, e.g. `Result<T> = std::result::Result<T, Error>`
+    fn describe(&self, name: &str) -> String {
+        let params = if self.params.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", self.params.join(", "))
+        };
+        format!("{}{} = {}", name, params, type_string(&self.ty()))
+    }
+}
+
+/// Simple type aliases of a crate, by name, expanded at their use sites
+pub struct TypeAliases {
+    mode: AliasExpansion,
+    aliases: HashMap<String, Alias>,
+}
+
+impl TypeAliases {
+    /// Collects the simple type aliases of the given files of the crate at `input`,
+    /// restricted to the `only` names unless empty
+    pub fn load(
+        input: &Path,
+        files: &[PathBuf],
+        mode: AliasExpansion,
+        only: &[String],
+    ) -> Result<Self> {
+        Ok(Self::collect(
+            &summary::load_sources(input, files)?,
+            mode,
+            only,
+        ))
+    }
+
+    fn collect(sources: &[SourceFile], mode: AliasExpansion, only: &[String]) -> Self {
+        let mut found = HashMap::new();
+        for source in sources {
+            collect_aliases(
+                &source.ast.items,
+                module_name(&source.path),
+                only,
+                &mut found,
+            );
+        }
+        Self {
+            mode,
+            // Names defined differently in several modules can't be expanded reliably
+            aliases: found
+                .into_iter()
+                .filter_map(|(name, alias)| Some((name, alias?)))
+                .collect(),
+        }
+    }
+
+    /// Expands the uses of the aliases in an output, leaving the definitions of the aliases
+    /// and output that doesn't parse unchanged
+    pub fn expand(&self, output: &str) -> String {
+        if self.aliases.is_empty() {
+            return output.to_string();
+        }
+        let (header, code) = script::split_header(output);
+        let Ok(file) = syn::parse_file(code) else {
+            return output.to_string();
+        };
+        match self.mode {
+            AliasExpansion::Inline => {
+                let mut finder = UseFinder {
+                    aliases: self,
+                    uses: Vec::new(),
+                };
+                finder.visit_file(&file);
+                let mut expanded = header.to_string();
+                let mut position = 0;
+                for (range, replacement) in finder.uses {
+                    expanded.push_str(&code[position..range.start]);
+                    expanded.push_str(&replacement);
+                    position = range.end;
+                }
+                expanded.push_str(&code[position..]);
+                expanded
+            }
+            AliasExpansion::Annotate => {
+                let mut notes = BTreeMap::new();
+                self.collect_notes(&file.items, &mut notes);
+                let mut annotated = header.to_string();
+                for (index, line) in code.split_inclusive('\n').enumerate() {
+                    match notes.get(&(index + 1)) {
+                        Some(note) => {
+                            let text = line.trim_end_matches(['\n', '\r']);
+                            annotated.push_str(text);
+                            annotated.push_str(" // ");
+                            annotated.push_str(note);
+                            annotated.push_str(&line[text.len()..]);
+                        }
+                        None => annotated.push_str(line),
+                    }
+                }
+                annotated
+            }
+        }
+    }
+
+    /// Records, by the line of the function name, the aliases used by each signature
+    fn collect_notes(&self, items: &[Item], notes: &mut BTreeMap<usize, String>) {
+        for item in items {
+            match item {
+                Item::Fn(item) => self.note(&item.sig, notes),
+                Item::Mod(module) => {
+                    if let Some((_, items)) = &module.content {
+                        self.collect_notes(items, notes);
+                    }
+                }
+                Item::Impl(item) => {
+                    for impl_item in &item.items {
+                        if let ImplItem::Fn(impl_item) = impl_item {
+                            self.note(&impl_item.sig, notes);
+                        }
+                    }
+                }
+                Item::Trait(item) => {
+                    for trait_item in &item.items {
+                        if let TraitItem::Fn(trait_item) = trait_item {
+                            self.note(&trait_item.sig, notes);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn note(&self, sig: &syn::Signature, notes: &mut BTreeMap<usize, String>) {
+        let mut names = Vec::new();
+        for name in NameCollector::names(sig, self) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if names.is_empty() {
+            return;
+        }
+        let note = names
+            .iter()
+            .map(|name| self.aliases[name].describe(name))
+            .collect::<Vec<_>>()
+            .join("; ");
+        notes.entry(sig.ident.span().start().line).or_insert(note);
+    }
+
+    /// Finds the alias a type path refers to, if its arguments match the alias parameters
+    fn resolve(&self, type_path: &syn::TypePath) -> Option<(&str, &Alias)> {
+        if type_path.qself.is_some() || type_path.path.leading_colon.is_some() {
+            return None;
+        }
+        let segments: Vec<_> = type_path.path.segments.iter().collect();
+        let (last, prefix) = segments.split_last()?;
+        let (name, alias) = self.aliases.get_key_value(&last.ident.to_string())?;
+        // Bare names, paths from the crate root, and paths through the defining module, but
+        // not `io::Result`
+        let within_crate = match (prefix.first(), prefix.last()) {
+            (None, _) => true,
+            (Some(first), Some(parent)) => {
+                ["crate", "self", "super"].contains(&first.ident.to_string().as_str())
+                    || alias
+                        .module
+                        .as_ref()
+                        .is_some_and(|module| parent.ident == module)
+            }
+            (Some(_), None) => false,
+        };
+        if !within_crate {
+            return None;
+        }
+        let arguments = type_arguments(&last.arguments)?;
+        (arguments.len() == alias.params.len()).then_some((name.as_str(), alias))
+    }
+
+    /// Replaces a use of an alias by the aliased type with its parameters substituted
+    fn substitute(&self, type_path: &syn::TypePath) -> Option<Type> {
+        let (_, alias) = self.resolve(type_path)?;
+        let arguments = type_arguments(&type_path.path.segments.last()?.arguments)?;
+        let mut substitution = Substitution {
+            params: alias.params.iter().cloned().zip(arguments).collect(),
+        };
+        let mut ty = alias.ty();
+        substitution.visit_type_mut(&mut ty);
+        Some(ty)
+    }
+}
+
+/// Records the simple aliases of an item list and its inline modules, mapping names
+/// defined differently more than once to `None`
+fn collect_aliases(
+    items: &[Item],
+    module: Option<&str>,
+    only: &[String],
+    found: &mut HashMap<String, Option<Alias>>,
+) {
+    for item in items {
+        match item {
+            Item::Type(item) => {
+                let name = item.ident.to_string();
+                if !only.is_empty() && !only.contains(&name) {
+                    continue;
+                }
+                let params: Option<Vec<String>> = item
+                    .generics
+                    .params
+                    .iter()
+                    .map(|param| match param {
+                        GenericParam::Type(param) if param.default.is_none() => {
+                            Some(param.ident.to_string())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let Some(params) = params.filter(|_| item.generics.where_clause.is_none()) else {
+                    continue;
+                };
+                let alias = Alias {
+                    params,
+                    ty: item.ty.to_token_stream().to_string(),
+                    module: module.map(str::to_string),
+                };
+                match found.get(&name) {
+                    Some(Some(existing))
+                        if existing.params == alias.params && existing.ty == alias.ty => {}
+                    Some(_) => {
+                        found.insert(name, None);
+                    }
+                    None => {
+                        found.insert(name, Some(alias));
+                    }
+                }
+            }
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect_aliases(items, Some(&module.ident.to_string()), only, found);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Name of the module a source file defines, `None` for crate roots
+fn module_name(path: &str) -> Option<&str> {
+    let path = Path::new(path);
+    match path.file_stem()?.to_str()? {
+        "lib" | "main" => None,
+        "mod" => path.parent()?.file_name()?.to_str(),
+        stem => Some(stem),
+    }
+}
+
+/// Type arguments of a path segment, or `None` if it has other kinds of arguments
+fn type_arguments(arguments: &PathArguments) -> Option<Vec<Type>> {
+    match arguments {
+        PathArguments::None => Some(Vec::new()),
+        PathArguments::AngleBracketed(arguments) => arguments
+            .args
+            .iter()
+            .map(|argument| match argument {
+                GenericArgument::Type(ty) => Some(ty.clone()),
+                _ => None,
+            })
+            .collect(),
+        PathArguments::Parenthesized(_) => None,
+    }
+}
+
+/// Finds the outermost uses of aliases with their expansions
+struct UseFinder<'a> {
+    aliases: &'a TypeAliases,
+    uses: Vec<(Range<usize>, String)>,
+}
+
+impl<'ast> Visit<'ast> for UseFinder<'_> {
+    fn visit_item_type(&mut self, item: &'ast syn::ItemType) {
+        // The definitions of the aliases themselves stay as they are
+        if !self.aliases.aliases.contains_key(&item.ident.to_string()) {
+            visit::visit_item_type(self, item);
+        }
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type) {
+        let Type::Path(type_path) = ty else {
+            return visit::visit_type(self, ty);
+        };
+        if self.aliases.resolve(type_path).is_none() {
+            return visit::visit_type(self, ty);
+        }
+        // Aliases among the arguments are expanded within the replacement
+        let mut expanded = ty.clone();
+        Expander {
+            aliases: self.aliases,
+        }
+        .visit_type_mut(&mut expanded);
+        self.uses
+            .push((ty.span().byte_range(), type_string(&expanded)));
+    }
+}
+
+/// Collects the names of the aliases used by a signature
+struct NameCollector<'a> {
+    aliases: &'a TypeAliases,
+    names: Vec<String>,
+}
+
+impl NameCollector<'_> {
+    fn names(sig: &syn::Signature, aliases: &TypeAliases) -> Vec<String> {
+        let mut collector = NameCollector {
+            aliases,
+            names: Vec::new(),
+        };
+        collector.visit_signature(sig);
+        collector.names
+    }
+}
+
+impl<'ast> Visit<'ast> for NameCollector<'_> {
+    fn visit_type_path(&mut self, type_path: &'ast syn::TypePath) {
+        if let Some((name, _)) = self.aliases.resolve(type_path) {
+            self.names.push(name.to_string());
+        }
+        visit::visit_type_path(self, type_path);
+    }
+}
+
+/// Replaces the uses of aliases within a type, innermost first
+struct Expander<'a> {
+    aliases: &'a TypeAliases,
+}
+
+impl VisitMut for Expander<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        visit_mut::visit_type_mut(self, ty);
+        if let Type::Path(type_path) = ty {
+            if let Some(expanded) = self.aliases.substitute(type_path) {
+                *ty = expanded;
+            }
+        }
+    }
+}
+
+/// Replaces the parameters of an alias with the arguments of a use
+struct Substitution {
+    params: HashMap<String, Type>,
+}
+
+impl VisitMut for Substitution {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(type_path) = ty {
+            if type_path.qself.is_none() {
+                if let Some(argument) = type_path
+                    .path
+                    .get_ident()
+                    .and_then(|ident| self.params.get(&ident.to_string()))
+                {
+                    *ty = argument.clone();
+                    return;
+                }
+            }
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases(mode: AliasExpansion, only: &[&str]) -> anyhow::Result<TypeAliases> {
+        let sources = vec![SourceFile {
+            path: "src/error.rs".to_string(),
+            ast: syn::parse_str(
+                "pub type Result<T> = std::result::Result<T, Error>;\n\
+                 pub type Id = u64;\n\
+                 type Table<K, V> where K: Eq = std::collections::HashMap<K, V>;\n\
+                 mod a { type Pair = (u8, u8); }\n\
+                 mod b { type Pair = (u16, u16); }",
+            )?,
+        }];
+        let only: Vec<String> = only.iter().map(|name| name.to_string()).collect();
+        Ok(TypeAliases::collect(&sources, mode, &only))
+    }
+
+    #[test]
+    fn test_inline_aliases() -> anyhow::Result<()> {
+        let output = "pub type Result<T> = std::result::Result<T, Error>;\n\
+                      pub fn find(id: error::Id, pair: Pair) -> Result<Option<Id>> {\n    todo!()\n}\n\
+                      fn io() -> io::Result<()> {}\n";
+        assert_eq!(
+            aliases(AliasExpansion::Inline, &[])?.expand(output),
+            "pub type Result<T> = std::result::Result<T, Error>;\n\
+             pub fn find(id: u64, pair: Pair) -> std::result::Result<Option<u64>, Error> {\n    todo!()\n}\n\
+             fn io() -> io::Result<()> {}\n"
+        );
+        assert_eq!(
+            aliases(AliasExpansion::Inline, &["Id"])?.expand(output),
+            "pub type Result<T> = std::result::Result<T, Error>;\n\
+             pub fn find(id: u64, pair: Pair) -> Result<Option<u64>> {\n    todo!()\n}\n\
+             fn io() -> io::Result<()> {}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_aliases() -> anyhow::Result<()> {
+        let output = "impl Store {\n    pub fn get(&self, id: Id) -> Result<Item> {\n        todo!()\n    }\n    fn len(&self) -> usize {}\n}\n";
+        assert_eq!(
+            aliases(AliasExpansion::Annotate, &[])?.expand(output),
+            "impl Store {\n    pub fn get(&self, id: Id) -> Result<Item> { // Id = u64; Result<T> = std::result::Result<T, Error>\n        todo!()\n    }\n    fn len(&self) -> usize {}\n}\n"
+        );
+        Ok(())
+    }
+}
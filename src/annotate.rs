@@ -0,0 +1,169 @@
+use crate::processor::estimate_tokens;
+use crate::script;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, ImplItem, Item, TraitItem};
+
+/// Prefix of the comments inserted by `--annotate`, so they are easy to spot and grep
+const ANNOTATION_PREFIX: &str = "// code-context:";
+
+/// Returns the source with summary comments inserted and no code removed: item counts and
+/// tokens of the file at the top, and the complexity and tokens of each function above it
+pub fn annotate(content: &str) -> Result<String> {
+    let (header, code) = script::split_header(content);
+    let file = syn::parse_file(code)
+        .with_context(|| "Failed to parse Rust file. Check for syntax errors")?;
+    let mut annotator = Annotator {
+        code,
+        counts: BTreeMap::new(),
+        annotations: Vec::new(),
+    };
+    annotator.items(&file.items);
+
+    let mut lines: Vec<String> = code.lines().map(str::to_string).collect();
+    // Insert from the bottom so the recorded line numbers stay valid
+    annotator
+        .annotations
+        .sort_by_key(|(line, ..)| std::cmp::Reverse(*line));
+    for (line, indent, text) in annotator.annotations {
+        lines.insert(line, format!("{}{} {}", indent, ANNOTATION_PREFIX, text));
+    }
+
+    let mut summary: Vec<_> = annotator
+        .counts
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect();
+    summary.push(format!("~{} tokens", estimate_tokens(content.len())));
+    let mut output = format!("{}{} {}\n", header, ANNOTATION_PREFIX, summary.join(", "));
+    for line in lines {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+struct Annotator<'a> {
+    code: &'a str,
+    /// Number of items of each kind, including nested ones
+    counts: BTreeMap<&'static str, usize>,
+    /// Line index an annotation goes before, its indentation, and its text
+    annotations: Vec<(usize, String, String)>,
+}
+
+impl Annotator<'_> {
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Fn(function) => {
+                    self.count("fn");
+                    self.function(item, &function.block);
+                }
+                Item::Struct(_) => self.count("struct"),
+                Item::Enum(_) => self.count("enum"),
+                Item::Union(_) => self.count("union"),
+                Item::Type(_) => self.count("type"),
+                Item::Const(_) | Item::Static(_) => self.count("const"),
+                Item::Macro(_) => self.count("macro"),
+                Item::Trait(item_trait) => {
+                    self.count("trait");
+                    for trait_item in &item_trait.items {
+                        if let TraitItem::Fn(method) = trait_item {
+                            self.count("fn");
+                            if let Some(default) = &method.default {
+                                self.function(trait_item, default);
+                            }
+                        }
+                    }
+                }
+                Item::Impl(item_impl) => {
+                    self.count("impl");
+                    for impl_item in &item_impl.items {
+                        if let ImplItem::Fn(method) = impl_item {
+                            self.count("fn");
+                            self.function(impl_item, &method.block);
+                        }
+                    }
+                }
+                Item::Mod(item_mod) => {
+                    self.count("mod");
+                    if let Some((_, items)) = &item_mod.content {
+                        self.items(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn count(&mut self, kind: &'static str) {
+        *self.counts.entry(kind).or_default() += 1;
+    }
+
+    /// Annotates a function, spanned with its attributes and doc comments, on the line
+    /// before it
+    fn function(&mut self, item: &impl Spanned, block: &syn::Block) {
+        let span = item.span();
+        let Some(source) = self.code.get(span.byte_range()) else {
+            return;
+        };
+        let mut complexity = Complexity(1);
+        complexity.visit_block(block);
+        let line = span.start().line - 1;
+        let indent = self
+            .code
+            .lines()
+            .nth(line)
+            .map_or("", |text| &text[..text.len() - text.trim_start().len()]);
+        self.annotations.push((
+            line,
+            indent.to_string(),
+            format!(
+                "complexity {}, ~{} tokens",
+                complexity.0,
+                estimate_tokens(source.len())
+            ),
+        ));
+    }
+}
+
+/// Cyclomatic complexity of a function body: one plus a point per branch, loop, short
+/// circuiting operator, and `?`
+struct Complexity(usize);
+
+impl<'ast> Visit<'ast> for Complexity {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            Expr::If(_) | Expr::While(_) | Expr::ForLoop(_) | Expr::Loop(_) | Expr::Try(_) => {
+                self.0 += 1
+            }
+            Expr::Match(expr_match) => self.0 += expr_match.arms.len().saturating_sub(1),
+            Expr::Binary(binary) if matches!(binary.op, BinOp::And(_) | BinOp::Or(_)) => {
+                self.0 += 1
+            }
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    // Nested functions are separate units
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate() -> Result<()> {
+        let source = "pub struct Config;\n\nimpl Config {\n    /// Loads\n    pub fn load(path: &str) -> Option<Self> {\n        if path.is_empty() || path == \"-\" {\n            return None;\n        }\n        Some(Config)\n    }\n}\n";
+        assert_eq!(
+            annotate(source)?,
+            "// code-context: 1 fn, 1 impl, 1 struct, ~51 tokens\npub struct Config;\n\nimpl Config {\n    // code-context: complexity 3, ~41 tokens\n    /// Loads\n    pub fn load(path: &str) -> Option<Self> {\n        if path.is_empty() || path == \"-\" {\n            return None;\n        }\n        Some(Config)\n    }\n}\n"
+        );
+        assert!(annotate("fn broken(").is_err());
+        Ok(())
+    }
+}
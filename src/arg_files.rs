@@ -0,0 +1,116 @@
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Replaces every `@path` argument with the arguments read from that file
+///
+/// Arguments in a file are separated by whitespace and may be quoted with `"` or `'`;
+/// lines starting with `#` are comments. Files can reference other argument files,
+/// relative to their own directory.
+pub fn expand(args: impl IntoIterator<Item = OsString>) -> Result<Vec<OsString>> {
+    let mut expanded = Vec::new();
+    for arg in args {
+        expand_arg(arg, Path::new(""), &mut Vec::new(), &mut expanded)?;
+    }
+    Ok(expanded)
+}
+
+fn expand_arg(
+    arg: OsString,
+    base_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+    expanded: &mut Vec<OsString>,
+) -> Result<()> {
+    let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix('@')) else {
+        expanded.push(arg);
+        return Ok(());
+    };
+    if path.is_empty() {
+        expanded.push(arg);
+        return Ok(());
+    }
+    let path = base_dir.join(path);
+    let canonical = std::fs::canonicalize(&path)
+        .with_context(|| format!("Failed to read argument file: {}", path.display()))?;
+    if stack.contains(&canonical) {
+        bail!("Argument file includes itself: {}", path.display());
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read argument file: {}", path.display()))?;
+    let args = split(&content)
+        .with_context(|| format!("Failed to parse argument file: {}", path.display()))?;
+
+    stack.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for arg in args {
+        expand_arg(arg.into(), dir, stack, expanded)?;
+    }
+    stack.pop();
+    Ok(())
+}
+
+/// Splits the content of an argument file into arguments
+fn split(content: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    for line in content.lines() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        let mut current: Option<String> = None;
+        let mut quote = None;
+        for c in line.chars() {
+            match (quote, c) {
+                (Some(open), c) if c == open => quote = None,
+                (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    current.get_or_insert_with(String::new);
+                }
+                (None, c) if c.is_whitespace() => args.extend(current.take()),
+                (None, c) => current.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if let Some(open) = quote {
+            bail!("Unterminated {} quote in: {}", open, line.trim());
+        }
+        args.extend(current);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_split_arguments() {
+        let args =
+            split("# shared flags\n--no-comments --single-file\n-o \"my context\"\n''\n").unwrap();
+        assert_eq!(
+            args,
+            vec!["--no-comments", "--single-file", "-o", "my context", ""]
+        );
+        assert!(split("--output-dir 'open").is_err());
+    }
+
+    #[test]
+    fn test_expand_nested_argument_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("flags"))?;
+        std::fs::write(root.join("args.txt"), "--no-comments\n@flags/extra.txt\n")?;
+        std::fs::write(root.join("flags/extra.txt"), "--single-file\n")?;
+        std::fs::write(root.join("loop.txt"), "@loop.txt\n")?;
+
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        let at = |name: &str| format!("@{}", root.join(name).display());
+        assert_eq!(
+            expand(args(&["program", &at("args.txt"), "src", "@"]))?,
+            args(&["program", "--no-comments", "--single-file", "src", "@"])
+        );
+        assert!(expand(args(&["program", &at("loop.txt")])).is_err());
+        assert!(expand(args(&["program", &at("missing.txt")])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use crate::lsif::LsifIndex;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Generation targets processed by one `batch` invocation, read from a JSON job file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobFile {
+    /// Options shared by all targets, before the options of each target
+    pub args: Vec<String>,
+    pub targets: Vec<Target>,
+    /// Directory containing the job file, which relative inputs are resolved against
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+/// A single generation: an input, where its output goes, and its options
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    /// Label printed before the target's output, the input path by default
+    pub name: Option<String>,
+    pub input: PathBuf,
+    /// Output directory name or template, as with `--output-dir`
+    pub output: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl JobFile {
+    /// Loads a job file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read job file: {}", path.display()))?;
+        let mut jobs: JobFile = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse job file: {}", path.display()))?;
+        jobs.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(jobs)
+    }
+
+    /// Returns the label and command line of each target
+    pub fn command_lines(&self) -> Vec<(String, Vec<OsString>)> {
+        self.targets
+            .iter()
+            .map(|target| {
+                let input = self.base_dir.join(&target.input);
+                let mut args = vec![OsString::from("code-context"), input.clone().into()];
+                if let Some(output) = &target.output {
+                    args.extend(["--output-dir".into(), output.into()]);
+                }
+                args.extend(self.args.iter().chain(&target.args).map(OsString::from));
+                let name = target
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| input.display().to_string());
+                (name, args)
+            })
+            .collect()
+    }
+}
+
+/// Data loaded once and shared by the targets of a batch
+#[derive(Default)]
+pub struct Caches {
+    lsif: HashMap<PathBuf, LsifIndex>,
+}
+
+impl Caches {
+    /// Loads an LSIF dump, or reuses it if an earlier target loaded it
+    pub fn lsif(&mut self, path: &Path) -> Result<LsifIndex> {
+        if let Some(index) = self.lsif.get(path) {
+            return Ok(index.clone());
+        }
+        let index = LsifIndex::load(path)?;
+        self.lsif.insert(path.to_path_buf(), index.clone());
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_job_file_command_lines() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("jobs.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "args": ["--no-stats"],
+                "targets": [
+                    {"name": "api", "input": "crates/api", "output": "ctx", "args": ["--single-file"]},
+                    {"input": "crates/core"}
+                ]
+            }"#,
+        )?;
+        let jobs = JobFile::load(&path)?;
+        let api = temp_dir.path().join("crates/api");
+        let core = temp_dir.path().join("crates/core");
+        assert_eq!(
+            jobs.command_lines(),
+            vec![
+                (
+                    "api".to_string(),
+                    vec![
+                        "code-context".into(),
+                        api.into_os_string(),
+                        "--output-dir".into(),
+                        "ctx".into(),
+                        "--no-stats".into(),
+                        "--single-file".into()
+                    ]
+                ),
+                (
+                    core.display().to_string(),
+                    vec![
+                        "code-context".into(),
+                        core.into_os_string(),
+                        "--no-stats".into()
+                    ]
+                ),
+            ]
+        );
+
+        std::fs::write(&path, r#"{"targets": [{"input": "a", "flags": []}]}"#)?;
+        assert!(JobFile::load(&path).is_err());
+        Ok(())
+    }
+}
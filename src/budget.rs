@@ -0,0 +1,143 @@
+use anyhow::{bail, Result};
+use std::fmt::Write;
+
+/// How much of a module the output keeps, from everything to nothing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// The module as processed with the given options
+    Full,
+    /// Without comments
+    NoComments,
+    /// Without comments and function bodies
+    NoBodies,
+    /// A stub with the doc summary, public signatures, and private item counts
+    Summary,
+    /// Left out of the output
+    Omitted,
+}
+
+/// All levels, from the least to the most reduced
+pub const LEVELS: [Level; 5] = [
+    Level::Full,
+    Level::NoComments,
+    Level::NoBodies,
+    Level::Summary,
+    Level::Omitted,
+];
+
+impl Level {
+    /// Relative loss of information of a module at this level
+    fn penalty(self) -> u64 {
+        match self {
+            Level::Full => 0,
+            Level::NoComments => 1,
+            Level::NoBodies => 3,
+            Level::Summary => 6,
+            Level::Omitted => 10,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Level::Full => "full",
+            Level::NoComments => "without comments",
+            Level::NoBodies => "without comments and bodies",
+            Level::Summary => "summary",
+            Level::Omitted => "omitted",
+        }
+    }
+}
+
+/// Token cost of a module at each level, measured in the first pass, and its priority
+#[derive(Clone, Debug)]
+pub struct ModuleCost {
+    /// Tokens at each of `LEVELS`
+    pub tokens: [usize; 5],
+    /// Priority weight; reducing a module costs its weight times the loss of the level
+    pub weight: i64,
+}
+
+/// Assigns a level to every module so the total fits the budget
+///
+/// Starting from the full output, each step reduces the module that saves the most tokens
+/// per weighted loss of information, so low-priority modules and modules with cheap
+/// reductions such as long comments are reduced first.
+pub fn allocate(modules: &[ModuleCost], budget: usize) -> Result<Vec<Level>> {
+    let mut levels = vec![Level::Full; modules.len()];
+    let mut total: usize = modules.iter().map(|module| module.tokens[0]).sum();
+    while total > budget {
+        let mut best: Option<(f64, usize, usize)> = None;
+        for (index, module) in modules.iter().enumerate() {
+            let current = levels[index] as usize;
+            for (next, level) in LEVELS.iter().enumerate().skip(current + 1) {
+                let saved = module.tokens[current].saturating_sub(module.tokens[next]);
+                if saved == 0 {
+                    continue;
+                }
+                let loss =
+                    (level.penalty() - levels[index].penalty()) * module.weight.max(1) as u64;
+                let ratio = saved as f64 / loss as f64;
+                if best.is_none_or(|(best_ratio, _, _)| ratio > best_ratio) {
+                    best = Some((ratio, index, next));
+                }
+            }
+        }
+        let Some((_, index, next)) = best else {
+            bail!(
+                "Output cannot fit the token budget of {} even with every module omitted",
+                budget
+            );
+        };
+        total = total - modules[index].tokens[levels[index] as usize] + modules[index].tokens[next];
+        levels[index] = LEVELS[next];
+    }
+    Ok(levels)
+}
+
+/// Renders the note listing the reduced modules at the top of the single-file output
+pub fn note(budget: usize, reduced: &[(String, Level)]) -> String {
+    if reduced.is_empty() {
+        return String::new();
+    }
+    let mut note = format!("// Reduced to fit the token budget of {}:\n", budget);
+    for (path, level) in reduced {
+        let _ = writeln!(note, "//   {}: {}", path, level.description());
+    }
+    note.push('\n');
+    note
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_protects_high_priority_modules() -> Result<()> {
+        let core = ModuleCost {
+            tokens: [1000, 800, 300, 50, 0],
+            weight: 10,
+        };
+        let migrations = ModuleCost {
+            tokens: [1000, 900, 400, 100, 0],
+            weight: 1,
+        };
+        let modules = [core, migrations];
+
+        assert_eq!(allocate(&modules, 2000)?, vec![Level::Full, Level::Full]);
+        assert_eq!(
+            allocate(&modules, 1500)?,
+            vec![Level::Full, Level::NoBodies]
+        );
+        assert_eq!(allocate(&modules, 1000)?, vec![Level::Full, Level::Omitted]);
+        let levels = allocate(&modules, 400)?;
+        assert!(levels[0] < levels[1]);
+        assert_eq!(allocate(&modules, 0)?, vec![Level::Omitted, Level::Omitted]);
+
+        let fixed = ModuleCost {
+            tokens: [10; 5],
+            weight: 5,
+        };
+        assert!(allocate(&[fixed], 5).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use icu_normalizer::ComposingNormalizerBorrowed;
+
+/// Normalizes text so it's byte-identical across platforms: Unicode NFC, `\n` line
+/// endings, no trailing whitespace, and a single final newline
+pub fn canonicalize(text: &str) -> String {
+    let text = ComposingNormalizerBorrowed::new_nfc().normalize(text);
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut canonical = String::with_capacity(text.len());
+    for line in text.lines() {
+        canonical.push_str(line.trim_end());
+        canonical.push('\n');
+    }
+    let trimmed = canonical.trim_end_matches('\n').len();
+    canonical.truncate(trimmed);
+    if !canonical.is_empty() {
+        canonical.push('\n');
+    }
+    canonical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(
+            canonicalize("fn a() {}  \r\n\r\n// cafe\u{301}\t\r\n\n\n"),
+            "fn a() {}\n\n// caf\u{e9}\n"
+        );
+        assert_eq!(canonicalize("fn a() {}"), "fn a() {}\n");
+        assert_eq!(canonicalize(""), "");
+    }
+}
@@ -0,0 +1,176 @@
+use crate::script;
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// Directories of a crate root holding integration tests, examples, and benchmarks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrateTarget {
+    Tests,
+    Examples,
+    Benches,
+}
+
+impl CrateTarget {
+    /// Returns the target directory that a path relative to the crate root is in
+    pub fn of(relative: &Path) -> Option<Self> {
+        match relative.components().next()?.as_os_str().to_str()? {
+            "tests" => Some(CrateTarget::Tests),
+            "examples" => Some(CrateTarget::Examples),
+            "benches" => Some(CrateTarget::Benches),
+            _ => None,
+        }
+    }
+
+    /// Returns the target directory of a file, if it's in one next to a Cargo.toml
+    pub fn of_file(path: &Path) -> Option<Self> {
+        path.ancestors().skip(1).find_map(|dir| {
+            let root = dir.parent()?;
+            if !root.join("Cargo.toml").is_file() {
+                return None;
+            }
+            Self::of(dir.strip_prefix(root).ok()?)
+        })
+    }
+
+    /// Describes the files of the target, used as the reason for skipping them
+    pub fn description(&self) -> &'static str {
+        match self {
+            CrateTarget::Tests => "integration test",
+            CrateTarget::Examples => "example",
+            CrateTarget::Benches => "benchmark file",
+        }
+    }
+}
+
+/// How the files of a target directory are included
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetForm {
+    /// Processed like the rest of the crate
+    Condensed,
+    /// Processed with all function bodies kept
+    Full,
+}
+
+/// Finds the nearest Cargo.toml at or above the given path
+pub fn find_manifest(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+}
+
+/// Returns the manifest embedded in a cargo script given as input
+fn script_manifest(path: &Path) -> Option<String> {
+    if !path.is_file() || path.extension().is_none_or(|ext| ext != "rs") {
+        return None;
+    }
+    script::manifest(&std::fs::read_to_string(path).ok()?)
+}
+
+/// Checks if the crate containing the given path is a proc-macro crate
+/// (`[lib] proc-macro = true` in its Cargo.toml)
+pub fn is_proc_macro_crate(path: &Path) -> bool {
+    let Some(manifest) = find_manifest(path) else {
+        return false;
+    };
+    let Ok(content) = std::fs::read_to_string(manifest) else {
+        return false;
+    };
+    let Ok(value) = content.parse::<toml::Table>() else {
+        return false;
+    };
+    value
+        .get("lib")
+        .and_then(|lib| lib.get("proc-macro").or_else(|| lib.get("proc_macro")))
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Returns a string field of the `[package]` table of the crate containing the given path,
+/// or of the manifest embedded in a cargo script, whose name defaults to the file stem
+pub fn package_field(path: &Path, field: &str) -> Option<String> {
+    if let Some(manifest) = script_manifest(path) {
+        let value = manifest.parse::<toml::Table>().ok()?;
+        let package_field = value.get("package").and_then(|package| package.get(field));
+        return match package_field {
+            Some(value) => value.as_str().map(str::to_string),
+            None if field == "name" => path.file_stem().map(|stem| stem.to_string_lossy().into()),
+            None => None,
+        };
+    }
+    let content = std::fs::read_to_string(find_manifest(path)?).ok()?;
+    let value = content.parse::<toml::Table>().ok()?;
+    value
+        .get("package")?
+        .get(field)?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_crate_target_of_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::create_dir_all(temp_dir.path().join("examples/demo"))?;
+        std::fs::create_dir_all(temp_dir.path().join("src/tests"))?;
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\n",
+        )?;
+
+        let file = |path: &str| CrateTarget::of_file(&temp_dir.path().join(path));
+        assert_eq!(file("examples/demo/main.rs"), Some(CrateTarget::Examples));
+        assert_eq!(file("tests/api.rs"), Some(CrateTarget::Tests));
+        assert_eq!(file("src/tests/mod.rs"), None);
+        assert_eq!(file("src/lib.rs"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_proc_macro_crate() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src = temp_dir.path().join("src");
+        std::fs::create_dir_all(&src)?;
+        assert!(!is_proc_macro_crate(&src));
+
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"derive\"\n\n[lib]\nproc-macro = true\n",
+        )?;
+        assert!(is_proc_macro_crate(&src));
+        assert!(is_proc_macro_crate(&src.join("lib.rs")));
+
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"plain\"\n",
+        )?;
+        assert!(!is_proc_macro_crate(&src));
+        assert_eq!(package_field(&src, "name").as_deref(), Some("plain"));
+        assert_eq!(package_field(&src, "version"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_field_of_cargo_script() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"outer\"\n",
+        )?;
+        let script = temp_dir.path().join("fetch.rs");
+        std::fs::write(
+            &script,
+            "---\n[package]\nedition = \"2024\"\n---\nfn main() {}\n",
+        )?;
+        assert_eq!(package_field(&script, "name").as_deref(), Some("fetch"));
+        assert_eq!(package_field(&script, "edition").as_deref(), Some("2024"));
+
+        std::fs::write(&script, "fn main() {}\n")?;
+        assert_eq!(package_field(&script, "name").as_deref(), Some("outer"));
+        Ok(())
+    }
+}
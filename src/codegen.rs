@@ -0,0 +1,151 @@
+use crate::cargo;
+use clap::ValueEnum;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// Label added to the output of files generated into `OUT_DIR`
+pub const GENERATED_LABEL: &str = "// Generated by the build script into OUT_DIR\n";
+
+/// How the build script of a crate is included
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildScriptForm {
+    /// Processed like the rest of the crate
+    Condensed,
+    /// Copied as-is
+    Verbatim,
+}
+
+/// Build script and build output of the crate at the input directory
+#[derive(Debug, Default)]
+pub struct CrateLayout {
+    is_crate_root: bool,
+    /// Build script path relative to the crate root
+    build_script: Option<PathBuf>,
+    /// Newest `OUT_DIR` of the crate relative to the crate root, if requested
+    out_dir: Option<PathBuf>,
+}
+
+impl CrateLayout {
+    /// Inspects the input directory; `find_out_dir` looks for code generated under `target/`
+    pub fn detect(input_dir: &Path, find_out_dir: bool) -> Self {
+        if !input_dir.join("Cargo.toml").is_file() {
+            return Self::default();
+        }
+        let build_script = cargo::package_field(input_dir, "build")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("build.rs"));
+        let out_dir = find_out_dir
+            .then(|| newest_out_dir(input_dir))
+            .flatten()
+            .and_then(|dir| dir.strip_prefix(input_dir).ok().map(Path::to_path_buf));
+        Self {
+            is_crate_root: true,
+            build_script: input_dir
+                .join(&build_script)
+                .is_file()
+                .then_some(build_script),
+            out_dir,
+        }
+    }
+
+    /// Whether the input directory holds a Cargo.toml
+    pub fn is_crate_root(&self) -> bool {
+        self.is_crate_root
+    }
+
+    /// Whether a path relative to the crate root is its build script
+    pub fn is_build_script(&self, relative: &Path) -> bool {
+        self.build_script.as_deref() == Some(relative)
+    }
+
+    /// Whether a path relative to the crate root is build output; files in the
+    /// requested `OUT_DIR` are not
+    pub fn is_build_output(&self, relative: &Path) -> bool {
+        self.is_crate_root
+            && relative.starts_with("target")
+            && !self
+                .out_dir
+                .as_ref()
+                .is_some_and(|out_dir| relative.starts_with(out_dir))
+    }
+}
+
+/// Finds the most recently built `target/**/build/<crate>-<hash>/out` directory of a crate
+fn newest_out_dir(crate_root: &Path) -> Option<PathBuf> {
+    let name = cargo::package_field(crate_root, "name")?;
+    let prefix = format!("{}-", name);
+    WalkDir::new(crate_root.join("target"))
+        .min_depth(3)
+        .max_depth(5)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir() && e.file_name() == "out")
+        .filter(|e| {
+            let build_dir = e.path().parent();
+            build_dir
+                .and_then(Path::file_name)
+                .is_some_and(|dir| dir.to_string_lossy().starts_with(&prefix))
+                && build_dir
+                    .and_then(Path::parent)
+                    .and_then(Path::file_name)
+                    .is_some_and(|dir| dir == "build")
+        })
+        .max_by_key(|e| {
+            e.metadata()
+                .ok()
+                .and_then(|metadata| metadata.modified().ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        })
+        .map(|e| e.into_path())
+}
+
+/// Whether a file lies in an `OUT_DIR`, i.e. under `build/<crate>-<hash>/out/`
+pub fn is_generated(path: &Path) -> bool {
+    let components: Vec<Component> = path.components().collect();
+    components
+        .windows(3)
+        .any(|window| window[0].as_os_str() == "build" && window[2].as_os_str() == "out")
+}
+
+/// Whether a file is the build script of the crate containing it
+pub fn is_build_script(path: &Path) -> bool {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .is_some_and(|root| {
+            path.strip_prefix(root)
+                .is_ok_and(|relative| CrateLayout::detect(root, false).is_build_script(relative))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_crate_layout() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"app\"\n")?;
+        std::fs::write(root.join("build.rs"), "fn main() {}")?;
+        std::fs::create_dir_all(root.join("target/debug/build/app-1234/out"))?;
+        std::fs::create_dir_all(root.join("target/debug/build/dep-5678/out"))?;
+
+        let layout = CrateLayout::detect(root, true);
+        assert!(layout.is_build_script(Path::new("build.rs")));
+        assert!(!layout.is_build_output(Path::new("target/debug/build/app-1234/out/gen.rs")));
+        assert!(layout.is_build_output(Path::new("target/debug/build/dep-5678/out/gen.rs")));
+        assert!(is_generated(
+            &root.join("target/debug/build/app-1234/out/gen.rs")
+        ));
+        assert!(is_build_script(&root.join("build.rs")));
+
+        let layout = CrateLayout::detect(root, false);
+        assert!(layout.is_build_output(Path::new("target/debug/build/app-1234/out/gen.rs")));
+        assert!(!CrateLayout::detect(&root.join("target"), true).is_build_output(Path::new("x")));
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::compression::{self, Compression, Encoder};
+use crate::impl_groups::Section;
+use crate::section_template::SectionTemplate;
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Destination of the combined single-file output, which is written section by section
+/// instead of being assembled in memory
+pub enum CombinedOutput {
+    /// Collected to compare with the existing output (`--check`)
+    Check { path: PathBuf, buffer: Vec<u8> },
+    /// Discarded (`--dry-run`)
+    DryRun,
+    /// Streamed into a partial file that replaces the output once complete
+    File {
+        path: PathBuf,
+        partial: PartialFile,
+        encoder: Encoder<BufWriter<File>>,
+    },
+}
+
+impl CombinedOutput {
+    /// Streams into the output file, creating its directory
+    pub fn create(path: &Path, compression: Option<Compression>) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context("Failed to create output directory for code context")?;
+        }
+        let partial = PartialFile::new(path);
+        let file = File::create(&partial.path).context("Failed to write code context file")?;
+        Ok(CombinedOutput::File {
+            path: path.to_path_buf(),
+            encoder: Encoder::new(compression, BufWriter::new(file))?,
+            partial,
+        })
+    }
+
+    /// Collects the output to compare it with the existing file when finished
+    pub fn check(path: &Path) -> Self {
+        CombinedOutput::Check {
+            path: path.to_path_buf(),
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn write(&mut self, text: &str) -> Result<()> {
+        let writer: &mut dyn Write = match self {
+            CombinedOutput::Check { buffer, .. } => buffer,
+            CombinedOutput::DryRun => return Ok(()),
+            CombinedOutput::File { encoder, .. } => encoder,
+        };
+        writer
+            .write_all(text.as_bytes())
+            .context("Failed to write code context file")
+    }
+
+    /// Writes a file's section between the header and footer of the template
+    pub fn write_section(&mut self, section: &Section, template: &SectionTemplate) -> Result<()> {
+        self.write(&template.header(&section.path))?;
+        self.write(&section.content)?;
+        self.write(&template.footer(&section.path))
+    }
+
+    /// Replaces the output file with the complete output, or fails if it differs from the
+    /// existing output, or there is none, in check mode
+    pub fn finish(self) -> Result<()> {
+        match self {
+            CombinedOutput::Check { path, buffer } => {
+                if !path.exists() || compression::read_output(&path)?.as_bytes() != buffer {
+                    bail!("Code context is out of date: {}", path.display());
+                }
+            }
+            CombinedOutput::DryRun => {}
+            CombinedOutput::File {
+                path,
+                partial,
+                encoder,
+            } => {
+                encoder
+                    .finish()?
+                    .flush()
+                    .context("Failed to write code context file")?;
+                partial.persist(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// File being written next to its final path, removed unless persisted
+pub struct PartialFile {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl PartialFile {
+    fn new(path: &Path) -> Self {
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        Self {
+            path: PathBuf::from(partial),
+            persisted: false,
+        }
+    }
+
+    fn persist(mut self, path: &Path) -> Result<()> {
+        std::fs::rename(&self.path, path).context("Failed to write code context file")?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorKind;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_combined_output_replaces_file_when_finished() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("out/code_context.rs.txt.gz");
+        let section = Section {
+            path: "lib.rs".to_string(),
+            content: "pub fn a();".to_string(),
+        };
+
+        let mut output = CombinedOutput::create(&path, Some(Compression::Gzip))?;
+        output.write("// Summary\n")?;
+        output.write_section(&section, &SectionTemplate::default())?;
+        assert!(!path.exists());
+        output.finish()?;
+        let expected = "// Summary\n\n// File: lib.rs\n\npub fn a();\n";
+        assert_eq!(compression::read_output(&path)?, expected);
+
+        let mut output = CombinedOutput::check(&path);
+        output.write(expected)?;
+        output.finish()?;
+        let mut output = CombinedOutput::check(&path);
+        output.write("// Changed\n")?;
+        assert!(output.finish().is_err());
+        // A missing output is out of date rather than a failed read
+        let error = CombinedOutput::check(&temp_dir.path().join("missing.rs.txt"))
+            .finish()
+            .unwrap_err();
+        assert!(error.to_string().contains("out of date"));
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Other);
+
+        // An unfinished output leaves the existing file as it was
+        let mut output = CombinedOutput::create(&path, None)?;
+        output.write("// Partial")?;
+        drop(output);
+        assert_eq!(compression::read_output(&path)?, expected);
+        assert_eq!(std::fs::read_dir(temp_dir.path().join("out"))?.count(), 1);
+        Ok(())
+    }
+}
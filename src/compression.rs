@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression formats supported for the combined output file
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the file extension appended to compressed output files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+
+    /// Detects the compression format from the leading magic bytes
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if data.starts_with(&ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Decompresses the given bytes
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        match self {
+            Compression::Gzip => {
+                flate2::read::GzDecoder::new(data)
+                    .read_to_end(&mut output)
+                    .context("Failed to decompress gzip data")?;
+            }
+            Compression::Zstd => {
+                output = zstd::decode_all(data).context("Failed to decompress zstd data")?;
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Writer that compresses what is written to it, if requested
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(compression: Option<Compression>, writer: W) -> Result<Self> {
+        Ok(match compression {
+            None => Encoder::Plain(writer),
+            Some(Compression::Gzip) => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            Some(Compression::Zstd) => Encoder::Zstd(
+                zstd::Encoder::new(writer, 0).context("Failed to start zstd compression")?,
+            ),
+        })
+    }
+
+    /// Writes the end of the compressed stream and returns the underlying writer
+    pub fn finish(self) -> Result<W> {
+        match self {
+            Encoder::Plain(writer) => Ok(writer),
+            Encoder::Gzip(encoder) => encoder.finish().context("Failed to gzip output"),
+            Encoder::Zstd(encoder) => encoder.finish().context("Failed to zstd output"),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Reads a generated output file, transparently decompressing it if needed
+pub fn read_output(path: &Path) -> Result<String> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let data = match Compression::detect(&data) {
+        Some(compression) => compression.decompress(&data)?,
+        None => data,
+    };
+    String::from_utf8(data).with_context(|| format!("File is not valid UTF-8: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::compress;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compression_round_trip() -> Result<()> {
+        let data = b"fn main() {}\n".repeat(100);
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let compressed = compress(compression, &data)?;
+            assert!(compressed.len() < data.len());
+            assert_eq!(Compression::detect(&compressed), Some(compression));
+            assert_eq!(compression.decompress(&compressed)?, data);
+        }
+        assert_eq!(Compression::detect(&data), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_output() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let plain = temp_dir.path().join("plain.rs.txt");
+        let packed = temp_dir.path().join("packed.rs.txt.zst");
+        std::fs::write(&plain, "fn main() {}")?;
+        std::fs::write(&packed, compress(Compression::Zstd, b"fn main() {}")?)?;
+
+        assert_eq!(read_output(&plain)?, "fn main() {}");
+        assert_eq!(read_output(&packed)?, "fn main() {}");
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::DirEntry;
+
+/// Name of the configuration file looked up in the input directory and its ancestors
+pub const CONFIG_FILE_NAME: &str = "code-context.toml";
+
+/// Priority weight of files that match no priority pattern
+pub const DEFAULT_PRIORITY: i64 = 5;
+
+/// Settings read from `code-context.toml`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Priority weights of files matching glob patterns, relative to the config file
+    pub priority: BTreeMap<String, i64>,
+    /// Priority weight of files that match no pattern
+    pub default_priority: Option<i64>,
+    /// Shell command run after a successful generation, unless `--post-hook` is given
+    pub post_hook: Option<String>,
+    /// Store of `snapshot`, relative to the config file
+    pub snapshot_store: Option<PathBuf>,
+    /// Shell command run after `snapshot` stores an output, e.g. to push it to an object store
+    pub snapshot_push: Option<String>,
+    /// Directory containing the config file
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+impl Config {
+    /// Loads a configuration file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        let mut config: Config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        config.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(config)
+    }
+
+    /// Loads the nearest configuration file at or above the input path, if any
+    pub fn discover(input: &Path) -> Result<Self> {
+        let start = std::fs::canonicalize(input).unwrap_or_else(|_| input.to_path_buf());
+        match start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+        {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Returns the priority weights, if any are configured
+    pub fn priority_weights(&self) -> Result<Option<PriorityWeights>> {
+        if self.priority.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        let mut weights = Vec::with_capacity(self.priority.len());
+        for (pattern, weight) in &self.priority {
+            builder.add(
+                Glob::new(pattern)
+                    .with_context(|| format!("Invalid priority pattern: {}", pattern))?,
+            );
+            weights.push(*weight);
+        }
+        Ok(Some(PriorityWeights {
+            globs: builder.build()?,
+            weights,
+            default: self.default_priority.unwrap_or(DEFAULT_PRIORITY),
+            base_dir: std::fs::canonicalize(&self.base_dir)
+                .unwrap_or_else(|_| self.base_dir.clone()),
+        }))
+    }
+}
+
+/// Priority weights of files, assigned by glob patterns
+#[derive(Debug)]
+pub struct PriorityWeights {
+    globs: GlobSet,
+    weights: Vec<i64>,
+    default: i64,
+    base_dir: PathBuf,
+}
+
+impl PriorityWeights {
+    /// Returns the highest weight of the patterns matching the file, or the default weight
+    pub fn weight(&self, path: &Path) -> i64 {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let relative = path.strip_prefix(&self.base_dir).unwrap_or(&path);
+        self.globs
+            .matches(relative)
+            .into_iter()
+            .map(|index| self.weights[index])
+            .max()
+            .unwrap_or(self.default)
+    }
+
+    /// Moves files with higher weights first, keeping the order of equally weighted files
+    pub fn sort(&self, files: &mut [DirEntry]) {
+        files.sort_by_cached_key(|entry| Reverse(self.weight(entry.path())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_priority_weights() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(
+            temp_dir.path().join(CONFIG_FILE_NAME),
+            "default-priority = 3\n\n[priority]\n\"src/core/**\" = 10\n\"src/migrations/**\" = 1\n",
+        )?;
+        let src = temp_dir.path().join("src");
+        std::fs::create_dir_all(src.join("core"))?;
+
+        let config = Config::discover(&src)?;
+        let weights = config.priority_weights()?.expect("weights");
+        assert_eq!(weights.weight(&src.join("core/engine.rs")), 10);
+        assert_eq!(weights.weight(&src.join("migrations/v1.rs")), 1);
+        assert_eq!(weights.weight(&src.join("lib.rs")), 3);
+
+        assert!(Config::default().priority_weights()?.is_none());
+
+        std::fs::write(
+            temp_dir.path().join(CONFIG_FILE_NAME),
+            "post-hook = \"aws s3 sync $CODE_CONTEXT_OUTPUT s3://contexts\"\n",
+        )?;
+        assert_eq!(
+            Config::discover(&src)?.post_hook.as_deref(),
+            Some("aws s3 sync $CODE_CONTEXT_OUTPUT s3://contexts")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+use crate::item_ids;
+use crate::module_path::ModuleMap;
+use crate::paths;
+use crate::processor::{FileProcessor, Processor};
+use crate::script;
+use crate::search::{self, IndexedItem};
+use crate::update::content_hash;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// Address the daemon listens on by default
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// A request sent to the daemon as one line of JSON
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "command", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Request {
+    /// Generates the combined output of the input directory
+    Generate {
+        /// Options as given to `code-context`, e.g. `["--no-function-bodies"]`
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Prints the condensed items whose name, doc aliases, signature, or docs contain a
+    /// pattern, as `code-context grep` does
+    Query {
+        pattern: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Reports the number of cached files
+    Status,
+    /// Stops the daemon
+    Shutdown,
+}
+
+/// The daemon's answer to a request, sent as one line of JSON
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Number of files in the output
+    pub files: usize,
+    /// Number of files whose parsed source came from the cache
+    pub cached: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Parsed source of a file, valid while its source hash is unchanged
+struct CachedFile {
+    source_hash: String,
+    /// Code after any script header, or `None` if it doesn't parse
+    ast: Option<syn::File>,
+}
+
+/// A processed file of the input
+struct ProcessedFile {
+    /// Path relative to the input directory
+    path: String,
+    module: Option<String>,
+    output: String,
+}
+
+/// Keeps the parsed files of a directory in memory between requests, building the processor
+/// of each request from its command-line options
+pub struct Daemon<B> {
+    input_dir: PathBuf,
+    build_processor: B,
+    /// Parsed files by path
+    cache: HashMap<PathBuf, CachedFile>,
+}
+
+impl<B: FnMut(&[String]) -> Result<FileProcessor>> Daemon<B> {
+    pub fn new(input_dir: &Path, build_processor: B) -> Self {
+        Self {
+            input_dir: input_dir.to_path_buf(),
+            build_processor,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Processes the files of the input with the given options, re-parsing only files
+    /// changed since the last request and evicting deleted ones
+    fn process(&mut self, args: &[String]) -> Result<(FileProcessor, Vec<ProcessedFile>, usize)> {
+        let processor = (self.build_processor)(args)?;
+        let modules = ModuleMap::build(&self.input_dir);
+        let mut files = Vec::new();
+        let mut cached = 0;
+        let source_files = processor.source_files(&self.input_dir, None)?;
+        let walked: HashSet<_> = source_files.iter().cloned().collect();
+        self.cache.retain(|path, _| walked.contains(path));
+        for path in source_files {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read file: {}", path.display()))?;
+            let source_hash = content_hash(&content);
+            let ast = match self.cache.get(&path) {
+                Some(file) if file.source_hash == source_hash => {
+                    cached += 1;
+                    file.ast.clone()
+                }
+                _ => {
+                    let ast = syn::parse_file(script::split_header(&content).1).ok();
+                    let file = CachedFile {
+                        source_hash,
+                        ast: ast.clone(),
+                    };
+                    self.cache.insert(path.clone(), file);
+                    ast
+                }
+            };
+            // A file that doesn't parse fails with the usual parse error
+            let output = match ast {
+                Some(ast) => processor.transform_parsed(&path, &content, ast)?,
+                None => processor.transform_source(&path, &content)?,
+            };
+            let relative = path.strip_prefix(&self.input_dir).unwrap_or(&path);
+            files.push(ProcessedFile {
+                path: paths::slash(relative),
+                module: modules.module_of(&path).map(str::to_string),
+                output,
+            });
+        }
+        Ok((processor, files, cached))
+    }
+
+    /// Generates the combined output with the given options
+    fn generate(&mut self, args: &[String]) -> Result<Response> {
+        let (processor, files, cached) = self.process(args)?;
+        let template = processor.section_template();
+        let mut output = String::new();
+        for file in &files {
+            output.push_str(&template.header(&file.path));
+            output.push_str(&file.output);
+            output.push_str(&template.footer(&file.path));
+        }
+        Ok(Response {
+            ok: true,
+            output: Some(output),
+            files: files.len(),
+            cached,
+            error: None,
+        })
+    }
+
+    /// Finds the condensed items matching a pattern, processed with the given options
+    fn query(&mut self, pattern: &str, args: &[String]) -> Result<Response> {
+        let (processor, files, cached) = self.process(args)?;
+        let mut items = Vec::new();
+        for file in &files {
+            let records = item_ids::collect_items(
+                processor.crate_name(),
+                file.module.as_deref(),
+                &file.output,
+            );
+            items.extend(records.into_iter().map(|record| IndexedItem {
+                path: file.path.clone(),
+                kind: record.kind,
+                name: record.name,
+                code: record.code,
+                aliases: record.metadata.aliases,
+            }));
+        }
+        Ok(Response {
+            ok: true,
+            output: Some(search::grep(&items, pattern)),
+            files: files.len(),
+            cached,
+            error: None,
+        })
+    }
+
+    /// Answers one request line, returning whether the daemon should stop
+    pub fn handle(&mut self, line: &str) -> (Response, bool) {
+        let result = serde_json::from_str::<Request>(line)
+            .context("Invalid request")
+            .and_then(|request| match request {
+                Request::Generate { args } => {
+                    self.generate(&args).map(|response| (response, false))
+                }
+                Request::Query { pattern, args } => self
+                    .query(&pattern, &args)
+                    .map(|response| (response, false)),
+                Request::Status => Ok((
+                    Response {
+                        ok: true,
+                        cached: self.cache.len(),
+                        ..Default::default()
+                    },
+                    false,
+                )),
+                Request::Shutdown => Ok((
+                    Response {
+                        ok: true,
+                        ..Default::default()
+                    },
+                    true,
+                )),
+            });
+        result.unwrap_or_else(|error| {
+            (
+                Response {
+                    error: Some(format!("{:#}", error)),
+                    ..Default::default()
+                },
+                false,
+            )
+        })
+    }
+
+    /// Serves connections at the same time until a shutdown request; each connection is
+    /// read on its own thread, while requests are answered in turn with the cache
+    pub fn serve(mut self, listener: TcpListener) -> Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let sender = sender.clone();
+                let result = stream.map(|stream| {
+                    thread::spawn(move || {
+                        if let Err(error) = read_requests(stream, &sender) {
+                            tracing::warn!("Connection failed: {:#}", error);
+                        }
+                    })
+                });
+                if let Err(error) = result {
+                    tracing::warn!("Connection failed: {:#}", error);
+                }
+            }
+        });
+        for (line, mut writer) in receiver {
+            let (response, stop) = self.handle(&line);
+            if let Err(error) = writeln!(writer, "{}", serde_json::to_string(&response)?) {
+                tracing::warn!("Failed to answer a request: {:#}", error);
+            }
+            if stop {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Forwards the request lines of a connection, each with the stream to answer it on
+fn read_requests(stream: TcpStream, requests: &Sender<(String, TcpStream)>) -> Result<()> {
+    let writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        requests
+            .send((line, writer.try_clone()?))
+            .context("The daemon stopped")?;
+    }
+    Ok(())
+}
+
+/// Sends a request to a running daemon and returns its answer, failing with the error of a
+/// failed request
+pub fn send(address: &str, request: &Request) -> Result<Response> {
+    let stream = TcpStream::connect(address)
+        .with_context(|| format!("Failed to connect to the daemon at {}", address))?;
+    writeln!(&stream, "{}", serde_json::to_string(request)?)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response: Response =
+        serde_json::from_str(&line).context("Invalid response from the daemon")?;
+    if let Some(error) = &response.error {
+        anyhow::bail!("{}", error);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Builds processors from the only option the tests use
+    fn build_processor(args: &[String]) -> Result<FileProcessor> {
+        let no_function_bodies = args.iter().any(|arg| arg == "--no-function-bodies");
+        Ok(FileProcessor::with_options(
+            false,
+            no_function_bodies,
+            false,
+            true,
+        ))
+    }
+
+    #[test]
+    fn test_generate_reuses_unchanged_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("a.rs"), "pub fn a() { 1; }")?;
+        std::fs::write(temp_dir.path().join("b.rs"), "pub fn b() {}")?;
+        let mut daemon = Daemon::new(temp_dir.path(), build_processor);
+
+        let request = r#"{"command": "generate", "args": ["--no-function-bodies"]}"#;
+        let (response, stop) = daemon.handle(request);
+        assert!(response.ok && !stop);
+        assert_eq!((response.files, response.cached), (2, 0));
+        assert!(response
+            .output
+            .unwrap_or_default()
+            .contains("pub fn a() {}"));
+
+        // Cached files are transformed again with the options of each request
+        std::fs::write(temp_dir.path().join("b.rs"), "pub fn c() {}")?;
+        let (response, _) = daemon.handle(r#"{"command": "generate"}"#);
+        assert_eq!((response.files, response.cached), (2, 1));
+        let output = response.output.unwrap_or_default();
+        assert!(output.contains("pub fn a() {\n    1;\n}"));
+        assert!(output.contains("pub fn c()"));
+
+        // Deleted files leave the cache
+        std::fs::remove_file(temp_dir.path().join("b.rs"))?;
+        let (response, _) = daemon
+            .handle(r#"{"command": "query", "pattern": "fn a", "args": ["--no-function-bodies"]}"#);
+        assert_eq!(
+            response.output.as_deref(),
+            Some("// a.rs (fn a)\npub fn a() {}\n\n")
+        );
+        assert_eq!(daemon.handle(r#"{"command": "status"}"#).0.cached, 1);
+
+        let (response, stop) = daemon.handle(r#"{"command": "shutdown"}"#);
+        assert!(response.ok && stop);
+        assert!(daemon.handle("{}").0.error.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_serve_connections_at_once() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("lib.rs"), "pub fn a() {}")?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?.to_string();
+
+        let client = thread::spawn(move || -> Result<()> {
+            // An idle connection doesn't hold up another one
+            let idle = TcpStream::connect(&address)?;
+            let response = send(&address, &Request::Generate { args: Vec::new() })?;
+            assert_eq!(response.files, 1);
+            send(&address, &Request::Shutdown)?;
+            drop(idle);
+            Ok(())
+        });
+        Daemon::new(temp_dir.path(), build_processor).serve(listener)?;
+        client.join().expect("the client panicked")
+    }
+}
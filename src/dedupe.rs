@@ -0,0 +1,73 @@
+use crate::processor::FileStats;
+use std::collections::HashMap;
+
+/// Returns the line emitted in place of a duplicate file's content
+pub fn reference(original: &str) -> String {
+    format!("// Identical to {}\n", original)
+}
+
+/// First file seen with each output hash
+#[derive(Default)]
+pub struct Originals(HashMap<String, String>);
+
+impl Originals {
+    /// Marks a file as a duplicate if an earlier file had identical output
+    pub fn mark(&mut self, file: &mut FileStats) {
+        let Some(hash) = &file.output_hash else {
+            return;
+        };
+        match self.0.get(hash) {
+            Some(original) => file.duplicate_of = Some(original.clone()),
+            None => {
+                self.0.insert(hash.clone(), file.path.clone());
+            }
+        }
+    }
+}
+
+/// Marks files whose output is identical to that of an earlier file as its duplicates
+pub fn mark_duplicates(files: &mut [FileStats]) {
+    let mut originals = Originals::default();
+    for file in files {
+        originals.mark(file);
+    }
+}
+
+/// Lists the duplicate files, or `None` if there are none
+pub fn report(files: &[FileStats]) -> Option<String> {
+    let duplicates: Vec<_> = files
+        .iter()
+        .filter_map(|file| Some((&file.path, file.duplicate_of.as_ref()?)))
+        .collect();
+    if duplicates.is_empty() {
+        return None;
+    }
+    let mut report = format!("Duplicate files: {}\n", duplicates.len());
+    for (path, original) in duplicates {
+        report.push_str(&format!("  {} is identical to {}\n", path, original));
+    }
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_duplicates() {
+        let file = |path: &str, hash: &str| {
+            let mut file = FileStats::new(path, 10, 10);
+            file.output_hash = Some(hash.to_string());
+            file
+        };
+        let mut files = vec![file("a.rs", "1"), file("b.rs", "2"), file("gen/a.rs", "1")];
+        mark_duplicates(&mut files);
+        assert_eq!(files[0].duplicate_of, None);
+        assert_eq!(files[2].duplicate_of.as_deref(), Some("a.rs"));
+        assert_eq!(
+            report(&files).as_deref(),
+            Some("Duplicate files: 1\n  gen/a.rs is identical to a.rs\n")
+        );
+        assert_eq!(report(&files[..2]), None);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::module_path::ModuleMap;
+use crate::summary::SourceFile;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use syn::visit::{self, Visit};
+use syn::{Item, Visibility};
+
+/// Name of the Mermaid diagram written into the output directory
+pub const DIAGRAM_FILE_NAME: &str = "code_context.mmd";
+
+/// Module depth shown in the diagram by default
+pub const DEFAULT_DIAGRAM_DEPTH: usize = 2;
+
+/// A public type shown in the diagram
+struct TypeNode {
+    id: String,
+    label: String,
+    /// Names of the types its fields use
+    uses: BTreeSet<String>,
+}
+
+/// Collects the names of the types a field refers to
+#[derive(Default)]
+struct TypeNames(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for TypeNames {
+    fn visit_path_segment(&mut self, segment: &'ast syn::PathSegment) {
+        self.0.insert(segment.ident.to_string());
+        visit::visit_path_segment(self, segment);
+    }
+}
+
+/// Converts a module path or name into a Mermaid node id
+fn node_id(prefix: &str, name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}", prefix, sanitized)
+}
+
+/// Module path of a file derived from its location, for files not reachable from a crate root
+fn fallback_module(path: &str) -> String {
+    let path = Path::new(path).with_extension("");
+    let mut segments = vec!["crate".to_string()];
+    segments.extend(
+        path.components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .filter(|segment| !matches!(segment.as_str(), "src" | "lib" | "main" | "mod")),
+    );
+    segments.join("::")
+}
+
+/// Keeps the first `depth` modules below the crate root
+fn truncate(module: &str, depth: usize) -> String {
+    module
+        .split("::")
+        .take(depth + 1)
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+fn collect_types(items: &[Item], module: &str, types: &mut BTreeMap<String, Vec<TypeNode>>) {
+    for item in items {
+        let (kind, ident, fields) = match item {
+            Item::Struct(item) if matches!(item.vis, Visibility::Public(_)) => (
+                "struct",
+                &item.ident,
+                item.fields.iter().collect::<Vec<_>>(),
+            ),
+            Item::Enum(item) if matches!(item.vis, Visibility::Public(_)) => (
+                "enum",
+                &item.ident,
+                item.variants
+                    .iter()
+                    .flat_map(|variant| variant.fields.iter())
+                    .collect(),
+            ),
+            Item::Trait(item) if matches!(item.vis, Visibility::Public(_)) => {
+                ("trait", &item.ident, Vec::new())
+            }
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    collect_types(items, &format!("{}::{}", module, item.ident), types);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let mut names = TypeNames::default();
+        for field in fields {
+            names.visit_type(&field.ty);
+        }
+        names.0.remove(&ident.to_string());
+        types.entry(module.to_string()).or_default().push(TypeNode {
+            id: node_id("t", &format!("{}::{}", module, ident)),
+            label: format!("{} {}", kind, ident),
+            uses: names.0,
+        });
+    }
+}
+
+/// Renders a Mermaid flowchart of the modules down to the given depth, their public
+/// types, and the types their fields use
+pub fn render(input: &Path, sources: &[SourceFile], depth: usize) -> String {
+    let modules = ModuleMap::build(input);
+    let mut types: BTreeMap<String, Vec<TypeNode>> = BTreeMap::new();
+    for source in sources {
+        let module = modules
+            .module_of(&input.join(&source.path))
+            .map(str::to_string)
+            .unwrap_or_else(|| fallback_module(&source.path));
+        collect_types(&source.ast.items, &module, &mut types);
+    }
+
+    // Types of modules deeper than the depth are shown in their ancestor
+    let mut grouped: BTreeMap<String, Vec<TypeNode>> = BTreeMap::new();
+    for (module, nodes) in types {
+        grouped
+            .entry(truncate(&module, depth))
+            .or_default()
+            .extend(nodes);
+    }
+    let mut ids: HashMap<String, String> = HashMap::new();
+    for node in grouped.values().flatten() {
+        let name = node
+            .label
+            .rsplit(' ')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        ids.entry(name).or_insert_with(|| node.id.clone());
+    }
+
+    let mut diagram = String::from("flowchart LR\n");
+    for (module, nodes) in &grouped {
+        diagram.push_str(&format!(
+            "    subgraph {}[\"{}\"]\n",
+            node_id("m", module),
+            module
+        ));
+        for node in nodes {
+            diagram.push_str(&format!("        {}[\"{}\"]\n", node.id, node.label));
+        }
+        diagram.push_str("    end\n");
+    }
+    for module in grouped.keys() {
+        if let Some((parent, _)) = module.rsplit_once("::") {
+            if grouped.contains_key(parent) {
+                diagram.push_str(&format!(
+                    "    {} --> {}\n",
+                    node_id("m", parent),
+                    node_id("m", module)
+                ));
+            }
+        }
+    }
+    for node in grouped.values().flatten() {
+        for used in node.uses.iter().filter_map(|name| ids.get(name)) {
+            if *used != node.id {
+                diagram.push_str(&format!("    {} -.-> {}\n", node.id, used));
+            }
+        }
+    }
+    diagram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_render_diagram() -> Result<()> {
+        let sources = vec![
+            SourceFile {
+                path: "lib.rs".to_string(),
+                ast: syn::parse_str("pub struct App { config: api::Config }")?,
+            },
+            SourceFile {
+                path: "api/client/mod.rs".to_string(),
+                ast: syn::parse_str("pub struct Config; pub trait Handler {} struct Private;")?,
+            },
+        ];
+        assert_eq!(
+            render(Path::new("/nonexistent"), &sources, 1),
+            "flowchart LR\n    subgraph m_crate[\"crate\"]\n        t_crate__App[\"struct App\"]\n    end\n    subgraph m_crate__api[\"crate::api\"]\n        t_crate__api__client__Config[\"struct Config\"]\n        t_crate__api__client__Handler[\"trait Handler\"]\n    end\n    m_crate --> m_crate__api\n    t_crate__App -.-> t_crate__api__client__Config\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+use crate::compression::read_output;
+use crate::manifest;
+use crate::module_path;
+use crate::paths;
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use syn::{parse_quote, ImplItem, Item, TraitItem, Visibility};
+use walkdir::WalkDir;
+
+/// Header that separates files in the combined single-file output
+pub const FILE_HEADER_PREFIX: &str = "// File: ";
+
+/// Items of a generated context, keyed by file and item path
+pub type ItemMap = BTreeMap<String, String>;
+
+/// Loads all items from a generated output file or directory, skipping the outputs of other
+/// runs nested in the directory
+pub fn load_items(path: &Path) -> Result<ItemMap> {
+    let mut items = ItemMap::new();
+    if path.is_dir() {
+        let files = WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !manifest::is_output_dir(e.path()))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file() && is_output_file(e.path()));
+        for entry in files {
+            let relative = entry
+                .path()
+                .strip_prefix(path)
+                .context("Failed to strip prefix from path")?;
+            let label = paths::slash(relative);
+            let label = label.split(".rs.txt").next().unwrap_or(&label).to_string() + ".rs";
+            collect_output(&label, &read_output(entry.path())?, &mut items)?;
+        }
+    } else {
+        let label = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        collect_output(&label, &read_output(path)?, &mut items)?;
+    }
+    Ok(items)
+}
+
+/// Checks if a path looks like a generated output file, compressed or not, or is a `.rs`
+/// output starting with the generated marker
+pub fn is_output_file(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".rs.txt")
+        || name.ends_with(".rs.txt.gz")
+        || name.ends_with(".rs.txt.zst")
+        || (name.ends_with(".rs") && module_path::is_own_output(path))
+}
+
+/// Collects items from output content, splitting the combined format by file headers
+fn collect_output(label: &str, content: &str, items: &mut ItemMap) -> Result<()> {
+    if !content
+        .lines()
+        .any(|line| line.starts_with(FILE_HEADER_PREFIX))
+    {
+        return collect_file(label, content, items);
+    }
+
+    let mut current: Option<&str> = None;
+    let mut section = String::new();
+    for line in content.lines() {
+        if let Some(file) = line.strip_prefix(FILE_HEADER_PREFIX) {
+            if let Some(file) = current {
+                collect_file(file, &section, items)?;
+            }
+            current = Some(file);
+            section.clear();
+        } else {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+    if let Some(file) = current {
+        collect_file(file, &section, items)?;
+    }
+    Ok(())
+}
+
+fn collect_file(label: &str, content: &str, items: &mut ItemMap) -> Result<()> {
+    let file = syn::parse_file(content)
+        .with_context(|| format!("Failed to parse generated output for {}", label))?;
+    for item in file.items {
+        collect_item(&format!("{}: ", label), item, items);
+    }
+    Ok(())
+}
+
+/// Renders an item without doc comments and function bodies
+fn render(mut item: Item) -> String {
+    match &mut item {
+        Item::Fn(f) => f.block = parse_quote!({}),
+        Item::Trait(t) => t.items.clear(),
+        _ => {}
+    }
+    let file = syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![strip_docs(item)],
+    };
+    prettyplease::unparse(&file).trim().to_string()
+}
+
+fn strip_docs(mut item: Item) -> Item {
+    let attrs = match &mut item {
+        Item::Fn(i) => &mut i.attrs,
+        Item::Struct(i) => &mut i.attrs,
+        Item::Enum(i) => &mut i.attrs,
+        Item::Union(i) => &mut i.attrs,
+        Item::Trait(i) => &mut i.attrs,
+        Item::Type(i) => &mut i.attrs,
+        Item::Const(i) => &mut i.attrs,
+        Item::Static(i) => &mut i.attrs,
+        _ => return item,
+    };
+    attrs.retain(|attr| !attr.path().is_ident("doc"));
+    item
+}
+
+/// Builds a free-standing function from a method signature for rendering
+fn method_item(vis: Visibility, sig: syn::Signature) -> Item {
+    Item::Fn(syn::ItemFn {
+        attrs: Vec::new(),
+        vis,
+        sig,
+        block: Box::new(parse_quote!({})),
+    })
+}
+
+fn collect_item(prefix: &str, item: Item, items: &mut ItemMap) {
+    let key = match &item {
+        Item::Fn(i) => format!("fn {}", i.sig.ident),
+        Item::Struct(i) => format!("struct {}", i.ident),
+        Item::Enum(i) => format!("enum {}", i.ident),
+        Item::Union(i) => format!("union {}", i.ident),
+        Item::Type(i) => format!("type {}", i.ident),
+        Item::Const(i) => format!("const {}", i.ident),
+        Item::Static(i) => format!("static {}", i.ident),
+        Item::TraitAlias(i) => format!("trait {}", i.ident),
+        Item::Mod(i) => {
+            let key = format!("mod {}", i.ident);
+            if let Some((_, content)) = &i.content {
+                let nested = format!("{}{}::", prefix, i.ident);
+                for item in content.clone() {
+                    collect_item(&nested, item, items);
+                }
+            }
+            key
+        }
+        Item::Trait(i) => {
+            let nested = format!("{}trait {}::", prefix, i.ident);
+            for trait_item in &i.items {
+                if let TraitItem::Fn(method) = trait_item {
+                    items.insert(
+                        format!("{}fn {}", nested, method.sig.ident),
+                        render(method_item(Visibility::Inherited, method.sig.clone())),
+                    );
+                } else {
+                    collect_other(&nested, trait_item.to_token_stream(), items);
+                }
+            }
+            format!("trait {}", i.ident)
+        }
+        Item::Impl(i) => {
+            let mut header = i.clone();
+            header.attrs.clear();
+            header.items.clear();
+            let header = render(Item::Impl(header));
+            let header = header.trim_end_matches("{}").trim();
+            let nested = format!("{}{}::", prefix, header);
+            for impl_item in &i.items {
+                if let ImplItem::Fn(method) = impl_item {
+                    items.insert(
+                        format!("{}fn {}", nested, method.sig.ident),
+                        render(method_item(method.vis.clone(), method.sig.clone())),
+                    );
+                } else {
+                    collect_other(&nested, impl_item.to_token_stream(), items);
+                }
+            }
+            return;
+        }
+        _ => {
+            collect_other(prefix, item.to_token_stream(), items);
+            return;
+        }
+    };
+    items.insert(format!("{}{}", prefix, key), render(item));
+}
+
+/// Records an item identified only by its tokens, so it can be added or removed but not changed
+fn collect_other(prefix: &str, tokens: proc_macro2::TokenStream, items: &mut ItemMap) {
+    let text = tokens.to_string();
+    items.insert(format!("{}{}", prefix, text), text);
+}
+
+/// Renders the semantic differences between two item maps
+pub fn diff(old: &ItemMap, new: &ItemMap) -> String {
+    let mut report = String::new();
+    for (key, signature) in new {
+        match old.get(key) {
+            None => {
+                let _ = writeln!(report, "+ {}", key);
+            }
+            Some(old_signature) if old_signature != signature => {
+                let _ = writeln!(report, "~ {}", key);
+                for line in old_signature.lines() {
+                    let _ = writeln!(report, "    - {}", line);
+                }
+                for line in signature.lines() {
+                    let _ = writeln!(report, "    + {}", line);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            let _ = writeln!(report, "- {}", key);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::Compression;
+    use crate::test_utils::compress;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    fn items(label: &str, content: &str) -> Result<ItemMap> {
+        let mut items = ItemMap::new();
+        collect_output(label, content, &mut items)?;
+        Ok(items)
+    }
+
+    #[test]
+    fn test_diff_items() -> Result<()> {
+        let old = items(
+            "lib.rs",
+            r#"
+            /// Adds numbers
+            pub fn add(a: i32, b: i32) -> i32 {}
+            pub struct Removed;
+            impl Foo {
+                pub fn bar(&self) -> u8 {}
+            }
+            "#,
+        )?;
+        let new = items(
+            "lib.rs",
+            r#"
+            /// Adds two numbers
+            pub fn add(a: i64, b: i64) -> i64 {}
+            pub enum Added {}
+            impl Foo {
+                pub fn bar(&self) -> u8 {}
+            }
+            "#,
+        )?;
+
+        let report = diff(&old, &new);
+        assert!(report.contains("~ lib.rs: fn add"));
+        assert!(report.contains("    - pub fn add(a: i32, b: i32) -> i32 {}"));
+        assert!(report.contains("    + pub fn add(a: i64, b: i64) -> i64 {}"));
+        assert!(report.contains("+ lib.rs: enum Added"));
+        assert!(report.contains("- lib.rs: struct Removed"));
+        assert!(!report.contains("bar"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_ignores_docs_and_formatting() -> Result<()> {
+        let old = items("lib.rs", "/// Old docs\nfn f(x: u8) {}")?;
+        let new = items("lib.rs", "/// New docs\nfn   f( x :u8 )   {}")?;
+        assert!(diff(&old, &new).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_items() -> Result<()> {
+        let items = items(
+            "lib.rs",
+            r#"
+            mod inner { pub fn nested() {} }
+            trait Shape { fn area(&self) -> f64; }
+            impl Shape for Circle { fn area(&self) -> f64 {} }
+            "#,
+        )?;
+        assert!(items.contains_key("lib.rs: inner::fn nested"));
+        assert!(items.contains_key("lib.rs: trait Shape::fn area"));
+        assert!(items.contains_key("lib.rs: impl Shape for Circle::fn area"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_combined_and_directory_outputs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let combined = temp_dir.path().join("code_context.rs.txt.zst");
+        let content =
+            "\n// File: lib.rs\n\npub fn a() {}\n\n// File: api/mod.rs\n\npub fn b() {}\n";
+        std::fs::write(&combined, compress(Compression::Zstd, content.as_bytes())?)?;
+
+        let output_dir = temp_dir.path().join("out");
+        std::fs::create_dir_all(output_dir.join("api"))?;
+        std::fs::write(output_dir.join("lib.rs.txt"), "pub fn a() {}")?;
+        std::fs::write(output_dir.join("api/mod.rs.txt"), "pub fn b() {}")?;
+        // The output of another run nested in the directory isn't part of it
+        std::fs::create_dir_all(output_dir.join("api/vendor-code-context"))?;
+        std::fs::write(
+            output_dir.join("api/vendor-code-context/lib.rs.txt"),
+            "pub fn c() {}",
+        )?;
+
+        let from_combined = load_items(&combined)?;
+        assert!(from_combined.contains_key("lib.rs: fn a"));
+        assert!(from_combined.contains_key("api/mod.rs: fn b"));
+        assert_eq!(from_combined, load_items(&output_dir)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use serde::Serialize;
+use syn::{Attribute, Expr, Lit, LitStr, Meta};
+
+/// Code block attributes of rustdoc that still mark a block as Rust
+const RUST_FENCE_ATTRIBUTES: &[&str] = &[
+    "rust",
+    "ignore",
+    "should_panic",
+    "no_run",
+    "compile_fail",
+    "test_harness",
+];
+
+/// Searchable metadata of an item from its attributes, kept in the JSONL output even when
+/// the text output strips docs and attributes
+#[derive(Serialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct DocMetadata {
+    /// Names of `#[doc(alias)]` attributes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Message of `#[must_use]`, empty for a bare `#[must_use]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub must_use: Option<String>,
+    /// Code of the first Rust example in the docs, without hidden `#` lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub example: Option<String>,
+}
+
+impl DocMetadata {
+    pub fn from_attrs(attrs: &[Attribute]) -> Self {
+        let mut metadata = Self::default();
+        let mut docs = Vec::new();
+        for attr in attrs {
+            if attr.path().is_ident("must_use") {
+                metadata.must_use = Some(match &attr.meta {
+                    Meta::NameValue(meta) => string_value(&meta.value).unwrap_or_default(),
+                    _ => String::new(),
+                });
+            } else if attr.path().is_ident("doc") {
+                match &attr.meta {
+                    Meta::NameValue(meta) => docs.extend(string_value(&meta.value)),
+                    Meta::List(_) => {
+                        let _ = attr.parse_nested_meta(|nested| {
+                            if nested.path.is_ident("alias") {
+                                if nested.input.peek(syn::token::Paren) {
+                                    let content;
+                                    syn::parenthesized!(content in nested.input);
+                                    let names = content.parse_terminated(
+                                        |input| input.parse::<LitStr>(),
+                                        syn::Token![,],
+                                    )?;
+                                    metadata.aliases.extend(names.iter().map(LitStr::value));
+                                } else {
+                                    let name: LitStr = nested.value()?.parse()?;
+                                    metadata.aliases.push(name.value());
+                                }
+                            } else if !nested.input.is_empty() && !nested.input.peek(syn::Token![,])
+                            {
+                                // Skip the value of other doc attributes such as `html_root_url`
+                                nested.value()?.parse::<Expr>()?;
+                            }
+                            Ok(())
+                        });
+                    }
+                    Meta::Path(_) => {}
+                }
+            }
+        }
+        metadata.example = first_example(&docs.join("\n"));
+        metadata
+    }
+}
+
+fn string_value(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(expr) => match &expr.lit {
+            Lit::Str(text) => Some(text.value()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Finds the first fenced Rust code block of a doc comment
+fn first_example(docs: &str) -> Option<String> {
+    let mut lines = docs
+        .lines()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line));
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let Some(info) = trimmed
+            .strip_prefix("```")
+            .or_else(|| trimmed.strip_prefix("~~~"))
+        else {
+            continue;
+        };
+        let fence = &trimmed[..3];
+        let mut code = Vec::new();
+        for line in lines.by_ref() {
+            if line.trim_start().starts_with(fence) {
+                break;
+            }
+            code.push(line);
+        }
+        if is_rust_fence(info) {
+            let code: Vec<_> = code
+                .into_iter()
+                .filter(|line| {
+                    let line = line.trim_start();
+                    line != "#" && !line.starts_with("# ")
+                })
+                .collect();
+            return Some(code.join("\n"));
+        }
+    }
+    None
+}
+
+/// Checks whether the info string of a code block leaves it a Rust block
+fn is_rust_fence(info: &str) -> bool {
+    info.split([',', ' '])
+        .map(str::trim)
+        .filter(|attribute| !attribute.is_empty())
+        .all(|attribute| {
+            RUST_FENCE_ATTRIBUTES.contains(&attribute) || attribute.starts_with("edition")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_metadata() -> syn::Result<()> {
+        let item: syn::ItemFn = syn::parse_str(
+            r#"
+            /// Parses a config.
+            ///
+            /// ```toml
+            /// name = "app"
+            /// ```
+            ///
+            /// ```no_run
+            /// # use app::parse;
+            /// let config = parse("app.toml");
+            /// ```
+            #[doc(alias = "load")]
+            #[doc(alias("read", "open"), hidden)]
+            #[must_use = "the parsed config is discarded"]
+            pub fn parse(path: &str) -> Config { todo!() }
+            "#,
+        )?;
+        let metadata = DocMetadata::from_attrs(&item.attrs);
+        assert_eq!(metadata.aliases, vec!["load", "read", "open"]);
+        assert_eq!(
+            metadata.must_use.as_deref(),
+            Some("the parsed config is discarded")
+        );
+        assert_eq!(
+            metadata.example.as_deref(),
+            Some("let config = parse(\"app.toml\");")
+        );
+
+        let item: syn::ItemStruct = syn::parse_str("#[must_use]\n/// Docs\npub struct Guard;")?;
+        let metadata = DocMetadata::from_attrs(&item.attrs);
+        assert_eq!(metadata.must_use.as_deref(), Some(""));
+        assert!(metadata.example.is_none());
+        assert_eq!(DocMetadata::from_attrs(&[]), DocMetadata::default());
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use crate::processor::FileStats;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Line counts of a source file before transformation
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommentStats {
+    pub code_lines: usize,
+    /// Lines of regular `//` and `/* */` comments
+    pub comment_lines: usize,
+    /// Lines of `///`, `//!`, `/** */`, and `/*! */` doc comments
+    pub doc_lines: usize,
+}
+
+/// Kind of a block comment spanning several lines
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Comment,
+    Doc,
+}
+
+impl CommentStats {
+    /// Counts code, comment, and doc lines; blank lines aren't counted and code lines with
+    /// trailing comments count as code
+    pub fn measure(source: &str) -> Self {
+        let mut stats = Self::default();
+        let mut block = None;
+        for line in source.lines().map(str::trim) {
+            if let Some(kind) = block {
+                stats.count(kind);
+                if line.contains("*/") {
+                    block = None;
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if (line.starts_with("///") && !line.starts_with("////")) || line.starts_with("//!") {
+                stats.doc_lines += 1;
+            } else if line.starts_with("//") {
+                stats.comment_lines += 1;
+            } else if let Some(rest) = line.strip_prefix("/*") {
+                let kind = if (rest.starts_with('*') && !rest.starts_with("**"))
+                    || rest.starts_with('!')
+                {
+                    Block::Doc
+                } else {
+                    Block::Comment
+                };
+                stats.count(kind);
+                if !rest.contains("*/") {
+                    block = Some(kind);
+                }
+            } else {
+                stats.code_lines += 1;
+            }
+        }
+        stats
+    }
+
+    fn count(&mut self, kind: Block) {
+        match kind {
+            Block::Comment => self.comment_lines += 1,
+            Block::Doc => self.doc_lines += 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.code_lines += other.code_lines;
+        self.comment_lines += other.comment_lines;
+        self.doc_lines += other.doc_lines;
+    }
+
+    fn total_lines(&self) -> usize {
+        self.code_lines + self.comment_lines + self.doc_lines
+    }
+
+    fn percentage(&self, lines: usize) -> f64 {
+        match self.total_lines() {
+            0 => 0.0,
+            total => lines as f64 / total as f64 * 100.0,
+        }
+    }
+
+    /// Renders the share of doc and comment lines, e.g. "12.5% docs, 3.1% comments"
+    pub fn format(&self) -> String {
+        format!(
+            "{:.1}% docs, {:.1}% comments ({} doc, {} comment, {} code lines)",
+            self.percentage(self.doc_lines),
+            self.percentage(self.comment_lines),
+            self.doc_lines,
+            self.comment_lines,
+            self.code_lines
+        )
+    }
+}
+
+/// Sums the comment statistics of all files
+pub fn total(files: &[FileStats]) -> CommentStats {
+    let mut total = CommentStats::default();
+    for file in files {
+        total.add(&file.comments);
+    }
+    total
+}
+
+/// Renders the documentation of each directory and file, least documented first
+pub fn report(files: &[FileStats]) -> String {
+    let mut modules: BTreeMap<String, CommentStats> = BTreeMap::new();
+    for file in files {
+        let module = Path::new(&file.path)
+            .parent()
+            .map(|parent| parent.display().to_string())
+            .filter(|parent| !parent.is_empty())
+            .unwrap_or_else(|| ".".to_string());
+        modules.entry(module).or_default().add(&file.comments);
+    }
+    let by_doc_share = |stats: &CommentStats| stats.percentage(stats.doc_lines);
+
+    let mut modules: Vec<_> = modules.into_iter().collect();
+    modules.sort_by(|a, b| by_doc_share(&a.1).total_cmp(&by_doc_share(&b.1)));
+    let mut files: Vec<_> = files.iter().collect();
+    files.sort_by(|a, b| by_doc_share(&a.comments).total_cmp(&by_doc_share(&b.comments)));
+
+    let mut report = String::from("Per-module documentation:\n");
+    for (module, stats) in &modules {
+        let _ = writeln!(report, "  {}: {}", module, stats.format());
+    }
+    report.push_str("\nPer-file documentation:\n");
+    for file in files {
+        let _ = writeln!(report, "  {}: {}", file.path, file.comments.format());
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_comment_stats() {
+        let source = "//! Crate docs\n\n/// Adds\nfn add() {} // trailing\n// note\n/*\n * block\n */\n/** Doc\n * block */\nfn main() {}\n";
+        let stats = CommentStats::measure(source);
+        assert_eq!(
+            stats,
+            CommentStats {
+                code_lines: 2,
+                comment_lines: 4,
+                doc_lines: 4,
+            }
+        );
+        assert_eq!(
+            stats.format(),
+            "40.0% docs, 40.0% comments (4 doc, 4 comment, 2 code lines)"
+        );
+    }
+
+    #[test]
+    fn test_report_least_documented_first() {
+        let files = vec![
+            FileStats::new("api/mod.rs", 10, 5)
+                .with_comments(CommentStats::measure("/// A\nfn a() {}\n")),
+            FileStats::new("lib.rs", 10, 5).with_comments(CommentStats::measure("fn b() {}\n")),
+        ];
+        let report = report(&files);
+        assert!(report.starts_with("Per-module documentation:\n  .: 0.0% docs"));
+        assert!(report.contains("  api: 50.0% docs"));
+        assert!(report.ends_with(
+            "  api/mod.rs: 50.0% docs, 0.0% comments (1 doc, 0 comment, 1 code lines)\n"
+        ));
+        assert_eq!(total(&files).code_lines, 2);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::summary::{signature, SourceFile};
+use std::collections::HashMap;
+use std::fmt;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprCall, ExprMethodCall, ImplItemFn, ItemFn, Lit, LitStr, Signature};
+
+/// HTTP methods recognized in route attributes and router builder calls
+const HTTP_METHODS: [&str; 7] = ["get", "post", "put", "delete", "patch", "head", "options"];
+
+/// An HTTP endpoint of a web service
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    pub method: String,
+    pub path: String,
+    pub handler: String,
+    /// Signature of the handler, if its definition was found
+    pub signature: Option<String>,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} -> ", self.method, self.path)?;
+        match &self.signature {
+            Some(signature) => write!(f, "{}", signature),
+            None => write!(f, "{}", self.handler),
+        }
+    }
+}
+
+#[derive(Default)]
+struct EndpointCollector {
+    endpoints: Vec<Endpoint>,
+    signatures: HashMap<String, String>,
+}
+
+impl EndpointCollector {
+    /// Records a function, and an endpoint if it has a route attribute (actix-web, rocket)
+    fn visit_function(&mut self, attrs: &[syn::Attribute], sig: &Signature) {
+        let handler = sig.ident.to_string();
+        self.signatures
+            .entry(handler.clone())
+            .or_insert_with(|| signature(sig));
+
+        for attr in attrs {
+            let Some(method) = attr.path().segments.last().map(|s| s.ident.to_string()) else {
+                continue;
+            };
+            if !HTTP_METHODS.contains(&method.as_str()) {
+                continue;
+            }
+            let Ok(path) = attr.parse_args_with(|input: syn::parse::ParseStream| {
+                let path: LitStr = input.parse()?;
+                input.parse::<proc_macro2::TokenStream>()?;
+                Ok(path)
+            }) else {
+                continue;
+            };
+            self.endpoints.push(Endpoint {
+                method: method.to_uppercase(),
+                path: path.value(),
+                handler: handler.clone(),
+                signature: None,
+            });
+        }
+    }
+
+    /// Finds the methods and handlers in a route definition such as
+    /// `get(list).post(create)` (axum) or `web::get().to(list)` (actix-web)
+    fn route_handlers(expr: &Expr) -> Vec<(String, Option<String>)> {
+        match expr {
+            Expr::Call(ExprCall { func, args, .. }) => {
+                let Expr::Path(func) = func.as_ref() else {
+                    return Vec::new();
+                };
+                let Some(method) = func.path.segments.last().map(|s| s.ident.to_string()) else {
+                    return Vec::new();
+                };
+                if !HTTP_METHODS.contains(&method.as_str()) {
+                    return Vec::new();
+                }
+                vec![(method, args.first().and_then(Self::handler_name))]
+            }
+            Expr::MethodCall(ExprMethodCall {
+                receiver,
+                method,
+                args,
+                ..
+            }) => {
+                let mut handlers = Self::route_handlers(receiver);
+                let method = method.to_string();
+                if method == "to" {
+                    let handler = args.first().and_then(Self::handler_name);
+                    for (_, missing) in handlers.iter_mut().filter(|(_, h)| h.is_none()) {
+                        missing.clone_from(&handler);
+                    }
+                } else if HTTP_METHODS.contains(&method.as_str()) {
+                    handlers.push((method, args.first().and_then(Self::handler_name)));
+                }
+                handlers
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn handler_name(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for EndpointCollector {
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        self.visit_function(&item.attrs, &item.sig);
+        visit::visit_item_fn(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        self.visit_function(&item.attrs, &item.sig);
+        visit::visit_impl_item_fn(self, item);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        // Router builder calls: `.route("/users", get(list_users))`
+        if call.method == "route" && call.args.len() == 2 {
+            if let Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(path),
+                ..
+            }) = &call.args[0]
+            {
+                for (method, handler) in Self::route_handlers(&call.args[1]) {
+                    self.endpoints.push(Endpoint {
+                        method: method.to_uppercase(),
+                        path: path.value(),
+                        handler: handler.unwrap_or_else(|| "?".to_string()),
+                        signature: None,
+                    });
+                }
+            }
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// Finds the HTTP endpoints defined by route attributes and router builder calls
+pub fn find_endpoints(sources: &[SourceFile]) -> Vec<Endpoint> {
+    let mut collector = EndpointCollector::default();
+    for source in sources {
+        collector.visit_file(&source.ast);
+    }
+
+    let EndpointCollector {
+        mut endpoints,
+        signatures,
+    } = collector;
+    for endpoint in &mut endpoints {
+        endpoint.signature = signatures.get(&endpoint.handler).cloned();
+    }
+    endpoints.sort_by(|a, b| (&a.path, &a.method).cmp(&(&b.path, &b.method)));
+    endpoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn endpoints(source: &str) -> Result<Vec<String>> {
+        let sources = vec![SourceFile {
+            path: "lib.rs".to_string(),
+            ast: syn::parse_str(source)?,
+        }];
+        Ok(find_endpoints(&sources)
+            .iter()
+            .map(Endpoint::to_string)
+            .collect())
+    }
+
+    #[test]
+    fn test_axum_router() -> Result<()> {
+        let source = r#"
+            pub fn router() -> Router {
+                Router::new()
+                    .route("/users", get(list_users).post(create_user))
+                    .route("/users/:id", axum::routing::delete(delete_user))
+            }
+            async fn list_users(State(db): State<Db>) -> Json<Vec<User>> { todo!() }
+            async fn create_user(Json(user): Json<User>) -> StatusCode { todo!() }
+        "#;
+        assert_eq!(
+            endpoints(source)?,
+            vec![
+                "GET /users -> async fn list_users(State(db): State<Db>) -> Json<Vec<User>>",
+                "POST /users -> async fn create_user(Json(user): Json<User>) -> StatusCode",
+                "DELETE /users/:id -> delete_user",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_route_attributes_and_actix_builder() -> Result<()> {
+        let source = r#"
+            #[get("/health")]
+            async fn health() -> impl Responder { "ok" }
+
+            #[post("/items", data = "<item>")]
+            fn create(item: Json<Item>) -> Status { Status::Ok }
+
+            fn config(cfg: &mut web::ServiceConfig) {
+                cfg.route("/items", web::get().to(list));
+            }
+        "#;
+        assert_eq!(
+            endpoints(source)?,
+            vec![
+                "GET /health -> async fn health() -> impl Responder",
+                "GET /items -> list",
+                "POST /items -> fn create(item: Json<Item>) -> Status",
+            ]
+        );
+        Ok(())
+    }
+}
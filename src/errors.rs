@@ -0,0 +1,152 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::process::ExitCode;
+
+/// How errors that end a run are written to stderr
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// The message and its causes, for people
+    #[default]
+    Human,
+    /// One JSON record per error, for automation
+    Json,
+}
+
+/// Category of an error that ends a run, which decides the exit code
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Anything not covered by the other kinds
+    Other,
+    /// A source file isn't valid Rust
+    Parse,
+    /// The output exceeds a size, token, or model context limit
+    Budget,
+    /// Some batch targets failed while others succeeded
+    Partial,
+    /// Reading or writing a file failed
+    Io,
+}
+
+impl ErrorKind {
+    /// Exit code of the process; 2 is left to command-line usage errors
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Parse => 3,
+            ErrorKind::Budget => 4,
+            ErrorKind::Io => 5,
+            ErrorKind::Partial => 6,
+        }
+    }
+
+    /// Classifies an error by the first categorized error in its chain
+    pub fn of(error: &anyhow::Error) -> Self {
+        let chain = || error.chain();
+        if let Some(failure) = chain().find_map(|cause| cause.downcast_ref::<Failure>()) {
+            failure.kind
+        } else if chain().any(|cause| cause.is::<syn::Error>()) {
+            ErrorKind::Parse
+        } else if chain().any(|cause| cause.is::<std::io::Error>()) {
+            ErrorKind::Io
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+/// An error of a kind that can't be told from its cause
+#[derive(Debug)]
+pub struct Failure {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl Failure {
+    pub fn error(kind: ErrorKind, message: impl Into<String>) -> anyhow::Error {
+        Self {
+            kind,
+            message: message.into(),
+        }
+        .into()
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Structured error written to stderr by `--error-format json`
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct ErrorRecord {
+    kind: ErrorKind,
+    exit_code: u8,
+    message: String,
+    /// Messages of the underlying errors, outermost first
+    causes: Vec<String>,
+}
+
+impl ErrorRecord {
+    fn new(error: &anyhow::Error) -> Self {
+        let kind = ErrorKind::of(error);
+        Self {
+            kind,
+            exit_code: kind.exit_code(),
+            message: error.to_string(),
+            causes: error.chain().skip(1).map(ToString::to_string).collect(),
+        }
+    }
+}
+
+/// Renders an error in the given format, without a trailing newline
+pub fn render(error: &anyhow::Error, format: ErrorFormat) -> String {
+    match format {
+        ErrorFormat::Human => format!("Error: {:?}", error),
+        ErrorFormat::Json => serde_json::to_string(&ErrorRecord::new(error))
+            .expect("error records serialize to JSON"),
+    }
+}
+
+/// Writes an error that ends the run to stderr and returns the exit code of its kind
+pub fn report(error: &anyhow::Error, format: ErrorFormat) -> ExitCode {
+    eprintln!("{}", render(error, format));
+    ExitCode::from(ErrorKind::of(error).exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_kinds_and_records() {
+        let parse = syn::parse_file("fn broken(")
+            .map(drop)
+            .context("Failed to parse Rust file")
+            .context("Failed to process file: src/lib.rs")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&parse), ErrorKind::Parse);
+        let io = std::fs::read_to_string("/nonexistent/code-context")
+            .context("Failed to read file")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&io), ErrorKind::Io);
+        let budget = Failure::error(ErrorKind::Budget, "Estimated 9 tokens exceed the limit");
+        assert_eq!(
+            ErrorKind::of(&budget.context("Run failed")),
+            ErrorKind::Budget
+        );
+        assert_eq!(ErrorKind::of(&anyhow::anyhow!("boom")), ErrorKind::Other);
+
+        let record: serde_json::Value =
+            serde_json::from_str(&render(&parse, ErrorFormat::Json)).expect("valid JSON");
+        assert_eq!(record["kind"], "parse");
+        assert_eq!(record["exit_code"], 3);
+        assert_eq!(record["message"], "Failed to process file: src/lib.rs");
+        assert_eq!(record["causes"][0], "Failed to parse Rust file");
+    }
+}
@@ -0,0 +1,299 @@
+use anyhow::{bail, Result};
+
+/// A transformation explained by the `explain` subcommand, with an example that is run
+/// through the real transformation rather than a hand-written result
+pub struct Topic {
+    /// Flag or concept name, without leading dashes
+    pub name: &'static str,
+    /// Options the example is transformed with
+    pub args: &'static [&'static str],
+    pub description: &'static str,
+    pub example: &'static str,
+}
+
+/// Topics in the order `explain` lists them
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "default",
+        args: &[],
+        description: "Without options, code-context keeps every item, signature, body, and \
+doc comment, and only drops regular comments, `#[cfg(test)]` modules, and `#[test]` \
+functions. The code is reprinted, so formatting and blank lines change.",
+        example: r#"/// Adds two numbers
+pub fn add(a: i32, b: i32) -> i32 {
+    // Plain addition
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_add() {
+        assert_eq!(super::add(1, 2), 3);
+    }
+}
+"#,
+    },
+    Topic {
+        name: "no-function-bodies",
+        args: &["--no-function-bodies"],
+        description: "Replaces function bodies with `{}` while keeping the complete \
+signature, doc comments, and attributes. Functions returning a string-like type \
+(`String`, `&str`, `Cow<str>`, or a `Result`/`Option` of one) keep their bodies, as \
+they usually hold messages or serialization formats worth reading. Default trait \
+methods lose their bodies unless `--keep-trait-defaults` is given, and trait methods \
+are annotated as required or default ones so the missing bodies don't hide the \
+difference.",
+        example: r#"pub struct Config {
+    pub name: String,
+}
+
+impl Config {
+    /// Loads the configuration
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        let name = std::fs::read_to_string(path)?;
+        Ok(Config { name })
+    }
+
+    /// Describes the configuration
+    pub fn describe(&self) -> String {
+        format!("config {}", self.name)
+    }
+}
+
+pub trait Named {
+    fn name(&self) -> &str;
+
+    fn len(&self) -> usize {
+        self.name().len()
+    }
+}
+"#,
+    },
+    Topic {
+        name: "no-comments",
+        args: &["--no-comments"],
+        description: "Removes doc comments (`///`, `//!`, and `#[doc]` attributes) on top \
+of the regular comments removed by default. Code and attributes stay unchanged.",
+        example: r#"//! Geometry helpers
+
+/// A point on the plane
+#[derive(Debug)]
+pub struct Point {
+    /// Horizontal position
+    pub x: f64,
+    pub y: f64,
+}
+"#,
+    },
+    Topic {
+        name: "keep-trait-defaults",
+        args: &["--no-function-bodies", "--keep-trait-defaults"],
+        description: "With `--no-function-bodies`, keeps the bodies of default trait \
+methods, which tell implementors what they get for free.",
+        example: r#"pub trait Shape {
+    fn area(&self) -> f64;
+
+    fn is_empty(&self) -> bool {
+        self.area() == 0.0
+    }
+}
+
+impl Shape for f64 {
+    fn area(&self) -> f64 {
+        self * self
+    }
+}
+"#,
+    },
+    Topic {
+        name: "visibility",
+        args: &["--visibility", "pub"],
+        description: "Drops the items below the given visibility: `pub` keeps only public \
+items, `pub(crate)` also keeps crate-internal ones. Impl items are filtered too, while \
+struct fields stay unless `--hide-private-fields` is given.",
+        example: r#"pub struct Cache {
+    pub capacity: usize,
+    entries: Vec<String>,
+}
+
+impl Cache {
+    pub fn new(capacity: usize) -> Self {
+        Cache { capacity, entries: Vec::new() }
+    }
+
+    pub(crate) fn evict(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn helper() {}
+"#,
+    },
+    Topic {
+        name: "hide-private-fields",
+        args: &["--hide-private-fields"],
+        description: "Replaces the non-public fields of structs with a \
+`/* private fields */` marker, as rustdoc shows them, so the struct is visibly \
+incomplete rather than silently smaller.",
+        example: r#"pub struct Cache {
+    pub capacity: usize,
+    entries: Vec<String>,
+    hits: u64,
+}
+"#,
+    },
+    Topic {
+        name: "collapse-impls",
+        args: &["--collapse-impls"],
+        description: "Keeps the first impl of each family of three or more trait impls \
+that differ only in one type, such as the `From` impls generated by a macro, and lists \
+the types of the others in a comment.",
+        example: r#"pub trait Zero {
+    fn zero() -> Self;
+}
+
+impl Zero for u8 {
+    fn zero() -> Self {
+        0
+    }
+}
+
+impl Zero for u16 {
+    fn zero() -> Self {
+        0
+    }
+}
+
+impl Zero for u32 {
+    fn zero() -> Self {
+        0
+    }
+}
+"#,
+    },
+    Topic {
+        name: "skip-trait-impls",
+        args: &["--skip-trait-impls", "Display"],
+        description: "Drops the impls of the listed traits, such as boilerplate `Display` \
+or `From` impls, keeping the types and their inherent impls.",
+        example: r#"pub struct Meters(pub f64);
+
+impl Meters {
+    pub fn new(value: f64) -> Self {
+        Meters(value)
+    }
+}
+
+impl std::fmt::Display for Meters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} m", self.0)
+    }
+}
+"#,
+    },
+    Topic {
+        name: "elide-lifetimes",
+        args: &["--elide-lifetimes"],
+        description: "Removes named lifetimes from function signatures where the elision \
+rules make them implicit.",
+        example: r#"pub fn first_word<'a>(text: &'a str) -> &'a str {
+    text.split(' ').next().unwrap_or(text)
+}
+"#,
+    },
+    Topic {
+        name: "short-std-paths",
+        args: &["--short-std-paths"],
+        description: "Shortens fully qualified standard library paths in signatures to \
+their last segment, which is how they are usually imported. Bodies stay unchanged.",
+        example: r#"pub fn index(words: &[String]) -> std::collections::HashMap<String, usize> {
+    std::collections::HashMap::new()
+}
+"#,
+    },
+];
+
+/// Returns the topic with the given name, with or without the leading dashes of a flag
+pub fn find(name: &str) -> Result<&'static Topic> {
+    let name = name.trim_start_matches('-');
+    match TOPICS.iter().find(|topic| topic.name == name) {
+        Some(topic) => Ok(topic),
+        None => bail!("No explanation for {}; topics:\n{}", name, list()),
+    }
+}
+
+/// Lists the topic names, one per line
+pub fn list() -> String {
+    TOPICS
+        .iter()
+        .map(|topic| format!("  {}\n", topic.name))
+        .collect()
+}
+
+/// Renders a topic, transforming its example with `transform`, which gets the topic's
+/// options and the example source
+pub fn explain(
+    topic: &Topic,
+    transform: impl Fn(&[&str], &str) -> Result<String>,
+) -> Result<String> {
+    let after = transform(topic.args, topic.example)?;
+    let command = std::iter::once("code-context")
+        .chain(topic.args.iter().copied())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(format!(
+        "{}\n\n{}\n\nBefore:\n\n{}\nAfter `{}`:\n\n{}",
+        topic.name,
+        wrap(topic.description, 80),
+        indent(topic.example),
+        command,
+        indent(&after)
+    ))
+}
+
+fn indent(code: &str) -> String {
+    code.lines()
+        .map(|line| match line {
+            "" => "\n".to_string(),
+            line => format!("    {}\n", line),
+        })
+        .collect()
+}
+
+fn wrap(text: &str, width: usize) -> String {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let line = lines.last_mut().expect("at least one line");
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(word.to_string());
+        } else {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_and_render_topic() -> Result<()> {
+        let topic = find("--no-comments")?;
+        assert_eq!(topic.name, "no-comments");
+        assert!(find("no-such-flag").is_err());
+
+        let rendered = explain(topic, |args, source| {
+            assert_eq!(args, ["--no-comments"]);
+            Ok(source.replace("/// Horizontal position\n", ""))
+        })?;
+        assert!(rendered.starts_with("no-comments\n\nRemoves doc comments"));
+        assert!(rendered.contains("After `code-context --no-comments`:\n\n    //! Geometry"));
+        assert_eq!(wrap("a bb ccc", 4), "a bb\nccc");
+        Ok(())
+    }
+}
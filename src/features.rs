@@ -0,0 +1,338 @@
+use crate::module_path::ModulePath;
+use crate::summary::{type_string, SourceFile};
+use quote::ToTokens;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, ImplItem, Item, Meta, Visibility};
+
+/// A public item that is only compiled with certain cargo features
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GatedItem {
+    pub item: String,
+    pub path: String,
+    pub requirement: String,
+}
+
+impl std::fmt::Display for GatedItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.item, self.path, self.requirement)
+    }
+}
+
+/// Renders a cfg predicate, e.g. `json + !std` for `all(feature = "json", not(feature = "std"))`
+fn render_predicate(meta: &Meta) -> String {
+    match meta {
+        Meta::NameValue(name_value) if name_value.path.is_ident("feature") => {
+            match &name_value.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(feature),
+                    ..
+                }) => feature.value(),
+                value => value.to_token_stream().to_string(),
+            }
+        }
+        Meta::List(list)
+            if ["all", "any", "not"]
+                .iter()
+                .any(|op| list.path.is_ident(op)) =>
+        {
+            let Ok(predicates) =
+                list.parse_args_with(Punctuated::<Meta, syn::Token![,]>::parse_terminated)
+            else {
+                return list.to_token_stream().to_string();
+            };
+            let rendered: Vec<String> = predicates
+                .iter()
+                .map(|predicate| match predicate {
+                    Meta::List(inner) if !inner.path.is_ident("not") => {
+                        format!("({})", render_predicate(predicate))
+                    }
+                    _ => render_predicate(predicate),
+                })
+                .collect();
+            if list.path.is_ident("not") {
+                format!("!{}", rendered.join(""))
+            } else if list.path.is_ident("all") {
+                rendered.join(" + ")
+            } else {
+                rendered.join(" | ")
+            }
+        }
+        _ => meta.to_token_stream().to_string().replace(" = ", "="),
+    }
+}
+
+fn mentions_feature(tokens: proc_macro2::TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => ident == "feature",
+        proc_macro2::TokenTree::Group(group) => mentions_feature(group.stream()),
+        _ => false,
+    })
+}
+
+/// Returns the feature requirements of the `#[cfg(...)]` attributes that mention features
+fn feature_requirements(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .filter_map(|attr| attr.parse_args::<Meta>().ok())
+        .filter(|meta| mentions_feature(meta.to_token_stream()))
+        .map(|meta| render_predicate(&meta))
+        .collect()
+}
+
+fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// Returns the name of a public item, e.g. `struct Config`
+fn item_name(item: &Item) -> Option<String> {
+    let (kind, ident, vis) = match item {
+        Item::Fn(item) => ("fn", &item.sig.ident, &item.vis),
+        Item::Struct(item) => ("struct", &item.ident, &item.vis),
+        Item::Enum(item) => ("enum", &item.ident, &item.vis),
+        Item::Union(item) => ("union", &item.ident, &item.vis),
+        Item::Trait(item) => ("trait", &item.ident, &item.vis),
+        Item::Type(item) => ("type", &item.ident, &item.vis),
+        Item::Const(item) => ("const", &item.ident, &item.vis),
+        Item::Static(item) => ("static", &item.ident, &item.vis),
+        Item::Mod(item) => ("mod", &item.ident, &item.vis),
+        Item::Macro(item) => {
+            let exported = item
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("macro_export"));
+            return item
+                .ident
+                .as_ref()
+                .filter(|_| exported)
+                .map(|ident| format!("macro {}!", ident));
+        }
+        _ => return None,
+    };
+    is_public(vis).then(|| format!("{} {}", kind, ident))
+}
+
+pub fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+/// Collects the gated items of one file and the gates of its out-of-line modules
+struct FileScan<'a> {
+    path: &'a str,
+    items: Vec<GatedItem>,
+    /// Candidate files of `mod name;` declarations with the requirements in force there
+    children: Vec<(PathBuf, Vec<String>)>,
+}
+
+impl FileScan<'_> {
+    fn scan(&mut self, items: &[Item], requirements: &[String], inline: &mut Vec<String>) {
+        for item in items {
+            let mut requirements = requirements.to_vec();
+            requirements.extend(feature_requirements(item_attrs(item)));
+
+            if !requirements.is_empty() {
+                if let Some(name) = item_name(item) {
+                    self.push(name, &requirements);
+                }
+            }
+
+            match item {
+                Item::Mod(item_mod) => match &item_mod.content {
+                    Some((_, items)) => {
+                        inline.push(item_mod.ident.to_string());
+                        self.scan(items, &requirements, inline);
+                        inline.pop();
+                    }
+                    None if !requirements.is_empty() => {
+                        let mut dir = ModulePath::new(Path::new(self.path)).child_dir();
+                        dir.extend(inline.iter());
+                        let name = item_mod.ident.to_string();
+                        for child in [
+                            dir.join(format!("{}.rs", name)),
+                            dir.join(name).join("mod.rs"),
+                        ] {
+                            self.children.push((child, requirements.clone()));
+                        }
+                    }
+                    None => {}
+                },
+                Item::Impl(item_impl) => {
+                    let self_ty = type_string(&item_impl.self_ty);
+                    if let Some((_, trait_path, _)) = &item_impl.trait_ {
+                        if !requirements.is_empty() {
+                            let trait_name = type_string(trait_path);
+                            self.push(
+                                format!("impl {} for {}", trait_name, self_ty),
+                                &requirements,
+                            );
+                        }
+                        continue;
+                    }
+                    for impl_item in &item_impl.items {
+                        let ImplItem::Fn(method) = impl_item else {
+                            continue;
+                        };
+                        let mut requirements = requirements.clone();
+                        requirements.extend(feature_requirements(&method.attrs));
+                        if !requirements.is_empty() && is_public(&method.vis) {
+                            self.push(
+                                format!("fn {}::{}", self_ty, method.sig.ident),
+                                &requirements,
+                            );
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn push(&mut self, item: String, requirements: &[String]) {
+        let requirement = if requirements.len() == 1 {
+            requirements[0].clone()
+        } else {
+            requirements
+                .iter()
+                .map(|requirement| {
+                    if requirement.contains(' ') {
+                        format!("({})", requirement)
+                    } else {
+                        requirement.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" + ")
+        };
+        self.items.push(GatedItem {
+            item,
+            path: self.path.to_string(),
+            requirement,
+        });
+    }
+}
+
+/// Returns the requirements inherited by a file from the `mod` declarations that load it
+fn inherited_requirements(
+    path: &Path,
+    gates: &HashMap<PathBuf, Vec<String>>,
+    declared_in: &HashMap<PathBuf, PathBuf>,
+    depth: usize,
+) -> Vec<String> {
+    let mut requirements = match declared_in.get(path) {
+        Some(parent) if depth < 32 => inherited_requirements(parent, gates, declared_in, depth + 1),
+        _ => Vec::new(),
+    };
+    requirements.extend(gates.get(path).cloned().unwrap_or_default());
+    requirements
+}
+
+/// Maps the public items of the crate to the cargo features required to enable them,
+/// following `#[cfg(feature = "...")]` on items, inline modules, and `mod` declarations
+pub fn find_gated_items(sources: &[SourceFile]) -> Vec<GatedItem> {
+    let mut gates = HashMap::new();
+    let mut declared_in = HashMap::new();
+    for source in sources {
+        let mut scan = FileScan {
+            path: &source.path,
+            items: Vec::new(),
+            children: Vec::new(),
+        };
+        scan.scan(&source.ast.items, &[], &mut Vec::new());
+        for (child, requirements) in scan.children {
+            declared_in.insert(child.clone(), PathBuf::from(&source.path));
+            gates.insert(child, requirements);
+        }
+    }
+
+    let mut items = Vec::new();
+    for source in sources {
+        let mut requirements =
+            inherited_requirements(Path::new(&source.path), &gates, &declared_in, 0);
+        requirements.extend(feature_requirements(&source.ast.attrs));
+        let mut scan = FileScan {
+            path: &source.path,
+            items: Vec::new(),
+            children: Vec::new(),
+        };
+        scan.scan(&source.ast.items, &requirements, &mut Vec::new());
+        items.extend(scan.items);
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn source(path: &str, code: &str) -> Result<SourceFile> {
+        Ok(SourceFile {
+            path: path.to_string(),
+            ast: syn::parse_str(code)?,
+        })
+    }
+
+    #[test]
+    fn test_find_gated_items() -> Result<()> {
+        let sources = vec![
+            source(
+                "lib.rs",
+                r#"
+                pub fn always() {}
+                #[cfg(feature = "json")]
+                pub fn to_json() {}
+                #[cfg(all(feature = "json", not(feature = "std")))]
+                pub struct Compact;
+                #[cfg(any(feature = "tokio", feature = "async-std"))]
+                pub mod runtime {
+                    pub fn spawn() {}
+                    fn private() {}
+                }
+                #[cfg(feature = "serde")]
+                impl Serialize for Config {}
+                impl Config {
+                    #[cfg(feature = "yaml")]
+                    pub fn to_yaml(&self) {}
+                }
+                #[cfg(feature = "cli")]
+                pub mod cli;
+                "#,
+            )?,
+            source("cli.rs", "pub struct Args;")?,
+        ];
+        let lines: Vec<String> = find_gated_items(&sources)
+            .iter()
+            .map(GatedItem::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "fn to_json (lib.rs): json",
+                "struct Compact (lib.rs): json + !std",
+                "mod runtime (lib.rs): tokio | async-std",
+                "fn spawn (lib.rs): tokio | async-std",
+                "impl Serialize for Config (lib.rs): serde",
+                "fn Config::to_yaml (lib.rs): yaml",
+                "mod cli (lib.rs): cli",
+                "struct Args (cli.rs): cli",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use crate::processor::ProcessingStats;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Files a run processed with the hashes of their content, recorded by `--record-fileset`
+/// and replayed by `--fileset` to process the same selection later
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileSet {
+    /// Content hash of each file, by path relative to the input
+    files: BTreeMap<String, String>,
+}
+
+impl FileSet {
+    /// Collects the processed files of a run
+    pub fn from_stats(stats: &ProcessingStats) -> Self {
+        let files = stats
+            .files
+            .iter()
+            .map(|file| {
+                let hash = file.source_hash.clone().unwrap_or_default();
+                (file.path.clone(), hash)
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Loads a file set of `<hash>  <path>` lines; blank lines and `#` comments are ignored
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file set: {}", path.display()))?;
+        let mut files = BTreeMap::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (hash, file) = line.split_once("  ").with_context(|| {
+                format!(
+                    "Invalid file set line {} in {}: expected `<hash>  <path>`",
+                    number + 1,
+                    path.display()
+                )
+            })?;
+            files.insert(file.to_string(), hash.to_string());
+        }
+        Ok(Self { files })
+    }
+
+    /// Writes one `<hash>  <path>` line per file, sorted by path
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut content = format!(
+            "# Files processed by code-context {}, with their content hashes\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        for (file, hash) in &self.files {
+            content.push_str(&format!("{}  {}\n", hash, file));
+        }
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write file set: {}", path.display()))
+    }
+
+    /// Checks if a file, by its path relative to the input, is part of the set
+    pub fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Compares the set with the files a replaying run processed, failing if any file of the
+    /// set is gone; returns the files whose content changed since the set was recorded
+    pub fn verify(&self, stats: &ProcessingStats) -> Result<Vec<String>> {
+        let processed = Self::from_stats(stats);
+        let missing: Vec<&str> = self
+            .files
+            .keys()
+            .filter(|file| !processed.files.contains_key(*file))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Files of the file set were not processed: {}",
+                missing.join(", ")
+            );
+        }
+        Ok(self
+            .files
+            .iter()
+            .filter(|(file, hash)| processed.files.get(*file) != Some(*hash))
+            .map(|(file, _)| file.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::FileStats;
+    use crate::update::content_hash;
+    use tempfile::TempDir;
+
+    fn stats(files: &[(&str, &str)]) -> ProcessingStats {
+        let mut stats = ProcessingStats::default();
+        for (path, content) in files {
+            stats.record(FileStats::new(*path, 10, 5).with_source_hash(content_hash(content)));
+        }
+        stats
+    }
+
+    #[test]
+    fn test_record_and_replay() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("fileset.txt");
+        let recorded = FileSet::from_stats(&stats(&[("src/lib.rs", "a"), ("src/api.rs", "b")]));
+        recorded.write(&path)?;
+        let loaded = FileSet::load(&path)?;
+        assert_eq!(loaded, recorded);
+        assert!(loaded.contains("src/api.rs"));
+        assert!(!loaded.contains("src/new.rs"));
+
+        let changed = stats(&[("src/lib.rs", "a"), ("src/api.rs", "edited")]);
+        assert_eq!(loaded.verify(&changed)?, ["src/api.rs"]);
+        assert!(loaded.verify(&stats(&[("src/lib.rs", "a")])).is_err());
+
+        std::fs::write(&path, "not a file set\n")?;
+        assert!(FileSet::load(&path).is_err());
+        Ok(())
+    }
+}
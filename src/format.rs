@@ -0,0 +1,197 @@
+use crate::cargo;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Names of rustfmt configuration files, in lookup order
+const RUSTFMT_CONFIG_FILES: [&str; 2] = ["rustfmt.toml", ".rustfmt.toml"];
+
+/// Names of toolchain files, in lookup order
+const TOOLCHAIN_FILES: [&str; 2] = ["rust-toolchain.toml", "rust-toolchain"];
+
+/// Edition used when neither the rustfmt config nor the crate's manifest sets one
+const DEFAULT_EDITION: &str = "2021";
+
+/// Formatter used to print the transformed code
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Formatter {
+    /// Built-in prettyplease printer
+    #[default]
+    Prettyplease,
+    /// The project's rustfmt, respecting its rustfmt.toml
+    Rustfmt,
+}
+
+/// Finds the nearest rustfmt config file in the given directory or its ancestors
+pub fn find_rustfmt_config(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(|dir| {
+        RUSTFMT_CONFIG_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Returns the toolchain pinned by the nearest `rust-toolchain.toml` or legacy
+/// `rust-toolchain` file in the given directory or its ancestors
+pub fn find_toolchain(start: &Path) -> Option<String> {
+    let path = start.ancestors().find_map(|dir| {
+        TOOLCHAIN_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })?;
+    let content = std::fs::read_to_string(path).ok()?;
+    match content.parse::<toml::Table>() {
+        Ok(table) => table
+            .get("toolchain")?
+            .get("channel")?
+            .as_str()
+            .map(str::to_string),
+        // Legacy files may hold just the channel name
+        Err(_) => Some(content.trim().to_string()).filter(|channel| !channel.is_empty()),
+    }
+}
+
+/// Returns the edition to pass to rustfmt: none if the rustfmt config sets one, else that of
+/// the crate containing `source_path`, as `cargo fmt` does
+fn edition(source_path: &Path, config: Option<&Path>) -> Option<String> {
+    let config_edition = config
+        .and_then(|config| std::fs::read_to_string(config).ok())
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .is_some_and(|table| table.contains_key("edition"));
+    if config_edition {
+        return None;
+    }
+    Some(
+        cargo::package_field(source_path, "edition").unwrap_or_else(|| DEFAULT_EDITION.to_string()),
+    )
+}
+
+/// Formats source code with rustfmt, using the config file nearest to `source_path` and the
+/// crate's edition and pinned toolchain, so the output matches what `cargo fmt` produces
+pub fn rustfmt(source: &str, source_path: &Path) -> Result<String> {
+    let dir = source_path.parent();
+    let config = dir.and_then(find_rustfmt_config);
+    let mut command = Command::new("rustfmt");
+    command.args(["--emit", "stdout"]);
+    if let Some(edition) = edition(source_path, config.as_deref()) {
+        command.arg("--edition").arg(edition);
+    }
+    if let Some(config) = config {
+        command.arg("--config-path").arg(config);
+    }
+    // Picked up by the rustup proxy; a rustfmt installed without rustup ignores it
+    if let Some(toolchain) = dir.and_then(find_toolchain) {
+        command.env("RUSTUP_TOOLCHAIN", toolchain);
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run rustfmt. Is it installed?")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open rustfmt stdin")?
+        .write_all(source.as_bytes())
+        .context("Failed to write to rustfmt")?;
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for rustfmt")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "rustfmt failed for {}: {}",
+            source_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).context("rustfmt produced invalid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    fn rustfmt_available() -> bool {
+        Command::new("rustfmt").arg("--version").output().is_ok()
+    }
+
+    #[test]
+    fn test_find_rustfmt_config() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let nested = temp_dir.path().join("src/api");
+        std::fs::create_dir_all(&nested)?;
+        assert_eq!(find_rustfmt_config(&nested), None);
+
+        std::fs::write(temp_dir.path().join(".rustfmt.toml"), "max_width = 40")?;
+        assert_eq!(
+            find_rustfmt_config(&nested),
+            Some(temp_dir.path().join(".rustfmt.toml"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_style_settings() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source_path = temp_dir.path().join("src/lib.rs");
+        std::fs::create_dir_all(temp_dir.path().join("src"))?;
+        assert_eq!(find_toolchain(&temp_dir.path().join("src")), None);
+        assert_eq!(edition(&source_path, None).as_deref(), Some("2021"));
+
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nedition = \"2024\"\n",
+        )?;
+        std::fs::write(
+            temp_dir.path().join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.82.0\"\ncomponents = [\"rustfmt\"]\n",
+        )?;
+        assert_eq!(edition(&source_path, None).as_deref(), Some("2024"));
+        assert_eq!(
+            find_toolchain(&temp_dir.path().join("src")).as_deref(),
+            Some("1.82.0")
+        );
+
+        // A config setting the edition overrides the manifest's
+        let config = temp_dir.path().join("rustfmt.toml");
+        std::fs::write(&config, "edition = \"2018\"\nmax_width = 80\n")?;
+        assert_eq!(edition(&source_path, Some(&config)), None);
+
+        std::fs::remove_file(temp_dir.path().join("rust-toolchain.toml"))?;
+        std::fs::write(
+            temp_dir.path().join("rust-toolchain"),
+            "nightly-2024-06-01\n",
+        )?;
+        assert_eq!(
+            find_toolchain(temp_dir.path()).as_deref(),
+            Some("nightly-2024-06-01")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rustfmt_respects_config() -> Result<()> {
+        if !rustfmt_available() {
+            return Ok(());
+        }
+        let temp_dir = TempDir::new()?;
+        std::fs::write(temp_dir.path().join("rustfmt.toml"), "max_width = 40")?;
+        let source_path = temp_dir.path().join("lib.rs");
+
+        let output = rustfmt("fn f(alpha: u32, beta: u32, gamma: u32) {}", &source_path)?;
+        assert_eq!(
+            output,
+            "fn f(\n    alpha: u32,\n    beta: u32,\n    gamma: u32,\n) {\n}\n"
+        );
+        Ok(())
+    }
+}
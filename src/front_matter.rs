@@ -0,0 +1,156 @@
+use crate::git::{CommitInfo, GitHistory};
+use crate::paths;
+use crate::processor::estimate_tokens;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::path::Path;
+use syn::visit::{self, Visit};
+
+/// Style of the metadata block emitted before each file
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrontMatter {
+    /// Structured `// key: value` comment header
+    Comment,
+    /// YAML front matter delimited by `---`, as used by Markdown chunkers
+    Yaml,
+}
+
+/// Metadata of a processed file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// Path relative to the input directory
+    pub path: String,
+    pub module: Option<String>,
+    /// Number of items of each kind in the output, e.g. `fn` or `struct`
+    pub items: BTreeMap<&'static str, usize>,
+    pub tokens: usize,
+    pub commit: Option<CommitInfo>,
+}
+
+#[derive(Default)]
+struct ItemCounter {
+    items: BTreeMap<&'static str, usize>,
+}
+
+impl ItemCounter {
+    fn count(&mut self, kind: &'static str) {
+        *self.items.entry(kind).or_default() += 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for ItemCounter {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        match item {
+            syn::Item::Fn(_) => self.count("fn"),
+            syn::Item::Struct(_) => self.count("struct"),
+            syn::Item::Enum(_) => self.count("enum"),
+            syn::Item::Union(_) => self.count("union"),
+            syn::Item::Trait(_) => self.count("trait"),
+            syn::Item::Impl(_) => self.count("impl"),
+            syn::Item::Type(_) => self.count("type"),
+            syn::Item::Const(_) => self.count("const"),
+            syn::Item::Static(_) => self.count("static"),
+            syn::Item::Macro(item) if item.ident.is_some() => self.count("macro"),
+            _ => {}
+        }
+        visit::visit_item(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        self.count("fn");
+        visit::visit_impl_item_fn(self, item);
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast syn::TraitItemFn) {
+        self.count("fn");
+        visit::visit_trait_item_fn(self, item);
+    }
+}
+
+impl FileMetadata {
+    /// Collects the metadata of a file from its source path and processed output
+    pub fn collect(source: &Path, relative: &Path, module: Option<&str>, output: &str) -> Self {
+        let mut counter = ItemCounter::default();
+        if let Ok(file) = syn::parse_file(output) {
+            counter.visit_file(&file);
+        }
+        Self {
+            path: paths::slash(relative),
+            module: module.map(str::to_string),
+            items: counter.items,
+            tokens: estimate_tokens(output.len()),
+            commit: GitHistory::discover(source).and_then(|history| history.last_commit(source)),
+        }
+    }
+
+    /// Renders the metadata block, ending with a blank line
+    pub fn render(&self, style: FrontMatter) -> String {
+        // JSON strings are valid YAML scalars
+        let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+        let mut block = String::new();
+        match style {
+            FrontMatter::Comment => {
+                block.push_str(&format!("// path: {}\n", self.path));
+                if let Some(module) = &self.module {
+                    block.push_str(&format!("// module: {}\n", module));
+                }
+                let items: Vec<String> = self
+                    .items
+                    .iter()
+                    .map(|(kind, count)| format!("{}={}", kind, count))
+                    .collect();
+                block.push_str(&format!("// items: {}\n", items.join(", ")));
+                block.push_str(&format!("// tokens: {}\n", self.tokens));
+                if let Some(commit) = &self.commit {
+                    block.push_str(&format!("// commit: {} ({})\n", commit.hash, commit.date));
+                }
+            }
+            FrontMatter::Yaml => {
+                block.push_str("---\n");
+                block.push_str(&format!("path: {}\n", quote(&self.path)));
+                if let Some(module) = &self.module {
+                    block.push_str(&format!("module: {}\n", quote(module)));
+                }
+                if self.items.is_empty() {
+                    block.push_str("items: {}\n");
+                } else {
+                    block.push_str("items:\n");
+                    for (kind, count) in &self.items {
+                        block.push_str(&format!("  {}: {}\n", kind, count));
+                    }
+                }
+                block.push_str(&format!("tokens: {}\n", self.tokens));
+                if let Some(commit) = &self.commit {
+                    block.push_str(&format!("commit: {}\n", quote(&commit.hash)));
+                    block.push_str(&format!("commit_date: {}\n", quote(&commit.date)));
+                }
+                block.push_str("---\n");
+            }
+        }
+        block.push('\n');
+        block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_front_matter() {
+        let metadata = FileMetadata::collect(
+            Path::new("/nonexistent/src/api.rs"),
+            Path::new("api.rs"),
+            Some("crate::api"),
+            "pub struct Client;\nimpl Client {\n    pub fn get(&self) {}\n}\npub fn connect() {}\n",
+        );
+        assert_eq!(
+            metadata.render(FrontMatter::Comment),
+            "// path: api.rs\n// module: crate::api\n// items: fn=2, impl=1, struct=1\n// tokens: 20\n\n"
+        );
+        assert_eq!(
+            metadata.render(FrontMatter::Yaml),
+            "---\npath: \"api.rs\"\nmodule: \"crate::api\"\nitems:\n  fn: 2\n  impl: 1\n  struct: 1\ntokens: 20\n---\n\n"
+        );
+    }
+}
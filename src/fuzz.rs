@@ -0,0 +1,53 @@
+use crate::preserve::PreservingTransformer;
+use crate::transformer::{CodeTransformer, RustAnalyzer};
+use crate::visibility::VisibilityLevel;
+use anyhow::Result;
+use syn::visit_mut::VisitMut;
+
+/// Option sets every input is transformed with: the defaults, and the stripping that
+/// changes the syntax tree the most
+const PASSES: [(bool, bool, VisibilityLevel); 2] = [
+    (false, false, VisibilityLevel::All),
+    (true, true, VisibilityLevel::Pub),
+];
+
+/// Parses arbitrary source text and transforms it with both transformers in every pass,
+/// returning an error for input that isn't valid Rust; this is the entry point of the fuzz
+/// targets in `fuzz/`, for which any panic is a bug
+pub fn transform(source: &str) -> Result<String> {
+    let analyzer = RustAnalyzer::new(source)?;
+    let skipped = ["Debug".to_string()];
+    let mut output = String::new();
+    for (no_comments, no_function_bodies, visibility) in PASSES {
+        let mut ast = analyzer.ast.clone();
+        let mut transformer = CodeTransformer::new(no_comments, no_function_bodies)
+            .with_visibility(visibility)
+            .with_hide_private_fields(no_function_bodies)
+            .with_skipped_trait_impls(&skipped);
+        transformer.visit_file_mut(&mut ast);
+        output += &transformer.finish(&prettyplease::unparse(&ast));
+
+        let (preserved, _) = PreservingTransformer::new(source, no_comments, no_function_bodies)
+            .with_visibility(visibility)
+            .with_hide_private_fields(no_function_bodies)
+            .with_skipped_trait_impls(&skipped)
+            .transform(&analyzer.ast);
+        output += &preserved;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform() -> Result<()> {
+        assert!(transform("fn broken(").is_err());
+        let output = transform(
+            "/// Docs\npub struct A { pub x: u8, y: u8 }\n#[derive(Debug)]\nmod m { fn f() -> u8 { 1 } }\n",
+        )?;
+        assert!(output.contains("/* private fields */"));
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use crate::summary::type_string;
+use git2::{Commit, Oid, Repository, Time};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::Item;
+
+/// A commit that changed a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// Abbreviated commit hash
+    pub hash: String,
+    pub author: String,
+    /// Commit date as `YYYY-MM-DD` (UTC)
+    pub date: String,
+}
+
+impl CommitInfo {
+    fn new(commit: &Commit) -> Self {
+        Self {
+            hash: commit.id().to_string().chars().take(8).collect(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+            date: format_date(commit.time()),
+        }
+    }
+}
+
+impl fmt::Display for CommitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} by {} on {}", self.hash, self.author, self.date)
+    }
+}
+
+/// Formats a git timestamp as a UTC date
+fn format_date(time: Time) -> String {
+    format_unix_date(time.seconds())
+}
+
+/// Formats seconds since the Unix epoch as a `YYYY-MM-DD` UTC date
+pub fn format_unix_date(seconds: i64) -> String {
+    // Civil date from days since the Unix epoch (Howard Hinnant's algorithm)
+    let days = seconds.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Git history of the repository containing a path
+pub struct GitHistory {
+    repo: Repository,
+}
+
+impl GitHistory {
+    /// Opens the repository containing the path, if any
+    pub fn discover(path: &Path) -> Option<Self> {
+        Repository::discover(path).ok().map(|repo| Self { repo })
+    }
+
+    /// Abbreviated hash of the commit checked out at HEAD
+    pub fn head_hash(&self) -> Option<String> {
+        let commit = self.repo.head().ok()?.peel_to_commit().ok()?;
+        Some(CommitInfo::new(&commit).hash)
+    }
+
+    /// Checks if tracked files differ from HEAD, in the index or the working directory
+    pub fn has_changes(&self) -> bool {
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+        self.repo
+            .statuses(Some(&mut options))
+            .is_ok_and(|statuses| !statuses.is_empty())
+    }
+
+    /// Path of a file relative to the repository's working directory
+    pub fn relative_path(&self, path: &Path) -> Option<PathBuf> {
+        let workdir = std::fs::canonicalize(self.repo.workdir()?).ok()?;
+        let path = std::fs::canonicalize(path).ok()?;
+        path.strip_prefix(workdir).ok().map(Path::to_path_buf)
+    }
+
+    /// Returns the last commit reachable from HEAD that changed the file
+    pub fn last_commit(&self, path: &Path) -> Option<CommitInfo> {
+        let relative = self.relative_path(path)?;
+        let relative = relative.as_path();
+
+        let mut revwalk = self.repo.revwalk().ok()?;
+        revwalk.push_head().ok()?;
+        for oid in revwalk.filter_map(Result::ok) {
+            let commit = self.repo.find_commit(oid).ok()?;
+            let blob = commit.tree().ok()?.get_path(relative).ok().map(|e| e.id());
+            let Some(blob) = blob else {
+                continue;
+            };
+            let unchanged = commit.parents().any(|parent| {
+                parent
+                    .tree()
+                    .ok()
+                    .and_then(|tree| tree.get_path(relative).ok())
+                    .is_some_and(|entry| entry.id() == blob)
+            });
+            if !unchanged {
+                return Some(CommitInfo::new(&commit));
+            }
+        }
+        None
+    }
+
+    /// Counts the commits reachable from HEAD since the given Unix time that changed
+    /// each file, keyed by path relative to the working directory
+    pub fn churn(&self, since: i64) -> HashMap<PathBuf, usize> {
+        let mut churn = HashMap::new();
+        let Ok(mut revwalk) = self.repo.revwalk() else {
+            return churn;
+        };
+        if revwalk.push_head().is_err() {
+            return churn;
+        }
+        for oid in revwalk.filter_map(Result::ok) {
+            let Ok(commit) = self.repo.find_commit(oid) else {
+                continue;
+            };
+            if commit.time().seconds() < since {
+                continue;
+            }
+            let parent_tree = commit.parent(0).ok().and_then(|parent| parent.tree().ok());
+            let Ok(tree) = commit.tree() else {
+                continue;
+            };
+            let Ok(diff) = self
+                .repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+            else {
+                continue;
+            };
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path() {
+                    *churn.entry(path.to_path_buf()).or_default() += 1;
+                }
+            }
+        }
+        churn
+    }
+
+    /// Returns the most recent commit of each 1-based, inclusive line range of the file
+    /// as of HEAD, or `None` for ranges without committed lines
+    pub fn blame_ranges(&self, path: &Path, ranges: &[(usize, usize)]) -> Vec<Option<CommitInfo>> {
+        let blame = self
+            .relative_path(path)
+            .and_then(|relative| self.repo.blame_file(&relative, None).ok());
+        let Some(blame) = blame else {
+            return vec![None; ranges.len()];
+        };
+
+        let mut commits: HashMap<Oid, Option<Commit>> = HashMap::new();
+        ranges
+            .iter()
+            .map(|&(start, end)| {
+                let latest = (start..=end)
+                    .filter_map(|line| blame.get_line(line))
+                    .map(|hunk| hunk.final_commit_id())
+                    .filter(|oid| !oid.is_zero())
+                    .filter_map(|oid| {
+                        commits
+                            .entry(oid)
+                            .or_insert_with(|| self.repo.find_commit(oid).ok())
+                            .clone()
+                    })
+                    .max_by_key(|commit| commit.time().seconds());
+                latest.map(|commit| CommitInfo::new(&commit))
+            })
+            .collect()
+    }
+}
+
+/// Short label of an item, e.g. `fn load` or `impl Display for Config`
+pub fn item_label(item: &Item) -> Option<String> {
+    let label = match item {
+        Item::Fn(item) => format!("fn {}", item.sig.ident),
+        Item::Struct(item) => format!("struct {}", item.ident),
+        Item::Enum(item) => format!("enum {}", item.ident),
+        Item::Union(item) => format!("union {}", item.ident),
+        Item::Trait(item) => format!("trait {}", item.ident),
+        Item::Type(item) => format!("type {}", item.ident),
+        Item::Const(item) => format!("const {}", item.ident),
+        Item::Static(item) => format!("static {}", item.ident),
+        Item::Mod(item) => format!("mod {}", item.ident),
+        Item::Macro(item) => format!("macro {}!", item.ident.as_ref()?),
+        Item::Impl(item) => match &item.trait_ {
+            Some((_, path, _)) => format!(
+                "impl {} for {}",
+                type_string(path),
+                type_string(&item.self_ty)
+            ),
+            None => format!("impl {}", type_string(&item.self_ty)),
+        },
+        _ => return None,
+    };
+    Some(label)
+}
+
+/// Renders the header annotation of a file with its last commit and, optionally,
+/// the most recent commit of each top-level item
+pub fn annotation(source: &Path, content: &str, blame: bool) -> String {
+    let Some(history) = GitHistory::discover(source) else {
+        return String::new();
+    };
+    let mut annotation = String::new();
+    if let Some(commit) = history.last_commit(source) {
+        annotation.push_str(&format!("// Last commit: {}\n", commit));
+    }
+    if !blame {
+        return annotation;
+    }
+
+    let Ok(file) = syn::parse_file(content) else {
+        return annotation;
+    };
+    let items: Vec<(String, (usize, usize))> = file
+        .items
+        .iter()
+        .filter_map(|item| {
+            let span = item.span();
+            Some((item_label(item)?, (span.start().line, span.end().line)))
+        })
+        .collect();
+    let ranges: Vec<_> = items.iter().map(|(_, range)| *range).collect();
+    let commits = history.blame_ranges(source, &ranges);
+    let lines: Vec<String> = items
+        .iter()
+        .zip(commits)
+        .filter_map(|((label, _), commit)| Some(format!("//   {}: {}\n", label, commit?)))
+        .collect();
+    if !lines.is_empty() {
+        annotation.push_str("// Blame:\n");
+        annotation.extend(lines);
+    }
+    annotation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(Time::new(0, 0)), "1970-01-01");
+        assert_eq!(format_date(Time::new(1_709_208_000, 0)), "2024-02-29");
+    }
+
+    #[test]
+    fn test_last_commit() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo = Repository::init(temp_dir.path())?;
+        let signature = git2::Signature::new("Jane Doe", "jane@example.com", &Time::new(0, 0))?;
+        let commit = |file: &str, content: &str, message: &str| -> Result<git2::Oid> {
+            std::fs::write(temp_dir.path().join(file), content)?;
+            let mut index = repo.index()?;
+            index.add_path(Path::new(file))?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            index.write()?;
+            let parents: Vec<_> = repo
+                .head()
+                .ok()
+                .and_then(|head| head.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parents: Vec<_> = parents.iter().collect();
+            Ok(repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )?)
+        };
+        let first = commit("lib.rs", "pub fn a() {}", "Add lib")?;
+        commit("main.rs", "fn main() {}", "Add main")?;
+        commit("main.rs", "fn main() { run() }", "Run")?;
+
+        let history = GitHistory::discover(temp_dir.path()).expect("repository");
+        let info = history
+            .last_commit(&temp_dir.path().join("lib.rs"))
+            .expect("commit");
+        assert_eq!(info.hash, first.to_string()[..8]);
+        assert_eq!(info.author, "Jane Doe");
+        assert_eq!(info.date, "1970-01-01");
+        let churn = history.churn(0);
+        assert_eq!(churn[Path::new("main.rs")], 2);
+        assert_eq!(churn[Path::new("lib.rs")], 1);
+
+        let annotation = annotation(&temp_dir.path().join("lib.rs"), "pub fn a() {}", true);
+        assert_eq!(
+            annotation,
+            format!(
+                "// Last commit: {0} by Jane Doe on 1970-01-01\n// Blame:\n//   fn a: {0} by Jane Doe on 1970-01-01\n",
+                &first.to_string()[..8]
+            )
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::summary::{one_line, type_string, SourceFile};
+use std::collections::BTreeMap;
+use syn::visit::{self, Visit};
+use syn::{GenericParam, ItemImpl, ItemTrait, Type, Visibility};
+
+/// Relationships of a trait defined or implemented in the crate
+#[derive(Default)]
+struct TraitInfo {
+    /// Declaration with supertraits and the defining file, for traits of the crate
+    declaration: Option<String>,
+    implementors: Vec<String>,
+    blanket_impls: Vec<String>,
+}
+
+struct HierarchyCollector<'a> {
+    path: &'a str,
+    traits: BTreeMap<String, TraitInfo>,
+}
+
+impl HierarchyCollector<'_> {
+    /// Checks if an impl targets one of its own type parameters, e.g. `impl<T: Display> Foo for T`
+    fn is_blanket(item: &ItemImpl) -> bool {
+        let target = match item.self_ty.as_ref() {
+            Type::Reference(reference) => reference.elem.as_ref(),
+            ty => ty,
+        };
+        let Type::Path(target) = target else {
+            return false;
+        };
+        item.generics.params.iter().any(|param| {
+            matches!(param, GenericParam::Type(param) if target.qself.is_none() && target.path.is_ident(&param.ident))
+        })
+    }
+}
+
+impl<'ast> Visit<'ast> for HierarchyCollector<'_> {
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        let mut declaration = item.clone();
+        declaration.attrs.clear();
+        declaration.items.clear();
+        declaration.vis = Visibility::Inherited;
+        self.traits
+            .entry(item.ident.to_string())
+            .or_default()
+            .declaration = Some(format!(
+            "{} ({})",
+            one_line(syn::Item::Trait(declaration)),
+            self.path
+        ));
+        visit::visit_item_trait(self, item);
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if let Some((_, trait_path, _)) = &item.trait_ {
+            if let Some(segment) = trait_path.segments.last() {
+                let info = self.traits.entry(segment.ident.to_string()).or_default();
+                if Self::is_blanket(item) {
+                    let mut blanket = item.clone();
+                    blanket.attrs.clear();
+                    blanket.items.clear();
+                    info.blanket_impls.push(one_line(syn::Item::Impl(blanket)));
+                } else {
+                    let implementor = type_string(&item.self_ty);
+                    let implementor = if segment.arguments.is_empty() {
+                        implementor
+                    } else {
+                        format!("{} ({})", implementor, type_string(trait_path))
+                    };
+                    if !info.implementors.contains(&implementor) {
+                        info.implementors.push(implementor);
+                    }
+                }
+            }
+        }
+        visit::visit_item_impl(self, item);
+    }
+}
+
+/// Describes supertraits, implementors, and blanket impls of the traits used in the crate.
+/// Traits defined in the crate come first, followed by implemented foreign traits
+pub fn describe_traits(sources: &[SourceFile]) -> Vec<String> {
+    let mut traits = BTreeMap::new();
+    for source in sources {
+        let mut collector = HierarchyCollector {
+            path: &source.path,
+            traits: std::mem::take(&mut traits),
+        };
+        collector.visit_file(&source.ast);
+        traits = collector.traits;
+    }
+
+    let (local, foreign): (Vec<_>, Vec<_>) = traits
+        .into_iter()
+        .partition(|(_, info)| info.declaration.is_some());
+
+    let mut lines = Vec::new();
+    for (name, info) in local.into_iter().chain(foreign) {
+        lines.push(
+            info.declaration
+                .unwrap_or_else(|| format!("trait {} (external)", name)),
+        );
+        if !info.implementors.is_empty() {
+            lines.push(format!("  implemented by {}", info.implementors.join(", ")));
+        }
+        for blanket in info.blanket_impls {
+            lines.push(format!("  blanket {}", blanket));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_describe_traits() -> Result<()> {
+        let sources = vec![
+            SourceFile {
+                path: "shape.rs".to_string(),
+                ast: syn::parse_str(
+                    r#"
+                    /// A shape
+                    pub trait Shape: Debug + Clone {
+                        fn area(&self) -> f64;
+                    }
+                    pub trait Named {}
+                    impl<T: Shape> Named for T {}
+                    "#,
+                )?,
+            },
+            SourceFile {
+                path: "circle.rs".to_string(),
+                ast: syn::parse_str(
+                    r#"
+                    impl Shape for Circle { fn area(&self) -> f64 { 0.0 } }
+                    impl Shape for Square { fn area(&self) -> f64 { 0.0 } }
+                    impl fmt::Display for Circle {}
+                    impl From<f64> for Circle {}
+                    "#,
+                )?,
+            },
+        ];
+        assert_eq!(
+            describe_traits(&sources),
+            vec![
+                "trait Named (shape.rs)",
+                "  blanket impl<T: Shape> Named for T",
+                "trait Shape: Debug + Clone (shape.rs)",
+                "  implemented by Circle, Square",
+                "trait Display (external)",
+                "  implemented by Circle",
+                "trait From (external)",
+                "  implemented by Circle (From<f64>)",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use crate::manifest::MANIFEST_FILE_NAME;
+use crate::processor::ProcessingStats;
+use crate::snapshot::Snapshot;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Runs a shell command after a successful generation, with the output path and the
+/// statistics of the run in `CODE_CONTEXT_*` environment variables
+pub fn run_post_hook(command: &str, output: &Path, stats: &ProcessingStats) -> Result<()> {
+    let mut shell = shell();
+    let manifest = output.join(MANIFEST_FILE_NAME);
+    if manifest.is_file() {
+        shell.env("CODE_CONTEXT_MANIFEST", &manifest);
+    }
+    let status = shell
+        .arg(command)
+        .env("CODE_CONTEXT_OUTPUT", output)
+        .env("CODE_CONTEXT_FILES", stats.files_processed.to_string())
+        .env("CODE_CONTEXT_INPUT_SIZE", stats.input_size.to_string())
+        .env("CODE_CONTEXT_OUTPUT_SIZE", stats.output_size.to_string())
+        .env("CODE_CONTEXT_TOKENS", stats.tokens().to_string())
+        .status()
+        .with_context(|| format!("Failed to run post hook: {}", command))?;
+    if !status.success() {
+        bail!("Post hook failed with {}: {}", status, command);
+    }
+    Ok(())
+}
+
+/// Runs the `snapshot-push` command after a snapshot is stored, with the snapshot in
+/// `CODE_CONTEXT_SNAPSHOT_*` environment variables
+pub fn run_snapshot_push(command: &str, snapshot: &Snapshot) -> Result<()> {
+    let status = shell()
+        .arg(command)
+        .env("CODE_CONTEXT_SNAPSHOT_DIR", &snapshot.dir)
+        .env("CODE_CONTEXT_SNAPSHOT_HASH", &snapshot.hash)
+        .env("CODE_CONTEXT_SNAPSHOT_TAG", &snapshot.tag)
+        .status()
+        .with_context(|| format!("Failed to run snapshot push: {}", command))?;
+    if !status.success() {
+        bail!("Snapshot push failed with {}: {}", status, command);
+    }
+    Ok(())
+}
+
+fn shell() -> Command {
+    let mut shell = if cfg!(windows) {
+        Command::new("cmd")
+    } else {
+        Command::new("sh")
+    };
+    shell.arg(if cfg!(windows) { "/C" } else { "-c" });
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::FileStats;
+    use tempfile::TempDir;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_post_hook() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut stats = ProcessingStats::default();
+        stats.record(FileStats::new("src/lib.rs", 400, 100));
+        let report = temp_dir.path().join("report.txt");
+        let command = format!(
+            "echo \"$CODE_CONTEXT_OUTPUT $CODE_CONTEXT_FILES $CODE_CONTEXT_TOKENS\" > '{}'",
+            report.display()
+        );
+
+        run_post_hook(&command, temp_dir.path(), &stats)?;
+        assert_eq!(
+            std::fs::read_to_string(&report)?,
+            format!("{} 1 25\n", temp_dir.path().display())
+        );
+        assert!(run_post_hook("exit 3", temp_dir.path(), &stats).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use crate::script;
+use crate::summary::type_string;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use syn::spanned::Spanned;
+use syn::{GenericArgument, Item, ItemImpl, PathArguments, Type};
+
+/// Trait impls that must differ only in one type for `--collapse-impls` to collapse them
+const MIN_FAMILY_SIZE: usize = 3;
+
+/// Stands for the substituted type when comparing impls
+const PLACEHOLDER: &str = "__CodeContextType";
+
+/// Trait impls that are identical up to one substituted type
+struct Family {
+    /// Indices of the member impls, the first one being the representative
+    members: Vec<usize>,
+    /// Substituted type of each member
+    types: Vec<String>,
+}
+
+/// Collapses families of trait impls that differ only in one type, such as the
+/// `impl From<u8> for X`, `impl From<u16> for X`, ... walls generated by macros, into their
+/// first impl and a comment listing the other types; returns the output and the number of
+/// impls removed, leaving output that doesn't parse unchanged
+pub fn collapse(output: &str) -> (String, usize) {
+    let (header, code) = script::split_header(output);
+    let Ok(file) = syn::parse_file(code) else {
+        return (output.to_string(), 0);
+    };
+    let mut edits = Vec::new();
+    collect(&file.items, code, &mut edits);
+    if edits.is_empty() {
+        return (output.to_string(), 0);
+    }
+    let removed = edits
+        .iter()
+        .filter(|(_, replacement)| replacement.is_empty())
+        .count();
+
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut collapsed = header.to_string();
+    let mut position = 0;
+    for (range, replacement) in edits {
+        collapsed.push_str(&code[position..range.start]);
+        collapsed.push_str(&replacement);
+        position = range.end;
+    }
+    collapsed.push_str(&code[position..]);
+    (collapsed, removed)
+}
+
+/// Finds the families among the trait impls of an item list and its inline modules, and
+/// records the edits that collapse them
+fn collect(items: &[Item], code: &str, edits: &mut Vec<(Range<usize>, String)>) {
+    let impls: Vec<&ItemImpl> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Impl(item) if item.trait_.is_some() => Some(item),
+            _ => None,
+        })
+        .collect();
+    for item in items {
+        if let Item::Mod(module) = item {
+            if let Some((_, items)) = &module.content {
+                collect(items, code, edits);
+            }
+        }
+    }
+
+    for family in families(&impls) {
+        let (first, others) = family
+            .members
+            .split_first()
+            .expect("families are not empty");
+        let start = line_start(code, impls[*first].span().byte_range().start);
+        let indent = &code[start..impls[*first].span().byte_range().start];
+        let comment = format!(
+            "{}// Also implemented with `{}` replaced by: {}\n",
+            indent,
+            family.types[0],
+            family.types[1..].join(", ")
+        );
+        edits.push((start..start, comment));
+        for &member in others {
+            edits.push((
+                line_range(code, impls[member].span().byte_range()),
+                String::new(),
+            ));
+        }
+    }
+}
+
+/// Groups impls that are identical once one of their types is replaced by a placeholder:
+/// a type argument of the trait, or the self type. An impl joins at most one family, the
+/// largest one it belongs to.
+fn families(impls: &[&ItemImpl]) -> Vec<Family> {
+    let mut candidates: HashMap<String, Family> = HashMap::new();
+    for (index, item_impl) in impls.iter().enumerate() {
+        let tokens = flatten(item_impl.to_token_stream());
+        for (slot, ty) in substitutable_types(item_impl) {
+            let key = format!(
+                "{}\0{}",
+                slot,
+                substitute(&tokens, &flatten(ty.to_token_stream()))
+            );
+            let family = candidates.entry(key).or_insert_with(|| Family {
+                members: Vec::new(),
+                types: Vec::new(),
+            });
+            let ty = type_string(ty);
+            // Identical impls are left for the compiler to complain about
+            if !family.types.contains(&ty) {
+                family.members.push(index);
+                family.types.push(ty);
+            }
+        }
+    }
+
+    let mut candidates: Vec<_> = candidates.into_values().collect();
+    candidates.sort_by_key(|family| (std::cmp::Reverse(family.members.len()), family.members[0]));
+    let mut assigned = HashSet::new();
+    let mut families = Vec::new();
+    for family in candidates {
+        if family.members.len() < MIN_FAMILY_SIZE
+            || family
+                .members
+                .iter()
+                .any(|member| assigned.contains(member))
+        {
+            continue;
+        }
+        assigned.extend(family.members.iter().copied());
+        families.push(family);
+    }
+    families
+}
+
+/// Types that may vary within a family: the type arguments of the trait and the self type
+fn substitutable_types(item_impl: &ItemImpl) -> Vec<(String, &Type)> {
+    let mut types = vec![("self".to_string(), item_impl.self_ty.as_ref())];
+    let arguments = item_impl
+        .trait_
+        .as_ref()
+        .and_then(|(_, path, _)| path.segments.last())
+        .map(|segment| &segment.arguments);
+    if let Some(PathArguments::AngleBracketed(arguments)) = arguments {
+        for (index, argument) in arguments.args.iter().enumerate() {
+            if let GenericArgument::Type(ty) = argument {
+                types.push((format!("arg{}", index), ty));
+            }
+        }
+    }
+    types
+}
+
+/// Flattens tokens into strings, with the delimiters of groups as tokens of their own
+fn flatten(tokens: TokenStream) -> Vec<String> {
+    let mut flat = Vec::new();
+    for tree in tokens {
+        match tree {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                flat.push(open.to_string());
+                flat.extend(flatten(group.stream()));
+                flat.push(close.to_string());
+            }
+            tree => flat.push(tree.to_string()),
+        }
+    }
+    flat
+}
+
+/// Joins tokens with each occurrence of a type replaced by a placeholder
+fn substitute(tokens: &[String], ty: &[String]) -> String {
+    let mut substituted = Vec::with_capacity(tokens.len());
+    let mut index = 0;
+    while index < tokens.len() {
+        if !ty.is_empty() && tokens[index..].starts_with(ty) {
+            substituted.push(PLACEHOLDER);
+            index += ty.len();
+        } else {
+            substituted.push(tokens[index].as_str());
+            index += 1;
+        }
+    }
+    substituted.join(" ")
+}
+
+fn line_start(code: &str, position: usize) -> usize {
+    code[..position].rfind('\n').map_or(0, |index| index + 1)
+}
+
+/// Extends a range to whole lines, with the blank lines after it
+fn line_range(code: &str, range: Range<usize>) -> Range<usize> {
+    let start = line_start(code, range.start);
+    let end = code[range.end..]
+        .find('\n')
+        .map_or(code.len(), |index| range.end + index + 1);
+    let blank = code[end..]
+        .split_inclusive('\n')
+        .take_while(|line| line.trim().is_empty())
+        .map(str::len)
+        .sum::<usize>();
+    start..end + blank
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_impl_families() {
+        let mut output = String::from("pub struct Value(u64);\n\n");
+        for ty in ["u8", "u16", "u32"] {
+            output.push_str(&format!(
+                "impl From<{ty}> for Value {{\n    fn from(value: {ty}) -> Self {{\n        Value(value as u64)\n    }}\n}}\n\n"
+            ));
+        }
+        output.push_str("impl From<String> for Value {\n    fn from(value: String) -> Self {\n        Value(value.len() as u64)\n    }\n}\n");
+
+        let (collapsed, removed) = collapse(&output);
+        assert_eq!(removed, 2);
+        assert_eq!(
+            collapsed,
+            "pub struct Value(u64);\n\n\
+             // Also implemented with `u8` replaced by: u16, u32\n\
+             impl From<u8> for Value {\n    fn from(value: u8) -> Self {\n        Value(value as u64)\n    }\n}\n\n\
+             impl From<String> for Value {\n    fn from(value: String) -> Self {\n        Value(value.len() as u64)\n    }\n}\n"
+        );
+
+        // Two impls are not a family
+        let pair = "impl Zero for u8 {}\nimpl Zero for u16 {}\n";
+        assert_eq!(collapse(pair), (pair.to_string(), 0));
+        let (collapsed, removed) =
+            collapse("impl Zero for u8 {}\nimpl Zero for u16 {}\nimpl Zero for i8 {}\n");
+        assert_eq!(removed, 2);
+        assert_eq!(
+            collapsed,
+            "// Also implemented with `u8` replaced by: u16, i8\nimpl Zero for u8 {}\n"
+        );
+    }
+}
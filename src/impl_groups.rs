@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use syn::spanned::Spanned;
+use syn::{Item, Type};
+
+/// A transformed file of the combined single-file output
+pub struct Section {
+    /// Path relative to the input directory
+    pub path: String,
+    pub content: String,
+}
+
+/// Line-level edits of a section
+#[derive(Default)]
+struct Edits {
+    removed: Vec<(usize, usize)>,
+    /// Text inserted after a line, keyed by 1-based line number
+    inserted: HashMap<usize, Vec<String>>,
+}
+
+/// Name of the type an impl block is for, e.g. `Config` for `impl<T> Display for Config<T>`
+fn impl_target(item: &Item) -> Option<String> {
+    let Item::Impl(item_impl) = item else {
+        return None;
+    };
+    match item_impl.self_ty.as_ref() {
+        Type::Path(path) if path.qself.is_none() => {
+            path.path.segments.last().map(|s| s.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Name of the type defined by an item
+fn defined_type(item: &Item) -> Option<String> {
+    match item {
+        Item::Struct(item) => Some(item.ident.to_string()),
+        Item::Enum(item) => Some(item.ident.to_string()),
+        Item::Union(item) => Some(item.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Moves impl blocks for a type defined in another file right after the type's definition,
+/// annotated with their original file. Types defined in several files are left alone
+pub fn group_impls(sections: &mut [Section]) {
+    let parsed: Vec<Option<syn::File>> = sections
+        .iter()
+        .map(|section| syn::parse_file(&section.content).ok())
+        .collect();
+
+    // Type name -> (section, line after which impls are inserted), `None` if ambiguous
+    let mut definitions: HashMap<String, Option<(usize, usize)>> = HashMap::new();
+    for (index, file) in parsed.iter().enumerate() {
+        for item in file.iter().flat_map(|file| &file.items) {
+            if let Some(name) = defined_type(item) {
+                let end = item.span().end().line;
+                definitions
+                    .entry(name)
+                    .and_modify(|definition| *definition = None)
+                    .or_insert(Some((index, end)));
+            }
+        }
+    }
+
+    let mut edits: Vec<Edits> = sections.iter().map(|_| Edits::default()).collect();
+    for (index, file) in parsed.iter().enumerate() {
+        let lines: Vec<&str> = sections[index].content.lines().collect();
+        for item in file.iter().flat_map(|file| &file.items) {
+            let Some(Some((target, after))) =
+                impl_target(item).and_then(|name| definitions.get(&name))
+            else {
+                continue;
+            };
+            if *target == index {
+                continue;
+            }
+            let span = item.span();
+            let (start, end) = (span.start().line, span.end().line);
+            let text = lines[start - 1..end].join("\n");
+            edits[index].removed.push((start, end));
+            edits[*target]
+                .inserted
+                .entry(*after)
+                .or_default()
+                .push(format!(
+                    "\n// Impl relocated from {}\n{}",
+                    sections[index].path, text
+                ));
+        }
+    }
+
+    for (section, edits) in sections.iter_mut().zip(edits) {
+        if edits.removed.is_empty() && edits.inserted.is_empty() {
+            continue;
+        }
+        let mut content = String::with_capacity(section.content.len());
+        for (number, line) in section.content.lines().enumerate().map(|(i, l)| (i + 1, l)) {
+            if !edits
+                .removed
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&number))
+            {
+                content.push_str(line);
+                content.push('\n');
+            }
+            for text in edits.inserted.get(&number).into_iter().flatten() {
+                content.push_str(text);
+                content.push('\n');
+            }
+        }
+        section.content = content;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_impls() {
+        let mut sections = vec![
+            Section {
+                path: "config.rs".to_string(),
+                content: "/// Settings\npub struct Config {\n    pub name: String,\n}\npub fn load() {}\n"
+                    .to_string(),
+            },
+            Section {
+                path: "display.rs".to_string(),
+                content: "use std::fmt;\n/// Shows the name\nimpl fmt::Display for Config {\n    fn fmt(&self) {}\n}\nimpl Local {}\npub struct Local;\n"
+                    .to_string(),
+            },
+        ];
+        group_impls(&mut sections);
+        assert_eq!(
+            sections[0].content,
+            "/// Settings\npub struct Config {\n    pub name: String,\n}\n\n// Impl relocated from display.rs\n/// Shows the name\nimpl fmt::Display for Config {\n    fn fmt(&self) {}\n}\npub fn load() {}\n"
+        );
+        assert_eq!(
+            sections[1].content,
+            "use std::fmt;\nimpl Local {}\npub struct Local;\n"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use crate::presets::PathExclusions;
+use anyhow::{Context, Result};
+use std::path::{Component, Path};
+use syn::visit::{self, Visit};
+use syn::{File, Item, LitStr, Macro};
+
+/// Maximum depth of nested `include!` invocations that are inlined
+const MAX_INLINE_DEPTH: usize = 8;
+
+/// A file referenced by `include!`, `include_str!`, or `include_bytes!`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Include {
+    pub macro_name: String,
+    pub path: String,
+}
+
+#[derive(Default)]
+struct IncludeCollector {
+    includes: Vec<Include>,
+}
+
+impl<'ast> Visit<'ast> for IncludeCollector {
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        if let Some(include) = include_of(mac) {
+            self.includes.push(include);
+        }
+        visit::visit_macro(self, mac);
+    }
+}
+
+/// Returns the include referenced by a macro invocation with a literal path
+fn include_of(mac: &Macro) -> Option<Include> {
+    let name = mac.path.segments.last()?.ident.to_string();
+    if !matches!(name.as_str(), "include" | "include_str" | "include_bytes") {
+        return None;
+    }
+    let path = mac.parse_body::<LitStr>().ok()?;
+    Some(Include {
+        macro_name: name,
+        path: path.value(),
+    })
+}
+
+/// Finds all files included with a literal path
+pub fn find_includes(file: &File) -> Vec<Include> {
+    let mut collector = IncludeCollector::default();
+    collector.visit_file(file);
+    collector.includes
+}
+
+/// Renders comment lines noting the files the source depends on
+pub fn annotation(includes: &[Include]) -> String {
+    includes
+        .iter()
+        .map(|include| format!("// Includes {} via {}!\n", include.path, include.macro_name))
+        .collect()
+}
+
+/// Replaces item-level `include!("…")` invocations with the items of the included file,
+/// leaving the invocations of excluded files in place
+pub fn inline_includes(
+    file: &mut File,
+    source_dir: &Path,
+    exclusions: Option<&PathExclusions>,
+) -> Result<()> {
+    file.items = inline_items(std::mem::take(&mut file.items), source_dir, exclusions, 0)?;
+    Ok(())
+}
+
+fn inline_items(
+    items: Vec<Item>,
+    source_dir: &Path,
+    exclusions: Option<&PathExclusions>,
+    depth: usize,
+) -> Result<Vec<Item>> {
+    let mut inlined = Vec::with_capacity(items.len());
+    for item in items {
+        let include = match &item {
+            Item::Macro(item_macro) => {
+                include_of(&item_macro.mac).filter(|include| include.macro_name == "include")
+            }
+            _ => None,
+        };
+        let Some(include) = include
+            .filter(|_| depth < MAX_INLINE_DEPTH)
+            .filter(|include| !is_excluded(&include.path, exclusions))
+        else {
+            inlined.push(item);
+            continue;
+        };
+
+        let path = source_dir.join(&include.path);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read included file: {}", path.display()))?;
+        let included = syn::parse_file(&content)
+            .with_context(|| format!("Failed to parse included file: {}", path.display()))?;
+        let dir = path.parent().unwrap_or(source_dir);
+        inlined.extend(inline_items(included.items, dir, exclusions, depth + 1)?);
+    }
+    Ok(inlined)
+}
+
+/// Copies the files included by a source file into the output directory, keeping their
+/// relative paths. Includes that point outside the source directory or that are excluded
+/// are skipped
+pub fn copy_includes(
+    content: &str,
+    source_dir: &Path,
+    output_dir: &Path,
+    exclusions: Option<&PathExclusions>,
+) -> Result<()> {
+    let file = syn::parse_file(content).context("Failed to parse Rust file")?;
+    for include in find_includes(&file) {
+        let relative = Path::new(&include.path);
+        if is_excluded(&include.path, exclusions)
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            continue;
+        }
+
+        let source = source_dir.join(relative);
+        let destination = output_dir.join(relative);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create output directory")?;
+        }
+        std::fs::copy(&source, &destination)
+            .with_context(|| format!("Failed to copy included file: {}", source.display()))?;
+    }
+    Ok(())
+}
+
+fn is_excluded(path: &str, exclusions: Option<&PathExclusions>) -> bool {
+    exclusions.is_some_and(|exclusions| exclusions.excluded(Path::new(path)).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::Preset;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_includes_and_annotation() -> Result<()> {
+        let file = syn::parse_file(
+            r#"
+            include!("generated.rs");
+            const SCHEMA: &str = include_str!("sql/schema.sql");
+            fn load() -> &'static [u8] { include_bytes!(concat!(env!("OUT_DIR"), "/data")) }
+            "#,
+        )?;
+        let includes = find_includes(&file);
+        assert_eq!(includes.len(), 2);
+        assert_eq!(
+            annotation(&includes),
+            "// Includes generated.rs via include!\n// Includes sql/schema.sql via include_str!\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_and_copy_includes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        std::fs::create_dir_all(temp_dir.path().join("gen"))?;
+        std::fs::write(
+            temp_dir.path().join("gen/types.rs"),
+            "pub struct Generated;\ninclude!(\"more.rs\");",
+        )?;
+        std::fs::write(temp_dir.path().join("gen/more.rs"), "pub struct More;")?;
+
+        let source = "include!(\"gen/types.rs\");\npub fn keep() {}";
+        let mut file = syn::parse_file(source)?;
+        inline_includes(&mut file, temp_dir.path(), None)?;
+        assert_eq!(
+            prettyplease::unparse(&file),
+            "pub struct Generated;\npub struct More;\npub fn keep() {}\n"
+        );
+
+        let output_dir = temp_dir.path().join("out");
+        copy_includes(source, temp_dir.path(), &output_dir, None)?;
+        assert!(output_dir.join("gen/types.rs").exists());
+
+        // Excluded files are neither inlined nor copied
+        std::fs::write(
+            temp_dir.path().join(".env.rs"),
+            "pub const KEY: &str = \"k\";",
+        )?;
+        let source = "include!(\".env.rs\");";
+        let exclusions = PathExclusions::new(&[Preset::NoSecrets])?;
+        let mut file = syn::parse_file(source)?;
+        inline_includes(&mut file, temp_dir.path(), exclusions.as_ref())?;
+        assert_eq!(prettyplease::unparse(&file), "include!(\".env.rs\");\n");
+        copy_includes(source, temp_dir.path(), &output_dir, exclusions.as_ref())?;
+        assert!(!output_dir.join(".env.rs").exists());
+        Ok(())
+    }
+}
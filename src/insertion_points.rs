@@ -0,0 +1,121 @@
+use crate::pairs::locate_body;
+use crate::processor::{estimate_tokens, FileStats};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+/// Name of the JSONL file with one insertion point per elided function body
+pub const INSERTION_POINTS_FILE_NAME: &str = "code_context.insertion_points.jsonl";
+
+/// A line of the insertion points map: where the body a function lost in the output is in
+/// its source file, so a tool can fetch or patch exactly that region
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct InsertionPoint<'a> {
+    /// Item id, as in `code_context.jsonl`
+    id: &'a str,
+    /// Module path of the function, e.g. `crate::config::Config::load`
+    item: String,
+    path: &'a str,
+    /// Line of the opening brace of the body, counted from 1
+    line: usize,
+    /// Line of the closing brace
+    end_line: usize,
+    /// Byte offset of the opening brace
+    start_byte: usize,
+    /// Byte offset past the closing brace
+    end_byte: usize,
+    /// Estimated tokens of the body
+    tokens: usize,
+}
+
+/// Renders an insertion point for every emitted function whose output body is empty while
+/// its source body isn't, read from `source_root` by the spans recorded while processing
+pub fn render(files: &[FileStats], source_root: &Path) -> Result<String> {
+    let mut jsonl = String::new();
+    for file in files {
+        let elided: Vec<_> = file
+            .items
+            .iter()
+            .filter(|item| item.kind == "fn" && has_empty_body(&item.code))
+            .filter_map(|item| Some((item, item.source?)))
+            .collect();
+        if elided.is_empty() {
+            continue;
+        }
+        let path = source_root.join(&file.path);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read source file: {}", path.display()))?;
+        let module = file.module.as_deref().unwrap_or("crate");
+        for (item, span) in elided {
+            let Some((_, start_byte)) = locate_body(&content, &span) else {
+                continue;
+            };
+            let line = serde_json::to_string(&InsertionPoint {
+                id: &item.id,
+                item: format!("{}::{}", module, item.name),
+                path: &file.path,
+                line: content[..start_byte].matches('\n').count() + 1,
+                end_line: span.end_line,
+                start_byte,
+                end_byte: span.end_byte,
+                tokens: estimate_tokens(span.end_byte - start_byte),
+            })?;
+            jsonl.push_str(&line);
+            jsonl.push('\n');
+        }
+    }
+    Ok(jsonl)
+}
+
+/// Checks if the output of a function ends with an empty body, ignoring trailing comments
+/// added by `--visibility-tags` or `--token-costs`
+fn has_empty_body(code: &str) -> bool {
+    let last = code.lines().last().unwrap_or_default();
+    let last = last.split_once(" //").map_or(last, |(code, _)| code);
+    last.trim_end().ends_with("{}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item_ids;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_insertion_points() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source = "pub struct Meters(f64);\n\nimpl From<u8> for Meters {\n    fn from(value: u8) -> Self {\n        Meters(value.into())\n    }\n}\n\nimpl From<u16> for Meters {\n    fn from(value: u16) -> Self {\n        Meters(value.into())\n    }\n}\n\npub fn kept() -> u8 {\n    1\n}\n\npub fn empty() {}\n";
+        std::fs::write(temp_dir.path().join("units.rs"), source)?;
+        let output = "pub struct Meters(f64);\nimpl From<u8> for Meters {\n    fn from(value: u8) -> Self {} // ~8 tokens\n}\nimpl From<u16> for Meters {\n    fn from(value: u16) -> Self {}\n}\npub fn kept() -> u8 {\n    1\n}\npub fn empty() {}\n";
+        let mut items = item_ids::collect_items("app", Some("crate::units"), output);
+        item_ids::attach_source(&mut items, "app", Some("crate::units"), source);
+        let files = vec![FileStats::new("units.rs", source.len(), output.len())
+            .with_module(Some("crate::units"))
+            .with_items(items)];
+
+        let jsonl = render(&files, temp_dir.path())?;
+        let points: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        // Only the elided bodies, each at its own impl even though both share an id
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0]["item"], "crate::units::Meters::from");
+        assert_eq!(points[0]["path"], "units.rs");
+        assert_eq!(
+            (&points[0]["line"], &points[0]["end_line"]),
+            (&4.into(), &6.into())
+        );
+        assert_eq!(
+            (&points[1]["line"], &points[1]["end_line"]),
+            (&10.into(), &12.into())
+        );
+        let start = points[1]["start_byte"].as_u64().unwrap() as usize;
+        let end = points[1]["end_byte"].as_u64().unwrap() as usize;
+        assert_eq!(
+            &source[start..end],
+            "{\n        Meters(value.into())\n    }"
+        );
+        Ok(())
+    }
+}
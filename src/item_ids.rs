@@ -0,0 +1,258 @@
+use crate::doc_metadata::DocMetadata;
+use crate::features::item_attrs;
+use crate::git::item_label;
+use crate::processor::estimate_tokens;
+use crate::script;
+use crate::summary::type_string;
+use crate::update::content_hash;
+use serde::{Deserialize, Serialize};
+use syn::spanned::Spanned;
+use syn::{Attribute, ImplItem, Item};
+
+/// Name of the JSONL file with one record per emitted item
+pub const ITEMS_FILE_NAME: &str = "code_context.jsonl";
+
+/// An emitted item with an identifier that stays the same across runs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ItemRecord {
+    /// Hash of the crate, module path, kind, and name
+    pub id: String,
+    /// Item kind, e.g. `fn`, `struct`, or `impl`
+    pub kind: String,
+    /// Item name, e.g. `Config`, `Config::load`, or `Display for Config`
+    pub name: String,
+    /// Condensed code of the item
+    #[serde(skip)]
+    pub code: String,
+    /// Doc aliases, `#[must_use]` message, and first example of the item's source
+    #[serde(skip)]
+    pub metadata: DocMetadata,
+    /// Where a function is in its source file, kept when its body is elided
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceSpan>,
+}
+
+/// Span of an item in its source file, with its doc comments and attributes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// First line, counted from 1
+    pub start_line: usize,
+    /// Last line, inclusive
+    pub end_line: usize,
+    /// Byte offset of the start
+    pub start_byte: usize,
+    /// Byte offset past the end
+    pub end_byte: usize,
+    /// Estimated tokens of the full source of the item
+    pub tokens: usize,
+}
+
+/// A line of the JSONL output
+#[derive(Serialize)]
+pub struct JsonlItem<'a> {
+    pub id: &'a str,
+    pub path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module: Option<&'a str>,
+    pub kind: &'a str,
+    pub name: &'a str,
+    pub code: &'a str,
+    /// Estimated tokens of the condensed code
+    pub tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<&'a SourceSpan>,
+    #[serde(flatten)]
+    pub metadata: &'a DocMetadata,
+}
+
+/// Returns the stable identifier of an item
+pub fn item_id(crate_name: &str, module: &str, kind: &str, name: &str) -> String {
+    content_hash(&format!("{}\0{}\0{}\0{}", crate_name, module, kind, name))
+}
+
+struct Collector<'a> {
+    crate_name: &'a str,
+    lines: Vec<&'a str>,
+    /// Bytes and lines of the script header before the parsed code, when function spans
+    /// are recorded
+    span_offset: Option<(usize, usize)>,
+    items: Vec<ItemRecord>,
+}
+
+impl Collector<'_> {
+    fn record(
+        &mut self,
+        module: &str,
+        kind: &str,
+        name: String,
+        span: proc_macro2::Span,
+        attrs: &[Attribute],
+    ) {
+        let start = span.start().line.saturating_sub(1);
+        let end = span.end().line.min(self.lines.len());
+        let source = self
+            .span_offset
+            .filter(|_| kind == "fn")
+            .map(|(bytes, lines)| {
+                let range = span.byte_range();
+                SourceSpan {
+                    start_line: lines + span.start().line,
+                    end_line: lines + span.end().line,
+                    start_byte: bytes + range.start,
+                    end_byte: bytes + range.end,
+                    tokens: estimate_tokens(range.len()),
+                }
+            });
+        self.items.push(ItemRecord {
+            id: item_id(self.crate_name, module, kind, &name),
+            kind: kind.to_string(),
+            name,
+            code: self.lines.get(start..end).unwrap_or_default().join("\n"),
+            metadata: DocMetadata::from_attrs(attrs),
+            source,
+        });
+    }
+
+    fn collect(&mut self, items: &[Item], module: &str) {
+        for item in items {
+            let Some((kind, name)) = item_label(item)
+                .as_deref()
+                .and_then(|label| label.split_once(' '))
+                .map(|(kind, name)| (kind.to_string(), name.to_string()))
+            else {
+                continue;
+            };
+            self.record(module, &kind, name.clone(), item.span(), item_attrs(item));
+            match item {
+                Item::Mod(item) => {
+                    if let Some((_, items)) = &item.content {
+                        self.collect(items, &format!("{}::{}", module, item.ident));
+                    }
+                }
+                Item::Impl(item) => {
+                    let self_ty = type_string(&item.self_ty);
+                    for impl_item in &item.items {
+                        if let ImplItem::Fn(method) = impl_item {
+                            let name = format!("{}::{}", self_ty, method.sig.ident);
+                            self.record(module, "fn", name, method.span(), &method.attrs);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Collects the items of a file's condensed output, including inline modules and impl
+/// methods; nothing is collected if the output doesn't parse
+pub fn collect_items(crate_name: &str, module: Option<&str>, output: &str) -> Vec<ItemRecord> {
+    collect(crate_name, module, output, false)
+}
+
+fn collect(crate_name: &str, module: Option<&str>, text: &str, spans: bool) -> Vec<ItemRecord> {
+    let (header, code) = script::split_header(text);
+    let Ok(file) = syn::parse_file(code) else {
+        return Vec::new();
+    };
+    let mut collector = Collector {
+        crate_name,
+        lines: code.lines().collect(),
+        span_offset: spans.then(|| (header.len(), header.matches('\n').count())),
+        items: Vec::new(),
+    };
+    collector.collect(&file.items, module.unwrap_or("crate"));
+    collector.items
+}
+
+/// Gives items collected from a condensed output the doc metadata and function spans of
+/// the same items in the source, which the condensing may have stripped
+pub fn attach_source(
+    items: &mut [ItemRecord],
+    crate_name: &str,
+    module: Option<&str>,
+    source: &str,
+) {
+    copy_source(items, &collect(crate_name, module, source, true));
+}
+
+/// Copies the doc metadata and source spans of items with the same ids; items sharing an
+/// id, such as the methods of `From<u8>` and `From<u16>` impls, are paired in order
+pub fn copy_source(items: &mut [ItemRecord], from: &[ItemRecord]) {
+    for index in 0..items.len() {
+        let (earlier, rest) = items.split_at_mut(index);
+        let item = &mut rest[0];
+        let occurrence = earlier.iter().filter(|other| other.id == item.id).count();
+        let mut originals = from.iter().filter(|original| original.id == item.id);
+        if let Some(original) = originals.nth(occurrence) {
+            item.metadata = original.metadata.clone();
+            item.source = original.source;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_items() {
+        let output = "pub struct Config;\nimpl Config {\n    pub fn load() {}\n}\nmod inner {\n    pub fn helper() {}\n}\n";
+        let items = collect_items("app", Some("crate::config"), output);
+        let names: Vec<_> = items
+            .iter()
+            .map(|item| format!("{} {}", item.kind, item.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "struct Config",
+                "impl Config",
+                "fn Config::load",
+                "mod inner",
+                "fn helper"
+            ]
+        );
+        assert_eq!(items[2].code, "    pub fn load() {}");
+        assert_eq!(
+            items[0].id,
+            item_id("app", "crate::config", "struct", "Config")
+        );
+        assert_eq!(
+            items[4].id,
+            item_id("app", "crate::config::inner", "fn", "helper")
+        );
+
+        // Ids don't depend on the position or code of items
+        let moved = collect_items(
+            "app",
+            Some("crate::config"),
+            "\n\npub struct Config { x: u8 }\n",
+        );
+        assert_eq!(moved[0].id, items[0].id);
+        assert_ne!(
+            moved[0].id,
+            collect_items("app", Some("crate::api"), output)[0].id
+        );
+    }
+
+    #[test]
+    fn test_attach_source_spans() {
+        let source = "#!/usr/bin/env rust-script\nimpl Config {\n    /// Loads\n    pub fn load() -> Self {\n        todo!()\n    }\n}\n";
+        let mut items = collect_items(
+            "app",
+            None,
+            "impl Config {\n    pub fn load() -> Self {}\n}\n",
+        );
+        assert!(items.iter().all(|item| item.source.is_none()));
+
+        attach_source(&mut items, "app", None, source);
+        assert_eq!(items[0].source, None);
+        let span = items[1].source.expect("functions have spans");
+        assert_eq!((span.start_line, span.end_line), (3, 6));
+        assert_eq!(
+            &source[span.start_byte..span.end_byte],
+            "/// Loads\n    pub fn load() -> Self {\n        todo!()\n    }"
+        );
+    }
+}
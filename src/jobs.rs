@@ -0,0 +1,108 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use tracing::Dispatch;
+
+/// Number of files processed in parallel unless `--jobs` is given: the logical cores
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, usize::from)
+}
+
+/// Applies a function to every item on up to `jobs` threads and hands each result to
+/// `consume` as soon as all earlier ones have been, so results are consumed in the order of
+/// the items regardless of which thread finishes first; stops at the first error of `consume`
+pub fn for_each_ordered<T, R, F, C>(items: &[T], jobs: usize, f: F, mut consume: C) -> Result<()>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    C: FnMut(R) -> Result<()>,
+{
+    let threads = jobs.clamp(1, items.len().max(1));
+    if threads == 1 {
+        return items.iter().try_for_each(|item| consume(f(item)));
+    }
+
+    let next = AtomicUsize::new(0);
+    let cancelled = AtomicBool::new(false);
+    // Workers log and time their spans with the subscriber of the calling thread
+    let dispatch = tracing::dispatcher::get_default(Dispatch::clone);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (f, next, cancelled, dispatch) = (&f, &next, &cancelled, &dispatch);
+            scope.spawn(move || {
+                tracing::dispatcher::with_default(dispatch, || {
+                    while !cancelled.load(Ordering::Relaxed) {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        if sender.send((index, f(item))).is_err() {
+                            break;
+                        }
+                    }
+                })
+            });
+        }
+        drop(sender);
+
+        // Results that arrive early wait until all earlier ones are consumed
+        let mut waiting = BTreeMap::new();
+        let mut expected = 0;
+        for (index, result) in receiver {
+            waiting.insert(index, result);
+            while let Some(result) = waiting.remove(&expected) {
+                if let Err(error) = consume(result) {
+                    cancelled.store(true, Ordering::Relaxed);
+                    return Err(error);
+                }
+                expected += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_for_each_ordered_keeps_item_order() -> Result<()> {
+        let items: Vec<u64> = (0..16).collect();
+        let mut squares = Vec::new();
+        // Earlier items take longer, so they finish last
+        let square = |item: &u64| {
+            thread::sleep(Duration::from_millis(16 - item));
+            item * item
+        };
+        for_each_ordered(&items, 4, square, |square| {
+            squares.push(square);
+            Ok(())
+        })?;
+        assert_eq!(
+            squares,
+            items.iter().map(|item| item * item).collect::<Vec<_>>()
+        );
+
+        let mut consumed = 0;
+        let result = for_each_ordered(
+            &items,
+            4,
+            |item| *item,
+            |item| {
+                consumed += 1;
+                anyhow::ensure!(item < 2, "stop");
+                Ok(())
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(consumed, 3);
+        Ok(())
+    }
+}
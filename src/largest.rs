@@ -0,0 +1,47 @@
+use crate::processor::FileStats;
+use std::fmt::Write;
+
+/// Indices of the `percent`% largest outputs, rounded up so any percentage drops at least
+/// one file; of files with the same size the later ones are dropped first
+pub fn largest(sizes: &[usize], percent: usize) -> Vec<usize> {
+    let count = (sizes.len() * percent).div_ceil(100);
+    let mut indices: Vec<usize> = (0..sizes.len()).collect();
+    indices.sort_by_key(|&index| (std::cmp::Reverse(sizes[index]), std::cmp::Reverse(index)));
+    indices.truncate(count);
+    indices.sort_unstable();
+    indices
+}
+
+/// Renders the note listing the dropped files at the top of the single-file output
+pub fn note(dropped: &[FileStats], percent: usize) -> String {
+    if dropped.is_empty() {
+        return String::new();
+    }
+    let mut note = format!("// Dropped the largest {}% of files:\n", percent);
+    for file in dropped {
+        let _ = writeln!(note, "//   {} ({} tokens)", file.path, file.tokens);
+    }
+    note.push('\n');
+    note
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_largest() {
+        let sizes = [10, 400, 30, 400, 5, 60, 70, 80, 90, 100];
+        assert_eq!(largest(&sizes, 10), vec![3]);
+        assert_eq!(largest(&sizes, 25), vec![1, 3, 9]);
+        assert_eq!(largest(&sizes[..3], 1), vec![1]);
+        assert_eq!(largest(&sizes, 100).len(), sizes.len());
+        assert!(largest(&[], 50).is_empty());
+
+        let dropped = [FileStats::new("big.rs", 8000, 4000)];
+        assert_eq!(
+            note(&dropped, 10),
+            "// Dropped the largest 10% of files:\n//   big.rs (1000 tokens)\n\n"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Files that must start with the same banner for `--strip-license-headers` to treat it as
+/// a license header
+const MIN_REPETITIONS: usize = 2;
+
+/// License or copyright banners removed from the top of processed files
+#[derive(Default, Debug)]
+pub struct LicenseHeaders {
+    /// Normalized banners that start several files of the input
+    repeated: HashSet<String>,
+    /// Banners matching this pattern are removed as well
+    pattern: Option<Regex>,
+}
+
+impl LicenseHeaders {
+    /// Removes the banners matching a pattern, if given, and with `detect` those that start
+    /// several of the given Rust files
+    pub fn new(files: &[PathBuf], detect: bool, pattern: Option<&str>) -> Result<Self> {
+        let pattern = pattern
+            .map(Regex::new)
+            .transpose()
+            .context("Invalid license header pattern")?;
+        let repeated = if detect {
+            repeated_banners(files)?
+        } else {
+            HashSet::new()
+        };
+        Ok(Self { repeated, pattern })
+    }
+
+    /// Removes the license header from the top of a processed file, returning whether
+    /// there was one
+    pub fn strip(&self, output: &mut String) -> bool {
+        let Some((banner, len)) = leading_banner(output) else {
+            return false;
+        };
+        let is_license = self.repeated.contains(&normalize(banner))
+            || self
+                .pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.is_match(banner));
+        if is_license {
+            output.drain(..len);
+        }
+        is_license
+    }
+}
+
+/// Counts the banners of the given Rust files, keeping those shared by several files
+fn repeated_banners(files: &[PathBuf]) -> Result<HashSet<String>> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for file in files {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read file: {}", file.display()))?;
+        if let Some((banner, _)) = leading_banner(&content) {
+            *counts.entry(normalize(banner)).or_default() += 1;
+        }
+    }
+    Ok(counts
+        .into_iter()
+        .filter(|(_, count)| *count >= MIN_REPETITIONS)
+        .map(|(banner, _)| banner)
+        .collect())
+}
+
+/// Finds the regular comments at the top of a file, returning their text and the length of
+/// the banner including the blank lines after it; doc comments are never part of a banner
+fn leading_banner(source: &str) -> Option<(&str, usize)> {
+    let mut end = 0;
+    let mut in_block = false;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if in_block {
+            in_block = !trimmed.ends_with("*/");
+        } else if trimmed.starts_with("/*") && !is_doc_block(trimmed) {
+            in_block = !trimmed[2..].ends_with("*/");
+        } else if !trimmed.starts_with("//") || is_doc_line(trimmed) {
+            break;
+        }
+        end += line.len();
+    }
+    if end == 0 || in_block {
+        return None;
+    }
+    let banner = &source[..end];
+    let blank = source[end..]
+        .split_inclusive('\n')
+        .take_while(|line| line.trim().is_empty())
+        .map(str::len)
+        .sum::<usize>();
+    Some((banner, end + blank))
+}
+
+fn is_doc_line(line: &str) -> bool {
+    (line.starts_with("///") && !line.starts_with("////")) || line.starts_with("//!")
+}
+
+fn is_doc_block(line: &str) -> bool {
+    (line.starts_with("/**") && !line.starts_with("/***") && line != "/**/")
+        || line.starts_with("/*!")
+}
+
+/// Normalizes a banner so that banners differing only in years or spacing are the same
+fn normalize(banner: &str) -> String {
+    let mut normalized = String::with_capacity(banner.len());
+    for word in banner.split_whitespace() {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        let mut previous_digit = false;
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                if !previous_digit {
+                    normalized.push('#');
+                }
+            } else {
+                normalized.push(c);
+            }
+            previous_digit = c.is_ascii_digit();
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_leading_banner() {
+        let source = "// Copyright 2024 Acme\n// SPDX-License-Identifier: MIT\n\n//! Crate docs\nfn a() {}\n";
+        let (banner, len) = leading_banner(source).unwrap();
+        assert_eq!(
+            banner,
+            "// Copyright 2024 Acme\n// SPDX-License-Identifier: MIT\n"
+        );
+        assert_eq!(&source[len..], "//! Crate docs\nfn a() {}\n");
+
+        let source = "/*\n * Licensed under Apache-2.0\n */\nuse std::fmt;\n";
+        let (_, len) = leading_banner(source).unwrap();
+        assert_eq!(&source[len..], "use std::fmt;\n");
+
+        assert!(leading_banner("/// Docs\nfn a() {}\n").is_none());
+        assert!(leading_banner("/*! Docs */\nfn a() {}\n").is_none());
+        assert!(leading_banner("fn a() {}\n// trailing\n").is_none());
+        assert_eq!(
+            normalize("// Copyright 2019-2024  Acme"),
+            normalize("// Copyright 2021-2023 Acme")
+        );
+    }
+
+    #[test]
+    fn test_strip_repeated_or_matching_banners() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input = temp_dir.path();
+        std::fs::write(input.join("a.rs"), "// Copyright 2023 Acme\n\nfn a() {}\n")?;
+        std::fs::write(input.join("b.rs"), "// Copyright 2024 Acme\nfn b() {}\n")?;
+        std::fs::write(input.join("c.rs"), "// Parses the input\nfn c() {}\n")?;
+        let files: Vec<_> = ["a.rs", "b.rs", "c.rs"]
+            .iter()
+            .map(|name| input.join(name))
+            .collect();
+
+        let headers = LicenseHeaders::new(&files, true, None)?;
+        let mut output = "// Copyright 2022 Acme\n\nfn a() {}\n".to_string();
+        assert!(headers.strip(&mut output));
+        assert_eq!(output, "fn a() {}\n");
+        let mut output = "// Parses the input\nfn c() {}\n".to_string();
+        assert!(!headers.strip(&mut output));
+
+        let headers = LicenseHeaders::new(&files, false, Some("(?i)parses"))?;
+        assert!(headers.strip(&mut output));
+        assert_eq!(output, "fn c() {}\n");
+        assert!(LicenseHeaders::new(&files, false, Some("(")).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::git::item_label;
+use crate::paths;
+use clap::ValueEnum;
+use std::path::Path;
+use syn::spanned::Spanned;
+
+/// Style of the links to source locations emitted before each file
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// `file:///abs/path/to/file.rs:L123`
+    File,
+    /// `vscode://file/abs/path/to/file.rs:123`, opened by VS Code
+    Vscode,
+}
+
+impl LinkStyle {
+    /// Returns a link to a 1-based line of a file
+    pub fn link(&self, path: &Path, line: usize) -> String {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let path = paths::slash(&path).replace(' ', "%20");
+        let path = path.trim_start_matches('/');
+        match self {
+            LinkStyle::File => format!("file:///{}:L{}", path, line),
+            LinkStyle::Vscode => format!("vscode://file/{}:{}", path, line),
+        }
+    }
+}
+
+/// Renders links to a source file and each of its top-level items
+pub fn annotation(source: &Path, content: &str, style: LinkStyle) -> String {
+    let mut annotation = format!("// Source: {}\n", style.link(source, 1));
+    let Ok(file) = syn::parse_file(content) else {
+        return annotation;
+    };
+    let items: Vec<String> = file
+        .items
+        .iter()
+        .filter_map(|item| {
+            let line = item.span().start().line;
+            Some(format!(
+                "//   {}: {}\n",
+                item_label(item)?,
+                style.link(source, line)
+            ))
+        })
+        .collect();
+    if !items.is_empty() {
+        annotation.push_str("// Items:\n");
+        annotation.extend(items);
+    }
+    annotation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_links() {
+        let source = Path::new("/nonexistent/src/my lib.rs");
+        assert_eq!(
+            annotation(source, "use std::fmt;\n\npub struct Config;\n", LinkStyle::Vscode),
+            "// Source: vscode://file/nonexistent/src/my%20lib.rs:1\n// Items:\n//   struct Config: vscode://file/nonexistent/src/my%20lib.rs:3\n"
+        );
+        assert_eq!(
+            LinkStyle::File.link(source, 12),
+            "file:///nonexistent/src/my%20lib.rs:L12"
+        );
+    }
+}
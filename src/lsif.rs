@@ -0,0 +1,471 @@
+use crate::transformer::RustAnalyzer;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use syn::visit::{self, Visit};
+
+/// Position of a definition in the indexed project
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    /// 0-based line number
+    pub line: u32,
+    /// 0-based character offset of the start of the range
+    pub character: u32,
+}
+
+/// An identifier in a source file resolved to its definition
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Reference {
+    line: u32,
+    start: u32,
+    end: u32,
+    definition: Location,
+}
+
+/// A definition in a source file with the places that implement it, e.g. a trait and its
+/// `impl` blocks
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Implemented {
+    line: u32,
+    start: u32,
+    end: u32,
+    implementations: Vec<Location>,
+}
+
+/// Definitions resolved by rust-analyzer, read from an LSIF dump
+/// (`rust-analyzer lsif <crate> > dump.lsif`)
+#[derive(Clone, Debug, Default)]
+pub struct LsifIndex {
+    project_root: PathBuf,
+    /// References by absolute source path
+    references: HashMap<PathBuf, Vec<Reference>>,
+    /// Implemented definitions by absolute source path
+    implemented: HashMap<PathBuf, Vec<Implemented>>,
+    /// Type aliases whose definition resolves to a string-like type, shared by the
+    /// transformers of all files
+    string_aliases: Arc<HashSet<String>>,
+}
+
+/// Converts a `file://` URI to a path; the drive of a Windows URI such as `file:///C:/src`
+/// loses its leading slash
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut bytes = path.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+            if let Some(value) = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(value);
+                continue;
+            }
+            decoded.push(byte);
+            decoded.extend(hex);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    if let [b'/', drive, b':', ..] = decoded[..] {
+        if drive.is_ascii_alphabetic() {
+            decoded.remove(0);
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Collects the type aliases of a file, including those of inline modules
+#[derive(Default)]
+struct TypeAliases<'ast> {
+    aliases: Vec<&'ast syn::ItemType>,
+}
+
+impl<'ast> Visit<'ast> for TypeAliases<'ast> {
+    fn visit_item_type(&mut self, item: &'ast syn::ItemType) {
+        self.aliases.push(item);
+        visit::visit_item_type(self, item);
+    }
+}
+
+/// Returns the text of a range on one line, counting characters
+fn range_text(content: &str, line: u32, start: u32, end: u32) -> Option<String> {
+    let line = content.lines().nth(line as usize)?;
+    let text: String = line
+        .chars()
+        .skip(start as usize)
+        .take(end.saturating_sub(start) as usize)
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+impl LsifIndex {
+    /// Loads an LSIF dump in JSON lines or JSON array form
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read LSIF dump: {}", path.display()))?;
+        let elements: Vec<Value> = if content.trim_start().starts_with('[') {
+            serde_json::from_str(&content)
+        } else {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect()
+        }
+        .with_context(|| format!("Failed to parse LSIF dump: {}", path.display()))?;
+        Ok(Self::from_elements(&elements))
+    }
+
+    fn from_elements(elements: &[Value]) -> Self {
+        let mut project_root = PathBuf::new();
+        let mut documents: HashMap<u64, PathBuf> = HashMap::new();
+        let mut ranges: HashMap<u64, (u32, u32, u32)> = HashMap::new();
+        let mut range_documents: HashMap<u64, u64> = HashMap::new();
+        let mut next: HashMap<u64, u64> = HashMap::new();
+        let mut definition_results: HashMap<u64, u64> = HashMap::new();
+        let mut implementation_results: HashMap<u64, u64> = HashMap::new();
+        let mut items: HashMap<u64, Vec<(u64, u64)>> = HashMap::new();
+
+        let id = |value: &Value| value.as_u64();
+        let in_vertices = |element: &Value| -> Vec<u64> {
+            match (element.get("inV"), element.get("inVs")) {
+                (Some(vertex), _) => id(vertex).into_iter().collect(),
+                (None, Some(Value::Array(vertices))) => vertices.iter().filter_map(id).collect(),
+                _ => Vec::new(),
+            }
+        };
+        let position = |element: &Value, key: &str, field: &str| {
+            element
+                .get(key)
+                .and_then(|position| position.get(field))
+                .and_then(Value::as_u64)
+                .and_then(|value| u32::try_from(value).ok())
+        };
+
+        for element in elements {
+            let (Some(element_id), Some(label)) = (
+                element.get("id").and_then(id),
+                element.get("label").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            let out_vertex = element.get("outV").and_then(id);
+            match (label, out_vertex) {
+                ("metaData", _) => {
+                    if let Some(root) = element.get("projectRoot").and_then(Value::as_str) {
+                        project_root = uri_to_path(root);
+                    }
+                }
+                ("document", _) => {
+                    if let Some(uri) = element.get("uri").and_then(Value::as_str) {
+                        documents.insert(element_id, uri_to_path(uri));
+                    }
+                }
+                ("range", _) => {
+                    if let (Some(line), Some(start), Some(end)) = (
+                        position(element, "start", "line"),
+                        position(element, "start", "character"),
+                        position(element, "end", "character"),
+                    ) {
+                        ranges.insert(element_id, (line, start, end));
+                    }
+                }
+                ("contains", Some(document)) => {
+                    for range in in_vertices(element) {
+                        range_documents.insert(range, document);
+                    }
+                }
+                ("next", Some(from)) => {
+                    if let Some(&to) = in_vertices(element).first() {
+                        next.insert(from, to);
+                    }
+                }
+                ("textDocument/definition", Some(from)) => {
+                    if let Some(&to) = in_vertices(element).first() {
+                        definition_results.insert(from, to);
+                    }
+                }
+                ("textDocument/implementation", Some(from)) => {
+                    if let Some(&to) = in_vertices(element).first() {
+                        implementation_results.insert(from, to);
+                    }
+                }
+                ("item", Some(result)) => {
+                    if let Some(document) = element.get("document").and_then(id) {
+                        items.entry(result).or_default().extend(
+                            in_vertices(element)
+                                .into_iter()
+                                .map(|range| (range, document)),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let location = |range: u64, document: u64| {
+            let (&(line, character, _), path) = (ranges.get(&range)?, documents.get(&document)?);
+            Some(Location {
+                path: path.clone(),
+                line,
+                character,
+            })
+        };
+        let mut references: HashMap<PathBuf, Vec<Reference>> = HashMap::new();
+        let mut implemented: HashMap<PathBuf, Vec<Implemented>> = HashMap::new();
+        for (&range, &document) in &range_documents {
+            let (Some(&(line, start, end)), Some(path)) =
+                (ranges.get(&range), documents.get(&document))
+            else {
+                continue;
+            };
+            let result_set = next.get(&range).copied().unwrap_or(range);
+            let results = |results: &HashMap<u64, u64>| {
+                results
+                    .get(&result_set)
+                    .or_else(|| results.get(&range))
+                    .and_then(|result| items.get(result))
+            };
+            let Some(&(target, target_document)) =
+                results(&definition_results).and_then(|targets| targets.first())
+            else {
+                continue;
+            };
+            if target == range {
+                // The definition itself, which may have implementations
+                let implementations: Vec<_> = results(&implementation_results)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|&(range, document)| location(range, document))
+                    .collect();
+                if !implementations.is_empty() {
+                    implemented
+                        .entry(path.clone())
+                        .or_default()
+                        .push(Implemented {
+                            line,
+                            start,
+                            end,
+                            implementations,
+                        });
+                }
+                continue;
+            }
+            let Some(definition) = location(target, target_document) else {
+                continue;
+            };
+            references.entry(path.clone()).or_default().push(Reference {
+                line,
+                start,
+                end,
+                definition,
+            });
+        }
+        for file_references in references.values_mut() {
+            file_references.sort_by_key(|reference| (reference.line, reference.start));
+        }
+        for definitions in implemented.values_mut() {
+            definitions.sort_by_key(|definition| (definition.line, definition.start));
+            for definition in definitions.iter_mut() {
+                definition
+                    .implementations
+                    .sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+                definition.implementations.dedup();
+            }
+        }
+
+        let string_aliases = Arc::new(resolve_string_aliases(&references));
+        Self {
+            project_root,
+            references,
+            implemented,
+            string_aliases,
+        }
+    }
+
+    /// Type aliases that resolve to string-like types
+    pub fn string_aliases(&self) -> Arc<HashSet<String>> {
+        Arc::clone(&self.string_aliases)
+    }
+
+    /// Shows a location relative to the project root, e.g. `src/types.rs:12`
+    fn display(&self, location: &Location) -> String {
+        let path = location
+            .path
+            .strip_prefix(&self.project_root)
+            .unwrap_or(&location.path);
+        format!("{}:{}", path.display(), location.line + 1)
+    }
+
+    /// Renders links from the identifiers of a source file that remain in its output to
+    /// their definitions in other files, and from its definitions, such as traits, to their
+    /// implementations in other files
+    pub fn definition_links(&self, source: &Path, content: &str, output: &str) -> String {
+        let source = std::fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+        let mut links = BTreeMap::new();
+        for reference in self.references.get(&source).into_iter().flatten() {
+            if reference.definition.path == source {
+                continue;
+            }
+            let Some(name) = range_text(content, reference.line, reference.start, reference.end)
+            else {
+                continue;
+            };
+            if !output.contains(&name) {
+                continue;
+            }
+            links
+                .entry(name)
+                .or_insert_with(|| self.display(&reference.definition));
+        }
+        let mut implementations = BTreeMap::new();
+        for definition in self.implemented.get(&source).into_iter().flatten() {
+            let locations: Vec<_> = definition
+                .implementations
+                .iter()
+                .filter(|location| location.path != source)
+                .map(|location| self.display(location))
+                .collect();
+            let Some(name) = range_text(content, definition.line, definition.start, definition.end)
+            else {
+                continue;
+            };
+            if !locations.is_empty() && output.contains(&name) {
+                implementations
+                    .entry(name)
+                    .or_insert_with(|| locations.join(", "));
+            }
+        }
+
+        let mut block = String::new();
+        for (heading, entries) in [("Definitions", links), ("Implementations", implementations)] {
+            if entries.is_empty() {
+                continue;
+            }
+            block.push_str(&format!("// {}:\n", heading));
+            for (name, locations) in entries {
+                block.push_str(&format!("//   {}: {}\n", name, locations));
+            }
+        }
+        block
+    }
+}
+
+/// Finds the type aliases among the definitions that alias string-like types, parsing each
+/// file with definitions once and matching the alias that starts at a definition
+fn resolve_string_aliases(references: &HashMap<PathBuf, Vec<Reference>>) -> HashSet<String> {
+    let mut definitions: HashMap<&Path, HashSet<(usize, usize)>> = HashMap::new();
+    for reference in references.values().flatten() {
+        let definition = &reference.definition;
+        definitions
+            .entry(&definition.path)
+            .or_default()
+            .insert((definition.line as usize + 1, definition.character as usize));
+    }
+    let mut aliases = HashSet::new();
+    for (path, positions) in definitions {
+        let Some(file) = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|source| syn::parse_file(&source).ok())
+        else {
+            continue;
+        };
+        let mut collector = TypeAliases::default();
+        collector.visit_file(&file);
+        for alias in collector.aliases {
+            let start = alias.ident.span().start();
+            if positions.contains(&(start.line, start.column))
+                && RustAnalyzer::is_string_or_json_type(&alias.ty)
+            {
+                aliases.insert(alias.ident.to_string());
+            }
+        }
+    }
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_definitions() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = std::fs::canonicalize(temp_dir.path())?;
+        // An alias whose definition spans lines
+        let types = "pub type Name =\n    String;\npub trait Named {}\n";
+        let lib = "pub fn name() -> Name { todo!() }\nimpl Named for Name {}\n";
+        std::fs::write(root.join("types.rs"), types)?;
+        std::fs::write(root.join("lib.rs"), lib)?;
+        let uri = |file: &str| format!("file://{}", root.join(file).display());
+
+        let elements = vec![
+            json!({"id": 1, "type": "vertex", "label": "metaData", "projectRoot": uri("")}),
+            json!({"id": 2, "type": "vertex", "label": "document", "uri": uri("types.rs")}),
+            json!({"id": 3, "type": "vertex", "label": "document", "uri": uri("lib.rs")}),
+            json!({"id": 4, "type": "vertex", "label": "range",
+                "start": {"line": 0, "character": 9}, "end": {"line": 0, "character": 13}}),
+            json!({"id": 5, "type": "vertex", "label": "range",
+                "start": {"line": 0, "character": 17}, "end": {"line": 0, "character": 21}}),
+            json!({"id": 6, "type": "edge", "label": "contains", "outV": 2, "inVs": [4]}),
+            json!({"id": 7, "type": "edge", "label": "contains", "outV": 3, "inVs": [5]}),
+            json!({"id": 8, "type": "vertex", "label": "resultSet"}),
+            json!({"id": 9, "type": "edge", "label": "next", "outV": 4, "inV": 8}),
+            json!({"id": 10, "type": "edge", "label": "next", "outV": 5, "inV": 8}),
+            json!({"id": 11, "type": "vertex", "label": "definitionResult"}),
+            json!({"id": 12, "type": "edge", "label": "textDocument/definition", "outV": 8, "inV": 11}),
+            json!({"id": 13, "type": "edge", "label": "item", "outV": 11, "inVs": [4], "document": 2}),
+            // The trait `Named` and its implementation in lib.rs
+            json!({"id": 14, "type": "vertex", "label": "range",
+                "start": {"line": 2, "character": 10}, "end": {"line": 2, "character": 15}}),
+            json!({"id": 15, "type": "vertex", "label": "range",
+                "start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 22}}),
+            json!({"id": 16, "type": "edge", "label": "contains", "outV": 2, "inVs": [14]}),
+            json!({"id": 17, "type": "edge", "label": "contains", "outV": 3, "inVs": [15]}),
+            json!({"id": 18, "type": "vertex", "label": "resultSet"}),
+            json!({"id": 19, "type": "edge", "label": "next", "outV": 14, "inV": 18}),
+            json!({"id": 20, "type": "vertex", "label": "definitionResult"}),
+            json!({"id": 21, "type": "edge", "label": "textDocument/definition", "outV": 18, "inV": 20}),
+            json!({"id": 22, "type": "edge", "label": "item", "outV": 20, "inVs": [14], "document": 2}),
+            json!({"id": 23, "type": "vertex", "label": "implementationResult"}),
+            json!({"id": 24, "type": "edge", "label": "textDocument/implementation", "outV": 18, "inV": 23}),
+            json!({"id": 25, "type": "edge", "label": "item", "outV": 23, "inVs": [15], "document": 3}),
+        ];
+        let index = LsifIndex::from_elements(&elements);
+
+        assert!(index.string_aliases().contains("Name"));
+        assert_eq!(
+            index.definition_links(&root.join("lib.rs"), lib, lib),
+            "// Definitions:\n//   Name: types.rs:1\n"
+        );
+        assert_eq!(
+            index.definition_links(&root.join("types.rs"), types, types),
+            "// Implementations:\n//   Named: lib.rs:2\n"
+        );
+        assert_eq!(
+            index.definition_links(&root.join("types.rs"), types, "pub type Name = String;\n"),
+            ""
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_uri_to_path() {
+        assert_eq!(
+            uri_to_path("file:///home/me/my%20app"),
+            PathBuf::from("/home/me/my app")
+        );
+        assert_eq!(
+            uri_to_path("file:///C:/src/app"),
+            PathBuf::from("C:/src/app")
+        );
+        assert_eq!(uri_to_path("file:///c%3A/src"), PathBuf::from("c:/src"));
+    }
+}
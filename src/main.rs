@@ -1,83 +1,1281 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use std::path::PathBuf;
+use clap::builder::TypedValueParser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::ffi::OsString;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
 
-use self::processor::{FileProcessor, Processor};
+use self::aliases::{AliasExpansion, TypeAliases};
+use self::batch::{Caches, JobFile};
+use self::cargo::{CrateTarget, TargetForm};
+use self::codegen::BuildScriptForm;
+use self::compression::Compression;
+use self::config::Config;
+use self::errors::{ErrorFormat, ErrorKind, Failure};
+use self::fileset::FileSet;
+use self::format::Formatter;
+use self::front_matter::FrontMatter;
+use self::license::LicenseHeaders;
+use self::links::LinkStyle;
+use self::manifest::Manifest;
+use self::models::ModelContext;
+use self::module_filter::ModuleFilter;
+use self::normalize::SignatureStyle;
+use self::ordering::Order;
+use self::packaging::Tool;
+use self::plan::PlanFormat;
+use self::presets::{PathExclusions, Preset};
+use self::processor::{FileProcessor, ProcessingStats, Processor};
+use self::progress::ProgressMode;
+use self::provenance::Provenance;
+use self::sanitize::SanitizeMode;
+use self::scip::OutputFormat;
+use self::section_template::SectionTemplate;
+use self::snapshot::SnapshotStore;
+use self::stats::StatsFormat;
+use self::summary::SummarySection;
+use self::timings::TimingLayer;
+use self::transformer::TraitAnnotations;
+use self::visibility::VisibilityLevel;
 
+mod aliases;
+mod annotate;
+mod arg_files;
+mod batch;
+mod budget;
+mod canonical;
+mod cargo;
+mod codegen;
+mod combined;
+mod compression;
+mod config;
+mod daemon;
+mod dedupe;
+mod diagram;
+mod diff;
+mod doc_metadata;
+mod doc_stats;
+mod endpoints;
+mod errors;
+mod explain;
+mod features;
+mod fileset;
+mod format;
+mod front_matter;
+#[cfg(any(test, fuzzing))]
+mod fuzz;
+mod git;
+mod hierarchy;
+mod hook;
+mod impl_families;
+mod impl_groups;
+mod includes;
+mod insertion_points;
+mod item_ids;
+mod jobs;
+mod largest;
+mod license;
+mod links;
+mod lsif;
+mod manifest;
+mod metrics;
+mod models;
+mod module_filter;
 mod module_path;
+mod normalize;
+mod ordering;
+mod output_path;
+mod packaging;
+mod pairs;
+mod paths;
+mod plan;
+mod preserve;
+mod presets;
 mod processor;
+mod progress;
+mod provenance;
+mod queries;
+mod removals;
+mod repl;
+mod sanitize;
+mod scip;
+mod script;
+mod search;
+mod section_template;
+mod show;
+mod snapshot;
+mod stats;
+mod structure;
+mod stub;
+mod summary;
 mod test_utils;
+mod timings;
+mod token_costs;
 mod transformer;
+mod translate;
+mod trim;
+mod type_usage;
+mod update;
+mod validate;
+mod visibility;
 
-#[derive(Parser, Debug)]
-#[command(author, version, about)]
+/// Prefix of the environment variables that set options, e.g. `CODE_CONTEXT_NO_COMMENTS`
+const ENV_PREFIX: &str = "CODE_CONTEXT_";
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input file or directory path
-    input_path: PathBuf,
+    #[arg(required = true)]
+    input_path: Option<PathBuf>,
 
-    /// Output directory path (default: "code-context")
+    /// Output directory path (default: "code-context"), or a template of the whole output
+    /// path with {parent}, {name}, {crate}, {sha}, and {date} tokens
     #[arg(short = 'o', long = "output-dir")]
     output_dir_name: Option<String>,
 
+    /// Configuration file (default: nearest code-context.toml at or above the input path)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Shell command run after a successful generation, with the output path and stats in
+    /// CODE_CONTEXT_OUTPUT, CODE_CONTEXT_MANIFEST, CODE_CONTEXT_FILES, CODE_CONTEXT_INPUT_SIZE,
+    /// CODE_CONTEXT_OUTPUT_SIZE, and CODE_CONTEXT_TOKENS
+    #[arg(long, value_name = "CMD")]
+    post_hook: Option<String>,
+
+    /// Format of the error that ends a run, written to stderr; exit codes tell the kind of
+    /// error: 1 other, 2 usage, 3 parse failure, 4 size limit exceeded, 5 IO error, 6 partial
+    /// success of a batch
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human, global = true)]
+    error_format: ErrorFormat,
+
     /// Remove all comments (including doc comments)
     #[arg(long)]
     no_comments: bool,
 
+    /// Keep comments, overriding an earlier `--no-comments` or its environment variable
+    #[arg(long)]
+    comments: bool,
+
     /// Remove function bodies except for string/serialization methods
     #[arg(long)]
     no_function_bodies: bool,
 
+    /// Keep function bodies, overriding an earlier `--no-function-bodies` or its
+    /// environment variable
+    #[arg(long)]
+    function_bodies: bool,
+
     /// Don't print processing statistics
     #[arg(long)]
     no_stats: bool,
 
-    /// Run without writing output files
+    /// Print processing statistics, overriding an earlier `--no-stats` or its environment
+    /// variable
+    #[arg(long)]
+    stats: bool,
+
+    /// Layout of the processing statistics
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    stats_format: StatsFormat,
+
+    /// Write run metrics (durations, sizes, file counts, cache hit ratio) to this file in the
+    /// OpenMetrics text format, also when the run fails
+    #[arg(long, value_name = "PATH")]
+    metrics_file: Option<PathBuf>,
+
+    /// Write the processed files and their content hashes to this file, to replay the
+    /// selection later with `--fileset`
+    #[arg(long, value_name = "FILE")]
+    record_fileset: Option<PathBuf>,
+
+    /// Process only the files recorded by `--record-fileset`, failing if any is gone and
+    /// warning about those whose content changed
+    #[arg(long, value_name = "FILE")]
+    fileset: Option<PathBuf>,
+
+    /// Run without writing output files and print what would be done
     #[arg(long)]
     dry_run: bool,
 
+    /// Format of the dry-run plan
+    #[arg(long, value_enum, default_value_t, requires = "dry_run")]
+    plan_format: PlanFormat,
+
+    /// Only write a structural report (file tree, item counts, public API, and token
+    /// estimates) instead of any code
+    #[arg(long, conflicts_with_all = ["check", "update"])]
+    summary_only: bool,
+
     /// Output all files into a single combined file
     #[arg(long)]
     single_file: bool,
+
+    /// Also write the per-file output tree next to the combined file
+    #[arg(long, requires = "single_file")]
+    also_tree: bool,
+
+    /// Write outputs as `.rs` files starting with a generated marker instead of `.rs.txt`
+    #[arg(long)]
+    keep_rs_extension: bool,
+
+    /// Copy the files with summary comments (item counts, function complexity, token
+    /// estimates) inserted instead of condensing them, for skimming in code review; implies
+    /// `--keep-rs-extension`
+    #[arg(long, conflicts_with_all = ["no_comments", "no_function_bodies", "preserve_formatting"])]
+    annotate: bool,
+
+    /// Edit the original source instead of reformatting it, keeping non-doc comments
+    #[arg(long)]
+    preserve_formatting: bool,
+
+    /// Formatter used to print the transformed code
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        conflicts_with = "preserve_formatting"
+    )]
+    format_with: Formatter,
+
+    /// Replace item-level include!("…") invocations with the included file's items
+    #[arg(long, conflicts_with = "preserve_formatting")]
+    inline_includes: bool,
+
+    /// Copy files referenced by include!, include_str!, and include_bytes! into the output
+    #[arg(long)]
+    copy_includes: bool,
+
+    /// Elide named lifetimes in function signatures (`&'a str` → `&str`)
+    #[arg(long, conflicts_with = "preserve_formatting")]
+    elide_lifetimes: bool,
+
+    /// Collapse fully-qualified std paths (`std::collections::HashMap` → `HashMap`)
+    #[arg(long, conflicts_with = "preserve_formatting")]
+    short_std_paths: bool,
+
+    /// Collapse where-clauses with more than N bounds to `where /* N bounds */`
+    #[arg(long, value_name = "N", conflicts_with = "preserve_formatting")]
+    collapse_where: Option<usize>,
+
+    /// Tag the declaration of each item with a trailing `// pub`, `// pub(crate)`, or
+    /// `// private` comment
+    #[arg(long)]
+    visibility_tags: bool,
+
+    /// Append the estimated tokens of each item as a trailing `// ~142 tokens` comment
+    #[arg(long)]
+    token_costs: bool,
+
+    /// Lowest visibility of the items kept in the output
+    #[arg(long, value_enum, value_name = "LEVEL", default_value_t)]
+    visibility: VisibilityLevel,
+
+    /// Replace the non-public fields of structs with a `/* private fields */` marker, as
+    /// rustdoc shows them
+    #[arg(long)]
+    hide_private_fields: bool,
+
+    /// Collapse families of three or more trait impls that differ only in one type, such as
+    /// `impl From<u8>`, `impl From<u16>`, ..., into the first one and a list of the types
+    #[arg(long)]
+    collapse_impls: bool,
+
+    /// Drop the impls of boilerplate traits, by path or name (e.g. `Clone,Debug,PartialEq`),
+    /// keeping inherent impls and the impls of other traits
+    #[arg(long, value_delimiter = ',', value_name = "TRAITS")]
+    skip_trait_impls: Vec<String>,
+
+    /// Keep only these module subtrees in full (e.g. `crate::api,crate::domain::*`, where `*`
+    /// matches any one module), reducing the files of other modules to summary stubs
+    #[arg(long, value_delimiter = ',', value_name = "PATHS")]
+    module_filter: Vec<String>,
+
+    /// Exclude the paths of built-in presets (repeatable) from the walk, `include!` inlining,
+    /// and `--copy-includes`; `no-secrets` excludes secrets files, `.env*` files, migrations,
+    /// and key or certificate files
+    #[arg(long, value_delimiter = ',', value_name = "PRESET")]
+    preset: Vec<Preset>,
+
+    /// Stamp the top of every output file with the tool version, command line, source
+    /// commit, and generation time (`SOURCE_DATE_EPOCH` if set)
+    #[arg(long)]
+    provenance_banner: bool,
+
+    /// Expand simple type aliases such as `type Result<T> = std::result::Result<T, Error>`
+    /// where they are used: `inline` replaces the uses, `annotate` notes the expansion on the
+    /// line of each signature that uses them
+    #[arg(long, value_name = "MODE")]
+    expand_aliases: Option<AliasExpansion>,
+
+    /// Only expand the named type aliases (repeatable)
+    #[arg(long, value_name = "NAME", requires = "expand_aliases")]
+    expand_alias: Vec<String>,
+
+    /// Crate-wide summary sections to emit at the top of the context
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "SECTIONS")]
+    summary: Vec<SummarySection>,
+
+    /// Order of the files in the combined single-file output (default: directory walk order)
+    #[arg(long, value_enum)]
+    order: Option<Order>,
+
+    /// Number of days of git history considered by `--order churn`
+    #[arg(long, value_name = "DAYS", default_value_t = ordering::DEFAULT_CHURN_WINDOW_DAYS)]
+    churn_window: u64,
+
+    /// Emit a metadata block (module path, item counts, token estimate, last commit) per file
+    #[arg(long, value_enum, value_name = "STYLE")]
+    front_matter: Option<FrontMatter>,
+
+    /// Annotate each file with its last commit hash, author, and date
+    #[arg(long)]
+    git_metadata: bool,
+
+    /// Also annotate each file with the most recent commit of every top-level item
+    #[arg(long, requires = "git_metadata")]
+    git_blame: bool,
+
+    /// Output format; `scip` also writes a SCIP index of the condensed code, `jsonl` a
+    /// record per item with its stable id, `pairs-jsonl` the signature and body of each
+    /// function
+    #[arg(long, value_enum, default_value_t)]
+    format: OutputFormat,
+
+    /// Normalize line endings, trailing whitespace, and Unicode (NFC) of processed files
+    #[arg(long)]
+    normalize_output: bool,
+
+    /// Re-parse every processed file and fail if the output isn't valid Rust
+    #[arg(long)]
+    validate_output: bool,
+
+    /// Remove hidden Unicode characters and strip or flag prompt-injection phrases in
+    /// comments and literals
+    #[arg(long, value_enum, value_name = "MODE", num_args = 0..=1, default_missing_value = "strip")]
+    sanitize: Option<SanitizeMode>,
+
+    /// Shell command that reads each retained doc comment on stdin and writes its
+    /// replacement, e.g. a translation, to stdout
+    #[arg(long, value_name = "COMMAND")]
+    translate_docs: Option<String>,
+
+    /// Text written before each file of the single-file output; `{path}` is the file's
+    /// path, and `\n` and `\t` are newlines and tabs
+    #[arg(long, value_name = "TEMPLATE", default_value = section_template::DEFAULT_FILE_HEADER)]
+    file_header: String,
+
+    /// Text written after each file of the single-file output, with the same placeholders
+    /// as `--file-header`
+    #[arg(long, value_name = "TEMPLATE", default_value = section_template::DEFAULT_FILE_FOOTER)]
+    file_footer: String,
+
+    /// Remove the comment banner at the top of files when several files start with it,
+    /// such as a license or copyright header (with `--preserve-formatting` or a verbatim
+    /// build script, as other comments are dropped anyway)
+    #[arg(long)]
+    strip_license_headers: bool,
+
+    /// Also remove comment banners at the top of files that match this regex
+    #[arg(long, value_name = "REGEX")]
+    license_header_pattern: Option<String>,
+
+    /// Drop the `#!` shebang and `---` manifest frontmatter of cargo scripts instead of
+    /// keeping them at the top of the output
+    #[arg(long)]
+    strip_script_header: bool,
+
+    /// Remove inner attributes of files and modules, such as `#![allow(...)]` and
+    /// `#![cfg_attr(...)]`, keeping inner doc comments
+    #[arg(long)]
+    strip_inner_attributes: bool,
+
+    /// Emit links to the source location of each file and top-level item
+    #[arg(long, value_enum, value_name = "STYLE")]
+    links: Option<LinkStyle>,
+
+    /// Write a Mermaid diagram of modules and public types (code_context.mmd)
+    #[arg(long)]
+    mermaid: bool,
+
+    /// Module depth shown in the Mermaid diagram
+    #[arg(long, value_name = "N", default_value_t = diagram::DEFAULT_DIAGRAM_DEPTH, requires = "mermaid")]
+    mermaid_depth: usize,
+
+    /// Also package the processed files for a tool, in a directory named after it inside the
+    /// output directory
+    #[arg(long, value_name = "TOOL", conflicts_with = "single_file")]
+    target: Option<Tool>,
+
+    /// Write the source location of every elided function body
+    /// (code_context.insertion_points.jsonl)
+    #[arg(long)]
+    insertion_points: bool,
+
+    /// rust-analyzer LSIF dump (`rust-analyzer lsif <crate>`) used to resolve type aliases
+    /// and link definitions and trait implementations
+    #[arg(long, value_name = "FILE")]
+    lsif: Option<PathBuf>,
+
+    /// Re-generate only the sections of changed files in the existing single-file output
+    #[arg(long, requires = "single_file", conflicts_with = "group_impls")]
+    update: bool,
+
+    /// Move impl blocks from other files right after their type's definition
+    #[arg(long, requires = "single_file")]
+    group_impls: bool,
+
+    /// Keep the bodies of functions that run SQL queries (sqlx, diesel, raw SQL)
+    #[arg(long)]
+    keep_queries: bool,
+
+    /// Collect all SQL queries into a "Queries" appendix
+    #[arg(long)]
+    query_appendix: bool,
+
+    /// Attribute macro (path or name) to keep as-is and never treat as a test marker,
+    /// in addition to async_trait, axum::debug_handler, and tracing::instrument
+    #[arg(long, value_name = "PATH")]
+    transparent_attribute: Vec<String>,
+
+    /// Keep benchmark functions, modules, and files under benches/
+    #[arg(long)]
+    keep_benches: bool,
+
+    /// Include the `tests/` directory of a crate root
+    #[arg(long, value_enum, value_name = "FORM", num_args = 0..=1, default_missing_value = "condensed")]
+    tests: Option<TargetForm>,
+
+    /// Include the `examples/` directory of a crate root
+    #[arg(long, value_enum, value_name = "FORM", num_args = 0..=1, default_missing_value = "condensed")]
+    examples: Option<TargetForm>,
+
+    /// Include the `benches/` directory of a crate root
+    #[arg(long, value_enum, value_name = "FORM", num_args = 0..=1, default_missing_value = "condensed")]
+    benches: Option<TargetForm>,
+
+    /// Include the build script of a crate root
+    #[arg(long, value_enum, value_name = "FORM", num_args = 0..=1, default_missing_value = "condensed")]
+    build_script: Option<BuildScriptForm>,
+
+    /// Include the code the build script generated into OUT_DIR, found in the crate's `target/`
+    #[arg(long)]
+    generated_code: bool,
+
+    /// Keep default trait method bodies when function bodies are removed
+    #[arg(long, requires = "no_function_bodies")]
+    keep_trait_defaults: bool,
+
+    /// Don't add required/default status lines to trait method docs
+    #[arg(long)]
+    no_trait_annotations: bool,
+
+    /// Add status lines to trait method docs, overriding an earlier `--no-trait-annotations`
+    /// or its environment variable
+    #[arg(long)]
+    trait_annotations: bool,
+
+    /// Doc line added to required trait methods
+    #[arg(long, value_name = "TEXT", default_value = transformer::REQUIRED_METHOD_DOC, conflicts_with = "no_trait_annotations")]
+    required_method_note: String,
+
+    /// Doc line added to trait methods with a default implementation
+    #[arg(long, value_name = "TEXT", default_value = transformer::DEFAULT_METHOD_DOC, conflicts_with = "no_trait_annotations")]
+    default_method_note: String,
+
+    /// Compress the combined single-file output
+    #[arg(long, value_enum, requires = "single_file")]
+    compress: Option<Compression>,
+
+    /// Verify that the existing single-file output is up to date instead of writing it
+    #[arg(long, requires = "single_file")]
+    check: bool,
+
+    /// How to report progress: a bar on terminals, plain-text lines otherwise
+    #[arg(long, value_enum, default_value_t)]
+    progress: ProgressMode,
+
+    /// Number of files transformed in parallel (default: logical cores); the combined output
+    /// keeps the walk order regardless
+    #[arg(short = 'j', long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    jobs: Option<usize>,
+
+    /// Fail once the walk finds more than this many Rust files
+    #[arg(long, value_name = "N", default_value_t = processor::DEFAULT_MAX_FILES)]
+    max_files: usize,
+
+    /// Fail once the walk enters a directory more than this many levels below the input
+    #[arg(long, value_name = "N", default_value_t = processor::DEFAULT_MAX_WALK_DEPTH)]
+    max_depth_walk: usize,
+
+    /// Print per-phase durations, the slowest files, and peak memory
+    #[arg(long)]
+    timings: bool,
+
+    /// Print the share of doc and comment lines of each module and file
+    #[arg(long)]
+    doc_stats: bool,
+
+    /// Fail if the output exceeds the context window of this model
+    #[arg(long, value_name = "NAME")]
+    max_model: Option<String>,
+
+    /// Define or override a model context window, e.g. "local=32000"
+    #[arg(long, value_name = "NAME=TOKENS", value_parser = models::parse_model_context)]
+    model_context: Vec<ModelContext>,
+
+    /// Fail if the total output size exceeds this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_output_bytes: Option<usize>,
+
+    /// Fail if the estimated output token count exceeds this limit
+    #[arg(long, value_name = "TOKENS")]
+    max_output_tokens: Option<usize>,
+
+    /// Leave the largest files after transformation, this percentage of them rounded up,
+    /// out of the single-file output and list them at its top
+    #[arg(long, value_name = "PERCENT", requires = "single_file", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=100))]
+    drop_largest: Option<usize>,
+
+    /// Fit the single-file output into this many tokens by reducing modules, low-priority
+    /// ones first, to fewer comments, bodies, or summary stubs
+    #[arg(
+        long,
+        value_name = "TOKENS",
+        requires = "single_file",
+        conflicts_with = "drop_largest"
+    )]
+    token_budget: Option<usize>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Print statistics from the manifest of a previous run
+    Stats {
+        /// Manifest file or output directory of a run, or snapshot tag
+        manifest: PathBuf,
+
+        /// Manifest file or output directory of an older run, or snapshot tag, to compare
+        /// against
+        #[arg(long)]
+        compare: Option<PathBuf>,
+    },
+
+    /// Show added, removed, and changed items between two generated outputs
+    Diff {
+        /// Older output file or directory, or snapshot tag
+        old: PathBuf,
+
+        /// Newer output file or directory, or snapshot tag
+        new: PathBuf,
+    },
+
+    /// Store the output directory of a run under a tag in a content-addressed store, or list
+    /// the stored snapshots
+    Snapshot {
+        /// Output directory of a run to store
+        #[arg(requires = "tag", required_unless_present = "list")]
+        output: Option<PathBuf>,
+
+        /// Tag naming the snapshot, e.g. `release-1.4`; an existing tag is moved
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// List the tagged snapshots instead
+        #[arg(long, conflicts_with = "output")]
+        list: bool,
+
+        /// Store directory, overriding the `snapshot-store` setting of `code-context.toml`
+        #[arg(long)]
+        store: Option<PathBuf>,
+    },
+
+    /// Print an item of a previous run, condensed or with its complete original source
+    Show {
+        /// Name (`Config::load`), module-qualified name, or id of the item
+        item: String,
+
+        /// Manifest file or output directory of the run
+        manifest: PathBuf,
+
+        /// Print the complete original source of a function, including its elided body
+        #[arg(long)]
+        full: bool,
+    },
+
+    /// Generate several contexts in one invocation from a JSON job file of targets, each
+    /// with an input, an output, and options
+    Batch {
+        /// Job file
+        file: PathBuf,
+    },
+
+    /// Explain what a transformation keeps and removes, with an example transformed by it
+    Explain {
+        /// Flag or concept such as `no-function-bodies`; lists the topics if omitted
+        #[arg(allow_hyphen_values = true)]
+        topic: Option<String>,
+    },
+
+    /// Keep parsed files in memory and answer generate and query requests over TCP
+    Daemon {
+        /// Directory to serve
+        input: PathBuf,
+
+        /// Address to listen on
+        #[arg(long, default_value = daemon::DEFAULT_ADDRESS)]
+        listen: String,
+    },
+
+    /// Ask a running daemon for the context of its input and print it
+    Request {
+        /// Print only the items whose name, signature, or docs contain this text
+        #[arg(long, value_name = "PATTERN")]
+        query: Option<String>,
+
+        /// Address of the daemon
+        #[arg(long, default_value = daemon::DEFAULT_ADDRESS)]
+        address: String,
+
+        /// Options of the generation, as given to `code-context`, after `--`
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Print the items whose name, signature, or docs contain a pattern, from the item
+    /// index written by `--format jsonl`
+    Grep {
+        /// Text to search for, ignoring case
+        pattern: String,
+
+        /// Item index file or output directory of a run
+        index: PathBuf,
+    },
+
+    /// Load the processed project and explore it with `show`, `grep`, `tree`, and `copy`
+    Repl {
+        /// Directory to load
+        input: PathBuf,
+
+        /// Remove all comments (including doc comments)
+        #[arg(long)]
+        no_comments: bool,
+
+        /// Remove function bodies except for string/serialization methods
+        #[arg(long)]
+        no_function_bodies: bool,
+    },
+
+    /// Load a generated context, exclude items by token cost with `list`, `toggle`, and
+    /// `fit`, and write it trimmed
+    Trim {
+        /// Output file or directory of a run
+        context: PathBuf,
+
+        /// Where to write the trimmed context
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Token budget the trimmed context should fit
+        #[arg(long, value_name = "TOKENS")]
+        budget: Option<usize>,
+    },
+}
+
+impl Cli {
+    /// Matches a command line with every option of the main command also read from its
+    /// `CODE_CONTEXT_*` variable in `env`, unless given on the command line; the variables
+    /// are passed as arguments before the command line, so clap checks their conflicts and
+    /// requirements, and flags set to a false value are left out
+    fn try_matches_with_env(
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+        env: impl Fn(&str) -> Option<OsString>,
+    ) -> Result<clap::ArgMatches, clap::Error> {
+        let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let mut command = Self::command();
+        // Built, so that errors about the variables can describe their options
+        command.build();
+        let given = command
+            .clone()
+            .ignore_errors(true)
+            .try_get_matches_from(&args)?;
+        // Subcommands don't read the variables of the main command
+        if given.subcommand().is_some() {
+            return command.try_get_matches_from(args);
+        }
+
+        let mut env_args = Vec::new();
+        for arg in command.get_arguments() {
+            let Some(long) = arg
+                .get_long()
+                .filter(|long| !matches!(*long, "help" | "version"))
+            else {
+                continue;
+            };
+            if given.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+                continue;
+            }
+            let name = format!("{}{}", ENV_PREFIX, long.to_uppercase().replace('-', "_"));
+            let Some(value) = env(&name).filter(|value| !value.is_empty()) else {
+                continue;
+            };
+            if matches!(arg.get_action(), clap::ArgAction::SetTrue) {
+                // Accept `1`, `yes`, `on`, and their negations as well as `true` and `false`
+                let set = clap::builder::BoolishValueParser::new().parse_ref(
+                    &command,
+                    Some(arg),
+                    &value,
+                )?;
+                if set {
+                    env_args.push(OsString::from(format!("--{}", long)));
+                }
+            } else {
+                let mut option = OsString::from(format!("--{}=", long));
+                option.push(value);
+                env_args.push(option);
+            }
+        }
+        let after_program = args.len().min(1);
+        args.splice(after_program..after_program, env_args);
+        command.try_get_matches_from(args)
+    }
+
+    /// Parses the command line, with `@path` arguments expanded, and `CODE_CONTEXT_*`
+    /// environment variables
+    fn parse_with_env() -> Result<Self> {
+        let args = arg_files::expand(std::env::args_os())?;
+        let matches = Self::try_matches_with_env(args, |name| std::env::var_os(name))
+            .and_then(Self::from_matches)
+            .unwrap_or_else(|error| error.exit());
+        Ok(matches)
+    }
+
+    /// Builds the options from parsed matches, resolving negatable flag pairs: the last of
+    /// a pair on the command line wins, and either form on the command line wins over an
+    /// environment variable
+    fn from_matches(matches: clap::ArgMatches) -> Result<Self, clap::Error> {
+        let mut cli = Self::from_arg_matches(&matches)?;
+        // Environment variables come before the command line, which is in argument order
+        let position = |id: &str| {
+            let on_command_line = matches.value_source(id) == Some(ValueSource::CommandLine);
+            (on_command_line, matches.index_of(id))
+        };
+        let negated = |negative: &str, positive: &str| {
+            matches.get_flag(negative)
+                && (!matches.get_flag(positive) || position(negative) > position(positive))
+        };
+        cli.no_comments = negated("no_comments", "comments");
+        cli.no_function_bodies = negated("no_function_bodies", "function_bodies");
+        cli.no_stats = negated("no_stats", "stats");
+        cli.no_trait_annotations = negated("no_trait_annotations", "trait_annotations");
+        Ok(cli)
+    }
+
+    /// Returns the input path, which clap requires unless a subcommand is given
+    fn input_path(&self) -> &Path {
+        self.input_path
+            .as_deref()
+            .expect("input path is required without a subcommand")
+    }
 }
 
-fn main() -> Result<()> {
-    // Initialize logging, using try_init() to handle errors gracefully
-    let _ = tracing_subscriber::fmt::try_init();
+fn main() -> ExitCode {
+    // Initialize logging and the timing of phases, using try_init() to handle errors
+    // gracefully
+    let _ = tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(LevelFilter::INFO))
+        .with(TimingLayer::default())
+        .try_init();
 
-    let cli = Cli::parse();
+    let (result, error_format) = match Cli::parse_with_env() {
+        Ok(cli) => (run(&cli), cli.error_format),
+        Err(error) => (Err(error), ErrorFormat::Human),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => errors::report(&error, error_format),
+    }
+}
 
+/// Runs the subcommand of a command line, or generates the context of its input
+fn run(cli: &Cli) -> Result<()> {
+    if let Some(command) = &cli.command {
+        return run_command(command, cli.error_format);
+    }
+    generate(cli, &mut Caches::default())
+}
+
+/// Loads the `code-context.toml` given on the command line or found for the input
+fn load_config(cli: &Cli) -> Result<Config> {
+    match &cli.config {
+        Some(path) => Config::load(path),
+        None => Config::discover(cli.input_path()),
+    }
+}
+
+/// Builds the processor of a command line, with the data its options load from disk
+fn build_processor(cli: &Cli, config: &Config, caches: &mut Caches) -> Result<FileProcessor> {
+    let lsif = cli
+        .lsif
+        .as_deref()
+        .map(|path| caches.lsif(path))
+        .transpose()?;
+    let processor = create_processor(cli)
+        .with_priority_weights(config.priority_weights()?)
+        .with_lsif(lsif)
+        .with_exclusions(PathExclusions::new(&cli.preset)?)
+        .with_fileset(cli.fileset.as_deref().map(FileSet::load).transpose()?)
+        .with_provenance_banner(
+            cli.provenance_banner
+                .then(|| Provenance::collect(cli.input_path()).banner()),
+        );
+    // Aliases and license banners are collected from the files the run processes
+    let sources = if cli.expand_aliases.is_some() || cli.strip_license_headers {
+        let output_base =
+            FileProcessor::get_output_path(cli.input_path(), cli.output_dir_name.as_deref())?;
+        processor.source_files(cli.input_path(), Some(&output_base))?
+    } else {
+        Vec::new()
+    };
+    let type_aliases = cli
+        .expand_aliases
+        .map(|mode| TypeAliases::load(cli.input_path(), &sources, mode, &cli.expand_alias))
+        .transpose()?;
+    let license_headers = LicenseHeaders::new(
+        &sources,
+        cli.strip_license_headers,
+        cli.license_header_pattern.as_deref(),
+    )?;
+    Ok(processor
+        .with_type_aliases(type_aliases)
+        .with_license_headers(license_headers))
+}
+
+/// Generates the context of the input of a command line
+fn generate(cli: &Cli, caches: &mut Caches) -> Result<()> {
+    let started = Instant::now();
     tracing::info!("Starting code context generation...");
-    tracing::debug!("Input path: {:?}", cli.input_path);
+    tracing::debug!("Input path: {:?}", cli.input_path());
+
+    let config = load_config(cli)?;
+    let processor = build_processor(cli, &config, caches)?;
+    let stats = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref());
+    if let Some(path) = &cli.metrics_file {
+        let finished = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let metrics = metrics::render(stats.as_ref().ok(), started.elapsed(), finished);
+        metrics::write(path, &metrics)?;
+    }
+    let stats =
+        stats.with_context(|| format!("Failed to process path: {}", cli.input_path().display()))?;
+    if let Some(fileset) = processor.fileset() {
+        for changed in fileset.verify(&stats)? {
+            tracing::warn!("{} changed since the file set was recorded", changed);
+        }
+    }
+    if let Some(path) = &cli.record_fileset {
+        FileSet::from_stats(&stats).write(path)?;
+    }
 
-    let processor = create_processor(&cli);
-    let stats = processor
-        .process_path(&cli.input_path, cli.output_dir_name.as_deref())
-        .with_context(|| format!("Failed to process path: {}", cli.input_path.display()))?;
+    if cli.dry_run {
+        print!(
+            "{}",
+            plan::render(&plan::build_plan(&stats), cli.plan_format)?
+        );
+    }
+    if cli.summary_only {
+        write_structure(cli, &stats)?;
+    }
 
+    let known_models = models::models(&cli.model_context);
     if !cli.no_stats {
-        println!("\nProcessing Statistics:");
-        println!("Files processed: {}", stats.files_processed);
-        println!("Total input size: {} bytes", stats.input_size);
-        println!("Total output size: {} bytes", stats.output_size);
-        println!("Size reduction: {:.1}%", stats.reduction_percentage());
+        print!("{}", stats::render(&stats, &known_models, cli.stats_format));
+    }
+
+    if let Some(warning) = models::warning(
+        &known_models,
+        stats.tokens(),
+        cli.no_comments,
+        cli.no_function_bodies,
+    ) {
+        eprintln!("\n{}", warning);
+    }
+    if cli.doc_stats {
+        print!("\n{}", doc_stats::report(&stats.files));
+    }
+    if cli.timings {
+        println!("\nTimings:");
+        print!("{}", stats.timings.report(10));
+        if let Some(peak) = timings::peak_memory() {
+            println!("\nPeak memory: {:.1} MB", peak as f64 / (1024.0 * 1024.0));
+        }
+    }
+
+    check_limits(cli, &stats, &known_models)?;
+
+    if let Some(command) = cli.post_hook.as_ref().or(config.post_hook.as_ref()) {
+        if !cli.dry_run && !cli.check {
+            let output =
+                FileProcessor::get_output_path(cli.input_path(), cli.output_dir_name.as_deref())?;
+            hook::run_post_hook(command, &output, &stats)?;
+        }
     }
 
     tracing::info!("Processing complete!");
     Ok(())
 }
 
-fn create_processor(cli: &Cli) -> impl Processor {
+/// Writes the `--summary-only` report into the output directory, or prints it on dry runs
+fn write_structure(cli: &Cli, stats: &ProcessingStats) -> Result<()> {
+    let report = structure::render(cli.input_path(), stats)?;
+    if cli.dry_run {
+        print!("{}", report);
+        return Ok(());
+    }
+    let output_dir =
+        FileProcessor::get_output_path(cli.input_path(), cli.output_dir_name.as_deref())?;
+    std::fs::create_dir_all(&output_dir).context("Failed to create output directory")?;
+    let path = output_dir.join(structure::MAP_FILE_NAME);
+    std::fs::write(&path, report).context("Failed to write structural report")?;
+    println!("Structural report: {}", path.display());
+    Ok(())
+}
+
+/// Runs every target of a job file, continuing past failed targets and failing at the end
+fn run_batch(path: &Path, error_format: ErrorFormat) -> Result<()> {
+    let jobs = JobFile::load(path)?;
+    let mut caches = Caches::default();
+    let mut failed = Vec::new();
+    for (name, args) in jobs.command_lines() {
+        println!("\n=== {} ===", name);
+        let result = arg_files::expand(args).and_then(|args| {
+            let matches = Cli::try_matches_with_env(args, |name| std::env::var_os(name))?;
+            let cli = Cli::from_matches(matches)?;
+            if cli.command.is_some() {
+                anyhow::bail!("Job targets can't run subcommands");
+            }
+            generate(&cli, &mut caches)
+        });
+        if let Err(error) = result {
+            let error = error.context(format!("Target {} failed", name));
+            eprintln!("{}", errors::render(&error, error_format));
+            failed.push(name);
+        }
+    }
+    if !failed.is_empty() {
+        let message = format!(
+            "{} of {} targets failed: {}",
+            failed.len(),
+            jobs.targets.len(),
+            failed.join(", ")
+        );
+        // Failing every target is a failure like any other
+        let kind = if failed.len() < jobs.targets.len() {
+            ErrorKind::Partial
+        } else {
+            ErrorKind::Other
+        };
+        return Err(Failure::error(kind, message));
+    }
+    Ok(())
+}
+
+/// Fails the run if the output exceeds any of the configured size limits
+fn check_limits(cli: &Cli, stats: &ProcessingStats, known_models: &[ModelContext]) -> Result<()> {
+    if let Some(max_bytes) = cli.max_output_bytes {
+        if stats.output_size > max_bytes {
+            return Err(Failure::error(
+                ErrorKind::Budget,
+                format!(
+                    "Output size of {} bytes exceeds the limit of {} bytes",
+                    stats.output_size, max_bytes
+                ),
+            ));
+        }
+    }
+    if let Some(max_tokens) = cli.max_output_tokens {
+        if stats.tokens() > max_tokens {
+            return Err(Failure::error(
+                ErrorKind::Budget,
+                format!(
+                    "Estimated {} tokens exceed the limit of {} tokens",
+                    stats.tokens(),
+                    max_tokens
+                ),
+            ));
+        }
+    }
+    if let Some(name) = &cli.max_model {
+        let model = models::find(known_models, name)?;
+        if stats.tokens() > model.context_window {
+            return Err(Failure::error(
+                ErrorKind::Budget,
+                format!(
+                    "Estimated {} tokens exceed the context window of {} ({} tokens)",
+                    stats.tokens(),
+                    model.name,
+                    model.context_window
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn run_command(command: &Command, error_format: ErrorFormat) -> Result<()> {
+    // Commands reading outputs also accept the tags of the snapshot store
+    let resolve = |path: &Path| -> Result<PathBuf> {
+        let (snapshots, _) = snapshot_store(None)?;
+        Ok(snapshot::resolve_path(path, &snapshots))
+    };
+    match command {
+        Command::Stats { manifest, compare } => {
+            let current = Manifest::load(&resolve(manifest)?)?;
+            match compare {
+                Some(old) => print!(
+                    "{}",
+                    stats::compare(&Manifest::load(&resolve(old)?)?, &current)
+                ),
+                None => print!("{}", stats::summarize(&current)),
+            }
+        }
+        Command::Diff { old, new } => {
+            print!(
+                "{}",
+                diff::diff(
+                    &diff::load_items(&resolve(old)?)?,
+                    &diff::load_items(&resolve(new)?)?
+                )
+            );
+        }
+        Command::Snapshot {
+            output,
+            tag,
+            list,
+            store,
+        } => {
+            let (store, config) = snapshot_store(store.as_deref())?;
+            if *list {
+                for snapshot in store.list()? {
+                    println!("{}  {}", snapshot.hash, snapshot.tag);
+                }
+            } else if let (Some(output), Some(tag)) = (output, tag) {
+                let snapshot = store.store(output, tag)?;
+                println!("Stored {} as {}", snapshot.tag, paths::slash(&snapshot.dir));
+                if let Some(command) = &config.snapshot_push {
+                    hook::run_snapshot_push(command, &snapshot)?;
+                }
+            }
+        }
+        Command::Show {
+            item,
+            manifest,
+            full,
+        } => print!("{}", show::show(&Manifest::load(manifest)?, item, *full)?),
+        Command::Batch { file } => run_batch(file, error_format)?,
+        Command::Explain { topic: None } => print!("Topics:\n{}", explain::list()),
+        Command::Explain { topic: Some(topic) } => print!(
+            "{}",
+            explain::explain(explain::find(topic)?, transform_example)?
+        ),
+        Command::Daemon { input, listen } => {
+            let listener = TcpListener::bind(listen)
+                .with_context(|| format!("Failed to listen on {}", listen))?;
+            println!("Listening on {}", listener.local_addr()?);
+            let mut caches = Caches::default();
+            let build_processor = |args: &[String]| {
+                let args = [OsString::from("code-context"), input.clone().into()]
+                    .into_iter()
+                    .chain(args.iter().map(OsString::from));
+                let cli = Cli::from_matches(Cli::try_matches_with_env(args, |name| {
+                    std::env::var_os(name)
+                })?)?;
+                if cli.command.is_some() {
+                    anyhow::bail!("Daemon requests can't run subcommands");
+                }
+                build_processor(&cli, &load_config(&cli)?, &mut caches)
+            };
+            daemon::Daemon::new(input, build_processor).serve(listener)?;
+        }
+        Command::Request {
+            query,
+            address,
+            args,
+        } => {
+            let args = args.clone();
+            let request = match query {
+                Some(pattern) => daemon::Request::Query {
+                    pattern: pattern.clone(),
+                    args,
+                },
+                None => daemon::Request::Generate { args },
+            };
+            let output = daemon::send(address, &request)?.output.unwrap_or_default();
+            if let (Some(pattern), true) = (query, output.is_empty()) {
+                anyhow::bail!("No items match: {}", pattern);
+            }
+            print!("{}", output);
+        }
+        Command::Grep { pattern, index } => {
+            let output = search::grep(&search::load_index(index)?, pattern);
+            if output.is_empty() {
+                anyhow::bail!("No items match: {}", pattern);
+            }
+            print!("{}", output);
+        }
+        Command::Repl {
+            input,
+            no_comments,
+            no_function_bodies,
+        } => {
+            let mut repl = repl::Repl::load(input, *no_comments, *no_function_bodies)?;
+            for skipped in repl.skipped() {
+                eprintln!("Skipped {}: {}", skipped.path, skipped.reason);
+            }
+            println!("Loaded {} files; type `help` for commands", repl.len());
+            repl.run(std::io::stdin().lock(), std::io::stdout())?;
+        }
+        Command::Trim {
+            context,
+            output,
+            budget,
+        } => {
+            let mut session = trim::TrimSession::load(context, output, *budget)?;
+            println!(
+                "Loaded {} items; type `list` to see the most expensive ones or `help` for commands",
+                session.len()
+            );
+            session.run(std::io::stdin().lock(), std::io::stdout())?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens the snapshot store given on the command line or set in the `code-context.toml` of
+/// the current directory, returning the configuration too
+fn snapshot_store(store: Option<&Path>) -> Result<(SnapshotStore, Config)> {
+    let config = Config::discover(Path::new("."))?;
+    Ok((SnapshotStore::configured(store, &config), config))
+}
+
+/// Hashes the options given on the command line, so `--update` only reuses sections
+/// generated with the same ones
+fn options_fingerprint(cli: &Cli) -> String {
+    let mut options = cli.clone();
+    // Options that don't change the content of the output
+    options.command = None;
+    options.update = false;
+    options.check = false;
+    options.post_hook = None;
+    options.no_stats = false;
+    options.stats = false;
+    options.metrics_file = None;
+    options.record_fileset = None;
+    options.progress = ProgressMode::default();
+    options.jobs = None;
+    options.timings = false;
+    options.doc_stats = false;
+    update::content_hash(&format!("{:?}", options))
+}
+
+/// Transforms an `explain` example with the given options, as a run on a one-file crate
+fn transform_example(args: &[&str], source: &str) -> Result<String> {
+    let args = ["code-context", "src/lib.rs"].iter().chain(args);
+    let cli = Cli::try_parse_from(args)?;
+    create_processor(&cli).transform_source(Path::new("src/lib.rs"), source)
+}
+
+fn create_processor(cli: &Cli) -> FileProcessor {
     FileProcessor::with_options(
         cli.no_comments,
         cli.no_function_bodies,
-        cli.dry_run,
+        cli.dry_run || cli.summary_only,
         cli.single_file,
     )
+    .with_compression(cli.compress)
+    .with_check(cli.check)
+    .with_progress(cli.progress)
+    .with_preserve_formatting(cli.preserve_formatting)
+    .with_formatter(cli.format_with)
+    .with_trait_annotations((!cli.no_trait_annotations).then(|| TraitAnnotations {
+        required: cli.required_method_note.clone(),
+        default: cli.default_method_note.clone(),
+    }))
+    .with_keep_trait_defaults(cli.keep_trait_defaults)
+    .with_keep_benches(cli.keep_benches)
+    .with_target_form(CrateTarget::Tests, cli.tests)
+    .with_target_form(CrateTarget::Examples, cli.examples)
+    .with_target_form(CrateTarget::Benches, cli.benches)
+    .with_build_script(cli.build_script)
+    .with_generated_code(cli.generated_code)
+    .with_also_tree(cli.also_tree)
+    .with_keep_rs_extension(cli.keep_rs_extension || cli.annotate)
+    .with_annotate(cli.annotate)
+    .with_jobs(cli.jobs)
+    .with_max_files(cli.max_files)
+    .with_max_walk_depth(cli.max_depth_walk)
+    .with_inline_includes(cli.inline_includes)
+    .with_copy_includes(cli.copy_includes)
+    .with_proc_macro(cargo::is_proc_macro_crate(cli.input_path()))
+    .with_transparent_attributes(cli.transparent_attribute.clone())
+    .with_summary_sections(cli.summary.clone())
+    .with_keep_queries(cli.keep_queries)
+    .with_query_appendix(cli.query_appendix)
+    .with_order(cli.order)
+    .with_churn_window_days(cli.churn_window)
+    .with_group_impls(cli.group_impls)
+    .with_front_matter(cli.front_matter)
+    .with_git_metadata(cli.git_metadata)
+    .with_git_blame(cli.git_blame)
+    .with_update(cli.update)
+    .with_options_fingerprint(options_fingerprint(cli))
+    .with_output_format(cli.format)
+    .with_diagram_depth(cli.mermaid.then_some(cli.mermaid_depth))
+    .with_insertion_points(cli.insertion_points)
+    .with_target(cli.target)
+    .with_link_style(cli.links)
+    .with_normalize_output(cli.normalize_output)
+    .with_validate_output(cli.validate_output)
+    .with_sanitize_mode(cli.sanitize)
+    .with_doc_translator(cli.translate_docs.clone())
+    .with_section_template(SectionTemplate::new(&cli.file_header, &cli.file_footer))
+    .with_strip_script_header(cli.strip_script_header)
+    .with_strip_inner_attributes(cli.strip_inner_attributes)
+    .with_drop_largest(cli.drop_largest)
+    .with_token_budget(cli.token_budget)
+    .with_visibility_tags(cli.visibility_tags)
+    .with_token_costs(cli.token_costs)
+    .with_visibility(cli.visibility)
+    .with_hide_private_fields(cli.hide_private_fields)
+    .with_collapse_impls(cli.collapse_impls)
+    .with_skipped_trait_impls(cli.skip_trait_impls.clone())
+    .with_module_filter(ModuleFilter::new(&cli.module_filter))
+    .with_crate_name(cargo::package_field(cli.input_path(), "name"))
+    .with_signature_style(SignatureStyle {
+        elide_lifetimes: cli.elide_lifetimes,
+        short_std_paths: cli.short_std_paths,
+        collapse_where: cli.collapse_where,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use std::fs;
     use tempfile::TempDir;
 
@@ -99,8 +1297,8 @@ mod tests {
         assert!(cli.dry_run);
         assert!(cli.single_file);
         assert!(cli.no_stats);
+        assert_eq!(cli.input_path(), Path::new("input-path"));
         assert_eq!(cli.output_dir_name.unwrap(), "output-dir");
-        assert_eq!(cli.input_path, PathBuf::from("input-path"));
     }
 
     #[test]
@@ -123,21 +1321,293 @@ mod tests {
         assert!(cli.dry_run);
         assert!(cli.single_file);
         assert_eq!(cli.output_dir_name.as_deref(), Some("custom-output"));
-        assert_eq!(cli.input_path.to_str().unwrap(), "input.rs");
+        assert_eq!(cli.input_path().to_str().unwrap(), "input.rs");
 
         Ok(())
     }
 
+    #[test]
+    fn test_cli_compress_requires_single_file() {
+        let cli = Cli::try_parse_from(["program", "input", "--single-file", "--compress", "zstd"])
+            .unwrap();
+        assert_eq!(cli.compress, Some(Compression::Zstd));
+
+        assert!(Cli::try_parse_from(["program", "input", "--compress", "gzip"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_stats_subcommand() {
+        let cli =
+            Cli::try_parse_from(["program", "stats", "new.json", "--compare", "old.json"]).unwrap();
+        match cli.command {
+            Some(Command::Stats { manifest, compare }) => {
+                assert_eq!(manifest, PathBuf::from("new.json"));
+                assert_eq!(compare, Some(PathBuf::from("old.json")));
+            }
+            _ => panic!("Expected stats subcommand"),
+        }
+        assert!(cli.input_path.is_none());
+
+        assert!(Cli::try_parse_from(["program"]).is_err());
+
+        let cli = Cli::try_parse_from(["program", "diff", "old", "new"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Diff { .. })));
+    }
+
+    #[test]
+    fn test_cli_sanitize_defaults_to_strip() {
+        let cli = Cli::try_parse_from(["program", "input", "--sanitize"]).unwrap();
+        assert_eq!(cli.sanitize, Some(SanitizeMode::Strip));
+        let cli = Cli::try_parse_from(["program", "input", "--sanitize", "flag"]).unwrap();
+        assert_eq!(cli.sanitize, Some(SanitizeMode::Flag));
+    }
+
+    #[test]
+    fn test_cli_daemon_subcommand() {
+        let cli = Cli::try_parse_from(["program", "daemon", "src"]).unwrap();
+        match cli.command {
+            Some(Command::Daemon { input, listen }) => {
+                assert_eq!(input, PathBuf::from("src"));
+                assert_eq!(listen, daemon::DEFAULT_ADDRESS);
+            }
+            _ => panic!("Expected daemon subcommand"),
+        }
+        let cli = Cli::try_parse_from([
+            "program",
+            "request",
+            "--query",
+            "Config",
+            "--",
+            "--no-function-bodies",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Command::Request { query, args, .. }) => {
+                assert_eq!(query.as_deref(), Some("Config"));
+                assert_eq!(args, vec!["--no-function-bodies"]);
+            }
+            _ => panic!("Expected request subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_cli_options_from_env() {
+        let try_parse = |env: &[(&str, &str)], args: &[&str]| {
+            let env: HashMap<String, OsString> = env
+                .iter()
+                .map(|(name, value)| (name.to_string(), OsString::from(value)))
+                .collect();
+            Cli::try_matches_with_env(args, |name| env.get(name).cloned())
+                .and_then(Cli::from_matches)
+        };
+        let env = [
+            ("CODE_CONTEXT_REQUIRED_METHOD_NOTE", "Must implement"),
+            ("CODE_CONTEXT_SINGLE_FILE", "off"),
+            ("CODE_CONTEXT_NO_COMMENTS", "1"),
+        ];
+        let parse = |args: &[&str]| try_parse(&env, args).unwrap();
+
+        let cli = parse(&["program", "input"]);
+        assert_eq!(cli.required_method_note, "Must implement");
+        assert!(cli.no_comments);
+        assert!(!cli.single_file);
+
+        let cli = parse(&["program", "input", "--required-method-note", "Required"]);
+        assert_eq!(cli.required_method_note, "Required");
+        let cli = parse(&["program", "input", "--comments"]);
+        assert!(!cli.no_comments);
+
+        // Flags set to a false value are left out of the conflicts and requirements
+        let cli = try_parse(
+            &[("CODE_CONTEXT_NO_COMMENTS", "0")],
+            &["program", "input", "--annotate"],
+        );
+        assert!(cli.is_ok_and(|cli| cli.annotate && !cli.no_comments));
+        assert!(try_parse(&env, &["program", "input", "--annotate"]).is_err());
+        assert!(try_parse(&env, &["program", "input", "--also-tree"]).is_err());
+        assert!(try_parse(
+            &[("CODE_CONTEXT_SINGLE_FILE", "maybe")],
+            &["program", "input"]
+        )
+        .is_err());
+        // Subcommands don't read the variables
+        assert!(try_parse(&env, &["program", "stats", "out"]).is_ok());
+    }
+
+    #[test]
+    fn test_cli_negatable_flags_last_wins() {
+        let parse = |args: &[&str]| {
+            Cli::from_matches(Cli::command().try_get_matches_from(args).unwrap()).unwrap()
+        };
+        let cli = parse(&[
+            "program",
+            "input",
+            "--no-function-bodies",
+            "--function-bodies",
+        ]);
+        assert!(!cli.no_function_bodies);
+        let cli = parse(&["program", "input", "--stats", "--no-stats", "--no-comments"]);
+        assert!(cli.no_stats);
+        assert!(cli.no_comments);
+    }
+
+    #[test]
+    fn test_cli_plan_format_requires_dry_run() {
+        let cli = Cli::try_parse_from(["program", "input", "--dry-run", "--plan-format", "json"])
+            .unwrap();
+        assert_eq!(cli.plan_format, PlanFormat::Json);
+
+        assert!(Cli::try_parse_from(["program", "input", "--plan-format", "json"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_model_options() {
+        let cli = Cli::try_parse_from([
+            "program",
+            "input",
+            "--max-model",
+            "local",
+            "--model-context",
+            "local=32000",
+        ])
+        .unwrap();
+        assert_eq!(cli.max_model.as_deref(), Some("local"));
+        assert_eq!(cli.model_context[0].context_window, 32_000);
+
+        assert!(Cli::try_parse_from(["program", "input", "--model-context", "local"]).is_err());
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let stats = ProcessingStats {
+            output_size: 4_000,
+            ..Default::default()
+        };
+        let models = models::models(&[]);
+
+        let cli = Cli::try_parse_from(["program", "input", "--max-output-bytes", "4000"]).unwrap();
+        assert!(check_limits(&cli, &stats, &models).is_ok());
+
+        let cli = Cli::try_parse_from(["program", "input", "--max-output-bytes", "3999"]).unwrap();
+        let err = check_limits(&cli, &stats, &models).unwrap_err();
+        assert!(err.to_string().contains("exceeds the limit of 3999 bytes"));
+
+        let cli = Cli::try_parse_from(["program", "input", "--max-output-tokens", "999"]).unwrap();
+        let err = check_limits(&cli, &stats, &models).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1000 tokens exceed the limit of 999"));
+
+        let cli = Cli::try_parse_from(["program", "input", "--max-model", "gpt-4o"]).unwrap();
+        assert!(check_limits(&cli, &stats, &models).is_ok());
+    }
+
+    #[test]
+    fn test_cli_progress() {
+        let cli = Cli::try_parse_from(["program", "input"]).unwrap();
+        assert_eq!(cli.progress, ProgressMode::Auto);
+
+        let cli = Cli::try_parse_from(["program", "input", "--progress", "never"]).unwrap();
+        assert_eq!(create_processor(&cli).progress(), ProgressMode::Never);
+    }
+
     #[test]
     fn test_processor_creation() {
         let cli = Cli {
-            input_path: PathBuf::from("test"),
+            command: None,
+            input_path: Some(PathBuf::from("test")),
             output_dir_name: None,
+            config: None,
+            post_hook: None,
+            stats_format: StatsFormat::Text,
+            metrics_file: None,
+            record_fileset: None,
+            fileset: None,
+            module_filter: Vec::new(),
+            preset: Vec::new(),
+            provenance_banner: false,
+            error_format: ErrorFormat::Human,
             no_comments: true,
+            comments: false,
             no_function_bodies: false,
+            function_bodies: false,
             no_stats: false,
+            stats: false,
             dry_run: true,
+            plan_format: PlanFormat::Table,
+            summary_only: false,
             single_file: true,
+            also_tree: false,
+            keep_rs_extension: false,
+            annotate: false,
+            jobs: None,
+            max_files: processor::DEFAULT_MAX_FILES,
+            max_depth_walk: processor::DEFAULT_MAX_WALK_DEPTH,
+            preserve_formatting: false,
+            format_with: Formatter::default(),
+            inline_includes: false,
+            copy_includes: false,
+            elide_lifetimes: false,
+            short_std_paths: false,
+            collapse_where: None,
+            summary: Vec::new(),
+            order: None,
+            churn_window: ordering::DEFAULT_CHURN_WINDOW_DAYS,
+            group_impls: false,
+            front_matter: None,
+            git_metadata: false,
+            git_blame: false,
+            update: false,
+            lsif: None,
+            format: OutputFormat::Text,
+            links: None,
+            normalize_output: false,
+            validate_output: false,
+            sanitize: None,
+            translate_docs: None,
+            file_header: section_template::DEFAULT_FILE_HEADER.to_string(),
+            file_footer: section_template::DEFAULT_FILE_FOOTER.to_string(),
+            strip_license_headers: false,
+            license_header_pattern: None,
+            strip_script_header: false,
+            strip_inner_attributes: false,
+            drop_largest: None,
+            token_budget: None,
+            visibility_tags: false,
+            token_costs: false,
+            visibility: VisibilityLevel::All,
+            hide_private_fields: false,
+            collapse_impls: false,
+            skip_trait_impls: Vec::new(),
+            expand_aliases: None,
+            expand_alias: Vec::new(),
+            mermaid: false,
+            mermaid_depth: diagram::DEFAULT_DIAGRAM_DEPTH,
+            insertion_points: false,
+            target: None,
+            keep_queries: false,
+            query_appendix: false,
+            transparent_attribute: Vec::new(),
+            keep_benches: false,
+            tests: None,
+            examples: None,
+            benches: None,
+            build_script: None,
+            generated_code: false,
+            keep_trait_defaults: false,
+            no_trait_annotations: false,
+            trait_annotations: false,
+            required_method_note: transformer::REQUIRED_METHOD_DOC.to_string(),
+            default_method_note: transformer::DEFAULT_METHOD_DOC.to_string(),
+            compress: None,
+            check: false,
+            progress: ProgressMode::Never,
+            timings: false,
+            doc_stats: false,
+            max_model: None,
+            model_context: Vec::new(),
+            max_output_bytes: None,
+            max_output_tokens: None,
         };
 
         let processor = create_processor(&cli);
@@ -151,7 +1621,7 @@ mod tests {
         let args = vec!["program", "nonexistent-path"];
         let cli = Cli::try_parse_from(args).unwrap();
 
-        let result = cli.input_path.try_exists();
+        let result = cli.input_path().try_exists();
         assert!(result.is_ok());
         assert!(!result.unwrap());
     }
@@ -166,7 +1636,7 @@ mod tests {
         let cli = Cli::try_parse_from(args).unwrap();
 
         let processor = create_processor(&cli);
-        let stats = processor.process_path(&cli.input_path, cli.output_dir_name.as_deref())?;
+        let stats = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref())?;
 
         assert_eq!(stats.files_processed, 1);
         assert!(stats.input_size > 0);
@@ -188,8 +1658,8 @@ mod tests {
         println!("Processor dry_run: {}", processor.dry_run());
 
         if !cli.dry_run {
-            let output_dir = FileProcessor::get_output_path(&cli.input_path, Some(output_dir))?;
-            let result = processor.process_path(&cli.input_path, cli.output_dir_name.as_deref());
+            let output_dir = FileProcessor::get_output_path(cli.input_path(), Some(output_dir))?;
+            let result = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref());
             println!("Process result: {:?}", result);
             println!("Output dir exists: {}", output_dir.exists());
             result?;
@@ -212,7 +1682,7 @@ mod tests {
         let cli = Cli::try_parse_from(args).unwrap();
 
         let processor = create_processor(&cli);
-        let stats = processor.process_path(&cli.input_path, cli.output_dir_name.as_deref())?;
+        let stats = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref())?;
 
         assert_eq!(stats.files_processed, 1);
         Ok(())
@@ -227,7 +1697,7 @@ mod tests {
 
         let cli = Cli::try_parse_from(args)?;
         let processor = create_processor(&cli);
-        let result = processor.process_path(&cli.input_path, cli.output_dir_name.as_deref());
+        let result = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref());
 
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("does not exist"));
@@ -261,8 +1731,8 @@ mod tests {
             "--no-function-bodies",
         ];
         let cli = Cli::try_parse_from(args)?;
-        let stats =
-            create_processor(&cli).process_path(&cli.input_path, cli.output_dir_name.as_deref())?;
+        let stats = create_processor(&cli)
+            .process_path(cli.input_path(), cli.output_dir_name.as_deref())?;
 
         assert!(stats.reduction_percentage() > 0.0);
         Ok(())
@@ -278,17 +1748,104 @@ mod tests {
         fs::write(&test_file, "fn main() {}")?;
 
         let cli = Cli {
-            input_path: test_file,
+            command: None,
+            input_path: Some(test_file),
             output_dir_name: Some("test-output".to_string()),
+            config: None,
+            post_hook: None,
+            stats_format: StatsFormat::Text,
+            metrics_file: None,
+            record_fileset: None,
+            fileset: None,
+            module_filter: Vec::new(),
+            preset: Vec::new(),
+            provenance_banner: false,
+            error_format: ErrorFormat::Human,
             no_comments: true,
+            comments: false,
             no_function_bodies: false,
+            function_bodies: false,
             no_stats: true,
+            stats: false,
             dry_run: true,
+            plan_format: PlanFormat::Table,
+            summary_only: false,
             single_file: false,
+            also_tree: false,
+            keep_rs_extension: false,
+            annotate: false,
+            jobs: None,
+            max_files: processor::DEFAULT_MAX_FILES,
+            max_depth_walk: processor::DEFAULT_MAX_WALK_DEPTH,
+            preserve_formatting: false,
+            format_with: Formatter::default(),
+            inline_includes: false,
+            copy_includes: false,
+            elide_lifetimes: false,
+            short_std_paths: false,
+            collapse_where: None,
+            summary: Vec::new(),
+            order: None,
+            churn_window: ordering::DEFAULT_CHURN_WINDOW_DAYS,
+            group_impls: false,
+            front_matter: None,
+            git_metadata: false,
+            git_blame: false,
+            update: false,
+            lsif: None,
+            format: OutputFormat::Text,
+            links: None,
+            normalize_output: false,
+            validate_output: false,
+            sanitize: None,
+            translate_docs: None,
+            file_header: section_template::DEFAULT_FILE_HEADER.to_string(),
+            file_footer: section_template::DEFAULT_FILE_FOOTER.to_string(),
+            strip_license_headers: false,
+            license_header_pattern: None,
+            strip_script_header: false,
+            strip_inner_attributes: false,
+            drop_largest: None,
+            token_budget: None,
+            visibility_tags: false,
+            token_costs: false,
+            visibility: VisibilityLevel::All,
+            hide_private_fields: false,
+            collapse_impls: false,
+            skip_trait_impls: Vec::new(),
+            expand_aliases: None,
+            expand_alias: Vec::new(),
+            mermaid: false,
+            mermaid_depth: diagram::DEFAULT_DIAGRAM_DEPTH,
+            insertion_points: false,
+            target: None,
+            keep_queries: false,
+            query_appendix: false,
+            transparent_attribute: Vec::new(),
+            keep_benches: false,
+            tests: None,
+            examples: None,
+            benches: None,
+            build_script: None,
+            generated_code: false,
+            keep_trait_defaults: false,
+            no_trait_annotations: false,
+            trait_annotations: false,
+            required_method_note: transformer::REQUIRED_METHOD_DOC.to_string(),
+            default_method_note: transformer::DEFAULT_METHOD_DOC.to_string(),
+            compress: None,
+            check: false,
+            progress: ProgressMode::Never,
+            timings: false,
+            doc_stats: false,
+            max_model: None,
+            model_context: Vec::new(),
+            max_output_bytes: None,
+            max_output_tokens: None,
         };
 
         let processor = create_processor(&cli);
-        let result = processor.process_path(&cli.input_path, cli.output_dir_name.as_deref());
+        let result = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref());
 
         assert!(result.is_ok(), "Processing should succeed");
         Ok(())
@@ -311,7 +1868,7 @@ mod tests {
         // Use try_init() instead of init() to handle case where logger is already initialized
         let _ = tracing_subscriber::fmt::try_init();
         let processor = create_processor(&cli);
-        let stats = processor.process_path(&cli.input_path, cli.output_dir_name.as_deref())?;
+        let stats = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref())?;
 
         assert!(stats.files_processed > 0);
         assert!(stats.input_size > 0);
@@ -319,4 +1876,19 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_explain_examples_change() -> Result<()> {
+        // Every example must show its transformation, so a changed transformer fails here
+        // instead of leaving a stale explanation
+        for topic in explain::TOPICS {
+            let after = transform_example(topic.args, topic.example)?;
+            assert_ne!(after, topic.example, "{} example is unchanged", topic.name);
+        }
+        let explained =
+            explain::explain(explain::find("--no-function-bodies")?, transform_example)?;
+        assert!(explained.contains("pub fn load(path: &str) -> std::io::Result<Self> {}"));
+        assert!(explained.contains("format!(\"config {}\", self.name)"));
+        Ok(())
+    }
 }
@@ -0,0 +1,138 @@
+use crate::paths;
+use crate::processor::{FileStats, ProcessingStats};
+use crate::removals::Removals;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Name of the manifest file written next to the generated output
+pub const MANIFEST_FILE_NAME: &str = "code-context-manifest.json";
+
+/// Suffix of the default output directory name
+const DEFAULT_OUTPUT_SUFFIX: &str = "-code-context";
+
+/// Checks if a directory holds the output of a previous run: it contains a manifest or has
+/// the default output directory name
+pub fn is_output_dir(path: &Path) -> bool {
+    path.join(MANIFEST_FILE_NAME).is_file()
+        || path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(DEFAULT_OUTPUT_SUFFIX))
+}
+
+/// Machine-readable record of a single run, written to the output directory
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Manifest {
+    pub version: String,
+    pub files_processed: usize,
+    pub input_size: usize,
+    pub output_size: usize,
+    pub tokens: usize,
+    /// Directory the paths of the files are relative to
+    pub source_root: String,
+    /// Hash of the options the output was generated with
+    pub options: String,
+    /// What was cut from all files, by category
+    pub removals: Removals,
+    pub files: Vec<FileStats>,
+}
+
+impl Manifest {
+    /// Builds a manifest from the statistics of a finished run
+    pub fn from_stats(stats: &ProcessingStats) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            files_processed: stats.files_processed,
+            input_size: stats.input_size,
+            output_size: stats.output_size,
+            tokens: stats.tokens(),
+            source_root: String::new(),
+            options: String::new(),
+            removals: stats.removals(),
+            files: stats.files.clone(),
+        }
+    }
+
+    /// Records the directory the paths of the files are relative to, absolute if possible
+    pub fn with_source_root(mut self, root: &Path) -> Self {
+        let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        self.source_root = paths::slash(&root);
+        self
+    }
+
+    /// Records the hash of the options the output was generated with
+    pub fn with_options(mut self, fingerprint: &str) -> Self {
+        self.options = fingerprint.to_string();
+        self
+    }
+
+    /// Loads a manifest from a JSON file or from the output directory containing it
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = if path.is_dir() {
+            &path.join(MANIFEST_FILE_NAME)
+        } else {
+            path
+        };
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse manifest: {}", path.display()))
+    }
+
+    /// Writes the manifest as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("Failed to serialize manifest")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip() -> Result<()> {
+        let mut stats = ProcessingStats::default();
+        stats.record(FileStats::new("src/lib.rs", 400, 100));
+        stats.record(FileStats::new("src/main.rs", 200, 60));
+
+        let manifest = Manifest::from_stats(&stats);
+        assert_eq!(manifest.files_processed, 2);
+        assert_eq!(manifest.tokens, 40);
+
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(MANIFEST_FILE_NAME);
+        manifest.write(&path)?;
+        assert_eq!(Manifest::load(&path)?, manifest);
+        assert_eq!(Manifest::load(temp_dir.path())?, manifest);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_output_dir() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let custom = temp_dir.path().join("context");
+        std::fs::create_dir(&custom)?;
+        assert!(!is_output_dir(&custom));
+        Manifest::default().write(&custom.join(MANIFEST_FILE_NAME))?;
+        assert!(is_output_dir(&custom));
+        assert!(is_output_dir(Path::new("src-code-context")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_missing_fields() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(MANIFEST_FILE_NAME);
+        std::fs::write(&path, r#"{"files_processed": 3}"#)?;
+
+        let manifest = Manifest::load(&path)?;
+        assert_eq!(manifest.files_processed, 3);
+        assert!(manifest.files.is_empty());
+        Ok(())
+    }
+}
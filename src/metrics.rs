@@ -0,0 +1,167 @@
+use crate::processor::ProcessingStats;
+use anyhow::{Context, Result};
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// Renders the metrics of a run in the OpenMetrics text format; a failed run has no
+/// statistics and only reports its duration and failure
+pub fn render(stats: Option<&ProcessingStats>, duration: Duration, timestamp: u64) -> String {
+    let mut metrics = Metrics::default();
+    metrics.gauge(
+        "run_success",
+        "",
+        "Whether the run succeeded (1) or failed (0).",
+        &[(String::new(), if stats.is_some() { 1.0 } else { 0.0 })],
+    );
+    metrics.gauge(
+        "run_timestamp_seconds",
+        "seconds",
+        "Unix time at which the run finished.",
+        &[(String::new(), timestamp as f64)],
+    );
+    metrics.gauge(
+        "run_duration_seconds",
+        "seconds",
+        "Wall-clock duration of the run.",
+        &[(String::new(), duration.as_secs_f64())],
+    );
+    let Some(stats) = stats else {
+        return metrics.finish();
+    };
+
+    let phases: Vec<_> = stats
+        .timings
+        .phases
+        .iter()
+        .map(|(phase, duration)| {
+            (
+                format!("phase=\"{}\"", phase.name()),
+                duration.as_secs_f64(),
+            )
+        })
+        .collect();
+    metrics.gauge(
+        "phase_duration_seconds",
+        "seconds",
+        "Time spent in each processing phase, summed over worker threads.",
+        &phases,
+    );
+    metrics.gauge(
+        "files",
+        "",
+        "Files by outcome.",
+        &[
+            (
+                "outcome=\"processed\"".to_string(),
+                stats.files_processed as f64,
+            ),
+            (
+                "outcome=\"skipped\"".to_string(),
+                stats.skipped.len() as f64,
+            ),
+        ],
+    );
+    metrics.gauge(
+        "size_bytes",
+        "bytes",
+        "Total size of the processed sources and of their output.",
+        &[
+            ("stage=\"input\"".to_string(), stats.input_size as f64),
+            ("stage=\"output\"".to_string(), stats.output_size as f64),
+        ],
+    );
+    metrics.gauge(
+        "output_tokens",
+        "",
+        "Estimated tokens of the output.",
+        &[(String::new(), stats.tokens() as f64)],
+    );
+    let hit_ratio = if stats.files_processed == 0 {
+        0.0
+    } else {
+        stats.reused_files as f64 / stats.files_processed as f64
+    };
+    metrics.gauge(
+        "cache_hit_ratio",
+        "ratio",
+        "Share of the processed files whose previous output was reused by --update.",
+        &[(String::new(), hit_ratio)],
+    );
+    metrics.finish()
+}
+
+/// Writes the metrics through a temporary file, so collectors never read a partial file
+pub fn write(path: &Path, metrics: &str) -> Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    std::fs::write(&partial, metrics)
+        .with_context(|| format!("Failed to write metrics file: {}", path.display()))?;
+    std::fs::rename(&partial, path)
+        .with_context(|| format!("Failed to write metrics file: {}", path.display()))
+}
+
+#[derive(Default)]
+struct Metrics(String);
+
+impl Metrics {
+    /// Adds a gauge family with one sample per label set
+    fn gauge(&mut self, name: &str, unit: &str, help: &str, samples: &[(String, f64)]) {
+        let name = format!("code_context_{}", name);
+        let _ = writeln!(self.0, "# TYPE {} gauge", name);
+        if !unit.is_empty() {
+            let _ = writeln!(self.0, "# UNIT {} {}", name, unit);
+        }
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        for (labels, value) in samples {
+            if labels.is_empty() {
+                let _ = writeln!(self.0, "{} {}", name, value);
+            } else {
+                let _ = writeln!(self.0, "{}{{{}}} {}", name, labels, value);
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.0.push_str("# EOF\n");
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::FileStats;
+    use crate::timings::Phase;
+
+    #[test]
+    fn test_render_metrics() {
+        let mut stats = ProcessingStats::default();
+        stats.record(FileStats::new("src/lib.rs", 800, 400));
+        stats.record(FileStats::new("src/api.rs", 200, 100));
+        stats.skip("benches/speed.rs", "bench");
+        stats.reused_files = 1;
+        stats
+            .timings
+            .phases
+            .insert(Phase::Parse, Duration::from_millis(250));
+
+        let metrics = render(Some(&stats), Duration::from_millis(1500), 1_700_000_000);
+        assert!(metrics.starts_with("# TYPE code_context_run_success gauge\n"));
+        assert!(metrics.contains("code_context_run_success 1\n"));
+        assert!(metrics.contains(
+            "# UNIT code_context_run_duration_seconds seconds\n\
+             # HELP code_context_run_duration_seconds Wall-clock duration of the run.\n\
+             code_context_run_duration_seconds 1.5\n"
+        ));
+        assert!(metrics.contains("code_context_phase_duration_seconds{phase=\"parse\"} 0.25\n"));
+        assert!(metrics.contains("code_context_files{outcome=\"skipped\"} 1\n"));
+        assert!(metrics.contains("code_context_size_bytes{stage=\"output\"} 500\n"));
+        assert!(metrics.contains("code_context_cache_hit_ratio 0.5\n"));
+        assert!(metrics.ends_with("# EOF\n"));
+
+        let failed = render(None, Duration::from_secs(2), 1_700_000_000);
+        assert!(failed.contains("code_context_run_success 0\n"));
+        assert!(!failed.contains("code_context_files"));
+    }
+}
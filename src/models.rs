@@ -0,0 +1,159 @@
+use anyhow::Result;
+
+/// Context window size of an LLM, in tokens
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelContext {
+    pub name: String,
+    pub context_window: usize,
+}
+
+/// Context window sizes of commonly used models
+const BUILTIN_MODELS: &[(&str, usize)] = &[
+    ("llama-3.1-70b", 128_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("claude-3.5-sonnet", 200_000),
+    ("claude-3-opus", 200_000),
+    ("gemini-1.5-flash", 1_000_000),
+    ("gemini-1.5-pro", 2_000_000),
+];
+
+/// Parses a `<name>=<tokens>` model definition
+pub fn parse_model_context(value: &str) -> Result<ModelContext, String> {
+    let (name, tokens) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected <name>=<tokens>, got '{}'", value))?;
+    let context_window = tokens
+        .trim()
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("invalid token count '{}'", tokens))?;
+    Ok(ModelContext {
+        name: name.trim().to_string(),
+        context_window,
+    })
+}
+
+/// Returns the built-in models, with user definitions overriding or extending them
+pub fn models(overrides: &[ModelContext]) -> Vec<ModelContext> {
+    let mut models: Vec<ModelContext> = BUILTIN_MODELS
+        .iter()
+        .map(|(name, context_window)| ModelContext {
+            name: name.to_string(),
+            context_window: *context_window,
+        })
+        .collect();
+    for model in overrides {
+        match models.iter_mut().find(|m| m.name == model.name) {
+            Some(existing) => existing.context_window = model.context_window,
+            None => models.push(model.clone()),
+        }
+    }
+    models.sort_by_key(|model| model.context_window);
+    models
+}
+
+/// Finds a model by name
+pub fn find<'a>(models: &'a [ModelContext], name: &str) -> Result<&'a ModelContext> {
+    models.iter().find(|m| m.name == name).ok_or_else(|| {
+        let known: Vec<_> = models.iter().map(|m| m.name.as_str()).collect();
+        anyhow::anyhow!(
+            "Unknown model '{}'. Known models: {}",
+            name,
+            known.join(", ")
+        )
+    })
+}
+
+/// Returns the models whose context window is smaller than the given token count
+pub fn exceeded(models: &[ModelContext], tokens: usize) -> Vec<&ModelContext> {
+    models
+        .iter()
+        .filter(|m| tokens > m.context_window)
+        .collect()
+}
+
+/// Suggests options that would reduce the output size further
+pub fn suggestions(no_comments: bool, no_function_bodies: bool) -> Vec<&'static str> {
+    let mut suggestions = Vec::new();
+    if !no_function_bodies {
+        suggestions.push("add --no-function-bodies to drop implementation details");
+    }
+    if !no_comments {
+        suggestions.push("add --no-comments to drop doc comments");
+    }
+    suggestions.push("process a subdirectory instead of the whole crate");
+    suggestions
+}
+
+/// Renders a warning listing the exceeded models, or nothing if all models fit
+pub fn warning(
+    models: &[ModelContext],
+    tokens: usize,
+    no_comments: bool,
+    no_function_bodies: bool,
+) -> Option<String> {
+    let exceeded = exceeded(models, tokens);
+    if exceeded.is_empty() {
+        return None;
+    }
+
+    let names: Vec<_> = exceeded
+        .iter()
+        .map(|m| format!("{} ({} tokens)", m.name, m.context_window))
+        .collect();
+    let mut warning = format!(
+        "Warning: estimated {} tokens exceed the context window of {}",
+        tokens,
+        names.join(", ")
+    );
+    for suggestion in suggestions(no_comments, no_function_bodies) {
+        warning.push_str("\n  - ");
+        warning.push_str(suggestion);
+    }
+    Some(warning)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_model_context() {
+        assert_eq!(
+            parse_model_context("local=32_000"),
+            Ok(ModelContext {
+                name: "local".to_string(),
+                context_window: 32_000
+            })
+        );
+        assert!(parse_model_context("local").is_err());
+        assert!(parse_model_context("local=many").is_err());
+    }
+
+    #[test]
+    fn test_models_with_overrides() -> Result<()> {
+        let overrides = vec![
+            parse_model_context("gpt-4o=64000").unwrap(),
+            parse_model_context("tiny=1000").unwrap(),
+        ];
+        let models = models(&overrides);
+
+        assert_eq!(find(&models, "gpt-4o")?.context_window, 64_000);
+        assert_eq!(models[0].name, "tiny");
+        assert!(find(&models, "unknown").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_warning() {
+        let models = models(&[]);
+        assert_eq!(warning(&models, 1_000, false, false), None);
+
+        let warning = warning(&models, 150_000, false, true).unwrap();
+        assert!(warning.contains("gpt-4o (128000 tokens)"));
+        assert!(!warning.contains("claude-3.5-sonnet"));
+        assert!(warning.contains("--no-comments"));
+        assert!(!warning.contains("--no-function-bodies"));
+    }
+}
@@ -0,0 +1,61 @@
+/// Module subtrees kept in full by `--module-filter`; files of other modules are reduced to
+/// their summary stubs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleFilter {
+    /// Path segments of each filter, `*` matching any one module
+    patterns: Vec<Vec<String>>,
+}
+
+impl ModuleFilter {
+    /// Builds a filter from paths such as `crate::api` or `crate::domain::*`, with the
+    /// `crate::` prefix optional; returns `None` without paths
+    pub fn new(paths: &[String]) -> Option<Self> {
+        let patterns: Vec<Vec<String>> = paths
+            .iter()
+            .map(|path| {
+                let path = path.trim().trim_end_matches("::");
+                let mut segments: Vec<String> = path.split("::").map(str::to_string).collect();
+                if segments.first().map(String::as_str) != Some("crate") {
+                    segments.insert(0, "crate".to_string());
+                }
+                segments
+            })
+            .collect();
+        (!patterns.is_empty()).then_some(Self { patterns })
+    }
+
+    /// Checks whether a module lies in one of the subtrees; files outside the module tree,
+    /// such as tests and build scripts, never do
+    pub fn includes(&self, module: Option<&str>) -> bool {
+        let Some(module) = module else {
+            return false;
+        };
+        let segments: Vec<&str> = module.split("::").collect();
+        self.patterns.iter().any(|pattern| {
+            pattern.len() <= segments.len()
+                && pattern
+                    .iter()
+                    .zip(&segments)
+                    .all(|(expected, segment)| expected == "*" || expected == segment)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_filter() {
+        let filter =
+            ModuleFilter::new(&["crate::api".to_string(), "domain::*".to_string()]).unwrap();
+        assert!(filter.includes(Some("crate::api")));
+        assert!(filter.includes(Some("crate::api::v1::users")));
+        assert!(!filter.includes(Some("crate::apis")));
+        assert!(!filter.includes(Some("crate::domain")));
+        assert!(filter.includes(Some("crate::domain::orders")));
+        assert!(!filter.includes(Some("crate")));
+        assert!(!filter.includes(None));
+        assert!(ModuleFilter::new(&[]).is_none());
+    }
+}
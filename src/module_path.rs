@@ -1,29 +1,197 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use syn::{Item, ItemMod};
+
+/// File names of crate roots, relative to the processed directory
+const CRATE_ROOTS: [&str; 4] = ["lib.rs", "main.rs", "src/lib.rs", "src/main.rs"];
+
+/// First line of outputs written with a `.rs` extension, which marks them as generated
+pub const OUTPUT_MARKER: &str = "// @generated by code-context, do not edit\n";
+
+/// Checks if a file starts with the marker of a previous output
+pub fn is_own_output(path: &Path) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut first_line = String::new();
+    BufReader::new(file).read_line(&mut first_line).is_ok() && first_line == OUTPUT_MARKER
+}
 
 /// Handles module path resolution and manipulation
 pub struct ModulePath {
     path: PathBuf,
+    dir_owner: bool,
 }
 
 impl ModulePath {
     /// Creates a new ModulePath from a Path
     pub fn new(path: &Path) -> Self {
+        let dir_owner = path
+            .file_name()
+            .is_some_and(|name| name == "mod.rs" || name == "lib.rs" || name == "main.rs");
         Self {
             path: path.to_path_buf(),
+            dir_owner,
         }
     }
 
+    /// Marks the module as loaded through a `#[path]` attribute, which makes its
+    /// child modules resolve next to it like those of a `mod.rs` file
+    pub fn loaded_by_path_attribute(mut self) -> Self {
+        self.dir_owner = true;
+        self
+    }
+
     /// Checks if this is a valid Rust module path
     pub fn is_valid_module(&self) -> bool {
         self.path.extension().is_some_and(|ext| ext == "rs")
             && !self.path.to_str().is_some_and(|s| s.ends_with(".rs.txt"))
     }
+
+    /// Directory that contains the files of this module's children
+    pub fn child_dir(&self) -> PathBuf {
+        let parent = self.path.parent().unwrap_or_else(|| Path::new("."));
+        match self.path.file_stem() {
+            Some(stem) if !self.dir_owner => parent.join(stem),
+            _ => parent.to_path_buf(),
+        }
+    }
+
+    /// Resolves the file of a `mod name;` declaration nested in the given inline modules,
+    /// honoring `#[path = "..."]` attributes and both `name.rs` and `name/mod.rs` layouts
+    pub fn resolve_child(
+        &self,
+        name: &str,
+        path_attribute: Option<&str>,
+        inline: &[String],
+    ) -> Option<PathBuf> {
+        let mut dir = self.child_dir();
+        dir.extend(inline);
+
+        if let Some(path) = path_attribute {
+            let base = if inline.is_empty() {
+                self.path.parent().unwrap_or_else(|| Path::new("."))
+            } else {
+                &dir
+            };
+            return Some(base.join(path)).filter(|path| path.is_file());
+        }
+
+        [
+            dir.join(format!("{}.rs", name)),
+            dir.join(name).join("mod.rs"),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+    }
+}
+
+/// Maps source files to their module paths by following `mod` declarations from the crate roots
+#[derive(Default, Debug)]
+pub struct ModuleMap {
+    modules: HashMap<PathBuf, String>,
+}
+
+impl ModuleMap {
+    /// Builds the module map for a crate directory or its `src` directory
+    pub fn build(dir: &Path) -> Self {
+        let mut map = Self::default();
+        for root in CRATE_ROOTS.iter().map(|root| dir.join(root)) {
+            if root.is_file() {
+                map.visit_file(ModulePath::new(&root), "crate".to_string());
+            }
+        }
+        map
+    }
+
+    /// Returns the module path of a source file, e.g. `crate::api::client`
+    pub fn module_of(&self, path: &Path) -> Option<&str> {
+        self.modules.get(&Self::key(path)).map(String::as_str)
+    }
+
+    fn key(path: &Path) -> PathBuf {
+        std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    fn visit_file(&mut self, module: ModulePath, name: String) {
+        let key = Self::key(&module.path);
+        if self.modules.contains_key(&key) {
+            return;
+        }
+        self.modules.insert(key, name.clone());
+
+        let Ok(content) = std::fs::read_to_string(&module.path) else {
+            return;
+        };
+        let Ok(file) = syn::parse_file(&content) else {
+            return;
+        };
+        self.visit_items(&module, &file.items, &name, &mut Vec::new());
+    }
+
+    fn visit_items(
+        &mut self,
+        module: &ModulePath,
+        items: &[Item],
+        parent: &str,
+        inline: &mut Vec<String>,
+    ) {
+        for item in items {
+            let Item::Mod(item_mod) = item else {
+                continue;
+            };
+            let ident = item_mod.ident.to_string();
+            let name = format!("{}::{}", parent, ident);
+            let path_attribute = Self::path_attribute(item_mod);
+
+            match &item_mod.content {
+                Some((_, items)) => {
+                    inline.push(path_attribute.unwrap_or(ident));
+                    self.visit_items(module, items, &name, inline);
+                    inline.pop();
+                }
+                None => {
+                    let Some(child) =
+                        module.resolve_child(&ident, path_attribute.as_deref(), inline)
+                    else {
+                        continue;
+                    };
+                    let child = if path_attribute.is_some() {
+                        ModulePath::new(&child).loaded_by_path_attribute()
+                    } else {
+                        ModulePath::new(&child)
+                    };
+                    self.visit_file(child, name);
+                }
+            }
+        }
+    }
+
+    /// Returns the value of a `#[path = "..."]` attribute
+    fn path_attribute(item_mod: &ItemMod) -> Option<String> {
+        item_mod.attrs.iter().find_map(|attr| {
+            if !attr.path().is_ident("path") {
+                return None;
+            }
+            match &attr.meta.require_name_value().ok()?.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(path),
+                    ..
+                }) => Some(path.value()),
+                _ => None,
+            }
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
+    use std::fs;
     use std::path::PathBuf;
+    use tempfile::TempDir;
 
     #[test]
     fn test_is_valid_module() {
@@ -33,4 +201,73 @@ mod tests {
         assert!(ModulePath::new(&valid_path).is_valid_module());
         assert!(!ModulePath::new(&invalid_path).is_valid_module());
     }
+
+    #[test]
+    fn test_is_own_output() -> std::io::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let output = temp_dir.path().join("lib.rs");
+        std::fs::write(&output, format!("{}pub fn a() {{}}\n", OUTPUT_MARKER))?;
+        let source = temp_dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() {}\n")?;
+
+        assert!(is_own_output(&output));
+        assert!(!is_own_output(&source));
+        assert!(!is_own_output(&temp_dir.path().join("missing.rs")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_child_dir() {
+        assert_eq!(
+            ModulePath::new(Path::new("src/lib.rs")).child_dir(),
+            PathBuf::from("src")
+        );
+        assert_eq!(
+            ModulePath::new(Path::new("src/api/mod.rs")).child_dir(),
+            PathBuf::from("src/api")
+        );
+        assert_eq!(
+            ModulePath::new(Path::new("src/api.rs")).child_dir(),
+            PathBuf::from("src/api")
+        );
+    }
+
+    #[test]
+    fn test_module_map_layouts_and_path_attributes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src = temp_dir.path().join("src");
+        fs::create_dir_all(src.join("api"))?;
+        fs::create_dir_all(src.join("legacy"))?;
+        fs::create_dir_all(src.join("platform"))?;
+        fs::write(
+            src.join("lib.rs"),
+            r#"
+            mod api;
+            mod legacy;
+            #[path = "platform/unix.rs"]
+            mod sys;
+            mod outer {
+                mod inner;
+            }
+            "#,
+        )?;
+        fs::write(src.join("api.rs"), "mod client;")?;
+        fs::write(src.join("api/client.rs"), "")?;
+        fs::write(src.join("legacy/mod.rs"), "mod old;")?;
+        fs::write(src.join("legacy/old.rs"), "")?;
+        fs::write(src.join("platform/unix.rs"), "mod fd;")?;
+        fs::write(src.join("platform/fd.rs"), "")?;
+        fs::create_dir_all(src.join("outer"))?;
+        fs::write(src.join("outer/inner.rs"), "")?;
+
+        let map = ModuleMap::build(temp_dir.path());
+        let module = |path: &str| map.module_of(&src.join(path));
+        assert_eq!(module("lib.rs"), Some("crate"));
+        assert_eq!(module("api/client.rs"), Some("crate::api::client"));
+        assert_eq!(module("legacy/old.rs"), Some("crate::legacy::old"));
+        assert_eq!(module("platform/unix.rs"), Some("crate::sys"));
+        assert_eq!(module("platform/fd.rs"), Some("crate::sys::fd"));
+        assert_eq!(module("outer/inner.rs"), Some("crate::outer::inner"));
+        Ok(())
+    }
 }
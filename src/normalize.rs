@@ -0,0 +1,288 @@
+use proc_macro2::Span;
+use syn::punctuated::Punctuated;
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    AngleBracketedGenericArguments, GenericArgument, GenericParam, Generics, Ident, Lifetime,
+    PathArguments, Signature, TypeParamBound, TypeReference, WherePredicate,
+};
+
+/// Prefix of the placeholder predicate that stands for a collapsed where-clause until printing
+const WHERE_PLACEHOLDER: &str = "__CodeContextWhere";
+
+/// Roots of fully-qualified standard library paths
+const STD_ROOTS: [&str; 3] = ["std", "core", "alloc"];
+
+/// Names that stay qualified by their module when shortened, e.g. `io::Result`
+const AMBIGUOUS_NAMES: [&str; 2] = ["Result", "Error"];
+
+/// Options that simplify signatures for readability
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignatureStyle {
+    /// Elide named lifetimes in function signatures (`&'a str` → `&str`)
+    pub elide_lifetimes: bool,
+    /// Collapse fully-qualified std paths (`std::collections::HashMap` → `HashMap`)
+    pub short_std_paths: bool,
+    /// Collapse where-clauses with more predicates than this to `where /* N bounds */`
+    pub collapse_where: Option<usize>,
+}
+
+impl SignatureStyle {
+    /// Checks if any normalization is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.elide_lifetimes || self.short_std_paths || self.collapse_where.is_some()
+    }
+}
+
+/// Applies a [`SignatureStyle`] to a syntax tree
+pub struct SignatureNormalizer<'a> {
+    style: &'a SignatureStyle,
+}
+
+impl<'a> SignatureNormalizer<'a> {
+    pub fn new(style: &'a SignatureStyle) -> Self {
+        Self { style }
+    }
+
+    /// Replaces collapsed where-clause placeholders in printed code with comments
+    pub fn finish(&self, printed: &str) -> String {
+        let mut output = String::with_capacity(printed.len());
+        let mut rest = printed;
+        while let Some(start) = rest.find(WHERE_PLACEHOLDER) {
+            let Some(where_start) = rest[..start].trim_end().strip_suffix("where").map(str::len)
+            else {
+                output.push_str(&rest[..start + WHERE_PLACEHOLDER.len()]);
+                rest = &rest[start + WHERE_PLACEHOLDER.len()..];
+                continue;
+            };
+            let after = &rest[start + WHERE_PLACEHOLDER.len()..];
+            let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let end = after[digits..]
+                .strip_prefix(": Elided")
+                .map(|tail| tail.strip_prefix(',').unwrap_or(tail))
+                .map_or(after.len(), |tail| after.len() - tail.len());
+
+            output.push_str(&rest[..where_start]);
+            output.push_str(&format!("where /* {} bounds */", &after[..digits]));
+            rest = &after[end..];
+        }
+        output.push_str(rest);
+        output
+    }
+
+    fn shorten_path(path: &mut syn::Path) {
+        let is_std = path
+            .segments
+            .first()
+            .is_some_and(|segment| STD_ROOTS.contains(&segment.ident.to_string().as_str()));
+        if !is_std || path.segments.len() < 2 {
+            return;
+        }
+        let last = path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string());
+        let keep = if last.is_some_and(|name| AMBIGUOUS_NAMES.contains(&name.as_str())) {
+            2
+        } else {
+            1
+        };
+        let skip = path.segments.len().saturating_sub(keep);
+        path.segments = std::mem::take(&mut path.segments)
+            .into_iter()
+            .skip(skip)
+            .collect();
+        path.leading_colon = None;
+    }
+}
+
+impl VisitMut for SignatureNormalizer<'_> {
+    fn visit_signature_mut(&mut self, sig: &mut Signature) {
+        if self.style.elide_lifetimes {
+            LifetimeEraser.visit_signature_mut(sig);
+        }
+        visit_mut::visit_signature_mut(self, sig);
+    }
+
+    fn visit_generics_mut(&mut self, generics: &mut Generics) {
+        if let (Some(limit), Some(where_clause)) =
+            (self.style.collapse_where, &mut generics.where_clause)
+        {
+            let count = where_clause.predicates.len();
+            if count > limit {
+                let placeholder = Ident::new(
+                    &format!("{}{}", WHERE_PLACEHOLDER, count),
+                    Span::call_site(),
+                );
+                where_clause.predicates = Punctuated::new();
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote!(#placeholder: Elided));
+            }
+        }
+        visit_mut::visit_generics_mut(self, generics);
+    }
+
+    fn visit_type_path_mut(&mut self, type_path: &mut syn::TypePath) {
+        if self.style.short_std_paths && type_path.qself.is_none() {
+            Self::shorten_path(&mut type_path.path);
+        }
+        visit_mut::visit_type_path_mut(self, type_path);
+    }
+
+    fn visit_trait_bound_mut(&mut self, bound: &mut syn::TraitBound) {
+        if self.style.short_std_paths {
+            Self::shorten_path(&mut bound.path);
+        }
+        visit_mut::visit_trait_bound_mut(self, bound);
+    }
+
+    fn visit_item_impl_mut(&mut self, item: &mut syn::ItemImpl) {
+        if self.style.short_std_paths {
+            if let Some((_, path, _)) = &mut item.trait_ {
+                Self::shorten_path(path);
+            }
+        }
+        visit_mut::visit_item_impl_mut(self, item);
+    }
+}
+
+/// Removes named lifetimes other than `'static` from a signature
+struct LifetimeEraser;
+
+impl LifetimeEraser {
+    fn is_named(lifetime: &Lifetime) -> bool {
+        lifetime.ident != "static" && lifetime.ident != "_"
+    }
+
+    fn retain_bounds<P: Default>(bounds: &mut Punctuated<TypeParamBound, P>) {
+        *bounds = std::mem::take(bounds)
+            .into_iter()
+            .filter(|bound| !matches!(bound, TypeParamBound::Lifetime(lifetime) if Self::is_named(lifetime)))
+            .collect();
+    }
+}
+
+impl VisitMut for LifetimeEraser {
+    fn visit_generics_mut(&mut self, generics: &mut Generics) {
+        generics.params = std::mem::take(&mut generics.params)
+            .into_iter()
+            .filter(|param| !matches!(param, GenericParam::Lifetime(_)))
+            .collect();
+        if let Some(where_clause) = &mut generics.where_clause {
+            where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+                .into_iter()
+                .filter(|predicate| !matches!(predicate, WherePredicate::Lifetime(_)))
+                .collect();
+            if where_clause.predicates.is_empty() {
+                generics.where_clause = None;
+            }
+        }
+        if generics.params.is_empty() {
+            generics.lt_token = None;
+            generics.gt_token = None;
+        }
+        visit_mut::visit_generics_mut(self, generics);
+    }
+
+    fn visit_type_param_mut(&mut self, param: &mut syn::TypeParam) {
+        Self::retain_bounds(&mut param.bounds);
+        if param.bounds.is_empty() {
+            param.colon_token = None;
+        }
+        visit_mut::visit_type_param_mut(self, param);
+    }
+
+    fn visit_predicate_type_mut(&mut self, predicate: &mut syn::PredicateType) {
+        Self::retain_bounds(&mut predicate.bounds);
+        visit_mut::visit_predicate_type_mut(self, predicate);
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, ty: &mut syn::TypeImplTrait) {
+        Self::retain_bounds(&mut ty.bounds);
+        visit_mut::visit_type_impl_trait_mut(self, ty);
+    }
+
+    fn visit_type_trait_object_mut(&mut self, ty: &mut syn::TypeTraitObject) {
+        Self::retain_bounds(&mut ty.bounds);
+        visit_mut::visit_type_trait_object_mut(self, ty);
+    }
+
+    fn visit_type_reference_mut(&mut self, reference: &mut TypeReference) {
+        if reference.lifetime.as_ref().is_some_and(Self::is_named) {
+            reference.lifetime = None;
+        }
+        visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_receiver_mut(&mut self, receiver: &mut syn::Receiver) {
+        if let Some((_, lifetime)) = &receiver.reference {
+            if lifetime.as_ref().is_some_and(Self::is_named) {
+                receiver.reference = Some((syn::Token![&](Span::call_site()), None));
+            }
+        }
+        visit_mut::visit_receiver_mut(self, receiver);
+    }
+
+    fn visit_path_arguments_mut(&mut self, arguments: &mut PathArguments) {
+        if let PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) =
+            arguments
+        {
+            *args = std::mem::take(args)
+                .into_iter()
+                .filter(|arg| !matches!(arg, GenericArgument::Lifetime(lifetime) if Self::is_named(lifetime)))
+                .collect();
+            if args.is_empty() {
+                *arguments = PathArguments::None;
+            }
+        }
+        visit_mut::visit_path_arguments_mut(self, arguments);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn normalize(style: &SignatureStyle, source: &str) -> Result<String> {
+        let mut file = syn::parse_file(source)?;
+        let mut normalizer = SignatureNormalizer::new(style);
+        normalizer.visit_file_mut(&mut file);
+        Ok(normalizer.finish(&prettyplease::unparse(&file)))
+    }
+
+    #[test]
+    fn test_elide_lifetimes() -> Result<()> {
+        let style = SignatureStyle {
+            elide_lifetimes: true,
+            ..Default::default()
+        };
+        let output = normalize(
+            &style,
+            "fn parse<'a, T: 'a + Clone>(&'a self, input: &'a str, cow: Cow<'a, str>) -> Token<'a> where 'a: 'static {}\nfn name() -> &'static str {}",
+        )?;
+        assert_eq!(
+            output,
+            "fn parse<T: Clone>(&self, input: &str, cow: Cow<str>) -> Token {}\nfn name() -> &'static str {}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_std_paths_and_collapsed_where() -> Result<()> {
+        let style = SignatureStyle {
+            short_std_paths: true,
+            collapse_where: Some(2),
+            ..Default::default()
+        };
+        let output = normalize(
+            &style,
+            "fn load(map: std::collections::HashMap<String, ::std::sync::Arc<Data>>) -> std::io::Result<()> {}\nimpl<A, B, C> std::fmt::Display for Triple<A, B, C> where A: Display, B: Display, C: Display {}",
+        )?;
+        assert_eq!(
+            output,
+            "fn load(map: HashMap<String, Arc<Data>>) -> io::Result<()> {}\nimpl<A, B, C> Display for Triple<A, B, C>\nwhere /* 3 bounds */\n{}\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::git::GitHistory;
+use crate::{paths, summary, type_usage};
+use anyhow::Result;
+use clap::ValueEnum;
+use std::cmp::Reverse;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::DirEntry;
+
+/// Number of days of git history considered by the churn order by default
+pub const DEFAULT_CHURN_WINDOW_DAYS: u64 = 90;
+
+/// Order of the files in the combined single-file output
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Files defining the types used most in signatures first
+    Types,
+    /// Files changed by the most commits within the churn window first
+    Churn,
+    /// Most recently modified files first
+    Mtime,
+}
+
+/// Sorts files in the requested order, keeping the walk order of equally ranked files
+pub fn sort_files(
+    order: Order,
+    input_dir: &Path,
+    files: &mut [DirEntry],
+    churn_window_days: u64,
+) -> Result<()> {
+    match order {
+        Order::Types => {
+            let sources: Vec<_> = files
+                .iter()
+                .map(|entry| entry.path().to_path_buf())
+                .collect();
+            let scores = type_usage::file_scores(&summary::load_sources(input_dir, &sources)?);
+            files.sort_by_key(|entry| {
+                let relative = entry.path().strip_prefix(input_dir).unwrap_or(entry.path());
+                Reverse(scores.get(&paths::slash(relative)).copied().unwrap_or(0))
+            });
+        }
+        Order::Churn => {
+            let Some(history) = GitHistory::discover(input_dir) else {
+                return Ok(());
+            };
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+            let since = now.saturating_sub(churn_window_days.saturating_mul(86_400));
+            let churn = history.churn(i64::try_from(since).unwrap_or(i64::MAX));
+            files.sort_by_cached_key(|entry| {
+                Reverse(
+                    history
+                        .relative_path(entry.path())
+                        .and_then(|path| churn.get(&path).copied())
+                        .unwrap_or(0),
+                )
+            });
+        }
+        Order::Mtime => {
+            files.sort_by_cached_key(|entry| {
+                Reverse(
+                    entry
+                        .metadata()
+                        .ok()
+                        .and_then(|metadata| metadata.modified().ok())
+                        .unwrap_or(UNIX_EPOCH),
+                )
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use walkdir::WalkDir;
+
+    #[test]
+    fn test_sort_by_mtime() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for (name, age) in [("old.rs", 3600), ("new.rs", 0), ("older.rs", 7200)] {
+            let file = std::fs::File::create(temp_dir.path().join(name))?;
+            file.set_modified(SystemTime::now() - Duration::from_secs(age))?;
+        }
+        let mut files: Vec<DirEntry> = WalkDir::new(temp_dir.path())
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+
+        sort_files(
+            Order::Mtime,
+            temp_dir.path(),
+            &mut files,
+            DEFAULT_CHURN_WINDOW_DAYS,
+        )?;
+        let names: Vec<_> = files
+            .iter()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["new.rs", "old.rs", "older.rs"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use crate::cargo;
+use crate::git::{self, GitHistory};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Whether an `--output-dir` value is a template of the whole output path rather than a
+/// suffix of the output directory name
+pub fn is_template(value: &str) -> bool {
+    value.contains('{')
+}
+
+/// Expands the tokens of an output path template: `{parent}` (directory containing the
+/// input), `{name}` or `{stem}` (input directory name or file stem), `{crate}` (package
+/// name, falling back to `{name}`), `{sha}` (abbreviated git HEAD), and `{date}` (UTC
+/// `YYYY-MM-DD`)
+pub fn expand(template: &str, input: &Path, parent: &Path, name: &str) -> Result<PathBuf> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unclosed token in output path template: {}", template))?;
+        let token = &rest[start + 1..start + end];
+        let value = match token {
+            "parent" if parent.as_os_str().is_empty() => ".".to_string(),
+            "parent" => parent.display().to_string(),
+            "name" | "stem" => name.to_string(),
+            "crate" => cargo::package_field(input, "name").unwrap_or_else(|| name.to_string()),
+            "sha" => GitHistory::discover(input)
+                .and_then(|history| history.head_hash())
+                .with_context(|| {
+                    format!(
+                        "{{sha}} requires the input to be in a git repository with commits: {}",
+                        input.display()
+                    )
+                })?,
+            "date" => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+                git::format_unix_date(i64::try_from(now.as_secs())?)
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown token {{{}}} in output path template: {}",
+                    token,
+                    template
+                ))
+            }
+        };
+        expanded.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/// Resolves a path that may not exist yet by canonicalizing its longest existing ancestor
+fn resolve(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.ancestors()
+        .find_map(|ancestor| {
+            let canonical = std::fs::canonicalize(ancestor).ok()?;
+            Some(canonical.join(path.strip_prefix(ancestor).ok()?))
+        })
+        .unwrap_or(path)
+}
+
+/// Returns the output directory as a path below the input directory if it lies inside it,
+/// so that walks of the input can skip it
+pub fn nested_output(input_dir: &Path, output: &Path) -> Option<PathBuf> {
+    let resolved = resolve(output);
+    let relative = resolved.strip_prefix(resolve(input_dir)).ok()?;
+    (!relative.as_os_str().is_empty()).then(|| input_dir.join(relative))
+}
+
+/// Fails if an input directory is the output directory or lies inside it, as the next run
+/// would process the output again
+pub fn ensure_outside_output(input_dir: &Path, output: &Path) -> Result<()> {
+    if resolve(input_dir).starts_with(resolve(output)) {
+        anyhow::bail!(
+            "Input {} is inside the output directory {}; choose an output path outside the input",
+            input_dir.display(),
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_expand_output_template() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input = temp_dir.path().join("app");
+        std::fs::create_dir(&input)?;
+        std::fs::write(input.join("Cargo.toml"), "[package]\nname = \"my-app\"\n")?;
+
+        let path = expand(
+            "{parent}/{name}-ctx/{crate}",
+            &input,
+            temp_dir.path(),
+            "app",
+        )?;
+        assert_eq!(path, temp_dir.path().join("app-ctx/my-app"));
+
+        let dated = expand("ctx-{date}", &input, temp_dir.path(), "app")?;
+        assert_eq!(dated.to_string_lossy().len(), "ctx-YYYY-MM-DD".len());
+
+        assert!(expand("{nope}", &input, temp_dir.path(), "app").is_err());
+        assert!(expand("{name", &input, temp_dir.path(), "app").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_nested_in_input() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input = temp_dir.path().join("app");
+        std::fs::create_dir(&input)?;
+
+        assert_eq!(
+            nested_output(&input, &input.join("ctx/nested")),
+            Some(input.join("ctx/nested"))
+        );
+        assert_eq!(nested_output(&input, &temp_dir.path().join("ctx")), None);
+        assert_eq!(nested_output(&input, &input), None);
+
+        assert!(ensure_outside_output(&input, &input.join("ctx")).is_ok());
+        assert!(ensure_outside_output(&input, &input).is_err());
+        assert!(ensure_outside_output(&input, temp_dir.path()).is_err());
+        Ok(())
+    }
+}
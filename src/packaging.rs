@@ -0,0 +1,267 @@
+use crate::processor::{estimate_tokens, FileStats};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Tools the processed files can be packaged for with `--target`, each with the layout,
+/// file naming, and metadata its ingestion handles best
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    /// Claude Projects: text files of XML-tagged documents, small enough to be retrieved
+    /// separately from the project knowledge
+    Claude,
+    /// Cursor: one Markdown file per folder, with front matter naming the folder's files
+    Cursor,
+    /// NotebookLM: Markdown sources of fenced code, at most 50 per notebook
+    #[value(name = "notebooklm")]
+    NotebookLm,
+}
+
+/// Largest number of sources a NotebookLM notebook takes
+const NOTEBOOKLM_MAX_SOURCES: usize = 50;
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Claude => "claude",
+            Tool::Cursor => "cursor",
+            Tool::NotebookLm => "notebooklm",
+        }
+    }
+
+    /// Tokens per package file for tools that take chunks of the whole crate, given the
+    /// tokens of all files
+    fn chunk_tokens(self, total: usize) -> Option<usize> {
+        match self {
+            Tool::Claude => Some(50_000),
+            Tool::Cursor => None,
+            Tool::NotebookLm => Some(150_000.max(total.div_ceil(NOTEBOOKLM_MAX_SOURCES))),
+        }
+    }
+}
+
+/// A processed file to package
+#[derive(Debug)]
+struct Document<'a> {
+    path: &'a str,
+    module: Option<&'a str>,
+    content: String,
+}
+
+/// Packages the output of the processed files for a tool into `package_dir`, reading each
+/// file's output from its destination; returns the number of files written
+pub fn write(
+    tool: Tool,
+    crate_name: &str,
+    files: &[FileStats],
+    package_dir: &Path,
+) -> Result<usize> {
+    let mut documents = Vec::new();
+    for file in files {
+        let content = std::fs::read_to_string(&file.destination)
+            .with_context(|| format!("Failed to read output file: {}", file.destination))?;
+        documents.push(Document {
+            path: &file.path,
+            module: file.module.as_deref(),
+            content,
+        });
+    }
+    let package = render(tool, crate_name, &documents);
+    let _ = std::fs::remove_dir_all(package_dir);
+    for (path, content) in &package {
+        let path = package_dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create package directory")?;
+        }
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write package file: {}", path.display()))?;
+    }
+    Ok(package.len())
+}
+
+/// Renders the package files by their path relative to the package directory
+fn render(tool: Tool, crate_name: &str, documents: &[Document]) -> Vec<(PathBuf, String)> {
+    let total = documents
+        .iter()
+        .map(|document| estimate_tokens(document.content.len()))
+        .sum();
+    let Some(chunk_tokens) = tool.chunk_tokens(total) else {
+        return folders(crate_name, documents);
+    };
+    let chunks = chunks(documents, chunk_tokens);
+    let count = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let part = index + 1;
+            match tool {
+                Tool::Claude => (
+                    PathBuf::from(format!("{}-{:02}.txt", crate_name, part)),
+                    claude_chunk(&chunk),
+                ),
+                _ => (
+                    PathBuf::from(format!("{}-{:02}.md", crate_name, part)),
+                    format!("# {} (part {} of {})\n\n", crate_name, part, count)
+                        + &chunk
+                            .iter()
+                            .map(|document| markdown(document))
+                            .collect::<String>(),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Splits the documents, in order, into chunks of at most `chunk_tokens` tokens; a larger
+/// document gets a chunk of its own
+fn chunks<'a, 'b>(
+    documents: &'b [Document<'a>],
+    chunk_tokens: usize,
+) -> Vec<Vec<&'b Document<'a>>> {
+    let mut chunks: Vec<Vec<&Document>> = Vec::new();
+    let mut tokens = 0;
+    for document in documents {
+        let document_tokens = estimate_tokens(document.content.len());
+        match chunks.last_mut() {
+            Some(chunk) if tokens + document_tokens <= chunk_tokens => chunk.push(document),
+            _ => {
+                chunks.push(vec![document]);
+                tokens = 0;
+            }
+        }
+        tokens += document_tokens;
+    }
+    chunks
+}
+
+/// Renders documents in the tags Claude is prompted with for long documents
+fn claude_chunk(documents: &[&Document]) -> String {
+    let mut text = String::from("<documents>\n");
+    for (index, document) in documents.iter().enumerate() {
+        text.push_str(&format!("<document index=\"{}\">\n", index + 1));
+        text.push_str(&format!("<source>{}</source>\n", document.path));
+        if let Some(module) = document.module {
+            text.push_str(&format!("<module>{}</module>\n", module));
+        }
+        text.push_str("<document_content>\n");
+        text.push_str(document.content.trim_end());
+        text.push_str("\n</document_content>\n</document>\n");
+    }
+    text.push_str("</documents>\n");
+    text
+}
+
+/// Renders one Markdown file per folder, named after the folder, with front matter
+/// describing which files it holds
+fn folders(crate_name: &str, documents: &[Document]) -> Vec<(PathBuf, String)> {
+    let mut folders: BTreeMap<&str, Vec<&Document>> = BTreeMap::new();
+    for document in documents {
+        let folder = document
+            .path
+            .rsplit_once('/')
+            .map_or("", |(folder, _)| folder);
+        folders.entry(folder).or_default().push(document);
+    }
+    folders
+        .into_iter()
+        .map(|(folder, documents)| {
+            let (path, title, globs) = if folder.is_empty() {
+                (format!("{}.md", crate_name), crate_name, "*".to_string())
+            } else {
+                (format!("{}.md", folder), folder, format!("{}/*", folder))
+            };
+            let text = format!(
+                "---\ndescription: Condensed code of {}\nglobs: {}\nalwaysApply: false\n---\n\n# {}\n\n",
+                title, globs, title
+            ) + &documents.iter().map(|document| markdown(document)).collect::<String>();
+            (PathBuf::from(path), text)
+        })
+        .collect()
+}
+
+/// Renders a document as a heading and a fenced code block, with a fence longer than any
+/// backtick run in the code
+fn markdown(document: &Document) -> String {
+    let longest = document
+        .content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    let module = document
+        .module
+        .map(|module| format!("Module: `{}`\n\n", module))
+        .unwrap_or_default();
+    format!(
+        "## {}\n\n{}{}rust\n{}\n{}\n\n",
+        document.path,
+        module,
+        fence,
+        document.content.trim_end(),
+        fence
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn documents() -> Vec<Document<'static>> {
+        vec![
+            Document {
+                path: "src/lib.rs",
+                module: Some("crate"),
+                content: "/// ```\n/// app::run();\n/// ```\npub fn run() {}\n".to_string(),
+            },
+            Document {
+                path: "src/api/client.rs",
+                module: Some("crate::api::client"),
+                content: "pub struct Client;\n".to_string(),
+            },
+            Document {
+                path: "build.rs",
+                module: None,
+                content: "fn main() {}\n".repeat(20_000),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_package_for_tools() {
+        let documents = documents();
+
+        let claude = render(Tool::Claude, "app", &documents);
+        let names: Vec<_> = claude.iter().map(|(path, _)| path.clone()).collect();
+        // The large build script doesn't fit in the first chunk
+        assert_eq!(names, [Path::new("app-01.txt"), Path::new("app-02.txt")]);
+        assert!(claude[0].1.starts_with(
+            "<documents>\n<document index=\"1\">\n<source>src/lib.rs</source>\n<module>crate</module>\n<document_content>\n/// ```"
+        ));
+        assert!(claude[0].1.contains("<source>src/api/client.rs</source>"));
+
+        let cursor = render(Tool::Cursor, "app", &documents);
+        let names: Vec<_> = cursor.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            names,
+            [
+                Path::new("app.md"),
+                Path::new("src.md"),
+                Path::new("src/api.md")
+            ]
+        );
+        assert!(cursor[2].1.starts_with(
+            "---\ndescription: Condensed code of src/api\nglobs: src/api/*\nalwaysApply: false\n---\n\n# src/api\n\n## src/api/client.rs\n\nModule: `crate::api::client`\n\n```rust\npub struct Client;\n```\n"
+        ));
+        // Doc examples don't close the fence
+        assert!(cursor[1].1.contains("````rust\n/// ```"));
+
+        let notebooklm = render(Tool::NotebookLm, "app", &documents);
+        assert_eq!(notebooklm.len(), 1);
+        assert!(notebooklm[0]
+            .1
+            .starts_with("# app (part 1 of 1)\n\n## src/lib.rs\n"));
+    }
+}
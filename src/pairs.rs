@@ -0,0 +1,129 @@
+use crate::item_ids::SourceSpan;
+use crate::processor::FileStats;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+/// Name of the JSONL file with one signature and body pair per function
+pub const PAIRS_FILE_NAME: &str = "code_context.pairs.jsonl";
+
+/// A line of the pairs output: what a model sees of a function and the body it should write
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct Pair<'a> {
+    id: &'a str,
+    path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<&'a str>,
+    name: &'a str,
+    /// Doc comments, attributes, and signature of the function
+    signature: String,
+    /// Body of the function, braces included
+    body: String,
+}
+
+/// Renders a pair for every emitted function with a non-empty body in its source, read
+/// from `source_root` by the spans recorded while processing
+pub fn render(files: &[FileStats], source_root: &Path) -> Result<String> {
+    let mut jsonl = String::new();
+    for file in files {
+        let functions: Vec<_> = file
+            .items
+            .iter()
+            .filter_map(|item| Some((item, item.source?)))
+            .filter(|(item, _)| item.kind == "fn")
+            .collect();
+        if functions.is_empty() {
+            continue;
+        }
+        let path = source_root.join(&file.path);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read source file: {}", path.display()))?;
+        for (item, span) in functions {
+            let Some((signature, body)) = split(&content, &span) else {
+                continue;
+            };
+            let line = serde_json::to_string(&Pair {
+                id: &item.id,
+                path: &file.path,
+                module: file.module.as_deref(),
+                name: &item.name,
+                signature,
+                body,
+            })?;
+            jsonl.push_str(&line);
+            jsonl.push('\n');
+        }
+    }
+    Ok(jsonl)
+}
+
+/// Splits the source of a function into its signature with docs and its body, both
+/// dedented, or returns `None` for functions without a body or with an empty one
+fn split(content: &str, span: &SourceSpan) -> Option<(String, String)> {
+    let (start, body_start) = locate_body(content, span)?;
+    let source = &content[start..span.end_byte];
+    let body_start = body_start - start;
+    let indent = source.len() - source.trim_start_matches([' ', '\t']).len();
+    Some((
+        dedent(source[..body_start].trim_end(), indent),
+        dedent(&source[body_start..], indent),
+    ))
+}
+
+/// Returns the offsets of the line a function starts on and of the opening brace of its
+/// body, or `None` for functions without a body or with an empty one
+pub fn locate_body(content: &str, span: &SourceSpan) -> Option<(usize, usize)> {
+    let start = content[..span.start_byte.min(content.len())]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let source = content.get(start..span.end_byte)?;
+    let function: syn::ItemFn = syn::parse_str(source).ok()?;
+    if function.block.stmts.is_empty() {
+        return None;
+    }
+    let body_start = function.block.brace_token.span.open().byte_range().start;
+    Some((start, start + body_start))
+}
+
+/// Removes up to `indent` leading spaces or tabs from every line
+fn dedent(text: &str, indent: usize) -> String {
+    text.lines()
+        .map(|line| {
+            let blank = line.len() - line.trim_start_matches([' ', '\t']).len();
+            &line[blank.min(indent)..]
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item_ids;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_pairs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source = "pub struct Config;\n\nimpl Config {\n    /// Loads\n    pub fn load() -> Self {\n        Config\n    }\n\n    pub fn check(&self) {}\n}\n";
+        std::fs::write(temp_dir.path().join("config.rs"), source)?;
+        let output = "pub struct Config;\nimpl Config {\n    /// Loads\n    pub fn load() -> Self {}\n    pub fn check(&self) {}\n}\n";
+        let mut items = item_ids::collect_items("app", Some("crate::config"), output);
+        item_ids::attach_source(&mut items, "app", Some("crate::config"), source);
+        let files = vec![FileStats::new("config.rs", source.len(), output.len())
+            .with_module(Some("crate::config"))
+            .with_items(items)];
+
+        let jsonl = render(&files, temp_dir.path())?;
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["name"], "Config::load");
+        assert_eq!(lines[0]["module"], "crate::config");
+        assert_eq!(lines[0]["signature"], "/// Loads\npub fn load() -> Self");
+        assert_eq!(lines[0]["body"], "{\n    Config\n}");
+        Ok(())
+    }
+}
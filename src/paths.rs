@@ -0,0 +1,110 @@
+use std::path::{Component, Path, PathBuf, Prefix};
+
+/// Renders a path with forward slashes on every platform, as headers, manifests, and git
+/// paths use them; Windows verbatim prefixes (`\\?\`) are dropped
+pub fn slash(path: &Path) -> String {
+    let mut slashed = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => slashed.push_str(&prefix_text(prefix.kind())),
+            Component::RootDir => slashed.push('/'),
+            component => {
+                if !slashed.is_empty() && !slashed.ends_with('/') {
+                    slashed.push('/');
+                }
+                slashed.push_str(&component.as_os_str().to_string_lossy());
+            }
+        }
+    }
+    slashed
+}
+
+fn prefix_text(prefix: Prefix) -> String {
+    match prefix {
+        Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => format!("{}:", drive as char),
+        Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+            format!("//{}/{}", server.to_string_lossy(), share.to_string_lossy())
+        }
+        Prefix::DeviceNS(device) => format!("//./{}", device.to_string_lossy()),
+        Prefix::Verbatim(path) => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Name of a Windows drive or share root such as `D:\` or `\\server\share\`, used to name
+/// its output directory; `None` for other paths, including the Unix root
+pub fn root_name(path: &Path) -> Option<String> {
+    if path.parent().is_some() {
+        return None;
+    }
+    match path.components().next()? {
+        Component::Prefix(prefix) => match prefix.kind() {
+            Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => Some((drive as char).to_string()),
+            Prefix::UNC(_, share) | Prefix::VerbatimUNC(_, share) => {
+                Some(share.to_string_lossy().into_owned())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Makes an absolute Windows path verbatim (`\\?\C:\…` or `\\?\UNC\server\share\…`) so
+/// files below it may exceed the 260 character `MAX_PATH` limit; a no-op elsewhere
+pub fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+    // `absolute` also normalizes separators and `..`, which verbatim paths don't allow
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let Some(Component::Prefix(prefix)) = absolute.components().next() else {
+        return absolute;
+    };
+    let text = absolute.to_string_lossy();
+    match prefix.kind() {
+        Prefix::Disk(_) => PathBuf::from(format!(r"\\?\{}", text)),
+        Prefix::UNC(..) => PathBuf::from(format!(r"\\?\UNC\{}", text.trim_start_matches('\\'))),
+        _ => absolute,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slash() {
+        assert_eq!(slash(Path::new("src/api/mod.rs")), "src/api/mod.rs");
+        assert_eq!(slash(Path::new("./src/../lib.rs")), "./src/../lib.rs");
+        assert_eq!(slash(Path::new("/home/me/crate")), "/home/me/crate");
+        assert_eq!(root_name(Path::new("/")), None);
+        assert_eq!(root_name(Path::new("src")), None);
+        assert!(long_path(Path::new("src")).ends_with("src"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_paths() {
+        assert_eq!(slash(Path::new(r"src\api\mod.rs")), "src/api/mod.rs");
+        assert_eq!(slash(Path::new(r"\\?\C:\work\crate")), "C:/work/crate");
+        assert_eq!(
+            slash(Path::new(r"\\server\share\crate")),
+            "//server/share/crate"
+        );
+        assert_eq!(root_name(Path::new(r"D:\")).as_deref(), Some("D"));
+        assert_eq!(
+            root_name(Path::new(r"\\server\share\")).as_deref(),
+            Some("share")
+        );
+        assert_eq!(root_name(Path::new(r"D:\crate")), None);
+        assert_eq!(
+            long_path(Path::new(r"C:\work\..\crate")),
+            Path::new(r"\\?\C:\crate")
+        );
+        assert_eq!(
+            long_path(Path::new(r"\\server\share\crate")),
+            Path::new(r"\\?\UNC\server\share\crate")
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::processor::ProcessingStats;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Output formats for the dry-run plan
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlanFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// What a run would do with a single file
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PlanEntry {
+    pub source: String,
+    pub destination: Option<String>,
+    pub skipped: Option<String>,
+    pub estimated_output_size: usize,
+    pub estimated_tokens: usize,
+}
+
+/// Builds the plan from the statistics of a dry run
+pub fn build_plan(stats: &ProcessingStats) -> Vec<PlanEntry> {
+    let processed = stats.files.iter().map(|file| PlanEntry {
+        source: file.path.clone(),
+        destination: Some(file.destination.clone()),
+        skipped: None,
+        estimated_output_size: file.output_size,
+        estimated_tokens: file.tokens,
+    });
+    let skipped = stats.skipped.iter().map(|file| PlanEntry {
+        source: file.path.clone(),
+        destination: None,
+        skipped: Some(file.reason.clone()),
+        estimated_output_size: 0,
+        estimated_tokens: 0,
+    });
+    processed.chain(skipped).collect()
+}
+
+/// Renders the plan in the requested format
+pub fn render(plan: &[PlanEntry], format: PlanFormat) -> Result<String> {
+    match format {
+        PlanFormat::Table => Ok(render_table(plan)),
+        PlanFormat::Json => {
+            serde_json::to_string_pretty(plan).context("Failed to serialize dry-run plan")
+        }
+    }
+}
+
+fn render_table(plan: &[PlanEntry]) -> String {
+    let source_width = plan
+        .iter()
+        .map(|entry| entry.source.len())
+        .chain(["Source".len()])
+        .max()
+        .unwrap_or_default();
+    let destination_width = plan
+        .iter()
+        .filter_map(|entry| entry.destination.as_ref().map(String::len))
+        .chain(["Destination".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = String::new();
+    let _ = writeln!(
+        table,
+        "{:<source_width$}  {:<destination_width$}  {:>12}  {:>8}",
+        "Source", "Destination", "Output size", "Tokens"
+    );
+    for entry in plan {
+        match &entry.skipped {
+            Some(reason) => {
+                let _ = writeln!(
+                    table,
+                    "{:<source_width$}  skipped: {}",
+                    entry.source, reason
+                );
+            }
+            None => {
+                let _ = writeln!(
+                    table,
+                    "{:<source_width$}  {:<destination_width$}  {:>12}  {:>8}",
+                    entry.source,
+                    entry.destination.as_deref().unwrap_or_default(),
+                    entry.estimated_output_size,
+                    entry.estimated_tokens
+                );
+            }
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::FileStats;
+    use anyhow::Result;
+    use std::path::Path;
+
+    fn stats() -> ProcessingStats {
+        let mut stats = ProcessingStats::default();
+        stats.record(
+            FileStats::new("lib.rs", 100, 40).with_destination(Path::new("out/lib.rs.txt")),
+        );
+        stats.skip("bad.rs", "not a valid Rust module");
+        stats
+    }
+
+    #[test]
+    fn test_plan_table() {
+        let table = render_table(&build_plan(&stats()));
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Source"));
+        assert!(lines[1].contains("out/lib.rs.txt"));
+        assert!(lines[1].ends_with("40        10"));
+        assert!(lines[2].contains("skipped: not a valid Rust module"));
+    }
+
+    #[test]
+    fn test_plan_json() -> Result<()> {
+        let json = render(&build_plan(&stats()), PlanFormat::Json)?;
+        let value: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(value[0]["destination"], "out/lib.rs.txt");
+        assert_eq!(value[0]["estimated_tokens"], 10);
+        assert_eq!(value[1]["skipped"], "not a valid Rust module");
+        Ok(())
+    }
+}
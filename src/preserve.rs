@@ -0,0 +1,622 @@
+use crate::removals::Removals;
+use crate::transformer::{
+    BodyRules, CodeTransformer, StripRules, TraitAnnotations, PRIVATE_FIELDS_MARKER,
+};
+use crate::visibility::VisibilityLevel;
+use proc_macro2::Span;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::Arc;
+use syn::{spanned::Spanned, Attribute, Fields, File, ImplItem, Item, TraitItem, Visibility};
+
+/// A replacement of a byte range in the original source
+struct Edit {
+    range: Range<usize>,
+    replacement: String,
+}
+
+/// Applies the same transformations as `CodeTransformer`, but by editing the original
+/// source text instead of re-printing the AST, so the author's formatting is kept
+pub struct PreservingTransformer<'a> {
+    source: &'a str,
+    no_comments: bool,
+    no_function_bodies: bool,
+    trait_annotations: Option<TraitAnnotations>,
+    keep_trait_defaults: bool,
+    strip_rules: StripRules,
+    body_rules: BodyRules,
+    strip_inner_attributes: bool,
+    hide_private_fields: bool,
+    edits: Vec<Edit>,
+    removals: Removals,
+}
+
+impl<'a> PreservingTransformer<'a> {
+    /// Creates a new PreservingTransformer for the given source text
+    pub fn new(source: &'a str, no_comments: bool, no_function_bodies: bool) -> Self {
+        Self {
+            source,
+            no_comments,
+            no_function_bodies,
+            trait_annotations: Some(TraitAnnotations::default()),
+            keep_trait_defaults: false,
+            strip_rules: StripRules::default(),
+            body_rules: BodyRules::default(),
+            strip_inner_attributes: false,
+            hide_private_fields: false,
+            edits: Vec::new(),
+            removals: Removals::default(),
+        }
+    }
+
+    /// Keeps the bodies of functions returning type aliases resolved to string-like types,
+    /// shared by the transformers of all files
+    pub fn with_string_aliases(mut self, string_aliases: Arc<HashSet<String>>) -> Self {
+        self.body_rules.string_aliases = string_aliases;
+        self
+    }
+
+    /// Keeps the bodies of functions that run SQL queries
+    pub fn with_keep_queries(mut self, keep_queries: bool) -> Self {
+        self.body_rules.keep_queries = keep_queries;
+        self
+    }
+
+    /// Keeps the bodies of proc-macro entry points and `TokenStream` functions
+    pub fn with_proc_macro(mut self, proc_macro: bool) -> Self {
+        self.body_rules.proc_macro = proc_macro;
+        self
+    }
+
+    /// Removes inner attributes of files and modules other than doc comments
+    pub fn with_strip_inner_attributes(mut self, strip_inner_attributes: bool) -> Self {
+        self.strip_inner_attributes = strip_inner_attributes;
+        self
+    }
+
+    /// Keeps benchmark functions, modules, and criterion harness code
+    pub fn with_keep_benches(mut self, keep_benches: bool) -> Self {
+        self.strip_rules.keep_benches = keep_benches;
+        self
+    }
+
+    /// Replaces the non-public fields of structs with a `/* private fields */` marker
+    pub fn with_hide_private_fields(mut self, hide_private_fields: bool) -> Self {
+        self.hide_private_fields = hide_private_fields;
+        self
+    }
+
+    /// Removes items with a visibility below the given level
+    pub fn with_visibility(mut self, visibility: VisibilityLevel) -> Self {
+        self.strip_rules.visibility = visibility;
+        self
+    }
+
+    /// Removes the impls of the given traits, by path or name
+    pub fn with_skipped_trait_impls(mut self, traits: &[String]) -> Self {
+        self.strip_rules.skipped_traits = traits.to_vec();
+        self
+    }
+
+    /// Adds attribute macros that are kept as-is and never treated as test markers
+    pub fn with_transparent_attributes(mut self, attributes: &[String]) -> Self {
+        self.strip_rules
+            .transparent_attributes
+            .extend_from_slice(attributes);
+        self
+    }
+
+    /// Keeps default trait method bodies even when function bodies are removed
+    pub fn with_keep_trait_defaults(mut self, keep_trait_defaults: bool) -> Self {
+        self.keep_trait_defaults = keep_trait_defaults;
+        self
+    }
+
+    /// Sets the doc lines added to trait methods, or disables them with `None`
+    pub fn with_trait_annotations(mut self, trait_annotations: Option<TraitAnnotations>) -> Self {
+        self.trait_annotations = trait_annotations;
+        self
+    }
+
+    /// Transforms a file parsed from the source text and returns the edited source along
+    /// with what was removed
+    pub fn transform(mut self, file: &File) -> (String, Removals) {
+        if self.no_comments {
+            self.remove_docs(&file.attrs);
+        }
+        self.remove_inner_attributes(&file.attrs);
+
+        for item in &file.items {
+            if !self.remove_stripped(item) {
+                self.visit_item(item, false);
+            }
+        }
+
+        let removals = self.removals;
+        (self.apply(), removals)
+    }
+
+    /// Removes an item if it is test code, hidden, a skipped trait impl, or would be left
+    /// empty, returning whether it was
+    fn remove_stripped(&mut self, item: &Item) -> bool {
+        if CodeTransformer::is_stripped_item(item, &self.strip_rules) {
+            self.removals.test_items += 1;
+        } else if CodeTransformer::is_hidden_item(item, &self.strip_rules) {
+            self.removals.hidden_items += 1;
+        } else if CodeTransformer::is_skipped_impl(item, &self.strip_rules) {
+            self.removals.skipped_impls += 1;
+        } else if CodeTransformer::becomes_empty(item, &self.strip_rules) {
+            self.removals.items += 1;
+        } else {
+            return false;
+        }
+        self.removals
+            .count_attributes(CodeTransformer::get_attrs(item));
+        self.remove(item.span());
+        true
+    }
+
+    fn visit_item(&mut self, item: &Item, in_module: bool) {
+        if in_module && self.no_comments {
+            self.remove_docs(CodeTransformer::get_attrs(item));
+        }
+
+        match item {
+            Item::Mod(item_mod) => {
+                self.remove_docs_if_needed(&item_mod.attrs);
+                self.remove_inner_attributes(&item_mod.attrs);
+                if let Some((_, items)) = &item_mod.content {
+                    for item in items {
+                        if !self.remove_stripped(item) {
+                            self.visit_item(item, true);
+                        }
+                    }
+                }
+            }
+            Item::Fn(item_fn) => {
+                self.remove_docs_if_needed(&item_fn.attrs);
+                let keeps_body =
+                    self.body_rules
+                        .keeps_body(&item_fn.attrs, &item_fn.sig, &item_fn.block);
+                if self.no_function_bodies && !keeps_body {
+                    self.elide_body(&item_fn.block);
+                }
+            }
+            Item::Trait(item_trait) => {
+                self.remove_docs_if_needed(&item_trait.attrs);
+                for trait_item in &item_trait.items {
+                    if let TraitItem::Fn(method) = trait_item {
+                        if let Some(default) = &method.default {
+                            if CodeTransformer::should_remove_trait_default(
+                                method,
+                                self.no_function_bodies,
+                                self.keep_trait_defaults,
+                                &self.body_rules,
+                            ) {
+                                self.elide_body(default);
+                            }
+                        }
+
+                        if self.no_comments {
+                            self.remove_docs(&method.attrs);
+                        } else {
+                            self.annotate_trait_method(method);
+                        }
+                    }
+                }
+            }
+            Item::Impl(item_impl) => {
+                self.remove_docs_if_needed(&item_impl.attrs);
+                let is_derived = CodeTransformer::is_derived_implementation(item_impl);
+                let is_serialize = CodeTransformer::is_serialize_impl(item_impl);
+
+                for impl_item in &item_impl.items {
+                    if CodeTransformer::is_test_impl_item(impl_item, &self.strip_rules) {
+                        self.removals.test_items += 1;
+                        self.removals
+                            .count_attributes(CodeTransformer::get_impl_item_attrs(impl_item));
+                        self.remove(impl_item.span());
+                        continue;
+                    }
+                    if CodeTransformer::is_hidden_impl_item(impl_item, item_impl, &self.strip_rules)
+                    {
+                        self.removals.hidden_items += 1;
+                        self.removals
+                            .count_attributes(CodeTransformer::get_impl_item_attrs(impl_item));
+                        self.remove(impl_item.span());
+                        continue;
+                    }
+                    if let ImplItem::Fn(method) = impl_item {
+                        self.remove_docs_if_needed(&method.attrs);
+                        let keeps_body = is_serialize
+                            || self.body_rules.keeps_body(
+                                &method.attrs,
+                                &method.sig,
+                                &method.block,
+                            );
+                        if self.no_function_bodies && (is_derived || !keeps_body) {
+                            self.elide_body(&method.block);
+                        }
+                    }
+                }
+            }
+            Item::Struct(item_struct) => {
+                self.remove_docs_if_needed(&item_struct.attrs);
+                for field in &item_struct.fields {
+                    self.remove_docs_if_needed(&field.attrs);
+                }
+                if self.hide_private_fields && CodeTransformer::hides_fields(&item_struct.fields) {
+                    self.hide_fields(&item_struct.fields);
+                }
+            }
+            Item::Enum(item_enum) => self.remove_docs_if_needed(&item_enum.attrs),
+            _ => {}
+        }
+    }
+
+    /// Inserts the required/default status line above a trait method's docs
+    fn annotate_trait_method(&mut self, method: &syn::TraitItemFn) {
+        let Some(annotations) = &self.trait_annotations else {
+            return;
+        };
+        let status = annotations.for_method(method.default.is_some()).to_string();
+        let first_doc = method.attrs.iter().find(|attr| attr.path().is_ident("doc"));
+        let position = match first_doc {
+            Some(attr) => attr.span().byte_range().start,
+            None => method.sig.span().byte_range().start,
+        };
+        let indent = self.indentation(position);
+
+        let mut annotation = format!("/// {}\n{}", status, indent);
+        if first_doc.is_some() {
+            annotation.push_str(&format!("///\n{}", indent));
+        }
+        self.edits.push(Edit {
+            range: position..position,
+            replacement: annotation,
+        });
+    }
+
+    fn remove_docs_if_needed(&mut self, attrs: &[Attribute]) {
+        if self.no_comments {
+            self.remove_docs(attrs);
+        }
+    }
+
+    fn remove_docs(&mut self, attrs: &[Attribute]) {
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("doc")) {
+            self.removals.doc_comments += 1;
+            self.remove(attr.span());
+        }
+    }
+
+    fn remove_inner_attributes(&mut self, attrs: &[Attribute]) {
+        if !self.strip_inner_attributes {
+            return;
+        }
+        for attr in attrs
+            .iter()
+            .filter(|attr| CodeTransformer::is_stripped_inner_attribute(attr))
+        {
+            self.removals.inner_attributes += 1;
+            self.remove(attr.span());
+        }
+    }
+
+    /// Removes the non-public fields of a struct and adds the private fields marker in their
+    /// place: after the public fields, or as the only tuple field
+    fn hide_fields(&mut self, fields: &Fields) {
+        match fields {
+            Fields::Named(fields) => {
+                for pair in fields.named.pairs() {
+                    let field = pair.value();
+                    if matches!(field.vis, Visibility::Public(_)) {
+                        continue;
+                    }
+                    self.removals.private_fields += 1;
+                    let range = field.span().byte_range();
+                    let end = pair
+                        .punct()
+                        .map_or(range.end, |comma| comma.span.byte_range().end);
+                    self.remove_range(range.start..end);
+                }
+                let close = fields.brace_token.span.close().byte_range().start;
+                let line_start = self.source[..close]
+                    .rfind('\n')
+                    .map_or(0, |index| index + 1);
+                let (position, marker) = if self.source[line_start..close].trim().is_empty() {
+                    let first = fields.named.span().byte_range().start;
+                    let indent = self.indentation(first);
+                    (line_start, format!("{}{}\n", indent, PRIVATE_FIELDS_MARKER))
+                } else {
+                    (close, format!("{} ", PRIVATE_FIELDS_MARKER))
+                };
+                self.edits.push(Edit {
+                    range: position..position,
+                    replacement: marker,
+                });
+            }
+            Fields::Unnamed(fields) => {
+                self.removals.private_fields += fields.unnamed.len();
+                let marker = format!("({})", PRIVATE_FIELDS_MARKER);
+                self.replace(fields.paren_token.span.join(), &marker);
+            }
+            Fields::Unit => {}
+        }
+    }
+
+    /// Replaces a function body with an empty block
+    fn elide_body(&mut self, block: &syn::Block) {
+        if !block.stmts.is_empty() {
+            self.removals.bodies += 1;
+        }
+        self.replace(block.span(), "{}");
+    }
+
+    /// Removes the source covered by a span, including its line if nothing else is on it
+    fn remove(&mut self, span: Span) {
+        self.remove_range(span.byte_range());
+    }
+
+    /// Removes a byte range of the source, including its line if nothing else is on it
+    fn remove_range(&mut self, range: Range<usize>) {
+        let line_start = self.source[..range.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let line_end = self.source[range.end..]
+            .find('\n')
+            .map_or(self.source.len(), |index| range.end + index + 1);
+
+        let range = if self.source[line_start..range.start].trim().is_empty()
+            && self.source[range.end..line_end].trim().is_empty()
+        {
+            line_start..line_end
+        } else {
+            range
+        };
+        self.edits.push(Edit {
+            range,
+            replacement: String::new(),
+        });
+    }
+
+    fn replace(&mut self, span: Span, replacement: &str) {
+        self.edits.push(Edit {
+            range: span.byte_range(),
+            replacement: replacement.to_string(),
+        });
+    }
+
+    /// Returns the whitespace preceding a position on its line
+    fn indentation(&self, position: usize) -> &'a str {
+        let line_start = self.source[..position]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let prefix = &self.source[line_start..position];
+        if prefix.trim().is_empty() {
+            prefix
+        } else {
+            ""
+        }
+    }
+
+    /// Applies all edits, skipping edits nested inside an already removed range
+    fn apply(mut self) -> String {
+        self.edits
+            .sort_by_key(|edit| (edit.range.start, std::cmp::Reverse(edit.range.end)));
+
+        let mut output = String::with_capacity(self.source.len());
+        let mut position = 0;
+        for edit in &self.edits {
+            if edit.range.start < position {
+                continue;
+            }
+            output.push_str(&self.source[position..edit.range.start]);
+            output.push_str(&edit.replacement);
+            position = edit.range.end;
+        }
+        output.push_str(&self.source[position..]);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn preserve(source: &str, no_comments: bool, no_function_bodies: bool) -> Result<String> {
+        let file = syn::parse_file(source)?;
+        Ok(
+            PreservingTransformer::new(source, no_comments, no_function_bodies)
+                .transform(&file)
+                .0,
+        )
+    }
+
+    #[test]
+    fn test_preserves_formatting_and_comments() -> Result<()> {
+        let input = r#"use std::fmt;   // aligned comment
+
+/// Adds numbers
+pub fn add(a: i32,
+           b: i32) -> i32 {
+    a + b
+}
+
+pub fn name() -> String { "name".to_string() }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_add() {}
+}
+"#;
+        let expected = r#"use std::fmt;   // aligned comment
+
+/// Adds numbers
+pub fn add(a: i32,
+           b: i32) -> i32 {}
+
+pub fn name() -> String { "name".to_string() }
+
+"#;
+        assert_eq!(preserve(input, false, true)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_removes_doc_comments() -> Result<()> {
+        let input = r#"//! Crate docs
+
+/// Struct docs
+pub struct Point {
+    /// Field docs
+    pub x: i32, // keep me
+}
+"#;
+        let expected = r#"
+pub struct Point {
+    pub x: i32, // keep me
+}
+"#;
+        assert_eq!(preserve(input, true, false)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trait_and_impl_methods() -> Result<()> {
+        let input = r#"trait Shape {
+    /// Area of the shape
+    fn area(&self) -> f64;
+
+    fn describe(&self) -> u32 {
+        0
+    }
+}
+
+impl Shape for Circle {
+    fn area(&self) -> f64 {
+        3.14 * self.r * self.r
+    }
+}
+"#;
+        let expected = r#"trait Shape {
+    /// This is a required method
+    ///
+    /// Area of the shape
+    fn area(&self) -> f64;
+
+    /// There is a default implementation
+    fn describe(&self) -> u32 {}
+}
+
+impl Shape for Circle {
+    fn area(&self) -> f64 {}
+}
+"#;
+        assert_eq!(preserve(input, false, true)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_test_items_in_module() -> Result<()> {
+        let input = "mod inner {\n    pub fn keep() {}\n    #[test]\n    fn drop() {}\n}\n";
+        let expected = "mod inner {\n    pub fn keep() {}\n}\n";
+        assert_eq!(preserve(input, false, false)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_visibility_level() -> Result<()> {
+        let input = "pub struct Config;\n\nimpl Config {\n    pub fn load() {}\n    pub(crate) fn check() {}\n}\n\npub(crate) mod detail {\n    pub fn helper() {}\n}\n";
+        let file = syn::parse_file(input)?;
+        let (output, removals) = PreservingTransformer::new(input, false, false)
+            .with_visibility(VisibilityLevel::Pub)
+            .transform(&file);
+        assert_eq!(
+            output,
+            "pub struct Config;\n\nimpl Config {\n    pub fn load() {}\n}\n\n"
+        );
+        assert_eq!(removals.hidden_items, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_trait_impls() -> Result<()> {
+        let input = "pub struct Id(u64);\n\nimpl Id {\n    pub fn get(&self) -> u64 { self.0 }\n}\n\nimpl Clone for Id {\n    fn clone(&self) -> Self { Id(self.0) }\n}\n\nimpl std::fmt::Debug for Id {\n    fn fmt(&self, f: &mut Formatter) -> Result { todo!() }\n}\n\nimpl Store for Id {}\n";
+        let file = syn::parse_file(input)?;
+        let (output, removals) = PreservingTransformer::new(input, false, false)
+            .with_skipped_trait_impls(&["Clone".to_string(), "std::fmt::Debug".to_string()])
+            .transform(&file);
+        assert_eq!(
+            output,
+            "pub struct Id(u64);\n\nimpl Id {\n    pub fn get(&self) -> u64 { self.0 }\n}\n\n\n\nimpl Store for Id {}\n"
+        );
+        assert_eq!(removals.skipped_impls, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hide_private_fields() -> Result<()> {
+        let input = "pub struct Config {\n    /// Name\n    pub name: String,\n    // Cached bytes\n    cache: Vec<u8>,\n    retries: u32\n}\n\npub struct Id(u64);\npub struct Pair { a: u8, pub b: u8 }\n";
+        let file = syn::parse_file(input)?;
+        let (output, removals) = PreservingTransformer::new(input, false, false)
+            .with_hide_private_fields(true)
+            .transform(&file);
+        assert_eq!(
+            output,
+            "pub struct Config {\n    /// Name\n    pub name: String,\n    // Cached bytes\n    /* private fields */\n}\n\npub struct Id(/* private fields */);\npub struct Pair {  pub b: u8 /* private fields */ }\n"
+        );
+        assert_eq!(removals.private_fields, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_trait_annotations() -> Result<()> {
+        let input = "trait Shape {\n    /// Area\n    fn area(&self) -> f64;\n}\n";
+        let file = syn::parse_file(input)?;
+        let (output, _) = PreservingTransformer::new(input, false, false)
+            .with_trait_annotations(None)
+            .transform(&file);
+        assert_eq!(output, input);
+        Ok(())
+    }
+
+    #[test]
+    fn test_removes_emptied_impls_and_modules() -> Result<()> {
+        let input = "impl Foo {\n    #[cfg(test)]\n    fn fixture() {}\n}\nimpl Foo {\n    fn keep() {}\n    #[cfg(test)]\n    fn fixture() {}\n}\nmod helpers {\n    #[test]\n    fn check() {}\n}\n";
+        let expected = "impl Foo {\n    fn keep() {}\n}\n";
+        assert_eq!(preserve(input, false, false)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_removals() -> Result<()> {
+        let input = "/// Adds numbers\nfn add(a: i32) -> i32 { a }\nimpl Foo {\n    #[cfg(test)]\n    fn fixture() {}\n    fn keep() {}\n}\n";
+        let file = syn::parse_file(input)?;
+        let (_, removals) = PreservingTransformer::new(input, true, true).transform(&file);
+        assert_eq!(
+            removals,
+            Removals {
+                doc_comments: 1,
+                test_items: 1,
+                bodies: 1,
+                attributes: 1,
+                items: 0,
+                license_headers: 0,
+                inner_attributes: 0,
+                hidden_items: 0,
+                private_fields: 0,
+                collapsed_impls: 0,
+                skipped_impls: 0,
+                unknown_items: 0,
+            }
+        );
+        assert_eq!(
+            removals.format(),
+            "1 doc comments, 1 test items, 1 bodies, 1 attributes"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Built-in sets of paths that `--preset` excludes from the walk and from included files
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Secrets, environment files, database migrations, and key or certificate files
+    NoSecrets,
+}
+
+impl Preset {
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::NoSecrets => "no-secrets",
+        }
+    }
+
+    /// Glob patterns of the excluded paths, matched against paths relative to the input
+    /// directory or to the including file
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Preset::NoSecrets => &[
+                "**/secrets*",
+                "**/secrets*/**",
+                "**/.env*",
+                "**/migrations/**",
+                "**/*.pem",
+                "**/*.key",
+                "**/*.crt",
+                "**/*.p12",
+                "**/*.pfx",
+                "**/id_rsa*",
+                "**/id_ed25519*",
+            ],
+        }
+    }
+}
+
+/// Paths excluded by the selected presets
+#[derive(Debug)]
+pub struct PathExclusions {
+    globs: GlobSet,
+    /// Preset of each pattern of `globs`
+    presets: Vec<Preset>,
+}
+
+impl PathExclusions {
+    /// Builds the exclusions of the given presets; returns `None` without presets
+    pub fn new(presets: &[Preset]) -> Result<Option<Self>> {
+        if presets.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        let mut pattern_presets = Vec::new();
+        for &preset in presets {
+            for pattern in preset.patterns() {
+                builder.add(glob(pattern)?);
+                pattern_presets.push(preset);
+            }
+        }
+        Ok(Some(PathExclusions {
+            globs: builder.build()?,
+            presets: pattern_presets,
+        }))
+    }
+
+    /// Returns the name of the preset excluding the path, if any
+    pub fn excluded(&self, path: &Path) -> Option<&'static str> {
+        self.globs
+            .matches(path)
+            .first()
+            .map(|&index| self.presets[index].name())
+    }
+}
+
+/// Compiles a pattern where `*` doesn't cross directories, so `**/secrets*` only matches
+/// file and directory names
+fn glob(pattern: &str) -> Result<Glob> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("Invalid preset pattern: {}", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_secrets_preset() -> Result<()> {
+        assert!(PathExclusions::new(&[])?.is_none());
+        let exclusions = PathExclusions::new(&[Preset::NoSecrets])?.unwrap();
+        for excluded in [
+            "secrets.rs",
+            "src/secrets_store.rs",
+            "src/secrets/vault.rs",
+            ".env",
+            "config/.env.production",
+            "db/migrations/v1.rs",
+            "certs/server.pem",
+            "tls/client.key",
+            "../keys/id_rsa.pub",
+        ] {
+            assert_eq!(
+                exclusions.excluded(Path::new(excluded)),
+                Some("no-secrets"),
+                "{}",
+                excluded
+            );
+        }
+        for kept in [
+            "src/lib.rs",
+            "src/migration.rs",
+            "src/keys.rs",
+            "sql/schema.sql",
+        ] {
+            assert_eq!(exclusions.excluded(Path::new(kept)), None, "{}", kept);
+        }
+        Ok(())
+    }
+}
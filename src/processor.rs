@@ -1,281 +1,2155 @@
 use crate::{
-    module_path::ModulePath,
-    transformer::{CodeTransformer, RustAnalyzer},
+    aliases::TypeAliases,
+    annotate,
+    budget::{self, Level, ModuleCost},
+    canonical,
+    cargo::{CrateTarget, TargetForm},
+    codegen::{self, BuildScriptForm, CrateLayout},
+    combined::CombinedOutput,
+    compression::Compression,
+    config::{PriorityWeights, DEFAULT_PRIORITY},
+    dedupe,
+    diagram::{self, DIAGRAM_FILE_NAME},
+    doc_stats::CommentStats,
+    fileset::FileSet,
+    format::{self, Formatter},
+    front_matter::{FileMetadata, FrontMatter},
+    git, impl_families,
+    impl_groups::{self, Section},
+    includes,
+    insertion_points::{self, INSERTION_POINTS_FILE_NAME},
+    item_ids::{self, ItemRecord, JsonlItem, ITEMS_FILE_NAME},
+    jobs, largest,
+    license::LicenseHeaders,
+    links::{self, LinkStyle},
+    lsif::LsifIndex,
+    manifest::{self, Manifest, MANIFEST_FILE_NAME},
+    module_filter::ModuleFilter,
+    module_path::{self, ModuleMap, ModulePath},
+    normalize::{SignatureNormalizer, SignatureStyle},
+    ordering::{self, Order},
+    output_path,
+    packaging::{self, Tool},
+    pairs::{self, PAIRS_FILE_NAME},
+    paths,
+    preserve::PreservingTransformer,
+    presets::PathExclusions,
+    progress::{Progress, ProgressMode},
+    queries::{self, QUERIES_FILE_NAME},
+    removals::Removals,
+    sanitize::{self, SanitizeMode},
+    scip::{self, OutputFormat, SourceDocument, SCIP_FILE_NAME},
+    script,
+    section_template::SectionTemplate,
+    stub,
+    summary::{self, SummarySection, SUMMARY_FILE_NAME},
+    timings::{self, Phase, Timings},
+    token_costs,
+    transformer::{CodeTransformer, RustAnalyzer, TraitAnnotations},
+    translate,
+    update::{self, PreviousOutput},
+    validate,
+    visibility::{self, VisibilityLevel},
 };
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use syn::visit_mut::VisitMut;
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
+
+/// Most Rust files a walk collects before failing, unless `--max-files` is given
+pub const DEFAULT_MAX_FILES: usize = 100_000;
+
+/// Deepest directory level below the input a walk enters before failing, unless
+/// `--max-depth-walk` is given
+pub const DEFAULT_MAX_WALK_DEPTH: usize = 32;
+
+/// Estimates the number of LLM tokens for a text of the given size in bytes
+pub fn estimate_tokens(size: usize) -> usize {
+    size.div_ceil(4)
+}
+
+/// Statistics for a single processed file
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileStats {
+    /// Path relative to the input directory
+    pub path: String,
+    pub input_size: usize,
+    pub output_size: usize,
+    pub tokens: usize,
+    /// Output file the processed content is written to
+    pub destination: String,
+    /// Module path of the file, e.g. `crate::api::client`, if it is reachable from a crate root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    /// Hash of the source content, used by `--update` to detect changed files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_hash: Option<String>,
+    /// Emitted items with their stable ids
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<ItemRecord>,
+    /// Hash of the output content, used to detect identical outputs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>,
+    /// Earlier file with identical output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+    /// What was cut from the file, by category
+    #[serde(skip_serializing_if = "Removals::is_empty")]
+    pub removals: Removals,
+    /// Code, comment, and doc lines of the source before transformation
+    #[serde(skip_serializing_if = "CommentStats::is_empty")]
+    pub comments: CommentStats,
+}
+
+impl FileStats {
+    pub fn new(path: impl Into<String>, input_size: usize, output_size: usize) -> Self {
+        Self {
+            path: path.into(),
+            input_size,
+            output_size,
+            tokens: estimate_tokens(output_size),
+            destination: String::new(),
+            module: None,
+            source_hash: None,
+            items: Vec::new(),
+            output_hash: None,
+            duplicate_of: None,
+            removals: Removals::default(),
+            comments: CommentStats::default(),
+        }
+    }
+
+    pub fn with_destination(mut self, destination: &Path) -> Self {
+        self.destination = paths::slash(destination);
+        self
+    }
+
+    pub fn with_module(mut self, module: Option<&str>) -> Self {
+        self.module = module.map(str::to_string);
+        self
+    }
+
+    pub fn with_source_hash(mut self, source_hash: String) -> Self {
+        self.source_hash = Some(source_hash);
+        self
+    }
+
+    pub fn with_items(mut self, items: Vec<ItemRecord>) -> Self {
+        self.items = items;
+        self
+    }
+
+    pub fn with_output_hash(mut self, output_hash: String) -> Self {
+        self.output_hash = Some(output_hash);
+        self
+    }
+
+    pub fn with_removals(mut self, removals: Removals) -> Self {
+        self.removals = removals;
+        self
+    }
+
+    pub fn with_comments(mut self, comments: CommentStats) -> Self {
+        self.comments = comments;
+        self
+    }
+}
+
+/// A file that was found but not processed
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
 
 #[derive(Default, Clone, Debug)]
 pub struct ProcessingStats {
     pub files_processed: usize,
     pub input_size: usize,
     pub output_size: usize,
+    pub files: Vec<FileStats>,
+    pub skipped: Vec<SkippedFile>,
+    pub timings: Timings,
+    /// Files whose previous section was reused by `--update`
+    pub reused_files: usize,
+}
+
+impl ProcessingStats {
+    /// Adds a processed file to the totals
+    pub fn record(&mut self, file: FileStats) {
+        self.files_processed += 1;
+        self.input_size += file.input_size;
+        self.output_size += file.output_size;
+        self.files.push(file);
+    }
+
+    /// Records a file that was not processed
+    pub fn skip(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(SkippedFile {
+            path: path.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// What was cut from all files, by category
+    pub fn removals(&self) -> Removals {
+        self.files.iter().map(|file| &file.removals).sum()
+    }
+
+    /// Estimated number of tokens in the whole output
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(self.output_size)
+    }
+
+    pub fn reduction_percentage(&self) -> f64 {
+        if self.input_size == 0 {
+            return 0.0;
+        }
+        // Use f64 for calculation to avoid integer overflow
+        let input_size = self.input_size as f64;
+        let output_size = self.output_size as f64;
+        ((input_size - output_size) / input_size) * 100.0
+    }
+}
+
+/// A file of the single-file output, processed and waiting to be written
+struct ProcessedFile {
+    section: Section,
+    stats: FileStats,
+    /// Reduced forms of the file for `--token-budget`, one per level after `Level::Full`
+    /// up to `Level::Summary`, with what each removes
+    reductions: Vec<(String, Removals)>,
+    /// Priority weight of the file
+    weight: i64,
+    /// Whether the section of a previous output was reused
+    reused: bool,
+}
+
+/// Reduces the buffered files of the single-file output to fit their sections into a token
+/// budget, returning the path and level of each reduced file
+fn fit_token_budget(
+    held: &mut Vec<ProcessedFile>,
+    tokens: usize,
+    template: &SectionTemplate,
+    crate_name: &str,
+    stats: &mut ProcessingStats,
+) -> Result<Vec<(String, Level)>> {
+    let costs: Vec<_> = held
+        .iter()
+        .map(|processed| {
+            let path = &processed.section.path;
+            let frame = template.header(path).len() + template.footer(path).len();
+            let mut tokens = [0; budget::LEVELS.len()];
+            tokens[0] = estimate_tokens(frame + processed.section.content.len());
+            for (level, (content, _)) in processed.reductions.iter().enumerate() {
+                tokens[level + 1] = estimate_tokens(frame + content.len());
+            }
+            ModuleCost {
+                tokens,
+                weight: processed.weight,
+            }
+        })
+        .collect();
+    let levels = budget::allocate(&costs, tokens)?;
+
+    let mut reduced = Vec::new();
+    for (index, level) in levels.into_iter().enumerate().rev() {
+        let path = held[index].section.path.clone();
+        match level {
+            Level::Full => continue,
+            Level::Omitted => {
+                held.remove(index);
+                stats.skip(path.clone(), "omitted to fit the token budget");
+            }
+            _ => {
+                let processed = &mut held[index];
+                let (content, removals) =
+                    std::mem::take(&mut processed.reductions[level as usize - 1]);
+                let file = &mut processed.stats;
+                let mut items =
+                    item_ids::collect_items(crate_name, file.module.as_deref(), &content);
+                item_ids::copy_source(&mut items, &file.items);
+                file.items = items;
+                file.removals = removals;
+                file.output_size = content.len();
+                file.tokens = estimate_tokens(content.len());
+                processed.section.content = content;
+            }
+        }
+        reduced.push((path, level));
+    }
+    reduced.reverse();
+    Ok(reduced)
+}
+
+/// Writes a section of the combined output, or a reference to an earlier identical one
+fn write_deduplicated(
+    output: &mut CombinedOutput,
+    mut section: Section,
+    stats: &mut ProcessingStats,
+    file: usize,
+    originals: &mut dedupe::Originals,
+    template: &SectionTemplate,
+) -> Result<()> {
+    let file = &mut stats.files[file];
+    file.output_hash = Some(update::content_hash(&section.content));
+    originals.mark(file);
+    if let Some(original) = &file.duplicate_of {
+        section.content = dedupe::reference(original);
+        stats.output_size = stats.output_size - file.output_size + section.content.len();
+        file.output_size = section.content.len();
+        file.tokens = estimate_tokens(file.output_size);
+        file.source_hash = None;
+    }
+    timings::time(Phase::Write, || output.write_section(&section, template))
+}
+
+/// Walks the input for Rust files, skipping the output directory of this run and those of
+/// previous runs, which are returned relative to the input with the reason; fails as soon
+/// as the walk finds more than `max_files` Rust files or enters a directory deeper than
+/// `max_depth` levels
+fn collect_rust_files(
+    input_dir: &Path,
+    output_dir: Option<&Path>,
+    max_files: usize,
+    max_depth: usize,
+) -> Result<(Vec<DirEntry>, Vec<SkippedFile>)> {
+    let mut rust_files = Vec::new();
+    let mut output_dirs = Vec::new();
+    let mut walker = WalkDir::new(input_dir).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.file_type().is_dir() && entry.depth() > max_depth {
+            anyhow::bail!(
+                "Input nests directories deeper than {} levels, e.g. {}; point it at a crate or raise --max-depth-walk",
+                max_depth,
+                entry.path().display()
+            );
+        }
+        let reason = if entry.depth() == 0 || !entry.file_type().is_dir() {
+            None
+        } else if output_dir == Some(entry.path()) {
+            Some("output directory")
+        } else if manifest::is_output_dir(entry.path()) {
+            Some("previous code-context output")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            let relative = entry.path().strip_prefix(input_dir).unwrap_or(entry.path());
+            output_dirs.push(SkippedFile {
+                path: paths::slash(relative),
+                reason: reason.to_string(),
+            });
+            walker.skip_current_dir();
+        } else if entry.path().is_file() && entry.path().extension().is_some_and(|ext| ext == "rs")
+        {
+            if rust_files.len() == max_files {
+                anyhow::bail!(
+                    "Input contains more than {} Rust files under {}; point it at a crate or raise --max-files",
+                    max_files,
+                    input_dir.display()
+                );
+            }
+            rust_files.push(entry);
+        }
+    }
+    Ok((rust_files, output_dirs))
+}
+
+pub trait Processor: Sync {
+    fn dry_run(&self) -> bool;
+    fn single_file(&self) -> bool;
+    fn no_comments(&self) -> bool;
+    fn no_function_body(&self) -> bool;
+    fn compression(&self) -> Option<Compression>;
+    fn check(&self) -> bool;
+    fn progress(&self) -> ProgressMode;
+    fn preserve_formatting(&self) -> bool;
+    fn formatter(&self) -> Formatter;
+    fn trait_annotations(&self) -> Option<&TraitAnnotations>;
+    fn keep_trait_defaults(&self) -> bool;
+    fn keep_benches(&self) -> bool;
+    fn target_form(&self, target: CrateTarget) -> Option<TargetForm>;
+    fn build_script(&self) -> Option<BuildScriptForm>;
+    fn generated_code(&self) -> bool;
+    fn also_tree(&self) -> bool;
+    fn keep_rs_extension(&self) -> bool;
+    fn annotate(&self) -> bool;
+    fn jobs(&self) -> usize;
+    fn max_files(&self) -> usize;
+    fn max_walk_depth(&self) -> usize;
+    fn inline_includes(&self) -> bool;
+    fn copy_includes(&self) -> bool;
+    fn proc_macro(&self) -> bool;
+    fn transparent_attributes(&self) -> &[String];
+    fn summary_sections(&self) -> &[SummarySection];
+    fn keep_queries(&self) -> bool;
+    fn query_appendix(&self) -> bool;
+    fn order(&self) -> Option<Order>;
+    fn churn_window_days(&self) -> u64;
+    fn priority_weights(&self) -> Option<&PriorityWeights>;
+    fn signature_style(&self) -> &SignatureStyle;
+    fn group_impls(&self) -> bool;
+    fn front_matter(&self) -> Option<FrontMatter>;
+    fn git_metadata(&self) -> bool;
+    fn git_blame(&self) -> bool;
+    fn update(&self) -> bool;
+    fn options_fingerprint(&self) -> &str;
+    fn lsif(&self) -> Option<&LsifIndex>;
+    fn output_format(&self) -> OutputFormat;
+    fn diagram_depth(&self) -> Option<usize>;
+    fn insertion_points(&self) -> bool;
+    fn target(&self) -> Option<Tool>;
+    fn link_style(&self) -> Option<LinkStyle>;
+    fn crate_name(&self) -> &str;
+    fn normalize_output(&self) -> bool;
+    fn validate_output(&self) -> bool;
+    fn sanitize_mode(&self) -> Option<SanitizeMode>;
+    fn doc_translator(&self) -> Option<&str>;
+    fn section_template(&self) -> &SectionTemplate;
+    fn license_headers(&self) -> &LicenseHeaders;
+    fn strip_script_header(&self) -> bool;
+    fn strip_inner_attributes(&self) -> bool;
+    fn drop_largest(&self) -> Option<usize>;
+    fn token_budget(&self) -> Option<usize>;
+    fn visibility_tags(&self) -> bool;
+    fn token_costs(&self) -> bool;
+    fn visibility(&self) -> VisibilityLevel;
+    fn hide_private_fields(&self) -> bool;
+    fn collapse_impls(&self) -> bool;
+    fn skipped_trait_impls(&self) -> &[String];
+    fn type_aliases(&self) -> Option<&TypeAliases>;
+    fn module_filter(&self) -> Option<&ModuleFilter>;
+    fn exclusions(&self) -> Option<&PathExclusions>;
+    fn fileset(&self) -> Option<&FileSet>;
+    /// Provenance banner stamped at the top of each output file, empty without one
+    fn provenance_banner(&self) -> &str;
+    /// Processes a single file; `relative` and `module` locate it in the processed crate
+    fn process_file(
+        &self,
+        input: &Path,
+        output: &Path,
+        relative: &Path,
+        module: Option<&str>,
+    ) -> Result<FileStats>;
+
+    /// Parses, transforms, and pretty-prints the source code of a single file
+    fn transform_source(&self, path: &Path, content: &str) -> Result<String> {
+        self.transform_source_with_removals(path, content)
+            .map(|(output, _)| output)
+    }
+
+    /// Transforms the source code of a single file, also returning what was removed
+    fn transform_source_with_removals(
+        &self,
+        path: &Path,
+        content: &str,
+    ) -> Result<(String, Removals)> {
+        self.transform_source_condensed(path, content, self.no_comments(), self.no_function_body())
+    }
+
+    /// Transforms the source code of a file of the given module, or reduces it to its
+    /// summary stub if `--module-filter` leaves the module out
+    fn transform_module(
+        &self,
+        path: &Path,
+        content: &str,
+        module: Option<&str>,
+    ) -> Result<(String, Removals)> {
+        if self
+            .module_filter()
+            .is_some_and(|filter| !filter.includes(module))
+        {
+            return Ok((stub::render(content), Removals::default()));
+        }
+        self.transform_source_with_removals(path, content)
+    }
+
+    /// Transforms a file whose code, after any script header, was parsed beforehand, such as
+    /// one cached by the daemon
+    fn transform_parsed(&self, path: &Path, content: &str, ast: syn::File) -> Result<String> {
+        self.transform_ast(
+            path,
+            content,
+            Some(ast),
+            self.no_comments(),
+            self.no_function_body(),
+        )
+        .map(|(output, _)| output)
+    }
+
+    /// Transforms the source code of a single file with the given comment and body removal,
+    /// also returning what was removed
+    fn transform_source_condensed(
+        &self,
+        path: &Path,
+        content: &str,
+        no_comments: bool,
+        no_function_bodies: bool,
+    ) -> Result<(String, Removals)> {
+        self.transform_ast(path, content, None, no_comments, no_function_bodies)
+    }
+
+    /// Transforms a file from its parsed code if given, or parses it first
+    fn transform_ast(
+        &self,
+        path: &Path,
+        content: &str,
+        ast: Option<syn::File>,
+        no_comments: bool,
+        no_function_bodies: bool,
+    ) -> Result<(String, Removals)> {
+        if self.build_script() == Some(BuildScriptForm::Verbatim) && codegen::is_build_script(path)
+        {
+            let mut output = content.to_string();
+            let removals = Removals {
+                license_headers: usize::from(self.license_headers().strip(&mut output)),
+                ..Removals::default()
+            };
+            return Ok((self.finish_output(path, output)?, removals));
+        }
+        if self.annotate() {
+            let annotated = timings::time(Phase::Transform, || annotate::annotate(content))?;
+            return Ok((annotated, Removals::default()));
+        }
+        let (script_header, content) = script::split_header(content);
+        let script_header = match (self.strip_script_header(), no_comments) {
+            (true, _) => String::new(),
+            // A rust-script manifest lives in the doc comment, which would be removed
+            (false, true) => {
+                script_header.to_string() + script::manifest_comment(content).unwrap_or_default()
+            }
+            (false, false) => script_header.to_string(),
+        };
+        let mut analyzer = match ast {
+            Some(ast) => RustAnalyzer { ast },
+            None => timings::time(Phase::Parse, || RustAnalyzer::new(content))?,
+        };
+        let no_function_bodies = no_function_bodies
+            && CrateTarget::of_file(path).and_then(|target| self.target_form(target))
+                != Some(TargetForm::Full);
+        let string_aliases = self
+            .lsif()
+            .map(LsifIndex::string_aliases)
+            .unwrap_or_default();
+
+        if self.preserve_formatting() {
+            let transformer = PreservingTransformer::new(content, no_comments, no_function_bodies)
+                .with_trait_annotations(self.trait_annotations().cloned())
+                .with_keep_trait_defaults(self.keep_trait_defaults())
+                .with_keep_benches(self.keep_benches())
+                .with_proc_macro(self.proc_macro())
+                .with_transparent_attributes(self.transparent_attributes())
+                .with_keep_queries(self.keep_queries())
+                .with_strip_inner_attributes(self.strip_inner_attributes())
+                .with_visibility(self.visibility())
+                .with_hide_private_fields(self.hide_private_fields())
+                .with_skipped_trait_impls(self.skipped_trait_impls())
+                .with_string_aliases(string_aliases);
+            let annotation = includes::annotation(&includes::find_includes(&analyzer.ast));
+            let (mut output, mut removals) =
+                timings::time(Phase::Transform, || transformer.transform(&analyzer.ast));
+            // Regular comments, and so license headers, only survive with preserved formatting
+            removals.license_headers += usize::from(self.license_headers().strip(&mut output));
+            if self.collapse_impls() {
+                let (collapsed, count) = impl_families::collapse(&output);
+                output = collapsed;
+                removals.collapsed_impls += count;
+            }
+            let output = script_header + &annotation + &output;
+            return Ok((self.finish_output(path, output)?, removals));
+        }
+
+        if self.inline_includes() {
+            let source_dir = path.parent().unwrap_or_else(|| Path::new("."));
+            includes::inline_includes(&mut analyzer.ast, source_dir, self.exclusions())?;
+        }
+
+        let mut transformer = CodeTransformer::new(no_comments, no_function_bodies)
+            .with_trait_annotations(self.trait_annotations().cloned())
+            .with_keep_trait_defaults(self.keep_trait_defaults())
+            .with_keep_benches(self.keep_benches())
+            .with_proc_macro(self.proc_macro())
+            .with_transparent_attributes(self.transparent_attributes())
+            .with_keep_queries(self.keep_queries())
+            .with_strip_inner_attributes(self.strip_inner_attributes())
+            .with_visibility(self.visibility())
+            .with_hide_private_fields(self.hide_private_fields())
+            .with_skipped_trait_impls(self.skipped_trait_impls())
+            .with_string_aliases(string_aliases);
+        let mut normalizer = SignatureNormalizer::new(self.signature_style());
+        timings::time(Phase::Transform, || {
+            transformer.visit_file_mut(&mut analyzer.ast);
+            if self.signature_style().is_enabled() {
+                normalizer.visit_file_mut(&mut analyzer.ast);
+            }
+        });
+        let annotation = includes::annotation(&includes::find_includes(&analyzer.ast));
+        let mut output = timings::time(Phase::Print, || {
+            let printed =
+                transformer.finish(&normalizer.finish(&prettyplease::unparse(&analyzer.ast)));
+            match self.formatter() {
+                Formatter::Prettyplease => Ok(printed),
+                Formatter::Rustfmt => format::rustfmt(&printed, path),
+            }
+        })?;
+        let mut removals = transformer.removals();
+        if removals.unknown_items > 0 {
+            tracing::warn!(
+                "Kept {} items code-context doesn't parse verbatim in {}",
+                removals.unknown_items,
+                path.display()
+            );
+        }
+        if self.collapse_impls() {
+            let (collapsed, count) = impl_families::collapse(&output);
+            output = collapsed;
+            removals.collapsed_impls += count;
+        }
+        Ok((
+            self.finish_output(path, script_header + &annotation + &output)?,
+            removals,
+        ))
+    }
+
+    /// Expands type aliases, tags visibilities, labels code generated into `OUT_DIR`, then
+    /// translates doc comments, sanitizes, canonicalizes, and annotates token costs of a
+    /// processed file, if requested
+    fn finish_output(&self, path: &Path, output: String) -> Result<String> {
+        let output = match self.type_aliases() {
+            Some(aliases) => aliases.expand(&output),
+            None => output,
+        };
+        let output = if self.visibility_tags() {
+            visibility::annotate(&output)
+        } else {
+            output
+        };
+        let output = if codegen::is_generated(path) {
+            codegen::GENERATED_LABEL.to_string() + &output
+        } else {
+            output
+        };
+        let output = match self.doc_translator() {
+            Some(command) => translate::translate_docs(&output, command)
+                .with_context(|| format!("Failed to translate docs of {}", path.display()))?,
+            None => output,
+        };
+        let output = match self.sanitize_mode() {
+            Some(mode) => {
+                let (sanitized, findings) = sanitize::sanitize(&output, mode);
+                if findings > 0 {
+                    tracing::warn!(
+                        "Sanitized {} suspicious strings in {}",
+                        findings,
+                        path.display()
+                    );
+                }
+                sanitized
+            }
+            None => output,
+        };
+        let output = if self.normalize_output() {
+            canonical::canonicalize(&output)
+        } else {
+            output
+        };
+        // Last, so the costs are those of the items as emitted
+        let output = if self.token_costs() {
+            token_costs::annotate(&output)
+        } else {
+            output
+        };
+        if self.validate_output() {
+            validate::validate_output(path, &output)?;
+        }
+        Ok(output)
+    }
+
+    /// Extension of output files: `rs.txt`, or `rs` with `--keep-rs-extension`
+    fn output_extension(&self) -> &'static str {
+        if self.keep_rs_extension() {
+            "rs"
+        } else {
+            "rs.txt"
+        }
+    }
+
+    /// Marker prepended to outputs with a `.rs` extension so later runs skip them
+    fn output_marker(&self) -> &'static str {
+        if self.keep_rs_extension() {
+            module_path::OUTPUT_MARKER
+        } else {
+            ""
+        }
+    }
+
+    /// Renders the requested crate-wide summary sections for the processed files of the input
+    fn summary(&self, input: &Path, files: &[PathBuf]) -> Result<String> {
+        if self.summary_sections().is_empty() {
+            return Ok(String::new());
+        }
+        let sources = summary::load_sources(input, files)?;
+        Ok(summary::render(self.summary_sections(), &sources))
+    }
+
+    /// Renders the metadata block of a processed file, if requested
+    fn front_matter_for(
+        &self,
+        source: &Path,
+        relative: &Path,
+        module: Option<&str>,
+        output: &str,
+    ) -> String {
+        match self.front_matter() {
+            Some(style) => FileMetadata::collect(source, relative, module, output).render(style),
+            None => String::new(),
+        }
+    }
+
+    /// Renders the git annotation of a source file, if requested
+    fn git_annotation_for(&self, source: &Path, content: &str) -> String {
+        if !self.git_metadata() {
+            return String::new();
+        }
+        git::annotation(source, content, self.git_blame())
+    }
+
+    /// Renders links to the source locations of a file and its items, if requested
+    fn source_links_for(&self, source: &Path, content: &str) -> String {
+        match self.link_style() {
+            Some(style) => links::annotation(source, content, style),
+            None => String::new(),
+        }
+    }
+
+    /// Renders links to the definitions used by a file and to the implementations of its
+    /// definitions, if an LSIF dump was given
+    fn definition_links_for(&self, source: &Path, content: &str, output: &str) -> String {
+        match self.lsif() {
+            Some(index) => index.definition_links(source, content, output),
+            None => String::new(),
+        }
+    }
+
+    /// Builds a SCIP index of the condensed code of the processed files of the input
+    fn scip_index(&self, input: &Path, files: &[PathBuf]) -> Result<Vec<u8>> {
+        let modules = ModuleMap::build(input);
+        let mut documents = Vec::new();
+        for path in files {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read file: {}", path.display()))?;
+            let relative = match path.strip_prefix(input) {
+                Ok(relative) if !relative.as_os_str().is_empty() => relative,
+                _ => Path::new(path.file_name().unwrap_or_default()),
+            };
+            documents.push(SourceDocument {
+                relative_path: paths::slash(relative),
+                module: modules.module_of(path).map(str::to_string),
+                text: self.transform_source(path, &content)?,
+            });
+        }
+        Ok(scip::encode_index(input, &documents))
+    }
+
+    /// Renders the appendix listing the SQL queries of the processed files, if requested
+    fn query_appendix_for(&self, input: &Path, files: &[PathBuf]) -> Result<String> {
+        if !self.query_appendix() {
+            return Ok(String::new());
+        }
+        Ok(queries::render_appendix(&summary::load_sources(
+            input, files,
+        )?))
+    }
+
+    /// Copies the files included by a source file next to its output
+    fn copy_included_files(&self, path: &Path, content: &str, output_dir: &Path) -> Result<()> {
+        if !self.copy_includes() || self.dry_run() || self.check() {
+            return Ok(());
+        }
+        let source_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        includes::copy_includes(content, source_dir, output_dir, self.exclusions())
+    }
+
+    /// Returns the preset excluding a file of the input directory, if any
+    fn excluded_by_preset(&self, relative: &Path) -> Option<&'static str> {
+        self.exclusions()?.excluded(relative)
+    }
+
+    /// Checks if a file of the input directory is in the replayed file set, if any
+    fn in_fileset(&self, relative: &Path) -> bool {
+        self.fileset()
+            .is_none_or(|fileset| fileset.contains(&paths::slash(relative)))
+    }
+
+    /// Returns the reason for skipping a file of the input crate root: build output, the
+    /// build script, and the tests, examples, or benches directories unless included
+    fn skipped_target(&self, layout: &CrateLayout, relative: &Path) -> Option<&'static str> {
+        if layout.is_build_output(relative) {
+            return Some("build output");
+        }
+        if layout.is_build_script(relative) {
+            return self.build_script().is_none().then_some("build script");
+        }
+        let target = CrateTarget::of(relative)?;
+        // Benches are skipped even without a Cargo.toml, as they always were
+        if target != CrateTarget::Benches && !layout.is_crate_root() {
+            return None;
+        }
+        self.target_form(target)
+            .is_none()
+            .then(|| target.description())
+    }
+
+    /// Returns the reason for skipping a walked Rust file of the input directory, if any
+    fn skip_reason(&self, layout: &CrateLayout, path: &Path, relative: &Path) -> Option<String> {
+        if let Some(reason) = self.skipped_target(layout, relative) {
+            Some(reason.to_string())
+        } else if let Some(preset) = self.excluded_by_preset(relative) {
+            Some(format!("excluded by preset {}", preset))
+        } else if !self.in_fileset(relative) {
+            Some("not in the file set".to_string())
+        } else if module_path::is_own_output(path) {
+            Some("code-context output".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the Rust files of the input that a run writing to `output_base`, if any,
+    /// processes, walked and skipped the same way
+    fn source_files(&self, input: &Path, output_base: Option<&Path>) -> Result<Vec<PathBuf>> {
+        if !input.is_dir() {
+            return Ok(vec![input.to_path_buf()]);
+        }
+        let (rust_files, _) = collect_rust_files(
+            input,
+            output_base
+                .and_then(|base| output_path::nested_output(input, base))
+                .as_deref(),
+            self.max_files(),
+            self.max_walk_depth(),
+        )?;
+        let layout = CrateLayout::detect(input, self.generated_code());
+        Ok(rust_files
+            .into_iter()
+            .filter(|entry| {
+                let relative = entry.path().strip_prefix(input).unwrap_or(entry.path());
+                self.skip_reason(&layout, entry.path(), relative).is_none()
+            })
+            .map(DirEntry::into_path)
+            .collect())
+    }
+
+    fn process_directory_to_single_file(
+        &self,
+        input_dir: &Path,
+        output_base: &Path,
+    ) -> Result<ProcessingStats> {
+        let mut total_stats = ProcessingStats::default();
+
+        // Collect all Rust files first
+        let (mut rust_files, output_dirs) = timings::time(Phase::Walk, || {
+            collect_rust_files(
+                input_dir,
+                output_path::nested_output(input_dir, output_base).as_deref(),
+                self.max_files(),
+                self.max_walk_depth(),
+            )
+        })?;
+        total_stats.skipped.extend(output_dirs);
+        let layout = CrateLayout::detect(input_dir, self.generated_code());
+        rust_files.retain(|entry| {
+            let path = entry.path();
+            let relative = path.strip_prefix(input_dir).unwrap_or(path);
+            let reason = self.skip_reason(&layout, path, relative).or_else(|| {
+                (!ModulePath::new(path).is_valid_module())
+                    .then(|| "not a valid Rust module".to_string())
+            });
+            if let Some(reason) = &reason {
+                total_stats.skip(paths::slash(relative), reason);
+            }
+            reason.is_none()
+        });
+        if let Some(order) = self.order() {
+            ordering::sort_files(order, input_dir, &mut rust_files, self.churn_window_days())?;
+        }
+        if let Some(weights) = self.priority_weights() {
+            weights.sort(&mut rust_files);
+        }
+        let sources: Vec<PathBuf> = rust_files
+            .iter()
+            .map(|entry| entry.path().to_path_buf())
+            .collect();
+        let header = self.output_marker().to_string()
+            + self.provenance_banner()
+            + &self.summary(input_dir, &sources)?;
+
+        let modules = timings::time(Phase::Walk, || ModuleMap::build(input_dir));
+        let progress = Mutex::new(Progress::new(rust_files.len() as u64, self.progress()));
+
+        let mut output_file = output_base.join("code_context");
+        output_file.set_extension(self.output_extension());
+        if let Some(compression) = self.compression() {
+            output_file.as_mut_os_string().push(".");
+            output_file.as_mut_os_string().push(compression.extension());
+        }
+
+        let previous = if self.update() {
+            PreviousOutput::load(
+                &output_base.join(MANIFEST_FILE_NAME),
+                &output_file,
+                self.section_template(),
+                self.options_fingerprint(),
+            )
+        } else {
+            PreviousOutput::default()
+        };
+
+        let mut files = Vec::with_capacity(rust_files.len());
+        for entry in rust_files.iter() {
+            let path = entry.path();
+            let relative = path
+                .strip_prefix(input_dir)
+                .context("Failed to strip prefix from path")?;
+            files.push((path, relative));
+        }
+
+        let process = |&(path, relative): &(&Path, &Path)| -> Result<ProcessedFile> {
+            let _file = timings::file_span(&paths::slash(relative)).entered();
+            let content = timings::time(Phase::Read, || std::fs::read_to_string(path))
+                .with_context(|| format!("Failed to read file: {}", path.display()))?;
+
+            let module = modules.module_of(path);
+            let source_hash = update::content_hash(&content);
+            let reused = previous.reuse(&paths::slash(relative), &source_hash);
+            let (section_content, mut items, removals) = match reused {
+                Some(section) => {
+                    // Item code isn't kept in the manifest, so recover it from the section
+                    let items =
+                        item_ids::collect_items(self.crate_name(), module, &section.content);
+                    let items = if items.is_empty() {
+                        section.items.clone()
+                    } else {
+                        items
+                    };
+                    (section.content.clone(), items, section.removals)
+                }
+                None => {
+                    let (processed_content, removals) =
+                        self.transform_module(path, &content, module)?;
+                    let items =
+                        item_ids::collect_items(self.crate_name(), module, &processed_content);
+                    let front_matter =
+                        self.front_matter_for(path, relative, module, &processed_content);
+                    let section_content = front_matter
+                        + &self.git_annotation_for(path, &content)
+                        + &self.source_links_for(path, &content)
+                        + &self.definition_links_for(path, &content, &processed_content)
+                        + &processed_content;
+                    (section_content, items, removals)
+                }
+            };
+            item_ids::attach_source(&mut items, self.crate_name(), module, &content);
+            let output_dir = output_base.join(relative);
+            self.copy_included_files(path, &content, output_dir.parent().unwrap_or(output_base))?;
+            if self.also_tree() && !self.dry_run() && !self.check() {
+                let mut tree_path = output_dir;
+                tree_path.set_extension(self.output_extension());
+                if let Some(parent) = tree_path.parent() {
+                    std::fs::create_dir_all(parent).context("Failed to create output directory")?;
+                }
+                let tree_content =
+                    self.output_marker().to_string() + self.provenance_banner() + &section_content;
+                timings::time(Phase::Write, || std::fs::write(&tree_path, tree_content))
+                    .context("Failed to write output file")?;
+            }
+
+            let mut reductions = Vec::new();
+            if self.token_budget().is_some() {
+                for no_function_bodies in [self.no_function_body(), true] {
+                    reductions.push(self.transform_source_condensed(
+                        path,
+                        &content,
+                        true,
+                        no_function_bodies,
+                    )?);
+                }
+                reductions.push((stub::render(&content), Removals::default()));
+            }
+            let weight = self
+                .priority_weights()
+                .map_or(DEFAULT_PRIORITY, |weights| weights.weight(path));
+
+            let relative = paths::slash(relative);
+            let stats = FileStats::new(relative.clone(), content.len(), section_content.len())
+                .with_destination(&output_file)
+                .with_module(module)
+                .with_source_hash(source_hash)
+                .with_items(items)
+                .with_removals(removals)
+                .with_comments(CommentStats::measure(&content));
+            progress.lock().expect("a worker panicked").inc();
+            Ok(ProcessedFile {
+                section: Section {
+                    path: relative,
+                    content: section_content,
+                },
+                stats,
+                reductions,
+                weight,
+                reused: reused.is_some(),
+            })
+        };
+
+        let mut output = if self.check() {
+            CombinedOutput::check(&output_file)
+        } else if self.dry_run() {
+            CombinedOutput::DryRun
+        } else {
+            CombinedOutput::create(&output_file, self.compression())?
+        };
+        output.write(&header)?;
+
+        // Sections are written as soon as all earlier ones are, in walk order however the
+        // transforms finish; grouping impls, dropping the largest files, and fitting a token
+        // budget need all of them first
+        let buffered =
+            self.group_impls() || self.drop_largest().is_some() || self.token_budget().is_some();
+        let mut originals = dedupe::Originals::default();
+        let mut held = Vec::new();
+        jobs::for_each_ordered(&files, self.jobs(), process, |processed| {
+            let processed = processed?;
+            total_stats.reused_files += usize::from(processed.reused);
+            if buffered {
+                held.push(processed);
+                return Ok(());
+            }
+            total_stats.record(processed.stats);
+            let file = total_stats.files.len() - 1;
+            write_deduplicated(
+                &mut output,
+                processed.section,
+                &mut total_stats,
+                file,
+                &mut originals,
+                self.section_template(),
+            )
+        })?;
+        progress
+            .into_inner()
+            .expect("a worker panicked")
+            .finish_with_message("Processing complete!");
+
+        if let Some(percent) = self.drop_largest() {
+            let sizes: Vec<_> = held
+                .iter()
+                .map(|processed| processed.section.content.len())
+                .collect();
+            let mut dropped = Vec::new();
+            for index in largest::largest(&sizes, percent).into_iter().rev() {
+                let stats = held.remove(index).stats;
+                let reason = format!("among the largest {}% after transformation", percent);
+                total_stats.skip(stats.path.clone(), reason);
+                dropped.push(stats);
+            }
+            dropped.reverse();
+            output.write(&largest::note(&dropped, percent))?;
+        }
+        if let Some(tokens) = self.token_budget() {
+            let reduced = fit_token_budget(
+                &mut held,
+                tokens.saturating_sub(estimate_tokens(header.len())),
+                self.section_template(),
+                self.crate_name(),
+                &mut total_stats,
+            )?;
+            output.write(&budget::note(tokens, &reduced))?;
+        }
+        let mut grouped = Vec::with_capacity(held.len());
+        for processed in held {
+            total_stats.record(processed.stats);
+            grouped.push(processed.section);
+        }
+        if self.group_impls() {
+            impl_groups::group_impls(&mut grouped);
+        }
+        for (file, section) in grouped.into_iter().enumerate() {
+            write_deduplicated(
+                &mut output,
+                section,
+                &mut total_stats,
+                file,
+                &mut originals,
+                self.section_template(),
+            )?;
+        }
+        output.write(&self.query_appendix_for(input_dir, &sources)?)?;
+        timings::time(Phase::Write, || output.finish())?;
+
+        Ok(total_stats)
+    }
+
+    fn get_output_path(input: &Path, output_dir_name: Option<&str>) -> Result<PathBuf> {
+        // Validate input path
+        if input.as_os_str().is_empty() {
+            return Err(anyhow::anyhow!("Input path cannot be empty"));
+        }
+
+        // A drive or share root gets its output inside itself, where walks skip it
+        let root_name = paths::root_name(input);
+        if input.parent().is_none() && root_name.is_none() {
+            return Err(anyhow::anyhow!("Cannot use root directory as input path"));
+        }
+
+        let parent = match root_name {
+            Some(_) => input,
+            None => input.parent().unwrap_or_else(|| Path::new(".")),
+        };
+        let name = if let Some(root_name) = &root_name {
+            root_name
+        } else if input.is_file() {
+            input
+                .file_stem()
+                .context("Failed to get file stem")?
+                .to_str()
+                .context("File stem must be valid UTF-8")?
+        } else {
+            input
+                .file_name()
+                .context("Failed to get directory name")?
+                .to_str()
+                .context("Directory name must be valid UTF-8")?
+        };
+
+        match output_dir_name {
+            Some(template) if output_path::is_template(template) => {
+                output_path::expand(template, input, parent, name)
+            }
+            _ => {
+                let output_dir_name = output_dir_name.unwrap_or("code-context");
+                Ok(parent.join(format!("{}-{}", name, output_dir_name)))
+            }
+        }
+    }
+
+    fn process_path(&self, input: &Path, output_dir_name: Option<&str>) -> Result<ProcessingStats> {
+        // First verify input path exists
+        if !input.try_exists()? {
+            return Err(anyhow::anyhow!(
+                "Input path does not exist: {}",
+                input.display()
+            ));
+        }
+
+        let output_base = paths::long_path(&Self::get_output_path(input, output_dir_name)?);
+        if input.is_dir() {
+            output_path::ensure_outside_output(input, &output_base)?;
+        }
+        let mut stats = ProcessingStats::default();
+
+        if !self.dry_run() {
+            // Always create the output directory, whether it's a file or directory input
+            std::fs::create_dir_all(&output_base)?;
+        }
+
+        if input.is_file() {
+            let output_file = if output_base.is_dir() {
+                output_base
+                    .join(input.file_name().unwrap())
+                    .with_extension(self.output_extension())
+            } else {
+                output_base.clone()
+            };
+            let file_name = input.file_name().unwrap_or_default().to_string_lossy();
+            let file_stats = timings::file_span(&file_name).in_scope(|| {
+                self.process_file(input, &output_file, Path::new(file_name.as_ref()), None)
+            })?;
+            stats.record(file_stats);
+        } else {
+            let dir_stats = self.process_directory(input, &output_base)?;
+            stats = dir_stats;
+        }
+
+        if !self.dry_run() && !self.check() {
+            // The combined single-file output already contains the summary and appendix
+            let sources = self.source_files(input, Some(&output_base))?;
+            if !(input.is_dir() && self.single_file()) {
+                let summary = self.summary(input, &sources)?;
+                if !summary.is_empty() {
+                    std::fs::write(output_base.join(SUMMARY_FILE_NAME), summary)
+                        .context("Failed to write summary file")?;
+                }
+                let appendix = self.query_appendix_for(input, &sources)?;
+                if !appendix.is_empty() {
+                    std::fs::write(output_base.join(QUERIES_FILE_NAME), appendix)
+                        .context("Failed to write queries appendix")?;
+                }
+            }
+            let artifact_dir = if output_base.is_dir() {
+                output_base.as_path()
+            } else {
+                output_base.parent().unwrap_or_else(|| Path::new("."))
+            };
+            if let Some(depth) = self.diagram_depth() {
+                let diagram =
+                    diagram::render(input, &summary::load_sources(input, &sources)?, depth);
+                std::fs::write(artifact_dir.join(DIAGRAM_FILE_NAME), diagram)
+                    .context("Failed to write Mermaid diagram")?;
+            }
+            if self.output_format() == OutputFormat::Jsonl {
+                let mut jsonl = String::new();
+                for file in &stats.files {
+                    for item in &file.items {
+                        let line = serde_json::to_string(&JsonlItem {
+                            id: &item.id,
+                            path: &file.path,
+                            module: file.module.as_deref(),
+                            kind: &item.kind,
+                            name: &item.name,
+                            code: &item.code,
+                            tokens: estimate_tokens(item.code.len()),
+                            source: item.source.as_ref(),
+                            metadata: &item.metadata,
+                        })?;
+                        jsonl.push_str(&line);
+                        jsonl.push('\n');
+                    }
+                }
+                std::fs::write(artifact_dir.join(ITEMS_FILE_NAME), jsonl)
+                    .context("Failed to write items file")?;
+            }
+            if self.output_format() == OutputFormat::Scip {
+                std::fs::write(
+                    artifact_dir.join(SCIP_FILE_NAME),
+                    self.scip_index(input, &sources)?,
+                )
+                .context("Failed to write SCIP index")?;
+            }
+            let source_root = if input.is_dir() {
+                input
+            } else {
+                input
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .unwrap_or_else(|| Path::new("."))
+            };
+            if self.output_format() == OutputFormat::PairsJsonl {
+                std::fs::write(
+                    artifact_dir.join(PAIRS_FILE_NAME),
+                    pairs::render(&stats.files, source_root)?,
+                )
+                .context("Failed to write pairs file")?;
+            }
+            if self.insertion_points() {
+                std::fs::write(
+                    artifact_dir.join(INSERTION_POINTS_FILE_NAME),
+                    insertion_points::render(&stats.files, source_root)?,
+                )
+                .context("Failed to write insertion points")?;
+            }
+            if let Some(tool) = self.target() {
+                let package_dir = artifact_dir.join(tool.name());
+                let count = packaging::write(tool, self.crate_name(), &stats.files, &package_dir)?;
+                tracing::info!(
+                    "Packaged {} files for {} in {}",
+                    count,
+                    tool.name(),
+                    paths::slash(&package_dir)
+                );
+            }
+            Manifest::from_stats(&stats)
+                .with_source_root(source_root)
+                .with_options(self.options_fingerprint())
+                .write(&output_base.join(MANIFEST_FILE_NAME))?;
+        }
+        // Phases timed on every thread of the run, if a timing layer is installed
+        stats.timings = timings::take();
+        Ok(stats)
+    }
+
+    fn process_directory(&self, input_dir: &Path, output_base: &Path) -> Result<ProcessingStats> {
+        if self.single_file() {
+            return self.process_directory_to_single_file(input_dir, output_base);
+        }
+
+        // Verify output_base doesn't exist as a file
+        if output_base.exists() && !output_base.is_dir() {
+            return Err(anyhow::anyhow!(
+                "Failed to create output directory: '{}' exists and is not a directory",
+                output_base.display()
+            ));
+        }
+
+        let mut total_stats = ProcessingStats::default();
+
+        // Collect all Rust files first
+        let (rust_files, output_dirs) = timings::time(Phase::Walk, || {
+            collect_rust_files(
+                input_dir,
+                output_path::nested_output(input_dir, output_base).as_deref(),
+                self.max_files(),
+                self.max_walk_depth(),
+            )
+        })?;
+        total_stats.skipped.extend(output_dirs);
+
+        let modules = timings::time(Phase::Walk, || ModuleMap::build(input_dir));
+        let layout = CrateLayout::detect(input_dir, self.generated_code());
+        let mut pb = Progress::new(rust_files.len() as u64, self.progress());
+
+        let mut files = Vec::with_capacity(rust_files.len());
+        for entry in rust_files.iter() {
+            let path = entry.path();
+            let relative = path
+                .strip_prefix(input_dir)
+                .context("Failed to strip prefix from path")?;
+            if let Some(reason) = self.skip_reason(&layout, path, relative) {
+                total_stats.skip(paths::slash(relative), reason);
+            } else {
+                files.push((path, relative));
+            }
+        }
+
+        let process = |&(path, relative): &(&Path, &Path)| -> Result<FileStats> {
+            let mut output_path = output_base.join(relative);
+            output_path.set_extension(self.output_extension());
+
+            if !self.dry_run() {
+                if let Some(parent) = output_path.parent() {
+                    std::fs::create_dir_all(parent).context("Failed to create output directory")?;
+                }
+            }
+
+            let _file = timings::file_span(&paths::slash(relative)).entered();
+            self.process_file(path, &output_path, relative, modules.module_of(path))
+                .with_context(|| format!("Failed to process file: {}", path.display()))
+        };
+
+        // Files are processed on `--jobs` threads and recorded in walk order
+        jobs::for_each_ordered(&files, self.jobs(), process, |processed| {
+            total_stats.record(processed?);
+            pb.inc();
+            Ok(())
+        })?;
+
+        pb.finish_with_message("Processing complete!");
+        dedupe::mark_duplicates(&mut total_stats.files);
+
+        Ok(total_stats)
+    }
+}
+
+pub struct FileProcessor {
+    no_comments: bool,
+    no_function_bodies: bool,
+    dry_run: bool,
+    single_file: bool,
+    compression: Option<Compression>,
+    check: bool,
+    progress: ProgressMode,
+    preserve_formatting: bool,
+    formatter: Formatter,
+    trait_annotations: Option<TraitAnnotations>,
+    keep_trait_defaults: bool,
+    keep_benches: bool,
+    inline_includes: bool,
+    copy_includes: bool,
+    proc_macro: bool,
+    transparent_attributes: Vec<String>,
+    summary_sections: Vec<SummarySection>,
+    keep_queries: bool,
+    query_appendix: bool,
+    order: Option<Order>,
+    churn_window_days: u64,
+    priority_weights: Option<PriorityWeights>,
+    signature_style: SignatureStyle,
+    group_impls: bool,
+    front_matter: Option<FrontMatter>,
+    git_metadata: bool,
+    git_blame: bool,
+    update: bool,
+    options_fingerprint: String,
+    lsif: Option<LsifIndex>,
+    output_format: OutputFormat,
+    diagram_depth: Option<usize>,
+    insertion_points: bool,
+    target: Option<Tool>,
+    link_style: Option<LinkStyle>,
+    crate_name: Option<String>,
+    normalize_output: bool,
+    validate_output: bool,
+    sanitize_mode: Option<SanitizeMode>,
+    doc_translator: Option<String>,
+    section_template: SectionTemplate,
+    license_headers: LicenseHeaders,
+    strip_script_header: bool,
+    strip_inner_attributes: bool,
+    drop_largest: Option<usize>,
+    token_budget: Option<usize>,
+    visibility_tags: bool,
+    token_costs: bool,
+    visibility: VisibilityLevel,
+    hide_private_fields: bool,
+    collapse_impls: bool,
+    skipped_trait_impls: Vec<String>,
+    type_aliases: Option<TypeAliases>,
+    module_filter: Option<ModuleFilter>,
+    exclusions: Option<PathExclusions>,
+    fileset: Option<FileSet>,
+    provenance_banner: Option<String>,
+    tests_form: Option<TargetForm>,
+    examples_form: Option<TargetForm>,
+    benches_form: Option<TargetForm>,
+    build_script: Option<BuildScriptForm>,
+    generated_code: bool,
+    also_tree: bool,
+    keep_rs_extension: bool,
+    annotate: bool,
+    jobs: usize,
+    max_files: usize,
+    max_walk_depth: usize,
 }
 
-impl ProcessingStats {
-    pub fn reduction_percentage(&self) -> f64 {
-        if self.input_size == 0 {
-            return 0.0;
-        }
-        // Use f64 for calculation to avoid integer overflow
-        let input_size = self.input_size as f64;
-        let output_size = self.output_size as f64;
-        ((input_size - output_size) / input_size) * 100.0
+impl FileProcessor {
+    pub fn with_options(
+        no_comments: bool,
+        no_function_bodies: bool,
+        dry_run: bool,
+        single_file: bool,
+    ) -> Self {
+        Self {
+            no_comments,
+            no_function_bodies,
+            dry_run,
+            single_file,
+            compression: None,
+            check: false,
+            progress: ProgressMode::default(),
+            preserve_formatting: false,
+            formatter: Formatter::default(),
+            trait_annotations: Some(TraitAnnotations::default()),
+            keep_trait_defaults: false,
+            keep_benches: false,
+            inline_includes: false,
+            copy_includes: false,
+            proc_macro: false,
+            transparent_attributes: Vec::new(),
+            summary_sections: Vec::new(),
+            keep_queries: false,
+            query_appendix: false,
+            order: None,
+            churn_window_days: ordering::DEFAULT_CHURN_WINDOW_DAYS,
+            priority_weights: None,
+            signature_style: SignatureStyle::default(),
+            group_impls: false,
+            front_matter: None,
+            git_metadata: false,
+            git_blame: false,
+            update: false,
+            options_fingerprint: String::new(),
+            lsif: None,
+            output_format: OutputFormat::default(),
+            diagram_depth: None,
+            insertion_points: false,
+            target: None,
+            link_style: None,
+            crate_name: None,
+            normalize_output: false,
+            validate_output: false,
+            sanitize_mode: None,
+            doc_translator: None,
+            section_template: SectionTemplate::default(),
+            license_headers: LicenseHeaders::default(),
+            strip_script_header: false,
+            strip_inner_attributes: false,
+            drop_largest: None,
+            token_budget: None,
+            visibility_tags: false,
+            token_costs: false,
+            visibility: VisibilityLevel::All,
+            hide_private_fields: false,
+            collapse_impls: false,
+            skipped_trait_impls: Vec::new(),
+            type_aliases: None,
+            module_filter: None,
+            exclusions: None,
+            fileset: None,
+            provenance_banner: None,
+            tests_form: None,
+            examples_form: None,
+            benches_form: None,
+            build_script: None,
+            generated_code: false,
+            also_tree: false,
+            keep_rs_extension: false,
+            annotate: false,
+            jobs: jobs::default_jobs(),
+            max_files: DEFAULT_MAX_FILES,
+            max_walk_depth: DEFAULT_MAX_WALK_DEPTH,
+        }
+    }
+
+    /// Sets the compression applied to the combined single-file output
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Verifies the existing single-file output instead of writing it
+    pub fn with_check(mut self, check: bool) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Sets how processing progress is reported
+    pub fn with_progress(mut self, progress: ProgressMode) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Edits the original source text instead of re-printing it with prettyplease
+    pub fn with_preserve_formatting(mut self, preserve_formatting: bool) -> Self {
+        self.preserve_formatting = preserve_formatting;
+        self
+    }
+
+    /// Sets the formatter used to print the transformed code
+    pub fn with_formatter(mut self, formatter: Formatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    /// Sets the doc lines added to trait methods, or disables them with `None`
+    pub fn with_trait_annotations(mut self, trait_annotations: Option<TraitAnnotations>) -> Self {
+        self.trait_annotations = trait_annotations;
+        self
+    }
+
+    /// Keeps default trait method bodies even when function bodies are removed
+    pub fn with_keep_trait_defaults(mut self, keep_trait_defaults: bool) -> Self {
+        self.keep_trait_defaults = keep_trait_defaults;
+        self
+    }
+
+    /// Keeps benchmark code and files under `benches/`
+    pub fn with_keep_benches(mut self, keep_benches: bool) -> Self {
+        self.keep_benches = keep_benches;
+        self
+    }
+
+    /// Replaces item-level `include!` invocations with the included items
+    pub fn with_inline_includes(mut self, inline_includes: bool) -> Self {
+        self.inline_includes = inline_includes;
+        self
+    }
+
+    /// Copies files referenced by `include!`-family macros into the output
+    pub fn with_copy_includes(mut self, copy_includes: bool) -> Self {
+        self.copy_includes = copy_includes;
+        self
+    }
+
+    /// Treats the input as a proc-macro crate, keeping the bodies of its macro functions
+    pub fn with_proc_macro(mut self, proc_macro: bool) -> Self {
+        self.proc_macro = proc_macro;
+        self
+    }
+
+    /// Adds attribute macros that are kept as-is and never treated as test markers
+    pub fn with_transparent_attributes(mut self, attributes: Vec<String>) -> Self {
+        self.transparent_attributes = attributes;
+        self
+    }
+
+    /// Sets the crate-wide summary sections emitted at the top of the context
+    pub fn with_summary_sections(mut self, sections: Vec<SummarySection>) -> Self {
+        self.summary_sections = sections;
+        self
+    }
+
+    /// Keeps the bodies of functions that run SQL queries
+    pub fn with_keep_queries(mut self, keep_queries: bool) -> Self {
+        self.keep_queries = keep_queries;
+        self
+    }
+
+    /// Collects all SQL queries into an appendix
+    pub fn with_query_appendix(mut self, query_appendix: bool) -> Self {
+        self.query_appendix = query_appendix;
+        self
+    }
+
+    /// Sets the order of the files in the combined single-file output
+    pub fn with_order(mut self, order: Option<Order>) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets the number of days of git history considered by the churn order
+    pub fn with_churn_window_days(mut self, days: u64) -> Self {
+        self.churn_window_days = days;
+        self
+    }
+
+    /// Moves files with higher priority weights first in the single-file output
+    pub fn with_priority_weights(mut self, priority_weights: Option<PriorityWeights>) -> Self {
+        self.priority_weights = priority_weights;
+        self
+    }
+
+    /// Sets the normalizations applied to signatures
+    pub fn with_signature_style(mut self, signature_style: SignatureStyle) -> Self {
+        self.signature_style = signature_style;
+        self
+    }
+
+    /// Moves impl blocks next to the definition of their type in the single-file output
+    pub fn with_group_impls(mut self, group_impls: bool) -> Self {
+        self.group_impls = group_impls;
+        self
+    }
+
+    /// Emits a metadata block before each file
+    pub fn with_front_matter(mut self, front_matter: Option<FrontMatter>) -> Self {
+        self.front_matter = front_matter;
+        self
+    }
+
+    /// Annotates each file with its last commit
+    pub fn with_git_metadata(mut self, git_metadata: bool) -> Self {
+        self.git_metadata = git_metadata;
+        self
+    }
+
+    /// Adds the most recent commit of each top-level item to the git annotation
+    pub fn with_git_blame(mut self, git_blame: bool) -> Self {
+        self.git_blame = git_blame;
+        self
+    }
+
+    /// Reuses the sections of unchanged files from the previous single-file output
+    pub fn with_update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Records the options of the run in the manifest, checked by `--update` before reusing
+    /// sections
+    pub fn with_options_fingerprint(mut self, fingerprint: String) -> Self {
+        self.options_fingerprint = fingerprint;
+        self
+    }
+
+    /// Uses definitions resolved by rust-analyzer to keep bodies and link definitions
+    pub fn with_lsif(mut self, lsif: Option<LsifIndex>) -> Self {
+        self.lsif = lsif;
+        self
+    }
+
+    pub fn with_output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Writes a Mermaid diagram of modules and public types down to the given module depth
+    pub fn with_diagram_depth(mut self, diagram_depth: Option<usize>) -> Self {
+        self.diagram_depth = diagram_depth;
+        self
+    }
+
+    /// Writes the source location of every elided function body next to the output
+    pub fn with_insertion_points(mut self, insertion_points: bool) -> Self {
+        self.insertion_points = insertion_points;
+        self
+    }
+
+    /// Also packages the processed files for a tool
+    pub fn with_target(mut self, target: Option<Tool>) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_link_style(mut self, link_style: Option<LinkStyle>) -> Self {
+        self.link_style = link_style;
+        self
+    }
+
+    /// Sets the crate name that item ids are derived from
+    pub fn with_crate_name(mut self, crate_name: Option<String>) -> Self {
+        self.crate_name = crate_name;
+        self
+    }
+
+    /// Normalizes line endings, trailing whitespace, and Unicode of processed files
+    pub fn with_normalize_output(mut self, normalize_output: bool) -> Self {
+        self.normalize_output = normalize_output;
+        self
+    }
+
+    /// Re-parses every processed file and fails on output that isn't valid Rust
+    pub fn with_validate_output(mut self, validate_output: bool) -> Self {
+        self.validate_output = validate_output;
+        self
+    }
+
+    /// Removes hidden characters and strips or flags prompt-injection phrases
+    pub fn with_sanitize_mode(mut self, sanitize_mode: Option<SanitizeMode>) -> Self {
+        self.sanitize_mode = sanitize_mode;
+        self
+    }
+
+    /// Runs retained doc comments through a shell command, e.g. to translate them
+    pub fn with_doc_translator(mut self, command: Option<String>) -> Self {
+        self.doc_translator = command;
+        self
+    }
+
+    /// Sets the header and footer around each file of the single-file output
+    pub fn with_section_template(mut self, template: SectionTemplate) -> Self {
+        self.section_template = template;
+        self
+    }
+
+    /// Removes license banners from the top of processed files
+    pub fn with_license_headers(mut self, license_headers: LicenseHeaders) -> Self {
+        self.license_headers = license_headers;
+        self
+    }
+
+    /// Drops the shebang and frontmatter of cargo scripts instead of keeping them
+    pub fn with_strip_script_header(mut self, strip_script_header: bool) -> Self {
+        self.strip_script_header = strip_script_header;
+        self
+    }
+
+    /// Removes inner attributes such as `#![allow(...)]` other than doc comments
+    pub fn with_strip_inner_attributes(mut self, strip_inner_attributes: bool) -> Self {
+        self.strip_inner_attributes = strip_inner_attributes;
+        self
+    }
+
+    /// Removes items with a visibility below the given level
+    pub fn with_visibility(mut self, visibility: VisibilityLevel) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    /// Replaces the non-public fields of structs with a `/* private fields */` marker
+    pub fn with_hide_private_fields(mut self, hide_private_fields: bool) -> Self {
+        self.hide_private_fields = hide_private_fields;
+        self
+    }
+
+    /// Sets the type aliases expanded at their use sites
+    pub fn with_type_aliases(mut self, type_aliases: Option<TypeAliases>) -> Self {
+        self.type_aliases = type_aliases;
+        self
+    }
+
+    /// Keeps only the given module subtrees in full, reducing other files to summary stubs
+    pub fn with_module_filter(mut self, module_filter: Option<ModuleFilter>) -> Self {
+        self.module_filter = module_filter;
+        self
+    }
+
+    /// Skips the files matched by exclusion presets, in the walk and in included files
+    pub fn with_exclusions(mut self, exclusions: Option<PathExclusions>) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Processes only the files of a recorded file set
+    pub fn with_fileset(mut self, fileset: Option<FileSet>) -> Self {
+        self.fileset = fileset;
+        self
+    }
+
+    /// Stamps the top of each output file with how it was produced
+    pub fn with_provenance_banner(mut self, banner: Option<String>) -> Self {
+        self.provenance_banner = banner;
+        self
+    }
+
+    /// Drops the impls of the given traits, by path or name
+    pub fn with_skipped_trait_impls(mut self, traits: Vec<String>) -> Self {
+        self.skipped_trait_impls = traits;
+        self
+    }
+
+    /// Collapses families of trait impls that differ only in one type
+    pub fn with_collapse_impls(mut self, collapse_impls: bool) -> Self {
+        self.collapse_impls = collapse_impls;
+        self
+    }
+
+    /// Tags the declaration of each item with its visibility
+    pub fn with_visibility_tags(mut self, visibility_tags: bool) -> Self {
+        self.visibility_tags = visibility_tags;
+        self
+    }
+
+    /// Appends the estimated tokens of each item to its last line
+    pub fn with_token_costs(mut self, token_costs: bool) -> Self {
+        self.token_costs = token_costs;
+        self
+    }
+
+    /// Fits the single-file output into a token budget by reducing low-priority modules
+    pub fn with_token_budget(mut self, tokens: Option<usize>) -> Self {
+        self.token_budget = tokens;
+        self
+    }
+
+    /// Leaves the given percentage of largest files out of the single-file output
+    pub fn with_drop_largest(mut self, percent: Option<usize>) -> Self {
+        self.drop_largest = percent;
+        self
+    }
+
+    /// Includes the `tests/`, `examples/`, or `benches/` directory of the input crate root
+    pub fn with_target_form(mut self, target: CrateTarget, form: Option<TargetForm>) -> Self {
+        match target {
+            CrateTarget::Tests => self.tests_form = form,
+            CrateTarget::Examples => self.examples_form = form,
+            CrateTarget::Benches => self.benches_form = form,
+        }
+        self
+    }
+
+    /// Includes the build script of the input crate root
+    pub fn with_build_script(mut self, build_script: Option<BuildScriptForm>) -> Self {
+        self.build_script = build_script;
+        self
+    }
+
+    /// Includes the code the build script generated into `OUT_DIR`, if a `target/` build exists
+    pub fn with_generated_code(mut self, generated_code: bool) -> Self {
+        self.generated_code = generated_code;
+        self
+    }
+
+    /// Also writes the per-file output tree in single-file mode
+    pub fn with_also_tree(mut self, also_tree: bool) -> Self {
+        self.also_tree = also_tree;
+        self
+    }
+
+    /// Writes outputs with a `.rs` extension and a generated marker instead of `.rs.txt`
+    pub fn with_keep_rs_extension(mut self, keep_rs_extension: bool) -> Self {
+        self.keep_rs_extension = keep_rs_extension;
+        self
+    }
+
+    /// Copies files with summary comments inserted instead of condensing them
+    pub fn with_annotate(mut self, annotate: bool) -> Self {
+        self.annotate = annotate;
+        self
+    }
+
+    /// Sets the number of files transformed in parallel; `None` uses all logical cores
+    pub fn with_jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs.unwrap_or_else(jobs::default_jobs);
+        self
+    }
+
+    /// Sets how many Rust files a walk may collect before failing
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Sets how many directory levels below the input a walk may enter before failing
+    pub fn with_max_walk_depth(mut self, max_walk_depth: usize) -> Self {
+        self.max_walk_depth = max_walk_depth;
+        self
     }
 }
 
-pub trait Processor {
-    fn dry_run(&self) -> bool;
-    fn single_file(&self) -> bool;
-    fn no_comments(&self) -> bool;
-    fn no_function_body(&self) -> bool;
-    fn process_file(&self, input: &Path, output: &Path) -> Result<(usize, usize)>;
+impl Processor for FileProcessor {
+    fn dry_run(&self) -> bool {
+        self.dry_run
+    }
 
-    fn process_directory_to_single_file(
-        &self,
-        input_dir: &Path,
-        output_base: &Path,
-    ) -> Result<ProcessingStats> {
-        let mut total_stats = ProcessingStats::default();
-        let mut combined_output = String::new();
+    fn single_file(&self) -> bool {
+        self.single_file
+    }
 
-        // Collect all Rust files first
-        let rust_files: Vec<_> = WalkDir::new(input_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().is_file() && e.path().extension().is_some_and(|ext| ext == "rs"))
-            .collect();
+    fn no_comments(&self) -> bool {
+        self.no_comments
+    }
 
-        let pb = ProgressBar::new(rust_files.len() as u64);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} files {msg}")
-                .unwrap()
-                .progress_chars("##-"),
-        );
+    fn no_function_body(&self) -> bool {
+        self.no_function_bodies
+    }
 
-        for entry in rust_files.iter() {
-            let path = entry.path();
-            let relative = path
-                .strip_prefix(input_dir)
-                .context("Failed to strip prefix from path")?;
+    fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
 
-            let content = std::fs::read_to_string(path)
-                .with_context(|| format!("Failed to read file: {}", path.display()))?;
-            let input_size = content.len();
+    fn check(&self) -> bool {
+        self.check
+    }
 
-            let module_path = ModulePath::new(path);
-            if !module_path.is_valid_module() {
-                continue;
-            }
+    fn progress(&self) -> ProgressMode {
+        self.progress
+    }
 
-            let mut analyzer = RustAnalyzer::new(&content)?;
-            let mut transformer = CodeTransformer::new(self.no_comments(), self.no_function_body());
-            transformer.visit_file_mut(&mut analyzer.ast);
+    fn preserve_formatting(&self) -> bool {
+        self.preserve_formatting
+    }
 
-            let processed_content = prettyplease::unparse(&analyzer.ast);
-            let output_size = processed_content.len();
+    fn formatter(&self) -> Formatter {
+        self.formatter
+    }
 
-            // Add file header and content to combined output
-            combined_output.push_str(&format!("\n// File: {}\n\n", relative.display()));
-            combined_output.push_str(&processed_content);
-            combined_output.push('\n');
+    fn trait_annotations(&self) -> Option<&TraitAnnotations> {
+        self.trait_annotations.as_ref()
+    }
 
-            total_stats.files_processed += 1;
-            total_stats.input_size += input_size;
-            total_stats.output_size += output_size;
-            pb.inc(1);
-        }
+    fn keep_trait_defaults(&self) -> bool {
+        self.keep_trait_defaults
+    }
 
-        pb.finish_with_message("Processing complete!");
+    fn keep_benches(&self) -> bool {
+        self.keep_benches
+    }
 
-        if !self.dry_run() {
-            let output_file = output_base.join("code_context.rs.txt");
-            if let Some(parent) = output_file.parent() {
-                std::fs::create_dir_all(parent)
-                    .context("Failed to create output directory for code context")?;
-            }
-            std::fs::write(output_file, combined_output)
-                .context("Failed to write code context file")?;
+    fn target_form(&self, target: CrateTarget) -> Option<TargetForm> {
+        match target {
+            CrateTarget::Tests => self.tests_form,
+            CrateTarget::Examples => self.examples_form,
+            CrateTarget::Benches => self
+                .benches_form
+                .or(self.keep_benches.then_some(TargetForm::Condensed)),
         }
+    }
 
-        Ok(total_stats)
+    fn build_script(&self) -> Option<BuildScriptForm> {
+        self.build_script
     }
 
-    fn get_output_path(input: &Path, output_dir_name: Option<&str>) -> Result<PathBuf> {
-        // Validate input path
-        if input.as_os_str().is_empty() {
-            return Err(anyhow::anyhow!("Input path cannot be empty"));
-        }
+    fn generated_code(&self) -> bool {
+        self.generated_code
+    }
 
-        if input == Path::new("/") {
-            return Err(anyhow::anyhow!("Cannot use root directory as input path"));
-        }
+    fn also_tree(&self) -> bool {
+        self.also_tree
+    }
 
-        let output_dir_name = output_dir_name.unwrap_or("code-context");
+    fn keep_rs_extension(&self) -> bool {
+        self.keep_rs_extension
+    }
 
-        if input.is_file() {
-            let parent = input.parent().unwrap_or_else(|| Path::new("."));
+    fn annotate(&self) -> bool {
+        self.annotate
+    }
 
-            let file_stem = input
-                .file_stem()
-                .context("Failed to get file stem")?
-                .to_str()
-                .context("File stem must be valid UTF-8")?;
+    fn jobs(&self) -> usize {
+        self.jobs
+    }
 
-            Ok(parent.join(format!("{}-{}", file_stem, output_dir_name)))
-        } else {
-            let parent = input.parent().unwrap_or_else(|| Path::new("."));
+    fn max_files(&self) -> usize {
+        self.max_files
+    }
 
-            let dir_name = input
-                .file_name()
-                .context("Failed to get directory name")?
-                .to_str()
-                .context("Directory name must be valid UTF-8")?;
+    fn max_walk_depth(&self) -> usize {
+        self.max_walk_depth
+    }
 
-            Ok(parent.join(format!("{}-{}", dir_name, output_dir_name)))
-        }
+    fn inline_includes(&self) -> bool {
+        self.inline_includes
     }
 
-    fn process_path(&self, input: &Path, output_dir_name: Option<&str>) -> Result<ProcessingStats> {
-        // First verify input path exists
-        if !input.try_exists()? {
-            return Err(anyhow::anyhow!(
-                "Input path does not exist: {}",
-                input.display()
-            ));
-        }
+    fn copy_includes(&self) -> bool {
+        self.copy_includes
+    }
 
-        let output_base = Self::get_output_path(input, output_dir_name)?;
-        let mut stats = ProcessingStats::default();
+    fn proc_macro(&self) -> bool {
+        self.proc_macro
+    }
 
-        if !self.dry_run() {
-            // Always create the output directory, whether it's a file or directory input
-            std::fs::create_dir_all(&output_base)?;
-        }
+    fn transparent_attributes(&self) -> &[String] {
+        &self.transparent_attributes
+    }
 
-        if input.is_file() {
-            let output_file = if output_base.is_dir() {
-                output_base
-                    .join(input.file_name().unwrap())
-                    .with_extension("rs.txt")
-            } else {
-                output_base
-            };
-            let (input_size, output_size) = self.process_file(input, &output_file)?;
-            stats.files_processed = 1;
-            stats.input_size = input_size;
-            stats.output_size = output_size;
-        } else {
-            let dir_stats = self.process_directory(input, &output_base)?;
-            stats = dir_stats;
-        }
-        Ok(stats)
+    fn summary_sections(&self) -> &[SummarySection] {
+        &self.summary_sections
     }
 
-    fn process_directory(&self, input_dir: &Path, output_base: &Path) -> Result<ProcessingStats> {
-        if self.single_file() {
-            return self.process_directory_to_single_file(input_dir, output_base);
-        }
+    fn keep_queries(&self) -> bool {
+        self.keep_queries
+    }
 
-        // Verify output_base doesn't exist as a file
-        if output_base.exists() && !output_base.is_dir() {
-            return Err(anyhow::anyhow!(
-                "Failed to create output directory: '{}' exists and is not a directory",
-                output_base.display()
-            ));
-        }
+    fn query_appendix(&self) -> bool {
+        self.query_appendix
+    }
 
-        // Collect all Rust files first
-        let rust_files: Vec<_> = WalkDir::new(input_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().is_file() && e.path().extension().is_some_and(|ext| ext == "rs"))
-            .collect();
+    fn order(&self) -> Option<Order> {
+        self.order
+    }
 
-        let pb = ProgressBar::new(rust_files.len() as u64);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} files {msg}")
-                .unwrap()
-                .progress_chars("##-"),
-        );
+    fn churn_window_days(&self) -> u64 {
+        self.churn_window_days
+    }
 
-        let mut total_stats = ProcessingStats::default();
+    fn priority_weights(&self) -> Option<&PriorityWeights> {
+        self.priority_weights.as_ref()
+    }
 
-        // Process files sequentially instead of in parallel
-        for entry in rust_files.iter() {
-            let path = entry.path();
-            let relative = path
-                .strip_prefix(input_dir)
-                .context("Failed to strip prefix from path")?;
-            let mut output_path = output_base.join(relative);
-            output_path.set_extension("rs.txt");
+    fn signature_style(&self) -> &SignatureStyle {
+        &self.signature_style
+    }
 
-            if let Some(parent) = output_path.parent() {
-                std::fs::create_dir_all(parent).context("Failed to create output directory")?;
-            }
+    fn group_impls(&self) -> bool {
+        self.group_impls
+    }
 
-            let (input_size, output_size) = self
-                .process_file(path, &output_path)
-                .with_context(|| format!("Failed to process file: {}", path.display()))?;
+    fn front_matter(&self) -> Option<FrontMatter> {
+        self.front_matter
+    }
 
-            total_stats.files_processed += 1;
-            total_stats.input_size += input_size;
-            total_stats.output_size += output_size;
-            pb.inc(1);
-        }
+    fn git_metadata(&self) -> bool {
+        self.git_metadata
+    }
 
-        pb.finish_with_message("Processing complete!");
+    fn git_blame(&self) -> bool {
+        self.git_blame
+    }
 
-        Ok(total_stats)
+    fn update(&self) -> bool {
+        self.update
     }
-}
 
-pub struct FileProcessor {
-    no_comments: bool,
-    no_function_bodies: bool,
-    dry_run: bool,
-    single_file: bool,
-}
+    fn options_fingerprint(&self) -> &str {
+        &self.options_fingerprint
+    }
 
-impl FileProcessor {
-    pub fn with_options(
-        no_comments: bool,
-        no_function_bodies: bool,
-        dry_run: bool,
-        single_file: bool,
-    ) -> Self {
-        Self {
-            no_comments,
-            no_function_bodies,
-            dry_run,
-            single_file,
-        }
+    fn lsif(&self) -> Option<&LsifIndex> {
+        self.lsif.as_ref()
     }
-}
 
-impl Processor for FileProcessor {
-    fn dry_run(&self) -> bool {
-        self.dry_run
+    fn output_format(&self) -> OutputFormat {
+        self.output_format
     }
 
-    fn single_file(&self) -> bool {
-        self.single_file
+    fn diagram_depth(&self) -> Option<usize> {
+        self.diagram_depth
     }
 
-    fn no_comments(&self) -> bool {
-        self.no_comments
+    fn insertion_points(&self) -> bool {
+        self.insertion_points
     }
 
-    fn no_function_body(&self) -> bool {
-        self.no_function_bodies
+    fn target(&self) -> Option<Tool> {
+        self.target
+    }
+
+    fn link_style(&self) -> Option<LinkStyle> {
+        self.link_style
+    }
+
+    fn crate_name(&self) -> &str {
+        self.crate_name.as_deref().unwrap_or_default()
+    }
+
+    fn normalize_output(&self) -> bool {
+        self.normalize_output
+    }
+
+    fn validate_output(&self) -> bool {
+        self.validate_output
+    }
+
+    fn sanitize_mode(&self) -> Option<SanitizeMode> {
+        self.sanitize_mode
+    }
+
+    fn doc_translator(&self) -> Option<&str> {
+        self.doc_translator.as_deref()
+    }
+
+    fn section_template(&self) -> &SectionTemplate {
+        &self.section_template
+    }
+
+    fn license_headers(&self) -> &LicenseHeaders {
+        &self.license_headers
+    }
+
+    fn strip_script_header(&self) -> bool {
+        self.strip_script_header
+    }
+
+    fn strip_inner_attributes(&self) -> bool {
+        self.strip_inner_attributes
+    }
+
+    fn drop_largest(&self) -> Option<usize> {
+        self.drop_largest
+    }
+
+    fn token_budget(&self) -> Option<usize> {
+        self.token_budget
+    }
+
+    fn visibility_tags(&self) -> bool {
+        self.visibility_tags
+    }
+
+    fn token_costs(&self) -> bool {
+        self.token_costs
+    }
+
+    fn visibility(&self) -> VisibilityLevel {
+        self.visibility
+    }
+
+    fn hide_private_fields(&self) -> bool {
+        self.hide_private_fields
+    }
+
+    fn collapse_impls(&self) -> bool {
+        self.collapse_impls
+    }
+
+    fn skipped_trait_impls(&self) -> &[String] {
+        &self.skipped_trait_impls
+    }
+
+    fn type_aliases(&self) -> Option<&TypeAliases> {
+        self.type_aliases.as_ref()
+    }
+
+    fn module_filter(&self) -> Option<&ModuleFilter> {
+        self.module_filter.as_ref()
+    }
+
+    fn exclusions(&self) -> Option<&PathExclusions> {
+        self.exclusions.as_ref()
+    }
+
+    fn fileset(&self) -> Option<&FileSet> {
+        self.fileset.as_ref()
+    }
+
+    fn provenance_banner(&self) -> &str {
+        self.provenance_banner.as_deref().unwrap_or_default()
     }
 
-    fn process_file(&self, input: &Path, output: &Path) -> Result<(usize, usize)> {
+    fn process_file(
+        &self,
+        input: &Path,
+        output: &Path,
+        relative: &Path,
+        module: Option<&str>,
+    ) -> Result<FileStats> {
         // Verify input file exists before trying to read it
         if !input.try_exists()? {
             return Err(anyhow::anyhow!(
@@ -284,7 +2158,8 @@ impl Processor for FileProcessor {
             ));
         }
 
-        let content = std::fs::read_to_string(input).context("Failed to read input file")?;
+        let content = timings::time(Phase::Read, || std::fs::read_to_string(input))
+            .context("Failed to read input file")?;
         let input_size = content.len();
 
         let module_path = ModulePath::new(input);
@@ -295,35 +2170,69 @@ impl Processor for FileProcessor {
             ));
         }
 
-        let mut analyzer = RustAnalyzer::new(&content)?;
-        let mut transformer = CodeTransformer::new(self.no_comments(), self.no_function_body());
-
-        transformer.visit_file_mut(&mut analyzer.ast);
-
-        let output_content = prettyplease::unparse(&analyzer.ast);
+        let (output_content, removals) = self.transform_module(input, &content, module)?;
+        let mut items = item_ids::collect_items(self.crate_name(), module, &output_content);
+        item_ids::attach_source(&mut items, self.crate_name(), module, &content);
+        let output_content = self.front_matter_for(input, relative, module, &output_content)
+            + self.provenance_banner()
+            + &self.git_annotation_for(input, &content)
+            + &self.source_links_for(input, &content)
+            + &self.definition_links_for(input, &content, &output_content)
+            + &output_content;
+        self.copy_included_files(
+            input,
+            &content,
+            output.parent().unwrap_or_else(|| Path::new(".")),
+        )?;
+        let output_content = self.output_marker().to_string() + &output_content;
         let output_size = output_content.len();
+        let output_hash = update::content_hash(&output_content);
 
         if !self.dry_run() {
             if let Some(parent) = output.parent() {
                 std::fs::create_dir_all(parent).context("Failed to create output directory")?;
             }
-            std::fs::write(output, output_content).context("Failed to write output file")?;
+            timings::time(Phase::Write, || std::fs::write(output, output_content))
+                .context("Failed to write output file")?;
         }
 
-        Ok((input_size, output_size))
+        Ok(
+            FileStats::new(paths::slash(relative), input_size, output_size)
+                .with_destination(output)
+                .with_module(module)
+                .with_source_hash(update::content_hash(&content))
+                .with_items(items)
+                .with_output_hash(output_hash)
+                .with_removals(removals)
+                .with_comments(CommentStats::measure(&content)),
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::presets::Preset;
     use crate::test_utils::process_code;
+    use crate::timings::TimingLayer;
     use crate::{create_processor, Cli};
     use anyhow::Result;
     use clap::Parser;
     use std::fs;
     use std::path::PathBuf;
     use tempfile::TempDir;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// A processor that fails on transformations breaking the output
+    fn test_processor(
+        no_comments: bool,
+        no_function_bodies: bool,
+        dry_run: bool,
+        single_file: bool,
+    ) -> FileProcessor {
+        FileProcessor::with_options(no_comments, no_function_bodies, dry_run, single_file)
+            .with_validate_output(true)
+    }
 
     #[test]
     fn test_process_path_with_file() -> Result<()> {
@@ -331,7 +2240,7 @@ mod tests {
         let test_file = temp_dir.path().join("test.rs");
         fs::write(&test_file, "fn main() {}")?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let stats = processor.process_path(&test_file, Some("output"))?;
 
         assert_eq!(stats.files_processed, 1);
@@ -355,7 +2264,7 @@ mod tests {
             "pub fn add(a: i32, b: i32) -> i32 { a + b }",
         )?;
 
-        let processor = FileProcessor::with_options(false, false, false, true);
+        let processor = test_processor(false, false, false, true);
         let output_dir = temp_dir.path().join("output");
         let stats = processor.process_directory_to_single_file(input_dir, &output_dir)?;
 
@@ -382,7 +2291,7 @@ mod tests {
             "pub fn add(a: i32, b: i32) -> i32 { a + b }",
         )?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false).with_jobs(Some(1));
         let output_dir = temp_dir.path().join("output");
         let stats = processor.process_directory(input_dir, &output_dir)?;
 
@@ -391,6 +2300,14 @@ mod tests {
         assert!(output_dir.join("src").join("main.rs.txt").exists());
         assert!(output_dir.join("src").join("lib.rs.txt").exists());
 
+        // Files processed in parallel are recorded in walk order
+        let processor = test_processor(false, false, false, false).with_jobs(Some(4));
+        let parallel = processor.process_directory(input_dir, &output_dir)?;
+        let paths = |stats: &ProcessingStats| -> Vec<String> {
+            stats.files.iter().map(|file| file.path.clone()).collect()
+        };
+        assert_eq!(paths(&parallel), paths(&stats));
+
         Ok(())
     }
 
@@ -459,7 +2376,7 @@ mod tests {
     fn test_invalid_input_path() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let invalid_path = temp_dir.path().join("nonexistent");
-        let processor = FileProcessor::with_options(false, true, false, false);
+        let processor = test_processor(false, true, false, false);
 
         let result = processor.process_path(&invalid_path, None);
         assert!(result.is_err());
@@ -470,7 +2387,7 @@ mod tests {
 
     #[test]
     fn test_processor_options() {
-        let processor = FileProcessor::with_options(true, true, true, true);
+        let processor = test_processor(true, true, true, true);
         assert!(processor.no_comments());
         assert!(processor.dry_run());
         assert!(processor.single_file());
@@ -479,7 +2396,7 @@ mod tests {
     #[test]
     fn test_process_directory_empty() -> Result<()> {
         let temp_dir = TempDir::new()?;
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let stats = processor.process_directory(temp_dir.path(), temp_dir.path())?;
         assert_eq!(stats.files_processed, 0);
         Ok(())
@@ -578,7 +2495,7 @@ impl MyStruct {
         // Use try_init() instead of init() to handle case where logger is already initialized
         let _ = tracing_subscriber::fmt::try_init();
         let processor = create_processor(&cli);
-        let stats = processor.process_path(&cli.input_path, cli.output_dir_name.as_deref())?;
+        let stats = processor.process_path(cli.input_path(), cli.output_dir_name.as_deref())?;
 
         assert!(stats.files_processed > 0);
         assert!(stats.input_size > 0);
@@ -618,7 +2535,7 @@ impl MyStruct {
             "#,
         )?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let output_dir = temp_dir.path().join("output");
         let stats = processor.process_directory(&src_dir, &output_dir)?;
 
@@ -658,7 +2575,7 @@ impl MyStruct {
             "#,
         )?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let output_dir = temp_dir.path().join("output");
         let stats = processor.process_directory(&src_dir, &output_dir)?;
 
@@ -692,7 +2609,7 @@ impl MyStruct {
         )?;
 
         // Test with comments preserved
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let output_dir = temp_dir.path().join("output-with-comments");
         processor.process_directory(&src_dir, &output_dir)?;
 
@@ -701,7 +2618,7 @@ impl MyStruct {
         assert!(content.contains("/// Function documentation"));
 
         // Test with comments removed
-        let processor = FileProcessor::with_options(true, false, false, false);
+        let processor = test_processor(true, false, false, false);
         let output_dir = temp_dir.path().join("output-no-comments");
         processor.process_directory(&src_dir, &output_dir)?;
 
@@ -735,18 +2652,50 @@ impl MyStruct {
             r#"pub fn lib_function() { println!("lib"); }"#,
         )?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
+        let output_dir = temp_dir.path().join("output");
+        let stats = processor.process_directory_to_single_file(&src_dir, &output_dir)?;
+
+        assert_eq!(stats.files_processed, 2);
+        assert!(output_dir.join("code_context.rs.txt").exists());
+
+        // Verify content includes both files
+        let content = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
+        assert!(content.contains("// File: main.rs"));
+        assert!(content.contains("// File: lib.rs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_directory_to_single_file_compressed() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("lib.rs"), "pub fn lib_function() {}")?;
+
+        let processor =
+            test_processor(false, false, false, true).with_compression(Some(Compression::Gzip));
         let output_dir = temp_dir.path().join("output");
-        let stats = processor.process_directory_to_single_file(&src_dir, &output_dir)?;
+        processor.process_directory_to_single_file(&src_dir, &output_dir)?;
 
-        assert_eq!(stats.files_processed, 2);
-        assert!(output_dir.join("code_context.rs.txt").exists());
+        let output_file = output_dir.join("code_context.rs.txt.gz");
+        assert!(output_file.exists());
+        assert!(!output_dir.join("code_context.rs.txt").exists());
 
-        // Verify content includes both files
-        let content = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
-        assert!(content.contains("// File: main.rs"));
+        let content = crate::compression::read_output(&output_file)?;
         assert!(content.contains("// File: lib.rs"));
+        assert!(content.contains("pub fn lib_function() {}"));
 
+        // Check mode decompresses the existing output before comparing
+        let checker = test_processor(false, false, false, true)
+            .with_compression(Some(Compression::Gzip))
+            .with_check(true);
+        checker.process_directory_to_single_file(&src_dir, &output_dir)?;
+
+        fs::write(src_dir.join("lib.rs"), "pub fn changed() {}")?;
+        let result = checker.process_directory_to_single_file(&src_dir, &output_dir);
+        assert!(result.unwrap_err().to_string().contains("out of date"));
         Ok(())
     }
 
@@ -755,7 +2704,7 @@ impl MyStruct {
         let temp_dir = TempDir::new()?;
         let nonexistent_parent = temp_dir.path().join("nonexistent").join("test.rs");
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let result = processor.process_path(&nonexistent_parent, None);
 
         assert!(result.is_err());
@@ -771,7 +2720,7 @@ impl MyStruct {
         fs::write(temp_dir.path().join("test.txt"), "not rust")?;
         fs::write(temp_dir.path().join("test.rs.txt"), "not rust module")?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let stats = processor.process_directory(temp_dir.path(), temp_dir.path())?;
 
         // Should skip non-rust and .rs.txt files
@@ -785,7 +2734,7 @@ impl MyStruct {
         let rust_file = temp_dir.path().join("test.rs");
         fs::write(&rust_file, "invalid rust code @#$%")?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let result = processor.process_directory(temp_dir.path(), temp_dir.path());
 
         assert!(result.is_err());
@@ -808,7 +2757,7 @@ impl MyStruct {
         let output_path = temp_dir.path().join("output");
         fs::write(&output_path, "blocking file")?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let result = processor.process_directory(&src_dir, &output_path);
 
         assert!(result.is_err());
@@ -823,7 +2772,7 @@ impl MyStruct {
     fn test_process_directory_to_single_file_empty() -> Result<()> {
         let temp_dir = TempDir::new()?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
+        let processor = test_processor(false, false, false, false);
         let stats = processor.process_directory_to_single_file(temp_dir.path(), temp_dir.path())?;
 
         assert_eq!(stats.files_processed, 0);
@@ -837,7 +2786,7 @@ impl MyStruct {
         let temp_dir = TempDir::new()?;
         fs::write(temp_dir.path().join("test.rs"), "invalid rust @#$%")?;
 
-        let processor = FileProcessor::with_options(false, false, true, false);
+        let processor = test_processor(false, false, true, false);
         let result = processor.process_directory_to_single_file(temp_dir.path(), temp_dir.path());
 
         assert!(result.is_err());
@@ -859,15 +2808,16 @@ impl MyStruct {
         let output_file = temp_dir.path().join("output");
         fs::create_dir(&output_file)?;
 
-        let processor = FileProcessor::with_options(false, false, false, false);
-        let result = processor.process_file(&input_file, &output_file);
+        let processor = test_processor(false, false, false, false);
+        let result = processor.process_file(&input_file, &output_file, Path::new("lib.rs"), None);
 
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Failed to write"));
 
         // Test an error with wrong input file
         let invalid_file = PathBuf::from("/invalid/file.rs");
-        let result = processor.process_file(&invalid_file, &output_file);
+        let result =
+            processor.process_file(&invalid_file, &output_file, Path::new("file.rs"), None);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("does not exist"));
         Ok(())
@@ -900,6 +2850,7 @@ impl MyStruct {
             files_processed: 0,
             input_size: 100,
             output_size: 0,
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), 100.0);
 
@@ -907,6 +2858,7 @@ impl MyStruct {
             files_processed: 0,
             input_size: 0,
             output_size: 0,
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), 0.0);
     }
@@ -917,6 +2869,7 @@ impl MyStruct {
             files_processed: 5,
             input_size: 1000,
             output_size: 500,
+            ..Default::default()
         };
         let cloned = stats.clone();
         assert_eq!(stats.files_processed, cloned.files_processed);
@@ -931,6 +2884,7 @@ impl MyStruct {
             files_processed: 3,
             input_size: 150,
             output_size: 75,
+            ..Default::default()
         };
         let debug_str = format!("{:?}", stats);
         assert!(debug_str.contains("files_processed: 3"));
@@ -944,6 +2898,7 @@ impl MyStruct {
             files_processed: 0,
             input_size: 0,
             output_size: 0,
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), 0.0);
 
@@ -951,6 +2906,7 @@ impl MyStruct {
             files_processed: 1,
             input_size: 100,
             output_size: 0,
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), 100.0);
 
@@ -958,6 +2914,7 @@ impl MyStruct {
             files_processed: 1,
             input_size: 100,
             output_size: 100,
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), 0.0);
 
@@ -965,6 +2922,7 @@ impl MyStruct {
             files_processed: 1,
             input_size: 100,
             output_size: 200, // Output larger than input
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), -100.0);
     }
@@ -978,12 +2936,14 @@ impl MyStruct {
             files_processed: 1,
             input_size: 100,
             output_size: 50,
+            ..Default::default()
         };
 
         let file2_stats = ProcessingStats {
             files_processed: 1,
             input_size: 200,
             output_size: 100,
+            ..Default::default()
         };
 
         total_stats.files_processed += file1_stats.files_processed + file2_stats.files_processed;
@@ -996,12 +2956,594 @@ impl MyStruct {
         assert_eq!(total_stats.reduction_percentage(), 50.0);
     }
 
+    #[test]
+    fn test_processing_stats_record() {
+        let mut stats = ProcessingStats::default();
+        stats.record(FileStats::new("lib.rs", 100, 40));
+        stats.record(FileStats::new("main.rs", 50, 10));
+
+        assert_eq!(stats.files_processed, 2);
+        assert_eq!(stats.input_size, 150);
+        assert_eq!(stats.output_size, 50);
+        assert_eq!(stats.files[0].tokens, 10);
+        assert_eq!(stats.tokens(), 13);
+    }
+
+    #[test]
+    fn test_process_path_writes_manifest() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("lib.rs"), "pub fn lib_function() {}")?;
+
+        let processor = test_processor(false, false, false, false);
+        processor.process_path(&src_dir, Some("out"))?;
+
+        let manifest = Manifest::load(&temp_dir.path().join("src-out").join(MANIFEST_FILE_NAME))?;
+        assert_eq!(manifest.files_processed, 1);
+        assert_eq!(manifest.files[0].path, "lib.rs");
+        assert_eq!(manifest.files[0].module.as_deref(), Some("crate"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_directory_skips_benches() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let crate_dir = temp_dir.path().join("my-crate");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::create_dir_all(crate_dir.join("benches"))?;
+        fs::write(crate_dir.join("src/lib.rs"), "pub fn lib_function() {}")?;
+        fs::write(crate_dir.join("benches/speed.rs"), "fn bench() {}")?;
+
+        let processor = test_processor(false, false, true, false);
+        let stats = processor.process_directory(&crate_dir, &temp_dir.path().join("out"))?;
+        assert_eq!(stats.files_processed, 1);
+        assert_eq!(stats.skipped[0].reason, "benchmark file");
+        assert!(!temp_dir.path().join("out").exists());
+
+        let processor = processor.with_keep_benches(true);
+        let stats = processor.process_directory(&crate_dir, &temp_dir.path().join("out"))?;
+        assert_eq!(stats.files_processed, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_directory_includes_examples() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let crate_dir = temp_dir.path().join("my-crate");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::create_dir_all(crate_dir.join("examples"))?;
+        fs::create_dir_all(crate_dir.join("tests"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"my-crate\"\n",
+        )?;
+        fs::write(
+            crate_dir.join("src/lib.rs"),
+            "pub fn lib_function() { run() }",
+        )?;
+        fs::write(crate_dir.join("examples/demo.rs"), "fn main() { run() }")?;
+        fs::write(crate_dir.join("tests/api.rs"), "fn api() {}")?;
+
+        let output_dir = temp_dir.path().join("out");
+        let processor = test_processor(false, true, false, false);
+        let stats = processor.process_directory(&crate_dir, &output_dir)?;
+        assert_eq!(stats.files_processed, 1);
+        assert_eq!(stats.skipped.len(), 2);
+
+        let processor = processor.with_target_form(CrateTarget::Examples, Some(TargetForm::Full));
+        let stats = processor.process_directory(&crate_dir, &output_dir)?;
+        assert_eq!(stats.files_processed, 2);
+        assert_eq!(stats.skipped[0].reason, "integration test");
+        let example = fs::read_to_string(output_dir.join("examples/demo.rs.txt"))?;
+        assert!(example.contains("run()"));
+        let lib = fs::read_to_string(output_dir.join("src/lib.rs.txt"))?;
+        assert!(!lib.contains("run()"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_directory_build_script_and_generated_code() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let crate_dir = temp_dir.path().join("app");
+        let out_dir = crate_dir.join("target/debug/build/app-1234/out");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::create_dir_all(&out_dir)?;
+        fs::write(crate_dir.join("Cargo.toml"), "[package]\nname = \"app\"\n")?;
+        fs::write(
+            crate_dir.join("src/lib.rs"),
+            "include!(concat!(env!(\"OUT_DIR\"), \"/gen.rs\"));",
+        )?;
+        fs::write(
+            crate_dir.join("build.rs"),
+            "// Writes gen.rs\nfn main() {}\n",
+        )?;
+        fs::write(out_dir.join("gen.rs"), "pub fn generated() { 1; }")?;
+
+        let output_dir = temp_dir.path().join("out");
+        let processor = test_processor(true, true, false, false);
+        let stats = processor.process_directory(&crate_dir, &output_dir)?;
+        assert_eq!(stats.files_processed, 1);
+        let reasons: Vec<_> = stats
+            .skipped
+            .iter()
+            .map(|skip| skip.reason.as_str())
+            .collect();
+        assert!(reasons.contains(&"build script") && reasons.contains(&"build output"));
+
+        let processor = processor
+            .with_build_script(Some(BuildScriptForm::Verbatim))
+            .with_generated_code(true);
+        let stats = processor.process_directory(&crate_dir, &output_dir)?;
+        assert_eq!(stats.files_processed, 3);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("build.rs.txt"))?,
+            "// Writes gen.rs\nfn main() {}\n"
+        );
+        let generated =
+            fs::read_to_string(output_dir.join("target/debug/build/app-1234/out/gen.rs.txt"))?;
+        assert!(generated.starts_with(codegen::GENERATED_LABEL));
+        assert!(generated.contains("pub fn generated()"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_also_writes_tree() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(src_dir.join("api"))?;
+        fs::write(src_dir.join("lib.rs"), "pub mod api;")?;
+        fs::write(src_dir.join("api/mod.rs"), "pub fn handler() {}")?;
+
+        let output_dir = temp_dir.path().join("out");
+        let processor = test_processor(false, false, false, true).with_also_tree(true);
+        let stats = processor.process_directory(&src_dir, &output_dir)?;
+        assert_eq!(stats.files_processed, 2);
+
+        let combined = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
+        let tree_file = fs::read_to_string(output_dir.join("api/mod.rs.txt"))?;
+        assert!(tree_file.contains("pub fn handler()"));
+        assert!(combined.contains(&tree_file));
+        assert!(output_dir.join("lib.rs.txt").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_order_independent_of_jobs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        for index in 0..12 {
+            fs::write(
+                src_dir.join(format!("file{}.rs", index)),
+                format!("pub fn item{}() {{}}", index),
+            )?;
+        }
+
+        let output = |jobs| -> Result<String> {
+            let output_dir = temp_dir.path().join(format!("out{}", jobs));
+            test_processor(false, false, false, true)
+                .with_jobs(Some(jobs))
+                .process_directory_to_single_file(&src_dir, &output_dir)?;
+            Ok(fs::read_to_string(output_dir.join("code_context.rs.txt"))?)
+        };
+        let sequential = output(1)?;
+        assert_eq!(sequential.matches("// File: ").count(), 12);
+        assert_eq!(output(4)?, sequential);
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk_limits_fail_fast() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(src_dir.join("a/b/c"))?;
+        fs::write(src_dir.join("lib.rs"), "pub fn a() {}")?;
+        fs::write(src_dir.join("a/b/c/deep.rs"), "pub fn b() {}")?;
+
+        let output_dir = temp_dir.path().join("out");
+        let processor = || test_processor(false, false, true, false);
+        let stats = processor().process_directory(&src_dir, &output_dir)?;
+        assert_eq!(stats.files_processed, 2);
+
+        let error = processor()
+            .with_max_files(1)
+            .process_directory(&src_dir, &output_dir)
+            .unwrap_err();
+        assert!(error.to_string().contains("more than 1 Rust files"));
+        let error = test_processor(false, false, true, true)
+            .with_max_walk_depth(2)
+            .process_directory(&src_dir, &output_dir)
+            .unwrap_err();
+        assert!(error.to_string().contains("deeper than 2 levels"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_rs_extension_skips_own_output() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("lib.rs"), "pub fn a() {}")?;
+
+        let output_dir = temp_dir.path().join("context");
+        let processor = test_processor(false, false, false, false).with_keep_rs_extension(true);
+        processor.process_directory(&src_dir, &output_dir)?;
+        let output = fs::read_to_string(output_dir.join("lib.rs"))?;
+        assert!(output.starts_with(module_path::OUTPUT_MARKER));
+
+        // An output copied into the input, as when vendoring it
+        fs::write(src_dir.join("vendored.rs"), output)?;
+        let stats = processor.process_directory(&src_dir, &output_dir)?;
+        assert_eq!(stats.files_processed, 1);
+        assert_eq!(stats.skipped[0].path, "vendored.rs");
+        assert_eq!(stats.skipped[0].reason, "code-context output");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_path_skips_previous_output() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("lib.rs"), "pub fn a() {}")?;
+
+        // A previous run with a custom output location inside the input
+        let processor = test_processor(false, false, false, false).with_keep_rs_extension(true);
+        let template = "{parent}/{name}/ctx";
+        processor.process_path(&src_dir, Some(template))?;
+        fs::create_dir_all(src_dir.join("old-code-context"))?;
+        fs::write(src_dir.join("old-code-context/lib.rs"), "pub fn old() {}")?;
+
+        let stats =
+            test_processor(false, false, false, false).process_path(&src_dir, Some(template))?;
+        assert_eq!(stats.files_processed, 1);
+        let mut skipped: Vec<_> = stats
+            .skipped
+            .iter()
+            .map(|skip| skip.path.as_str())
+            .collect();
+        skipped.sort();
+        assert_eq!(skipped, ["ctx", "old-code-context"]);
+        assert!(stats
+            .skipped
+            .iter()
+            .any(|skip| skip.path == "old-code-context"
+                && skip.reason == "previous code-context output"));
+
+        // The output directory of the run itself is skipped before it holds a manifest
+        let stats = test_processor(false, false, false, false)
+            .process_path(&src_dir, Some("{parent}/{name}/fresh"))?;
+        assert!(stats
+            .skipped
+            .iter()
+            .any(|skip| skip.path == "fresh" && skip.reason == "output directory"));
+        let error = test_processor(false, false, true, false)
+            .process_path(&src_dir, Some("{parent}"))
+            .unwrap_err();
+        assert!(error.to_string().contains("is inside the output directory"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_output_starts_with_summary() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(
+            src_dir.join("lib.rs"),
+            "pub fn app() -> Router { Router::new().route(\"/\", get(index)) }\nasync fn index() -> &'static str { \"hi\" }",
+        )?;
+        // Files skipped by the run are left out of the summary too
+        fs::write(
+            src_dir.join("secrets.rs"),
+            "pub fn admin() -> Router { Router::new().route(\"/keys\", get(keys)) }\nasync fn keys() {}",
+        )?;
+
+        let processor = test_processor(false, false, false, true)
+            .with_summary_sections(vec![SummarySection::Endpoints])
+            .with_exclusions(PathExclusions::new(&[Preset::NoSecrets])?);
+        let output_dir = temp_dir.path().join("output");
+        processor.process_directory_to_single_file(&src_dir, &output_dir)?;
+
+        let content = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
+        assert!(content.starts_with(
+            "// HTTP endpoints:\n//   GET / -> async fn index() -> &'static str\n\n\n// File: lib.rs"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_ordered_by_type_usage() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("a.rs"), "pub struct Rare;")?;
+        fs::write(src_dir.join("b.rs"), "pub struct Common;")?;
+        fs::write(
+            src_dir.join("lib.rs"),
+            "pub fn make(rare: Rare) -> Common { todo!() }\npub fn take(common: Common) {}",
+        )?;
+
+        let processor = test_processor(false, false, false, true).with_order(Some(Order::Types));
+        let output_dir = temp_dir.path().join("output");
+        processor.process_directory_to_single_file(&src_dir, &output_dir)?;
+
+        let content = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
+        let common = content.find("// File: b.rs").unwrap();
+        let rare = content.find("// File: a.rs").unwrap();
+        let lib = content.find("// File: lib.rs").unwrap();
+        assert!(common < rare && rare < lib);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_path_writes_scip_index() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("lib.rs"), "pub fn lib_function() {}")?;
+
+        let processor =
+            test_processor(false, false, false, false).with_output_format(OutputFormat::Scip);
+        processor.process_path(&src_dir, Some("out"))?;
+
+        let index = fs::read(temp_dir.path().join("src-out").join(SCIP_FILE_NAME))?;
+        assert!(String::from_utf8_lossy(&index).contains("lib_function()."));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_path_writes_item_ids() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(
+            src_dir.join("lib.rs"),
+            "pub fn lib_function() {}\n/// ```\n/// lib::run();\n/// ```\n#[doc(alias = \"start\")]\npub fn run() {}\n",
+        )?;
+
+        // Metadata comes from the source, even though the comments are removed
+        let processor = test_processor(true, false, false, false)
+            .with_crate_name(Some("app".to_string()))
+            .with_output_format(OutputFormat::Jsonl);
+        processor.process_path(&src_dir, Some("out"))?;
+
+        let id = item_ids::item_id("app", "crate", "fn", "lib_function");
+        let output_dir = temp_dir.path().join("src-out");
+        let manifest = Manifest::load(&output_dir)?;
+        assert_eq!(manifest.files[0].items[0].id, id);
+        let jsonl = fs::read_to_string(output_dir.join(ITEMS_FILE_NAME))?;
+        let items: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(items[0]["id"], id.as_str());
+        assert_eq!(items[0]["code"], "pub fn lib_function() {}");
+        assert!(items[0].get("aliases").is_none());
+        assert_eq!(items[1]["aliases"], serde_json::json!(["start"]));
+        assert_eq!(items[1]["example"], "lib::run();");
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_dedupes_identical_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(src_dir.join("generated"))?;
+        fs::write(src_dir.join("a.rs"), "pub fn generated() {}")?;
+        fs::write(src_dir.join("generated/b.rs"), "pub fn generated() {}")?;
+
+        let processor = test_processor(false, false, false, true);
+        let output_dir = temp_dir.path().join("output");
+        let stats = processor.process_directory_to_single_file(&src_dir, &output_dir)?;
+
+        let content = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
+        assert_eq!(content.matches("pub fn generated() {}").count(), 1);
+        assert_eq!(content.matches("// Identical to ").count(), 1);
+        let duplicates = stats.files.iter().filter(|f| f.duplicate_of.is_some());
+        assert_eq!(duplicates.count(), 1);
+        assert_eq!(
+            stats.output_size,
+            stats.files.iter().map(|f| f.output_size).sum::<usize>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_drops_largest_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("a.rs"), "pub fn a() {}")?;
+        fs::write(
+            src_dir.join("big.rs"),
+            "pub struct Big { pub first: String, pub second: String }",
+        )?;
+        fs::write(src_dir.join("c.rs"), "pub fn c() {}")?;
+
+        let processor = test_processor(false, false, false, true).with_drop_largest(Some(10));
+        let output_dir = temp_dir.path().join("output");
+        let stats = processor.process_directory_to_single_file(&src_dir, &output_dir)?;
+
+        let content = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
+        assert!(content.starts_with("// Dropped the largest 10% of files:\n//   big.rs ("));
+        assert!(!content.contains("pub struct Big"));
+        assert!(content.contains("// File: a.rs") && content.contains("// File: c.rs"));
+        assert_eq!(stats.files_processed, 2);
+        assert_eq!(stats.skipped[0].path, "big.rs");
+        assert_eq!(
+            stats.output_size,
+            stats.files.iter().map(|f| f.output_size).sum::<usize>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_fits_token_budget() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        let docs = "/// Explains the parser at great length.\n".repeat(50);
+        fs::write(src_dir.join("docs.rs"), docs + "pub fn parse() {}\n")?;
+        fs::write(src_dir.join("core.rs"), "pub fn run() -> u32 { 1 + 2 }\n")?;
+
+        let output_dir = temp_dir.path().join("output");
+        let full = test_processor(false, false, false, true)
+            .process_directory_to_single_file(&src_dir, &output_dir)?
+            .tokens();
+        let budget = full - 200;
+        let processor = test_processor(false, false, false, true).with_token_budget(Some(budget));
+        let stats = processor.process_directory_to_single_file(&src_dir, &output_dir)?;
+
+        let content = fs::read_to_string(output_dir.join("code_context.rs.txt"))?;
+        assert!(content.contains("//   docs.rs: without comments\n"));
+        assert!(!content.contains("Explains the parser"));
+        assert!(content.contains("pub fn run() -> u32"));
+        assert!(stats.tokens() <= budget);
+        assert_eq!(stats.files_processed, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_update_reuses_unchanged_sections() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("a.rs"), "pub fn alpha() {}")?;
+        fs::write(src_dir.join("b.rs"), "pub fn beta() {}")?;
+
+        let processor = test_processor(false, false, false, true).with_update(true);
+        processor.process_path(&src_dir, Some("out"))?;
+        let output_dir = temp_dir.path().join("src-out");
+        let output_file = output_dir.join("code_context.rs.txt");
+
+        // A reused section keeps its previous content, even if edited by hand
+        let content = fs::read_to_string(&output_file)?;
+        fs::write(&output_file, content.replace("alpha", "ALPHA"))?;
+        fs::write(src_dir.join("b.rs"), "pub fn gamma() {}")?;
+        processor.process_path(&src_dir, Some("out"))?;
+
+        let content = fs::read_to_string(&output_file)?;
+        assert!(content.contains("pub fn ALPHA() {}"));
+        assert!(content.contains("pub fn gamma() {}"));
+        assert!(!content.contains("beta"));
+
+        // Other options regenerate every section
+        let processor = test_processor(false, false, false, true)
+            .with_update(true)
+            .with_options_fingerprint("other".to_string());
+        processor.process_path(&src_dir, Some("out"))?;
+        assert!(fs::read_to_string(&output_file)?.contains("pub fn alpha() {}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_file_normalizes_output() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("lib.rs");
+        fs::write(&input_file, "// Cre\u{300}me  \r\npub fn add() {}\r\n\r\n")?;
+        let output_file = temp_dir.path().join("lib.rs.txt");
+
+        let processor = test_processor(false, false, false, false)
+            .with_preserve_formatting(true)
+            .with_normalize_output(true);
+        processor.process_file(&input_file, &output_file, Path::new("lib.rs"), None)?;
+
+        assert_eq!(
+            fs::read_to_string(&output_file)?,
+            "// Cr\u{e8}me\npub fn add() {}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_file_preserving_formatting() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("lib.rs");
+        fs::write(
+            &input_file,
+            "// Keep this comment\npub fn add(a: i32,   b: i32) -> i32 {\n    a + b\n}\n",
+        )?;
+        let output_file = temp_dir.path().join("lib.rs.txt");
+
+        let processor = test_processor(false, true, false, false).with_preserve_formatting(true);
+        processor.process_file(&input_file, &output_file, Path::new("lib.rs"), None)?;
+
+        assert_eq!(
+            fs::read_to_string(&output_file)?,
+            "// Keep this comment\npub fn add(a: i32,   b: i32) -> i32 {}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_transform_cargo_script() -> Result<()> {
+        let path = Path::new("script.rs");
+        let script = "#!/usr/bin/env cargo\n---\n[dependencies]\nclap = \"4\"\n---\n#![allow(dead_code)]\n//! Script\nfn main() {}\n";
+        let processor = test_processor(false, false, false, false);
+        assert_eq!(
+            processor.transform_source(path, script)?,
+            "#!/usr/bin/env cargo\n---\n[dependencies]\nclap = \"4\"\n---\n#![allow(dead_code)]\n//! Script\nfn main() {}\n"
+        );
+
+        for preserve_formatting in [false, true] {
+            let processor = test_processor(false, false, false, false)
+                .with_preserve_formatting(preserve_formatting)
+                .with_strip_script_header(true)
+                .with_strip_inner_attributes(true);
+            let (output, removals) = processor.transform_source_with_removals(path, script)?;
+            assert_eq!(output, "//! Script\nfn main() {}\n");
+            assert_eq!(removals.inner_attributes, 1);
+        }
+
+        // The manifest of a rust-script survives the removal of doc comments
+        let script = "//! Fetches a page\n//! ```cargo\n//! [dependencies]\n//! ureq = \"2\"\n//! ```\nfn main() {}\n";
+        let processor = test_processor(true, false, false, false);
+        assert_eq!(
+            processor.transform_source(path, script)?,
+            "//! ```cargo\n//! [dependencies]\n//! ureq = \"2\"\n//! ```\nfn main() {}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_directory_records_timings() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path().join("src");
+        fs::create_dir_all(&src_dir)?;
+        fs::write(src_dir.join("lib.rs"), "pub fn lib_function() {}")?;
+        fs::write(src_dir.join("main.rs"), "fn main() {}")?;
+
+        // Files are timed on the worker threads too
+        let processor = test_processor(false, false, false, false).with_jobs(Some(2));
+        let subscriber = tracing_subscriber::registry().with(TimingLayer::default());
+        let timings = tracing::subscriber::with_default(subscriber, || -> Result<Timings> {
+            processor.process_directory(&src_dir, &temp_dir.path().join("out"))?;
+            Ok(timings::take())
+        })?;
+
+        for phase in [
+            Phase::Walk,
+            Phase::Read,
+            Phase::Parse,
+            Phase::Transform,
+            Phase::Print,
+            Phase::Write,
+        ] {
+            assert!(timings.phases.contains_key(&phase), "{:?}", phase);
+        }
+        assert_eq!(timings.files.len(), 2);
+        Ok(())
+    }
+
     #[test]
     fn test_processing_stats_large_numbers() {
         let stats = ProcessingStats {
             files_processed: usize::MAX,
             input_size: usize::MAX,
             output_size: usize::MAX / 2,
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), 50.0);
 
@@ -1009,6 +3551,7 @@ impl MyStruct {
             files_processed: usize::MAX,
             input_size: usize::MAX,
             output_size: 0,
+            ..Default::default()
         };
         assert_eq!(stats.reduction_percentage(), 100.0);
     }
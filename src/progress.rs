@@ -0,0 +1,113 @@
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+/// Minimum interval between plain-text progress lines
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How processing progress is reported
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// Show a progress bar on terminals and plain-text lines otherwise
+    #[default]
+    Auto,
+    /// Always show a progress bar
+    Always,
+    /// Don't report progress
+    Never,
+}
+
+enum Reporter {
+    Bar(ProgressBar),
+    Lines { last_log: Instant },
+    Hidden,
+}
+
+/// Reports progress as a bar on terminals or as periodic log lines in CI
+pub struct Progress {
+    reporter: Reporter,
+    position: u64,
+    total: u64,
+}
+
+impl Progress {
+    pub fn new(total: u64, mode: ProgressMode) -> Self {
+        let use_bar = match mode {
+            ProgressMode::Auto => std::io::stderr().is_terminal(),
+            ProgressMode::Always => true,
+            ProgressMode::Never => false,
+        };
+        let reporter = if use_bar {
+            let pb = ProgressBar::new(total);
+            pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} files {msg}")
+                    .unwrap()
+                    .progress_chars("##-"),
+            );
+            Reporter::Bar(pb)
+        } else if mode == ProgressMode::Auto {
+            Reporter::Lines {
+                last_log: Instant::now(),
+            }
+        } else {
+            Reporter::Hidden
+        };
+
+        Self {
+            reporter,
+            position: 0,
+            total,
+        }
+    }
+
+    /// Advances progress by one file
+    pub fn inc(&mut self) {
+        self.position += 1;
+        match &mut self.reporter {
+            Reporter::Bar(pb) => pb.inc(1),
+            Reporter::Lines { last_log } => {
+                if last_log.elapsed() >= LOG_INTERVAL {
+                    *last_log = Instant::now();
+                    eprintln!("Processed {}/{} files", self.position, self.total);
+                }
+            }
+            Reporter::Hidden => {}
+        }
+    }
+
+    /// Marks processing as complete
+    pub fn finish_with_message(&self, message: &'static str) {
+        match &self.reporter {
+            Reporter::Bar(pb) => pb.finish_with_message(message),
+            Reporter::Lines { .. } => {
+                eprintln!(
+                    "Processed {}/{} files. {}",
+                    self.position, self.total, message
+                )
+            }
+            Reporter::Hidden => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_modes() {
+        let mut progress = Progress::new(2, ProgressMode::Never);
+        progress.inc();
+        progress.inc();
+        progress.finish_with_message("done");
+        assert_eq!(progress.position, 2);
+        assert!(matches!(progress.reporter, Reporter::Hidden));
+
+        assert!(matches!(
+            Progress::new(1, ProgressMode::Always).reporter,
+            Reporter::Bar(_)
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::git::{self, GitHistory};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How an output was produced, stamped at the top of each output file by
+/// `--provenance-banner`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    pub version: String,
+    /// Command-line arguments after the program name
+    pub args: Vec<String>,
+    /// Abbreviated HEAD hash of the input's repository, if any
+    pub commit: Option<String>,
+    /// Whether tracked files of the repository differ from HEAD
+    pub uncommitted_changes: bool,
+    /// Generation time in seconds since the Unix epoch
+    pub generated_at: i64,
+}
+
+impl Provenance {
+    /// Collects the provenance of the current run on the input. The generation time is
+    /// `SOURCE_DATE_EPOCH` when set, so reproducible builds get identical banners
+    pub fn collect(input: &Path) -> Self {
+        let history = GitHistory::discover(input);
+        let generated_at = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.parse().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |now| now.as_secs() as i64)
+            });
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            args: std::env::args().skip(1).collect(),
+            commit: history.as_ref().and_then(GitHistory::head_hash),
+            uncommitted_changes: history.as_ref().is_some_and(GitHistory::has_changes),
+            generated_at,
+        }
+    }
+
+    /// Renders the banner as line comments
+    pub fn banner(&self) -> String {
+        let command = std::iter::once("code-context".to_string())
+            .chain(self.args.iter().map(|arg| shell_quote(arg)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let commit = match &self.commit {
+            Some(hash) if self.uncommitted_changes => format!("{} with uncommitted changes", hash),
+            Some(hash) => hash.clone(),
+            None => "unknown (not a git repository)".to_string(),
+        };
+        format!(
+            "// Generated by code-context {}\n\
+             // Command: {}\n\
+             // Source commit: {}\n\
+             // Generated at: {}\n",
+            self.version,
+            command,
+            commit,
+            format_timestamp(self.generated_at)
+        )
+    }
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp
+fn format_timestamp(seconds: i64) -> String {
+    let time = seconds.rem_euclid(86_400);
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        git::format_unix_date(seconds),
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// Quotes an argument for a POSIX shell when it contains anything but plain characters
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=,:@+".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner() {
+        let provenance = Provenance {
+            version: "1.2.3".to_string(),
+            args: vec![
+                "my-crate".to_string(),
+                "--module-filter".to_string(),
+                "crate::api::*".to_string(),
+            ],
+            commit: Some("0123abcd".to_string()),
+            uncommitted_changes: true,
+            generated_at: 1_700_000_000,
+        };
+        assert_eq!(
+            provenance.banner(),
+            "// Generated by code-context 1.2.3\n\
+             // Command: code-context my-crate --module-filter 'crate::api::*'\n\
+             // Source commit: 0123abcd with uncommitted changes\n\
+             // Generated at: 2023-11-14T22:13:20Z\n"
+        );
+    }
+}
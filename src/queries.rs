@@ -0,0 +1,213 @@
+use crate::summary::SourceFile;
+use syn::visit::{self, Visit};
+use syn::{Block, Expr, ExprMethodCall, ImplItemFn, ItemFn, Lit, LitStr, Macro};
+
+/// Name of the queries appendix written next to per-file outputs
+pub const QUERIES_FILE_NAME: &str = "code_context_queries.txt";
+
+/// sqlx macros that take the query as a string literal argument
+const SQLX_MACROS: [&str; 4] = ["query", "query_as", "query_scalar", "query_unchecked"];
+/// First words of raw SQL statements
+const SQL_KEYWORDS: [&str; 8] = [
+    "select", "insert", "update", "delete", "with", "create", "alter", "drop",
+];
+/// Clauses of which a raw SQL statement contains at least one
+const SQL_CLAUSES: [&str; 6] = ["from", "into", "set", "table", "where", "values"];
+/// Diesel query builder methods
+const DIESEL_DSL_METHODS: [&str; 6] = [
+    "filter",
+    "select",
+    "order",
+    "inner_join",
+    "left_join",
+    "group_by",
+];
+/// Diesel methods that run a query
+const DIESEL_RUN_METHODS: [&str; 5] = ["load", "get_result", "get_results", "first", "execute"];
+
+/// Checks if a string literal looks like a raw SQL statement
+fn is_sql(text: &str) -> bool {
+    let mut words = text.split_whitespace();
+    let Some(first) = words.next() else {
+        return false;
+    };
+    SQL_KEYWORDS.contains(&first.to_lowercase().as_str())
+        && words.any(|word| SQL_CLAUSES.contains(&word.to_lowercase().as_str()))
+}
+
+/// Checks if a method call chain uses the diesel query builder and runs the query
+fn is_diesel_query(call: &ExprMethodCall) -> bool {
+    if !DIESEL_RUN_METHODS.contains(&call.method.to_string().as_str()) {
+        return false;
+    }
+    let mut receiver = call.receiver.as_ref();
+    while let Expr::MethodCall(inner) = receiver {
+        if DIESEL_DSL_METHODS.contains(&inner.method.to_string().as_str()) {
+            return true;
+        }
+        receiver = inner.receiver.as_ref();
+    }
+    false
+}
+
+/// Renders an expression on a single line
+fn render_expr(expr: &Expr) -> String {
+    quote::quote!(#expr)
+        .to_string()
+        .replace(" . ", ".")
+        .replace(" (", "(")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(" ,", ",")
+        .replace(" :: ", "::")
+}
+
+#[derive(Default)]
+struct QueryCollector {
+    queries: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for QueryCollector {
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        let is_sqlx = mac
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| SQLX_MACROS.contains(&segment.ident.to_string().as_str()));
+        if is_sqlx {
+            if let Ok(args) = mac.parse_body_with(
+                syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated,
+            ) {
+                // `query_as!` takes the record type before the query
+                let query = args.iter().find_map(|arg| match arg {
+                    Expr::Lit(syn::ExprLit {
+                        lit: Lit::Str(query),
+                        ..
+                    }) => Some(query.value()),
+                    _ => None,
+                });
+                if let Some(query) = query {
+                    self.queries.push(query);
+                }
+            }
+        }
+        visit::visit_macro(self, mac);
+    }
+
+    fn visit_lit_str(&mut self, lit: &'ast LitStr) {
+        let value = lit.value();
+        if is_sql(&value) {
+            self.queries.push(value);
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        if is_diesel_query(call) {
+            self.queries
+                .push(render_expr(&Expr::MethodCall(call.clone())));
+            return;
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// Finds the SQL queries in a function body
+pub fn find_queries(block: &Block) -> Vec<String> {
+    let mut collector = QueryCollector::default();
+    collector.visit_block(block);
+    collector.queries
+}
+
+/// Checks if a function body runs database queries
+pub fn contains_query(block: &Block) -> bool {
+    !find_queries(block).is_empty()
+}
+
+#[derive(Default)]
+struct FunctionCollector {
+    functions: Vec<(String, Vec<String>)>,
+}
+
+impl<'ast> Visit<'ast> for FunctionCollector {
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        let queries = find_queries(&item.block);
+        if !queries.is_empty() {
+            self.functions.push((item.sig.ident.to_string(), queries));
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        let queries = find_queries(&item.block);
+        if !queries.is_empty() {
+            self.functions.push((item.sig.ident.to_string(), queries));
+        }
+    }
+}
+
+/// Renders all queries of the crate, grouped by function, as a comment block
+pub fn render_appendix(sources: &[SourceFile]) -> String {
+    let mut appendix = String::new();
+    for source in sources {
+        let mut collector = FunctionCollector::default();
+        collector.visit_file(&source.ast);
+        for (function, queries) in collector.functions {
+            appendix.push_str(&format!("//\n// fn {} ({}):\n", function, source.path));
+            for query in queries {
+                for line in query.trim().lines() {
+                    appendix.push_str(&format!("//   {}\n", line.trim_end()));
+                }
+            }
+        }
+    }
+
+    if appendix.is_empty() {
+        return appendix;
+    }
+    format!("\n// Queries:\n{}", appendix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_find_queries() -> Result<()> {
+        let block: Block = syn::parse_str(
+            r#"{
+                let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+                    .fetch_one(&pool)
+                    .await?;
+                let raw = "delete from sessions where expired";
+                let posts = posts::table.filter(posts::published.eq(true)).load::<Post>(conn)?;
+                let greeting = "Select a user";
+                let label = "Update available";
+            }"#,
+        )?;
+        assert_eq!(
+            find_queries(&block),
+            vec![
+                "SELECT * FROM users WHERE id = $1",
+                "delete from sessions where expired",
+                "posts::table.filter(posts::published.eq(true)).load::< Post >(conn)",
+            ]
+        );
+        assert!(!contains_query(&syn::parse_str("{ let x = 1; }")?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_appendix() -> Result<()> {
+        let sources = vec![SourceFile {
+            path: "db.rs".to_string(),
+            ast: syn::parse_str(
+                "impl Repo { fn count(&self) -> i64 { sqlx::query_scalar!(\"SELECT count(*)\n FROM users\") } }",
+            )?,
+        }];
+        assert_eq!(
+            render_appendix(&sources),
+            "\n// Queries:\n//\n// fn count (db.rs):\n//   SELECT count(*)\n//    FROM users\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// What the transformer cut from a file, by category
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Removals {
+    /// Doc comments stripped by `--no-comments`
+    pub doc_comments: usize,
+    /// Test and benchmark functions, modules, and impl items dropped
+    pub test_items: usize,
+    /// Non-empty function bodies elided
+    pub bodies: usize,
+    /// Attributes removed along with dropped items, e.g. `#[test]` or `#[cfg(test)]`
+    pub attributes: usize,
+    /// Impl blocks and modules dropped because only test code was in them
+    pub items: usize,
+    /// License banners removed from the top of files
+    pub license_headers: usize,
+    /// Inner attributes such as `#![allow(...)]` stripped by `--strip-inner-attributes`
+    pub inner_attributes: usize,
+    /// Items below the `--visibility` level dropped
+    pub hidden_items: usize,
+    /// Non-public struct fields replaced by `--hide-private-fields`
+    pub private_fields: usize,
+    /// Trait impls folded into a representative of their family by `--collapse-impls`
+    pub collapsed_impls: usize,
+    /// Impls of the traits listed by `--skip-trait-impls`
+    pub skipped_impls: usize,
+    /// Items syn doesn't parse, such as new or unstable syntax, passed through verbatim
+    pub unknown_items: usize,
+}
+
+impl Removals {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Counts the non-doc attributes of a dropped item
+    pub fn count_attributes(&mut self, attrs: &[syn::Attribute]) {
+        self.attributes += attrs
+            .iter()
+            .filter(|attr| !attr.path().is_ident("doc"))
+            .count();
+    }
+
+    /// Renders the counts as one line, e.g. "3 test items, 12 bodies"
+    pub fn format(&self) -> String {
+        let counts = [
+            (self.doc_comments, "doc comments"),
+            (self.test_items, "test items"),
+            (self.bodies, "bodies"),
+            (self.attributes, "attributes"),
+            (self.items, "emptied items"),
+            (self.license_headers, "license headers"),
+            (self.inner_attributes, "inner attributes"),
+            (self.hidden_items, "hidden items"),
+            (self.private_fields, "private fields"),
+            (self.collapsed_impls, "collapsed impls"),
+            (self.skipped_impls, "skipped trait impls"),
+            (self.unknown_items, "unknown items kept"),
+        ];
+        let parts: Vec<_> = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect();
+        if parts.is_empty() {
+            "nothing".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+impl AddAssign for Removals {
+    fn add_assign(&mut self, other: Self) {
+        self.doc_comments += other.doc_comments;
+        self.test_items += other.test_items;
+        self.bodies += other.bodies;
+        self.attributes += other.attributes;
+        self.items += other.items;
+        self.license_headers += other.license_headers;
+        self.inner_attributes += other.inner_attributes;
+        self.hidden_items += other.hidden_items;
+        self.private_fields += other.private_fields;
+        self.collapsed_impls += other.collapsed_impls;
+        self.skipped_impls += other.skipped_impls;
+        self.unknown_items += other.unknown_items;
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Removals> for Removals {
+    fn sum<I: Iterator<Item = &'a Removals>>(iter: I) -> Self {
+        iter.fold(Self::default(), |mut total, removals| {
+            total += *removals;
+            total
+        })
+    }
+}
@@ -0,0 +1,261 @@
+use crate::item_ids::{self, ItemRecord};
+use crate::module_path::ModuleMap;
+use crate::paths;
+use crate::processor::{estimate_tokens, FileProcessor, Processor, SkippedFile};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Commands understood by the REPL, shown by `help`
+const HELP: &str = "\
+show <name>         Print the condensed items named <name>, e.g. Config or Config::load
+grep <text>         Print the condensed lines containing <text>
+tree [path]         List the files under [path] with token estimates
+copy [command]      Copy the output of [command], or of the previous command, to the clipboard
+help                Show this help
+quit                Leave the REPL
+";
+
+/// Clipboard commands tried in order by `copy`
+const CLIPBOARD_COMMANDS: [(&str, &[&str]); 4] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("clip.exe", &[]),
+];
+
+/// Condensed output of a file of the loaded project
+struct LoadedFile {
+    /// Path relative to the input directory
+    path: String,
+    output: String,
+    items: Vec<ItemRecord>,
+}
+
+/// Processed project held in memory and explored with line commands
+pub struct Repl {
+    files: Vec<LoadedFile>,
+    /// Files that failed to process, with the error
+    skipped: Vec<SkippedFile>,
+    /// Output of the previous command, copied by a bare `copy`
+    last_output: String,
+}
+
+impl Repl {
+    /// Processes every Rust file of the input directory, recording the files that fail
+    pub fn load(input_dir: &Path, no_comments: bool, no_function_bodies: bool) -> Result<Self> {
+        let processor = FileProcessor::with_options(no_comments, no_function_bodies, true, false);
+        let crate_name = processor.crate_name().to_string();
+        let modules = ModuleMap::build(input_dir);
+        let mut files = Vec::new();
+        let mut skipped = Vec::new();
+        for path in processor.source_files(input_dir, None)? {
+            let path = path.as_path();
+            let relative = paths::slash(path.strip_prefix(input_dir)?);
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read file: {}", path.display()))?;
+            match processor.transform_source(path, &content) {
+                Ok(output) => files.push(LoadedFile {
+                    path: relative,
+                    items: item_ids::collect_items(&crate_name, modules.module_of(path), &output),
+                    output,
+                }),
+                Err(error) => skipped.push(SkippedFile {
+                    path: relative,
+                    reason: format!("{:#}", error),
+                }),
+            }
+        }
+        Ok(Self {
+            files,
+            skipped,
+            last_output: String::new(),
+        })
+    }
+
+    /// Files that failed to process, with the error
+    pub fn skipped(&self) -> &[SkippedFile] {
+        &self.skipped
+    }
+
+    /// Number of loaded files
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Runs one command line, returning its output or `None` to quit
+    pub fn execute(&mut self, line: &str) -> Result<Option<String>> {
+        let (command, argument) = line
+            .trim()
+            .split_once(char::is_whitespace)
+            .map_or((line.trim(), ""), |(command, argument)| {
+                (command, argument.trim())
+            });
+        let output = match command {
+            "" => return Ok(Some(String::new())),
+            "quit" | "exit" => return Ok(None),
+            "help" => HELP.to_string(),
+            "show" => self.show(argument),
+            "grep" => self.grep(argument),
+            "tree" => self.tree(argument),
+            "copy" => {
+                let text = if argument.is_empty() {
+                    self.last_output.clone()
+                } else {
+                    self.execute(argument)?.unwrap_or_default()
+                };
+                let tool = copy_to_clipboard(&text)?;
+                return Ok(Some(format!(
+                    "Copied {} lines with {}\n",
+                    text.lines().count(),
+                    tool
+                )));
+            }
+            _ => anyhow::bail!("Unknown command: {} (try `help`)", command),
+        };
+        self.last_output = output.clone();
+        Ok(Some(output))
+    }
+
+    /// Prints the code of items whose name or last path segment matches
+    fn show(&self, name: &str) -> String {
+        let mut output = String::new();
+        for file in &self.files {
+            for item in &file.items {
+                if item.name == name || item.name.ends_with(&format!("::{}", name)) {
+                    let _ = writeln!(
+                        output,
+                        "// {} ({} {})\n{}\n",
+                        file.path, item.kind, item.name, item.code
+                    );
+                }
+            }
+        }
+        if output.is_empty() {
+            output = format!("No item named {}\n", name);
+        }
+        output
+    }
+
+    /// Prints the condensed lines that contain the text
+    fn grep(&self, text: &str) -> String {
+        let mut output = String::new();
+        for file in &self.files {
+            for (number, line) in file.output.lines().enumerate() {
+                if line.contains(text) {
+                    let _ = writeln!(output, "{}:{}: {}", file.path, number + 1, line.trim());
+                }
+            }
+        }
+        output
+    }
+
+    /// Lists the files under a path prefix with their token estimates
+    fn tree(&self, prefix: &str) -> String {
+        let prefix = prefix.trim_end_matches('/');
+        let mut output = String::new();
+        let files = self
+            .files
+            .iter()
+            .filter(|file| prefix.is_empty() || Path::new(&file.path).starts_with(prefix));
+        for file in files {
+            let _ = writeln!(
+                output,
+                "{}  {} tokens",
+                file.path,
+                estimate_tokens(file.output.len())
+            );
+        }
+        output
+    }
+
+    /// Reads commands from the input until `quit` or its end, writing prompts and outputs
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            match self.execute(&line?) {
+                Ok(Some(text)) => write!(output, "{}", text)?,
+                Ok(None) => return Ok(()),
+                Err(error) => writeln!(output, "Error: {:#}", error)?,
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        writeln!(output)?;
+        Ok(())
+    }
+}
+
+/// Copies text with the first clipboard command that is installed, returning its name
+fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    for (tool, args) in CLIPBOARD_COMMANDS {
+        let Ok(mut child) = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        child
+            .stdin
+            .take()
+            .context("Failed to open clipboard stdin")?
+            .write_all(text.as_bytes())
+            .context("Failed to write to the clipboard")?;
+        if child.wait()?.success() {
+            return Ok(tool);
+        }
+    }
+    anyhow::bail!("No clipboard command found; install pbcopy, wl-copy, xclip, or clip.exe")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_repl_commands() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_dir = temp_dir.path();
+        std::fs::create_dir(src_dir.join("api"))?;
+        std::fs::write(
+            src_dir.join("lib.rs"),
+            "pub mod api;\npub struct Config;\nimpl Config {\n    pub fn load() -> Config { Config }\n}\n",
+        )?;
+        std::fs::write(
+            src_dir.join("api/mod.rs"),
+            "pub fn token_budget() -> usize { 1 }\n",
+        )?;
+        // Outputs of earlier runs aren't loaded
+        std::fs::write(
+            src_dir.join("old.rs"),
+            format!("{}pub struct Config;\n", crate::module_path::OUTPUT_MARKER),
+        )?;
+        std::fs::write(src_dir.join("broken.rs"), "pub fn broken(\n")?;
+
+        let mut repl = Repl::load(src_dir, false, true)?;
+        assert_eq!(repl.len(), 2);
+        assert_eq!(repl.skipped()[0].path, "broken.rs");
+        assert!(repl.skipped()[0].reason.contains("Failed to parse"));
+        let shown = repl.execute("show load")?.unwrap();
+        assert!(shown.starts_with("// lib.rs (fn Config::load)\n"));
+        assert!(shown.contains("pub fn load() -> Config"));
+        assert_eq!(
+            repl.execute("grep token_budget")?.unwrap(),
+            "api/mod.rs:1: pub fn token_budget() -> usize {}\n"
+        );
+        assert_eq!(repl.execute("tree api/")?.unwrap().lines().count(), 1);
+        assert!(repl.execute("frobnicate").is_err());
+
+        let mut output = Vec::new();
+        repl.run("show Missing\nquit\nshow Config\n".as_bytes(), &mut output)?;
+        assert_eq!(String::from_utf8(output)?, "> No item named Missing\n> ");
+        Ok(())
+    }
+}
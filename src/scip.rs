@@ -0,0 +1,356 @@
+use crate::cargo;
+use crate::summary::{signature, type_string};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::path::Path;
+use syn::visit::{self, Visit};
+use syn::{ImplItem, Item, TraitItem};
+
+/// Name of the SCIP index written into the output directory
+pub const SCIP_FILE_NAME: &str = "index.scip";
+
+/// Format of the generated output
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Condensed source files
+    #[default]
+    Text,
+    /// Condensed source files and a SCIP index of their definitions and references
+    Scip,
+    /// Condensed source files and a JSONL file with one record per item and its stable id
+    Jsonl,
+    /// Condensed source files and a JSONL file pairing the docs and signature of each
+    /// function with its body, as training or evaluation data for code models
+    PairsJsonl,
+}
+
+/// `SymbolRole.Definition` in the SCIP schema
+const DEFINITION_ROLE: i32 = 1;
+/// `TextEncoding.UTF8` in the SCIP schema
+const UTF8_ENCODING: i32 = 1;
+
+/// Condensed code of a source file to index
+pub struct SourceDocument {
+    /// Path relative to the input directory
+    pub relative_path: String,
+    /// Module path, e.g. `crate::config`
+    pub module: Option<String>,
+    pub text: String,
+}
+
+/// Encoder of protobuf messages, which SCIP indexes are serialized as
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn int32(&mut self, field: u32, value: i32) {
+        if value != 0 {
+            self.key(field, 0);
+            // Negative int32 values are sign-extended to 64 bits
+            self.varint(i64::from(value) as u64);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        self.varint(value.len() as u64);
+        self.0.extend_from_slice(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        if !value.is_empty() {
+            self.bytes(field, value.as_bytes());
+        }
+    }
+
+    fn message(&mut self, field: u32, message: &Message) {
+        self.bytes(field, &message.0);
+    }
+
+    fn packed_int32(&mut self, field: u32, values: &[i32]) {
+        let mut packed = Message::default();
+        for &value in values {
+            packed.varint(i64::from(value) as u64);
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+/// A symbol defined in the condensed code
+struct Definition {
+    symbol: String,
+    name: String,
+    range: [i32; 3],
+    signature: Option<String>,
+}
+
+/// Zero-based `[line, start, end]` range of an identifier
+fn ident_range(ident: &syn::Ident) -> [i32; 3] {
+    let span = ident.span();
+    let (start, end) = (span.start(), span.end());
+    let to_i32 = |value: usize| i32::try_from(value).unwrap_or(i32::MAX);
+    [
+        to_i32(start.line.saturating_sub(1)),
+        to_i32(start.column),
+        to_i32(end.column),
+    ]
+}
+
+/// Collects the definitions of a file, with descriptors relative to its module
+struct DefinitionCollector {
+    prefix: String,
+    definitions: Vec<Definition>,
+}
+
+impl DefinitionCollector {
+    fn define(&mut self, ident: &syn::Ident, descriptor: String, signature: Option<String>) {
+        self.definitions.push(Definition {
+            symbol: descriptor,
+            name: ident.to_string(),
+            range: ident_range(ident),
+            signature,
+        });
+    }
+
+    fn collect(&mut self, items: &[Item]) {
+        for item in items {
+            let prefix = self.prefix.clone();
+            match item {
+                Item::Fn(item) => self.define(
+                    &item.sig.ident,
+                    format!("{}{}().", prefix, item.sig.ident),
+                    Some(signature(&item.sig)),
+                ),
+                Item::Struct(item) => {
+                    self.define(&item.ident, format!("{}{}#", prefix, item.ident), None)
+                }
+                Item::Enum(item) => {
+                    self.define(&item.ident, format!("{}{}#", prefix, item.ident), None)
+                }
+                Item::Union(item) => {
+                    self.define(&item.ident, format!("{}{}#", prefix, item.ident), None)
+                }
+                Item::Type(item) => {
+                    self.define(&item.ident, format!("{}{}#", prefix, item.ident), None)
+                }
+                Item::Const(item) => {
+                    self.define(&item.ident, format!("{}{}.", prefix, item.ident), None)
+                }
+                Item::Static(item) => {
+                    self.define(&item.ident, format!("{}{}.", prefix, item.ident), None)
+                }
+                Item::Trait(item) => {
+                    let owner = format!("{}{}#", prefix, item.ident);
+                    self.define(&item.ident, owner.clone(), None);
+                    for trait_item in &item.items {
+                        if let TraitItem::Fn(method) = trait_item {
+                            self.define(
+                                &method.sig.ident,
+                                format!("{}{}().", owner, method.sig.ident),
+                                Some(signature(&method.sig)),
+                            );
+                        }
+                    }
+                }
+                Item::Impl(item) => {
+                    let self_ty = type_string(&item.self_ty);
+                    let owner = self_ty.split('<').next().unwrap_or_default().trim();
+                    let owner = owner.rsplit("::").next().unwrap_or(owner);
+                    for impl_item in &item.items {
+                        if let ImplItem::Fn(method) = impl_item {
+                            self.define(
+                                &method.sig.ident,
+                                format!("{}{}#{}().", prefix, owner, method.sig.ident),
+                                Some(signature(&method.sig)),
+                            );
+                        }
+                    }
+                }
+                Item::Mod(item) => {
+                    self.define(&item.ident, format!("{}{}/", prefix, item.ident), None);
+                    if let Some((_, items)) = &item.content {
+                        self.prefix = format!("{}{}/", prefix, item.ident);
+                        self.collect(items);
+                        self.prefix = prefix;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Finds the identifiers of a file that refer to definitions by name
+struct ReferenceCollector<'a> {
+    symbols: &'a HashMap<String, Option<String>>,
+    references: Vec<([i32; 3], String)>,
+}
+
+impl ReferenceCollector<'_> {
+    fn reference(&mut self, ident: &syn::Ident) {
+        if let Some(Some(symbol)) = self.symbols.get(&ident.to_string()) {
+            self.references.push((ident_range(ident), symbol.clone()));
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ReferenceCollector<'_> {
+    fn visit_path_segment(&mut self, segment: &'ast syn::PathSegment) {
+        self.reference(&segment.ident);
+        visit::visit_path_segment(self, segment);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        self.reference(&call.method);
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// Descriptor prefix of a module, e.g. `config/` for `crate::config`
+fn module_prefix(document: &SourceDocument) -> String {
+    match &document.module {
+        Some(module) => module
+            .split("::")
+            .skip(1)
+            .map(|segment| format!("{}/", segment))
+            .collect(),
+        None => Path::new(&document.relative_path)
+            .file_stem()
+            .map(|stem| format!("{}/", stem.to_string_lossy()))
+            .unwrap_or_default(),
+    }
+}
+
+/// Package name and version of the crate, or `.` placeholders
+fn package(input: &Path) -> (String, String) {
+    let field = |name: &str| cargo::package_field(input, name).unwrap_or_else(|| ".".to_string());
+    (field("name"), field("version"))
+}
+
+/// Encodes a SCIP index of the definitions and references in the condensed code
+pub fn encode_index(input: &Path, documents: &[SourceDocument]) -> Vec<u8> {
+    let (name, version) = package(input);
+    let scheme = format!("code-context cargo {} {} ", name, version);
+
+    let parsed: Vec<_> = documents
+        .iter()
+        .map(|document| {
+            let file = syn::parse_file(&document.text).ok();
+            let mut collector = DefinitionCollector {
+                prefix: module_prefix(document),
+                definitions: Vec::new(),
+            };
+            if let Some(file) = &file {
+                collector.collect(&file.items);
+            }
+            (document, file, collector.definitions)
+        })
+        .collect();
+
+    // Names defined more than once are ambiguous and not resolved
+    let mut symbols: HashMap<String, Option<String>> = HashMap::new();
+    for definition in parsed.iter().flat_map(|(_, _, definitions)| definitions) {
+        symbols
+            .entry(definition.name.clone())
+            .and_modify(|symbol| *symbol = None)
+            .or_insert_with(|| Some(format!("{}{}", scheme, definition.symbol)));
+    }
+
+    let mut tool_info = Message::default();
+    tool_info.string(1, env!("CARGO_PKG_NAME"));
+    tool_info.string(2, env!("CARGO_PKG_VERSION"));
+    let project_root = std::fs::canonicalize(input).unwrap_or_else(|_| input.to_path_buf());
+    let mut metadata = Message::default();
+    metadata.message(2, &tool_info);
+    metadata.string(3, &format!("file://{}", project_root.display()));
+    metadata.int32(4, UTF8_ENCODING);
+
+    let mut index = Message::default();
+    index.message(1, &metadata);
+    for (document, file, definitions) in &parsed {
+        let mut message = Message::default();
+        message.string(1, &document.relative_path);
+        for definition in definitions {
+            let symbol = format!("{}{}", scheme, definition.symbol);
+            let mut occurrence = Message::default();
+            occurrence.packed_int32(1, &definition.range);
+            occurrence.string(2, &symbol);
+            occurrence.int32(3, DEFINITION_ROLE);
+            message.message(2, &occurrence);
+        }
+        if let Some(file) = file {
+            let mut references = ReferenceCollector {
+                symbols: &symbols,
+                references: Vec::new(),
+            };
+            references.visit_file(file);
+            for (range, symbol) in references.references {
+                let mut occurrence = Message::default();
+                occurrence.packed_int32(1, &range);
+                occurrence.string(2, &symbol);
+                message.message(2, &occurrence);
+            }
+        }
+        for definition in definitions {
+            let mut information = Message::default();
+            information.string(1, &format!("{}{}", scheme, definition.symbol));
+            if let Some(signature) = &definition.signature {
+                information.string(3, &format!("```rust\n{}\n```", signature));
+            }
+            information.string(6, &definition.name);
+            message.message(3, &information);
+        }
+        message.string(4, "rust");
+        message.string(5, &document.text);
+        index.message(2, &message);
+    }
+    index.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_varint() {
+        let mut message = Message::default();
+        message.int32(1, 300);
+        assert_eq!(message.0, vec![0x08, 0xac, 0x02]);
+
+        let mut message = Message::default();
+        message.packed_int32(1, &[0, 4, 7]);
+        assert_eq!(message.0, vec![0x0a, 0x03, 0x00, 0x04, 0x07]);
+    }
+
+    #[test]
+    fn test_encode_index_definitions_and_references() {
+        let documents = vec![SourceDocument {
+            relative_path: "config.rs".to_string(),
+            module: Some("crate::config".to_string()),
+            text: "pub struct Config;\nimpl Config {\n    pub fn load() -> Config {}\n}\n"
+                .to_string(),
+        }];
+        let index = encode_index(Path::new("/nonexistent"), &documents);
+        let text = String::from_utf8_lossy(&index);
+        assert!(text.contains("code-context cargo . . config/Config#"));
+        assert!(text.contains("code-context cargo . . config/Config#load()."));
+        assert!(text.contains("pub fn load() -> Config"));
+        // Definition occurrences and symbol information of both symbols, plus two
+        // references to `Config` in the impl block
+        assert_eq!(text.matches("config/Config#load().").count(), 2);
+        assert_eq!(text.matches("config/Config#").count(), 6);
+        assert_eq!(text.matches("config.rs").count(), 1);
+    }
+}
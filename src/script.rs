@@ -0,0 +1,128 @@
+/// Splits the header of a cargo script, a `#!` shebang line and a `---` frontmatter block
+/// with its manifest, from the Rust code that follows, which syn can then parse
+pub fn split_header(content: &str) -> (&str, &str) {
+    let mut end = 0;
+    // `#![` starts an inner attribute, not a shebang
+    if content.starts_with("#!") && !content[2..].trim_start().starts_with('[') {
+        end = content.find('\n').map_or(content.len(), |index| index + 1);
+    }
+
+    let blank = content[end..]
+        .split_inclusive('\n')
+        .take_while(|line| line.trim().is_empty())
+        .map(str::len)
+        .sum::<usize>();
+    let mut lines = content[end + blank..].split_inclusive('\n');
+    let opening = lines.next().unwrap_or_default();
+    let dashes = opening.len() - opening.trim_start_matches('-').len();
+    if dashes >= 3 {
+        let mut frontmatter = opening.len();
+        for line in lines {
+            frontmatter += line.len();
+            if line.trim_end() == &opening[..dashes] {
+                end += blank + frontmatter;
+                break;
+            }
+        }
+    }
+    content.split_at(end)
+}
+
+/// Returns the manifest embedded in a cargo script: the TOML of its frontmatter, or of a
+/// ```` ```cargo ```` block in its inner doc comment as used by rust-script
+pub fn manifest(content: &str) -> Option<String> {
+    let (header, _) = split_header(content);
+    let frontmatter = header
+        .split_inclusive('\n')
+        .skip_while(|line| !line.starts_with("---"))
+        .collect::<Vec<_>>();
+    if let [_, toml @ .., _] = frontmatter.as_slice() {
+        return Some(toml.concat());
+    }
+
+    let block = manifest_comment(content)?;
+    let lines: Vec<_> = block.lines().collect();
+    let toml = lines[1..lines.len() - 1]
+        .iter()
+        .map(|line| {
+            let text = line.trim_start().trim_start_matches("//!");
+            text.strip_prefix(' ').unwrap_or(text)
+        })
+        .map(|line| format!("{}\n", line));
+    Some(toml.collect())
+}
+
+/// Finds the ```` ```cargo ```` manifest block of a rust-script inner doc comment, returning
+/// its `//!` lines including the fences
+pub fn manifest_comment(content: &str) -> Option<&str> {
+    let (header, body) = split_header(content);
+    let mut start = None;
+    let mut offset = header.len();
+    for line in body.split_inclusive('\n') {
+        let text = line.trim().strip_prefix("//!").map(str::trim);
+        match (start, text) {
+            (None, Some("```cargo")) => start = Some(offset),
+            (Some(start), Some("```")) => return Some(&content[start..offset + line.len()]),
+            (Some(_), Some(_)) => {}
+            // The block must be one doc comment
+            (Some(_), None) => return None,
+            (None, _) => {}
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_script_header() {
+        let script = "#!/usr/bin/env -S cargo +nightly -Zscript\n---\n[dependencies]\nclap = \"4\"\n---\n#![allow(dead_code)]\nfn main() {}\n";
+        assert_eq!(
+            split_header(script),
+            (
+                "#!/usr/bin/env -S cargo +nightly -Zscript\n---\n[dependencies]\nclap = \"4\"\n---\n",
+                "#![allow(dead_code)]\nfn main() {}\n"
+            )
+        );
+        assert_eq!(
+            split_header("#!/usr/bin/env rust-script\nfn main() {}\n"),
+            ("#!/usr/bin/env rust-script\n", "fn main() {}\n")
+        );
+        assert_eq!(
+            split_header("---cargo\npackage.edition = \"2024\"\n---\n\nfn main() {}\n"),
+            (
+                "---cargo\npackage.edition = \"2024\"\n---\n",
+                "\nfn main() {}\n"
+            )
+        );
+        assert_eq!(
+            split_header("#![allow(dead_code)]\nfn main() {}\n"),
+            ("", "#![allow(dead_code)]\nfn main() {}\n")
+        );
+        // An unclosed frontmatter is left to fail parsing
+        assert_eq!(split_header("---\nfn main() {}\n").0, "");
+    }
+
+    #[test]
+    fn test_embedded_manifest() {
+        let script = "#!/usr/bin/env cargo\n---\n[dependencies]\nclap = \"4\"\n---\nfn main() {}\n";
+        assert_eq!(
+            manifest(script).as_deref(),
+            Some("[dependencies]\nclap = \"4\"\n")
+        );
+
+        let script = "#!/usr/bin/env rust-script\n//! Prints the time\n//!\n//! ```cargo\n//! [dependencies]\n//! time = \"0.1\"\n//! ```\nfn main() {}\n";
+        assert_eq!(
+            manifest_comment(script),
+            Some("//! ```cargo\n//! [dependencies]\n//! time = \"0.1\"\n//! ```\n")
+        );
+        assert_eq!(
+            manifest(script).as_deref(),
+            Some("[dependencies]\ntime = \"0.1\"\n")
+        );
+        assert_eq!(manifest("//! Docs\nfn main() {}\n"), None);
+    }
+}
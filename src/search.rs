@@ -0,0 +1,116 @@
+use crate::item_ids::ITEMS_FILE_NAME;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fmt::Write;
+use std::path::Path;
+
+/// An item read back from the JSONL index written by `--format jsonl`
+#[derive(Deserialize, Debug)]
+pub struct IndexedItem {
+    pub path: String,
+    pub kind: String,
+    pub name: String,
+    pub code: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+impl IndexedItem {
+    /// Retained doc comment lines of the item
+    fn docs(&self) -> impl Iterator<Item = &str> {
+        self.code
+            .lines()
+            .map(str::trim_start)
+            .filter(|line| is_doc(line))
+    }
+
+    /// First line of the item after its docs and attributes
+    fn signature(&self) -> &str {
+        self.code
+            .lines()
+            .map(str::trim_start)
+            .find(|line| !is_doc(line) && !line.starts_with("#["))
+            .unwrap_or_default()
+    }
+
+    /// Checks the name, doc aliases, signature, and docs against a lowercase pattern
+    fn matches(&self, pattern: &str) -> bool {
+        let contains = |text: &str| text.to_lowercase().contains(pattern);
+        contains(&self.name)
+            || self.aliases.iter().any(|alias| contains(alias))
+            || contains(self.signature())
+            || self.docs().any(contains)
+    }
+}
+
+fn is_doc(line: &str) -> bool {
+    line.starts_with("///") || line.starts_with("//!") || line.starts_with("/**")
+}
+
+/// Loads the items of a JSONL index, or of the output directory containing it
+pub fn load_index(path: &Path) -> Result<Vec<IndexedItem>> {
+    let path = if path.is_dir() {
+        &path.join(ITEMS_FILE_NAME)
+    } else {
+        path
+    };
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read item index: {}", path.display()))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse item index: {}", path.display()))
+        })
+        .collect()
+}
+
+/// Renders the condensed code of the items whose name, doc aliases, signature, or docs
+/// contain the pattern, ignoring case
+pub fn grep(items: &[IndexedItem], pattern: &str) -> String {
+    let pattern = pattern.to_lowercase();
+    let mut output = String::new();
+    for item in items.iter().filter(|item| item.matches(&pattern)) {
+        let _ = writeln!(
+            output,
+            "// {} ({} {})\n{}\n",
+            item.path, item.kind, item.name, item.code
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_grep_index() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let lines = [
+            r#"{"id":"1","path":"lib.rs","kind":"struct","name":"Config","code":"/// Limits the token budget\n#[derive(Debug)]\npub struct Config {\n    pub budget: usize,\n}"}"#,
+            r#"{"id":"2","path":"api.rs","module":"api","kind":"fn","name":"handler","code":"pub fn handler(max_tokens: usize) {}"}"#,
+            r#"{"id":"3","path":"api.rs","module":"api","kind":"fn","name":"serve","code":"pub fn serve() {\n    let token = 1;\n}","aliases":["listen"]}"#,
+        ];
+        std::fs::write(
+            temp_dir.path().join(ITEMS_FILE_NAME),
+            lines.join("\n") + "\n",
+        )?;
+
+        let items = load_index(temp_dir.path())?;
+        assert_eq!(items.len(), 3);
+        // Bodies are not searched
+        let output = grep(&items, "TOKEN");
+        assert_eq!(
+            output,
+            "// lib.rs (struct Config)\n/// Limits the token budget\n#[derive(Debug)]\npub struct Config {\n    pub budget: usize,\n}\n\n\
+             // api.rs (fn handler)\npub fn handler(max_tokens: usize) {}\n\n"
+        );
+        assert_eq!(grep(&items, "derive"), "");
+        assert!(grep(&items, "listen").starts_with("// api.rs (fn serve)"));
+        assert!(load_index(&temp_dir.path().join("missing.jsonl")).is_err());
+        Ok(())
+    }
+}
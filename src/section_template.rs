@@ -0,0 +1,81 @@
+/// Header written before each file of the single-file output unless `--file-header` is given
+pub const DEFAULT_FILE_HEADER: &str = "\\n// File: {path}\\n\\n";
+
+/// Footer written after each file of the single-file output unless `--file-footer` is given
+pub const DEFAULT_FILE_FOOTER: &str = "\\n";
+
+/// Text around each file's section in the single-file output, where `{path}` is replaced
+/// with the file's path
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionTemplate {
+    header: String,
+    footer: String,
+}
+
+impl Default for SectionTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_FILE_HEADER, DEFAULT_FILE_FOOTER)
+    }
+}
+
+impl SectionTemplate {
+    /// Creates a template from header and footer strings, which may contain `\n`, `\t`, and
+    /// `\\` escapes so they can be given on the command line
+    pub fn new(header: &str, footer: &str) -> Self {
+        Self {
+            header: unescape(header),
+            footer: unescape(footer),
+        }
+    }
+
+    pub fn header(&self, path: &str) -> String {
+        self.header.replace("{path}", path)
+    }
+
+    pub fn footer(&self, path: &str) -> String {
+        self.footer.replace("{path}", path)
+    }
+}
+
+/// Replaces `\n`, `\t`, and `\\` escapes, keeping any other backslash as is
+fn unescape(template: &str) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_template() {
+        let template = SectionTemplate::default();
+        assert_eq!(template.header("lib.rs"), "\n// File: lib.rs\n\n");
+        assert_eq!(template.footer("lib.rs"), "\n");
+
+        let template = SectionTemplate::new("<file path=\"{path}\">\\n", "\\n</file>\\n");
+        assert_eq!(
+            template.header("api/mod.rs"),
+            "<file path=\"api/mod.rs\">\n"
+        );
+        assert_eq!(template.footer("api/mod.rs"), "\n</file>\n");
+        assert_eq!(unescape("a\\\\n\\x\\"), "a\\n\\x\\");
+    }
+}
@@ -0,0 +1,148 @@
+use crate::item_ids::{self, ItemRecord};
+use crate::manifest::Manifest;
+use crate::processor::FileStats;
+use crate::update::content_hash;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// Prints an item of a previous run: its condensed code from the output, or with `full`
+/// its complete original source, found by the span recorded in the manifest
+pub fn show(manifest: &Manifest, query: &str, full: bool) -> Result<String> {
+    let (file, item) = find(manifest, query)?;
+    if full {
+        full_source(manifest, file, item)
+    } else {
+        condensed(file, item)
+    }
+}
+
+/// Finds the item with the given id, name (`Config::load`), or module-qualified name
+/// (`crate::config::Config::load`)
+fn find<'a>(manifest: &'a Manifest, query: &str) -> Result<(&'a FileStats, &'a ItemRecord)> {
+    let matches: Vec<_> = manifest
+        .files
+        .iter()
+        .flat_map(|file| file.items.iter().map(move |item| (file, item)))
+        .filter(|(file, item)| {
+            item.id == query || item.name == query || qualified_name(file, item) == query
+        })
+        .collect();
+    match matches.as_slice() {
+        [] => bail!("No item named {} in the manifest", query),
+        [found] => Ok(*found),
+        _ => bail!(
+            "{} matches several items, pick one by its qualified name or id:\n{}",
+            query,
+            matches
+                .iter()
+                .map(|(file, item)| format!(
+                    "  {} {} ({})",
+                    item.kind,
+                    qualified_name(file, item),
+                    item.id
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+fn qualified_name(file: &FileStats, item: &ItemRecord) -> String {
+    format!(
+        "{}::{}",
+        file.module.as_deref().unwrap_or("crate"),
+        item.name
+    )
+}
+
+fn condensed(file: &FileStats, item: &ItemRecord) -> Result<String> {
+    let output = std::fs::read_to_string(&file.destination)
+        .with_context(|| format!("Failed to read output file: {}", file.destination))?;
+    item_ids::collect_items("", file.module.as_deref(), &output)
+        .into_iter()
+        .find(|emitted| emitted.kind == item.kind && emitted.name == item.name)
+        .map(|emitted| emitted.code + "\n")
+        .with_context(|| {
+            format!(
+                "{} {} isn't in {}; try --full",
+                item.kind, item.name, file.destination
+            )
+        })
+}
+
+fn full_source(manifest: &Manifest, file: &FileStats, item: &ItemRecord) -> Result<String> {
+    let Some(span) = item.source else {
+        bail!(
+            "The manifest has no source span for {} {}; only functions have one",
+            item.kind,
+            item.name
+        );
+    };
+    let path = Path::new(&manifest.source_root).join(&file.path);
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read source file: {}", path.display()))?;
+    if file
+        .source_hash
+        .as_ref()
+        .is_some_and(|hash| *hash != content_hash(&content))
+    {
+        bail!(
+            "{} changed since the run; regenerate the context first",
+            path.display()
+        );
+    }
+    // Start at the beginning of the line so the first line keeps its indentation
+    let start = content[..span.start_byte.min(content.len())]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let code = content
+        .get(start..span.end_byte)
+        .with_context(|| format!("Source span of {} is outside {}", item.name, path.display()))?;
+    Ok(format!(
+        "// {}:{}-{}\n{}\n",
+        file.path, span.start_line, span.end_line, code
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_show_item() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source = "pub struct Config;\n\nimpl Config {\n    /// Loads\n    pub fn load() -> Self {\n        Config\n    }\n}\n";
+        let output =
+            "pub struct Config;\nimpl Config {\n    /// Loads\n    pub fn load() -> Self {}\n}\n";
+        std::fs::write(temp_dir.path().join("config.rs"), source)?;
+        let destination = temp_dir.path().join("config.rs.txt");
+        std::fs::write(&destination, output)?;
+
+        let mut items = item_ids::collect_items("app", Some("crate::config"), output);
+        item_ids::attach_source(&mut items, "app", Some("crate::config"), source);
+        let mut manifest = Manifest::default().with_source_root(temp_dir.path());
+        manifest.files.push(
+            FileStats::new("config.rs", source.len(), output.len())
+                .with_destination(&destination)
+                .with_module(Some("crate::config"))
+                .with_source_hash(content_hash(source))
+                .with_items(items),
+        );
+
+        assert_eq!(
+            show(&manifest, "Config::load", false)?,
+            "    /// Loads\n    pub fn load() -> Self {}\n"
+        );
+        assert_eq!(
+            show(&manifest, "crate::config::Config::load", true)?,
+            "// config.rs:4-7\n    /// Loads\n    pub fn load() -> Self {\n        Config\n    }\n"
+        );
+        assert!(show(&manifest, "Config", true).is_err());
+        assert!(show(&manifest, "Missing", false).is_err());
+
+        std::fs::write(temp_dir.path().join("config.rs"), "pub struct Config;\n")?;
+        assert!(show(&manifest, "Config::load", true).is_err());
+        Ok(())
+    }
+}
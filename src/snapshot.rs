@@ -0,0 +1,197 @@
+use crate::config::Config;
+use crate::manifest::MANIFEST_FILE_NAME;
+use crate::paths;
+use crate::update::bytes_hash;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Store directory used when neither `--store` nor the `snapshot-store` setting is given,
+/// relative to the current directory
+pub const DEFAULT_STORE: &str = ".code-context/snapshots";
+
+/// A stored output, identified by the hash of its files
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub tag: String,
+    pub hash: String,
+    /// Directory holding the stored files
+    pub dir: PathBuf,
+}
+
+/// Content-addressed store of generated outputs: `objects/<hash>/` holds the files of an
+/// output and `tags/<tag>` the hash of the output it names
+pub struct SnapshotStore {
+    root: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Opens the store given on the command line, or else the one of the configuration, or
+    /// else the default one
+    pub fn configured(store: Option<&Path>, config: &Config) -> Self {
+        match (store, &config.snapshot_store) {
+            (Some(store), _) => Self::new(store),
+            (None, Some(store)) => Self::new(config.base_dir.join(store)),
+            (None, None) => Self::new(DEFAULT_STORE),
+        }
+    }
+
+    /// Stores the output directory of a run under a tag, copying its files only if no
+    /// identical output is stored yet; an existing tag is moved to the new output
+    pub fn store(&self, output: &Path, tag: &str) -> Result<Snapshot> {
+        validate_tag(tag)?;
+        if !output.join(MANIFEST_FILE_NAME).is_file() {
+            bail!(
+                "{} is not the output directory of a run: it has no {}",
+                output.display(),
+                MANIFEST_FILE_NAME
+            );
+        }
+        let files = output_files(output)?;
+        let mut hashed = Vec::new();
+        for (relative, path) in &files {
+            hashed.extend_from_slice(relative.as_bytes());
+            hashed.push(0);
+            hashed.extend(
+                std::fs::read(path)
+                    .with_context(|| format!("Failed to read output file: {}", path.display()))?,
+            );
+            hashed.push(0);
+        }
+        let hash = bytes_hash(&hashed);
+
+        let dir = self.root.join("objects").join(&hash);
+        if !dir.is_dir() {
+            // Copied next to the final directory first, so an interrupted copy is never
+            // mistaken for a stored output
+            let partial = self.root.join("objects").join(format!("{}.partial", hash));
+            let _ = std::fs::remove_dir_all(&partial);
+            for (relative, path) in &files {
+                let destination = partial.join(relative);
+                if let Some(parent) = destination.parent() {
+                    std::fs::create_dir_all(parent)
+                        .context("Failed to create snapshot directory")?;
+                }
+                std::fs::copy(path, &destination)
+                    .with_context(|| format!("Failed to copy output file: {}", path.display()))?;
+            }
+            std::fs::rename(&partial, &dir).context("Failed to store snapshot")?;
+        }
+
+        let tags = self.root.join("tags");
+        std::fs::create_dir_all(&tags).context("Failed to create snapshot tags directory")?;
+        std::fs::write(tags.join(tag), format!("{}\n", hash))
+            .with_context(|| format!("Failed to write snapshot tag: {}", tag))?;
+        Ok(Snapshot {
+            tag: tag.to_string(),
+            hash,
+            dir,
+        })
+    }
+
+    /// Returns the snapshot a tag names
+    pub fn resolve(&self, tag: &str) -> Result<Snapshot> {
+        validate_tag(tag)?;
+        let hash = std::fs::read_to_string(self.root.join("tags").join(tag))
+            .with_context(|| format!("No snapshot tagged {} in {}", tag, paths::slash(&self.root)))?
+            .trim()
+            .to_string();
+        Ok(Snapshot {
+            tag: tag.to_string(),
+            dir: self.root.join("objects").join(&hash),
+            hash,
+        })
+    }
+
+    /// Lists the tagged snapshots, sorted by tag
+    pub fn list(&self) -> Result<Vec<Snapshot>> {
+        let tags = self.root.join("tags");
+        if !tags.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&tags).context("Failed to read snapshot tags")? {
+            names.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        names.iter().map(|tag| self.resolve(tag)).collect()
+    }
+}
+
+/// Resolves a command argument to an existing path, or else to the snapshot it tags in the
+/// given store, so commands reading outputs also accept tags
+pub fn resolve_path(argument: &Path, store: &SnapshotStore) -> PathBuf {
+    if argument.exists() {
+        return argument.to_path_buf();
+    }
+    argument
+        .to_str()
+        .and_then(|tag| store.resolve(tag).ok())
+        .map_or_else(|| argument.to_path_buf(), |snapshot| snapshot.dir)
+}
+
+/// Files of an output directory by their slash-separated relative path, sorted
+fn output_files(output: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(output).sort_by_file_name() {
+        let entry = entry.context("Failed to read output directory")?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(output)?;
+            files.push((paths::slash(relative), entry.into_path()));
+        }
+    }
+    Ok(files)
+}
+
+/// Tags name files of the store, so they can't contain separators or start with a dot
+fn validate_tag(tag: &str) -> Result<()> {
+    if tag.is_empty() || tag.starts_with('.') || tag.contains(['/', '\\', ':']) {
+        bail!(
+            "Invalid snapshot tag {:?}: tags can't be empty, start with `.`, or contain `/`, `\\`, or `:`",
+            tag
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_and_resolve_snapshots() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let output = temp_dir.path().join("out");
+        std::fs::create_dir_all(output.join("src"))?;
+        std::fs::write(output.join(MANIFEST_FILE_NAME), "{}")?;
+        std::fs::write(output.join("src/lib.rs.txt"), "pub fn a() {}\n")?;
+        let store = SnapshotStore::new(temp_dir.path().join("store"));
+
+        let first = store.store(&output, "release-1.3")?;
+        assert!(first.dir.join("src/lib.rs.txt").is_file());
+        // The same output is stored once, under both tags
+        let same = store.store(&output, "release-1.4")?;
+        assert_eq!(same.dir, first.dir);
+        std::fs::write(output.join("src/lib.rs.txt"), "pub fn b() {}\n")?;
+        let changed = store.store(&output, "release-1.4")?;
+        assert_ne!(changed.hash, first.hash);
+
+        assert_eq!(store.resolve("release-1.3")?, first);
+        let tags: Vec<_> = store.list()?.into_iter().map(|s| s.tag).collect();
+        assert_eq!(tags, ["release-1.3", "release-1.4"]);
+        assert_eq!(resolve_path(Path::new("release-1.4"), &store), changed.dir);
+        assert_eq!(resolve_path(&output, &store), output);
+
+        assert!(store.resolve("missing").is_err());
+        assert!(store.store(&output, "../escape").is_err());
+        assert!(store
+            .store(&temp_dir.path().join("store"), "no-manifest")
+            .is_err());
+        Ok(())
+    }
+}
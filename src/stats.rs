@@ -0,0 +1,456 @@
+use crate::manifest::Manifest;
+use crate::models::ModelContext;
+use crate::processor::{estimate_tokens, FileStats, ProcessingStats};
+use crate::{dedupe, doc_stats};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Layout of the statistics printed after a run
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StatsFormat {
+    /// One `label: value` line per statistic
+    #[default]
+    Text,
+    /// Aligned columns of the totals, the modules, and the context window of each model
+    Table,
+}
+
+/// Statistics aggregated over all files in one directory
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ModuleStats {
+    pub module: String,
+    pub files: usize,
+    pub input_size: usize,
+    pub output_size: usize,
+}
+
+impl ModuleStats {
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(self.output_size)
+    }
+
+    pub fn reduction_percentage(&self) -> f64 {
+        if self.input_size == 0 {
+            return 0.0;
+        }
+        let input_size = self.input_size as f64;
+        let output_size = self.output_size as f64;
+        ((input_size - output_size) / input_size) * 100.0
+    }
+}
+
+/// Groups file statistics by their parent directory, largest modules first
+pub fn aggregate_by_module(files: &[FileStats]) -> Vec<ModuleStats> {
+    let mut modules: BTreeMap<String, ModuleStats> = BTreeMap::new();
+    for file in files {
+        let module = Path::new(&file.path)
+            .parent()
+            .map(|parent| parent.display().to_string())
+            .filter(|parent| !parent.is_empty())
+            .unwrap_or_else(|| ".".to_string());
+        let entry = modules
+            .entry(module.clone())
+            .or_insert_with(|| ModuleStats {
+                module,
+                ..Default::default()
+            });
+        entry.files += 1;
+        entry.input_size += file.input_size;
+        entry.output_size += file.output_size;
+    }
+
+    let mut modules: Vec<_> = modules.into_values().collect();
+    modules.sort_by_key(|module| std::cmp::Reverse(module.output_size));
+    modules
+}
+
+/// Renders per-module token counts, reduction, and share of the total context
+pub fn format_modules(modules: &[ModuleStats]) -> String {
+    let total_tokens: usize = modules.iter().map(ModuleStats::tokens).sum();
+    let mut report = String::new();
+    for module in modules {
+        let _ = writeln!(
+            report,
+            "  {}: {} files, {} tokens, {:.1}% reduction, {:.1}% of context",
+            module.module,
+            format_count(module.files),
+            format_count(module.tokens()),
+            module.reduction_percentage(),
+            share(module.tokens(), total_tokens)
+        );
+    }
+    report
+}
+
+/// Renders the statistics of a run: totals, removals, documentation, duplicates, and
+/// per-module statistics, plus the context window use of each model as a table
+pub fn render(stats: &ProcessingStats, models: &[ModelContext], format: StatsFormat) -> String {
+    let mut report = String::from("\nProcessing Statistics:\n");
+    let totals = [
+        ("Files processed", format_count(stats.files_processed)),
+        ("Total input size", format_size(stats.input_size)),
+        ("Total output size", format_size(stats.output_size)),
+        (
+            "Size reduction",
+            format!("{:.1}%", stats.reduction_percentage()),
+        ),
+        ("Estimated tokens", format_count(stats.tokens())),
+        ("Removed", stats.removals().format()),
+        ("Documentation", doc_stats::total(&stats.files).format()),
+    ];
+    let modules = aggregate_by_module(&stats.files);
+    match format {
+        StatsFormat::Text => {
+            for (label, value) in totals {
+                let _ = writeln!(report, "{}: {}", label, value);
+            }
+            if let Some(duplicates) = dedupe::report(&stats.files) {
+                report.push_str(&duplicates);
+            }
+            if modules.len() > 1 {
+                report.push_str("\nPer-module statistics:\n");
+                report.push_str(&format_modules(&modules));
+            }
+        }
+        StatsFormat::Table => {
+            let width = totals
+                .iter()
+                .map(|(label, _)| label.len())
+                .max()
+                .unwrap_or(0);
+            for (label, value) in totals {
+                let _ = writeln!(report, "{:<width$}  {}", label, value, width = width);
+            }
+            if let Some(duplicates) = dedupe::report(&stats.files) {
+                report.push('\n');
+                report.push_str(&duplicates);
+            }
+            if modules.len() > 1 {
+                let total_tokens: usize = modules.iter().map(ModuleStats::tokens).sum();
+                let rows = modules
+                    .iter()
+                    .map(|module| {
+                        vec![
+                            module.module.clone(),
+                            format_count(module.files),
+                            format_size(module.input_size),
+                            format_size(module.output_size),
+                            format_count(module.tokens()),
+                            format!("{:.1}%", module.reduction_percentage()),
+                            format!("{:.1}%", share(module.tokens(), total_tokens)),
+                        ]
+                    })
+                    .collect();
+                report.push('\n');
+                report.push_str(&table(
+                    &[
+                        "Module",
+                        "Files",
+                        "Input",
+                        "Output",
+                        "Tokens",
+                        "Reduction",
+                        "Share",
+                    ],
+                    rows,
+                ));
+            }
+            let rows = models
+                .iter()
+                .map(|model| {
+                    vec![
+                        model.name.clone(),
+                        format_count(model.context_window),
+                        format!("{:.1}%", share(stats.tokens(), model.context_window)),
+                        if stats.tokens() <= model.context_window {
+                            "yes"
+                        } else {
+                            "no"
+                        }
+                        .to_string(),
+                    ]
+                })
+                .collect();
+            report.push('\n');
+            report.push_str(&table(&["Model", "Window", "Used", "Fits"], rows));
+        }
+    }
+    report
+}
+
+/// Formats a count with `,` thousands separators, whatever the locale
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Formats a size in bytes with binary units, e.g. "512 bytes" or "12.3 MiB"
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Percentage of `total` that `part` makes up, 0 for an empty total
+fn share(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64 * 100.0
+    }
+}
+
+/// Renders rows under a header, the first column aligned left and the others right
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    let header: Vec<String> = header.iter().map(|cell| cell.to_string()).collect();
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut rendered = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let mut line = String::new();
+        for (column, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if column == 0 {
+                let _ = write!(line, "{:<width$}", cell, width = width);
+            } else {
+                let _ = write!(line, "  {:>width$}", cell, width = width);
+            }
+        }
+        rendered.push_str(line.trim_end());
+        rendered.push('\n');
+    }
+    rendered
+}
+
+/// Formats a signed difference between two values with `format`, e.g. "+12" or
+/// "-1.5 KiB"
+fn format_delta(old: usize, new: usize, format: fn(usize) -> String) -> String {
+    if new >= old {
+        format!("+{}", format(new - old))
+    } else {
+        format!("-{}", format(old - new))
+    }
+}
+
+/// Formats a relative change between two counts, e.g. "+4.7%"
+fn format_percentage(old: usize, new: usize) -> String {
+    if old == 0 {
+        return "n/a".to_string();
+    }
+    let change = (new as f64 - old as f64) / old as f64 * 100.0;
+    format!("{:+.1}%", change)
+}
+
+/// Renders a summary of a single manifest
+pub fn summarize(manifest: &Manifest) -> String {
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "Files processed: {}",
+        format_count(manifest.files_processed)
+    );
+    let _ = writeln!(
+        report,
+        "Total input size: {}",
+        format_size(manifest.input_size)
+    );
+    let _ = writeln!(
+        report,
+        "Total output size: {}",
+        format_size(manifest.output_size)
+    );
+    let _ = writeln!(
+        report,
+        "Estimated tokens: {}",
+        format_count(manifest.tokens)
+    );
+    if !manifest.removals.is_empty() {
+        let _ = writeln!(report, "Removed: {}", manifest.removals.format());
+    }
+    let comments = doc_stats::total(&manifest.files);
+    if !comments.is_empty() {
+        let _ = writeln!(report, "Documentation: {}", comments.format());
+    }
+
+    let modules = aggregate_by_module(&manifest.files);
+    if !modules.is_empty() {
+        let _ = writeln!(report, "\nPer-module statistics:");
+        report.push_str(&format_modules(&modules));
+    }
+    report
+}
+
+/// Renders how the context changed between an old and a new manifest
+pub fn compare(old: &Manifest, new: &Manifest) -> String {
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "Files: {} -> {} ({})",
+        format_count(old.files_processed),
+        format_count(new.files_processed),
+        format_delta(old.files_processed, new.files_processed, format_count)
+    );
+    let _ = writeln!(
+        report,
+        "Output size: {} -> {} ({}, {})",
+        format_size(old.output_size),
+        format_size(new.output_size),
+        format_delta(old.output_size, new.output_size, format_size),
+        format_percentage(old.output_size, new.output_size)
+    );
+    let _ = writeln!(
+        report,
+        "Tokens: {} -> {} ({}, {})",
+        format_count(old.tokens),
+        format_count(new.tokens),
+        format_delta(old.tokens, new.tokens, format_count),
+        format_percentage(old.tokens, new.tokens)
+    );
+
+    let old_files: BTreeMap<_, _> = old.files.iter().map(|f| (&f.path, f.tokens)).collect();
+    let new_files: BTreeMap<_, _> = new.files.iter().map(|f| (&f.path, f.tokens)).collect();
+
+    let mut changes = Vec::new();
+    for (path, &tokens) in &new_files {
+        match old_files.get(path) {
+            None => changes.push(format!("  + {}: {} tokens", path, format_count(tokens))),
+            Some(&old_tokens) if old_tokens != tokens => changes.push(format!(
+                "  ~ {}: {} -> {} tokens ({})",
+                path,
+                format_count(old_tokens),
+                format_count(tokens),
+                format_delta(old_tokens, tokens, format_count)
+            )),
+            Some(_) => {}
+        }
+    }
+    for (path, &tokens) in &old_files {
+        if !new_files.contains_key(path) {
+            changes.push(format!("  - {}: {} tokens", path, format_count(tokens)));
+        }
+    }
+
+    if !changes.is_empty() {
+        let _ = writeln!(report, "\nChanged files:");
+        for change in changes {
+            let _ = writeln!(report, "{}", change);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::ProcessingStats;
+
+    fn manifest(files: &[(&str, usize)]) -> Manifest {
+        let mut stats = ProcessingStats::default();
+        for (path, output_size) in files {
+            stats.record(FileStats::new(*path, output_size * 2, *output_size));
+        }
+        Manifest::from_stats(&stats)
+    }
+
+    #[test]
+    fn test_compare_manifests() {
+        let old = manifest(&[("lib.rs", 400), ("old.rs", 40), ("same.rs", 80)]);
+        let new = manifest(&[("lib.rs", 800), ("new.rs", 20), ("same.rs", 80)]);
+
+        let report = compare(&old, &new);
+        assert!(report.contains("Files: 3 -> 3 (+0)"));
+        assert!(report.contains("Output size: 520 bytes -> 900 bytes (+380 bytes, +73.1%)"));
+        assert!(report.contains("  ~ lib.rs: 100 -> 200 tokens (+100)"));
+        assert!(report.contains("  + new.rs: 5 tokens"));
+        assert!(report.contains("  - old.rs: 10 tokens"));
+        assert!(!report.contains("same.rs"));
+
+        let old = manifest(&[("lib.rs", 3 * 1024 * 1024)]);
+        let new = manifest(&[("lib.rs", 2 * 1024 * 1024)]);
+        let report = compare(&old, &new);
+        assert!(report.contains("Output size: 3.0 MiB -> 2.0 MiB (-1.0 MiB, -33.3%)"));
+        assert!(report.contains("  ~ lib.rs: 786,432 -> 524,288 tokens (-262,144)"));
+    }
+
+    #[test]
+    fn test_compare_unchanged() {
+        let old = manifest(&[("lib.rs", 400)]);
+        let report = compare(&old, &old);
+        assert!(report.contains("Tokens: 100 -> 100 (+0, +0.0%)"));
+        assert!(!report.contains("Changed files"));
+    }
+
+    #[test]
+    fn test_aggregate_by_module() {
+        let files = vec![
+            FileStats::new("lib.rs", 100, 40),
+            FileStats::new("generated/a.rs", 1000, 200),
+            FileStats::new("generated/b.rs", 1000, 200),
+            FileStats::new("api/mod.rs", 400, 160),
+        ];
+        let modules = aggregate_by_module(&files);
+
+        assert_eq!(modules.len(), 3);
+        assert_eq!(modules[0].module, "generated");
+        assert_eq!(modules[0].files, 2);
+        assert_eq!(modules[0].tokens(), 100);
+        assert_eq!(modules[0].reduction_percentage(), 80.0);
+        assert_eq!(modules[2].module, ".");
+
+        let report = format_modules(&modules);
+        assert!(
+            report.contains("  generated: 2 files, 100 tokens, 80.0% reduction, 66.7% of context")
+        );
+        assert!(report.contains("  .: 1 files, 10 tokens, 60.0% reduction, 6.7% of context"));
+    }
+
+    #[test]
+    fn test_render_stats() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+        assert_eq!(format_size(512), "512 bytes");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(300 * 1024 * 1024), "300.0 MiB");
+
+        let mut stats = ProcessingStats::default();
+        stats.record(FileStats::new("src/lib.rs", 8192, 4096));
+        let models = [ModelContext {
+            name: "small".to_string(),
+            context_window: 1_000,
+        }];
+        let text = render(&stats, &models, StatsFormat::Text);
+        assert!(text.contains("Total input size: 8.0 KiB\n"));
+        assert!(text.contains("Estimated tokens: 1,024\n"));
+
+        let table = render(&stats, &models, StatsFormat::Table);
+        assert!(table.contains("Total output size  4.0 KiB\nSize reduction     50.0%\n"));
+        assert!(table.contains("Model  Window    Used  Fits\nsmall   1,000  102.4%    no\n"));
+    }
+
+    #[test]
+    fn test_compare_from_empty() {
+        let report = compare(&Manifest::default(), &manifest(&[("lib.rs", 400)]));
+        assert!(report.contains("Tokens: 0 -> 100 (+100, n/a)"));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::processor::{FileStats, ProcessingStats};
+use crate::summary::{one_line, signature, type_string};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+use syn::{ImplItem, Item, Visibility};
+
+/// Name of the structural report written by `--summary-only`
+pub const MAP_FILE_NAME: &str = "code_context_map.txt";
+
+/// Renders the file tree with token estimates, item counts by kind, and the public API of
+/// the processed files, without any of their code
+pub fn render(input: &Path, stats: &ProcessingStats) -> Result<String> {
+    let mut report = format!(
+        "Files: {}, estimated tokens: {}\n\nTree:\n{}",
+        stats.files_processed,
+        stats.tokens(),
+        tree(&stats.files)
+    );
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for item in stats.files.iter().flat_map(|file| &file.items) {
+        *counts.entry(item.kind.as_str()).or_default() += 1;
+    }
+    report.push_str("\nItems:\n");
+    for (kind, count) in counts {
+        let _ = writeln!(report, "  {}: {}", kind, count);
+    }
+
+    report.push_str("\nPublic API:\n");
+    for file in &stats.files {
+        let path = if input.is_file() {
+            input.to_path_buf()
+        } else {
+            input.join(&file.path)
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let Ok(ast) = syn::parse_file(&content) else {
+            continue;
+        };
+        let api = public_api(&ast.items);
+        if !api.is_empty() {
+            let _ = writeln!(report, "  {}", file.path);
+            for line in api {
+                let _ = writeln!(report, "    {}", line);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Renders the processed files as an indented tree, with the token estimate of each file
+fn tree(files: &[FileStats]) -> String {
+    let mut files: Vec<_> = files.iter().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut tree = String::new();
+    let mut printed_dirs = BTreeSet::new();
+    for file in files {
+        let path = Path::new(&file.path);
+        let dirs: Vec<_> = path.parent().into_iter().flat_map(Path::iter).collect();
+        for depth in 0..dirs.len() {
+            let dir = dirs[..=depth].iter().collect::<std::path::PathBuf>();
+            if printed_dirs.insert(dir) {
+                let _ = writeln!(
+                    tree,
+                    "{}{}/",
+                    "  ".repeat(depth + 1),
+                    dirs[depth].to_string_lossy()
+                );
+            }
+        }
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        let _ = writeln!(
+            tree,
+            "{}{}  {} tokens",
+            "  ".repeat(dirs.len() + 1),
+            name.to_string_lossy(),
+            file.tokens
+        );
+    }
+    tree
+}
+
+/// Lists the public items of a file on one line each, including the public methods of
+/// inherent impls and the items of inline modules
+pub fn public_api(items: &[Item]) -> Vec<String> {
+    let is_public = |vis: &Visibility| matches!(vis, Visibility::Public(_));
+    let mut api = Vec::new();
+    for item in items {
+        match item {
+            Item::Impl(item) if item.trait_.is_none() => {
+                let self_ty = type_string(&item.self_ty);
+                for impl_item in &item.items {
+                    if let ImplItem::Fn(method) = impl_item {
+                        if is_public(&method.vis) {
+                            api.push(format!("impl {}: pub {}", self_ty, signature(&method.sig)));
+                        }
+                    }
+                }
+            }
+            Item::Mod(module) if is_public(&module.vis) => {
+                api.push(format!("pub mod {}", module.ident));
+                if let Some((_, items)) = &module.content {
+                    api.extend(
+                        public_api(items)
+                            .into_iter()
+                            .map(|line| format!("{}::{}", module.ident, line)),
+                    );
+                }
+            }
+            _ if item_visibility(item).is_some_and(is_public) => {
+                api.push(one_line(strip_body(item.clone())));
+            }
+            _ => {}
+        }
+    }
+    api
+}
+
+/// Visibility of the items listed in the public API
+pub fn item_visibility(item: &Item) -> Option<&Visibility> {
+    match item {
+        Item::Fn(item) => Some(&item.vis),
+        Item::Struct(item) => Some(&item.vis),
+        Item::Enum(item) => Some(&item.vis),
+        Item::Union(item) => Some(&item.vis),
+        Item::Trait(item) => Some(&item.vis),
+        Item::Type(item) => Some(&item.vis),
+        Item::Const(item) => Some(&item.vis),
+        Item::Static(item) => Some(&item.vis),
+        _ => None,
+    }
+}
+
+/// Drops what follows the declaration of an item: attributes, fields, variants, trait
+/// items, and function bodies
+fn strip_body(mut item: Item) -> Item {
+    match &mut item {
+        Item::Fn(item) => {
+            item.attrs.clear();
+            item.block.stmts.clear();
+        }
+        Item::Struct(item) => {
+            item.attrs.clear();
+            item.fields = syn::Fields::Unit;
+        }
+        Item::Enum(item) => {
+            item.attrs.clear();
+            item.variants.clear();
+        }
+        Item::Union(item) => {
+            item.attrs.clear();
+            item.fields.named.clear();
+        }
+        Item::Trait(item) => {
+            item.attrs.clear();
+            item.items.clear();
+        }
+        Item::Type(item) => item.attrs.clear(),
+        Item::Const(item) => {
+            item.attrs.clear();
+            *item.expr = syn::parse_quote!(..);
+        }
+        Item::Static(item) => {
+            item.attrs.clear();
+            *item.expr = syn::parse_quote!(..);
+        }
+        _ => {}
+    }
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item_ids::collect_items;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_structure() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input = temp_dir.path();
+        std::fs::create_dir(input.join("api"))?;
+        let lib = "/// Settings\n#[derive(Debug)]\npub struct Config { pub name: String }\nimpl Config {\n    pub fn load(path: &str) -> Config { todo!() }\n    fn parse() {}\n}\nfn private() {}\npub mod api;\n";
+        let api = "pub async fn handler() -> u16 { 200 }\npub const LIMIT: usize = 10;\n";
+        std::fs::write(input.join("lib.rs"), lib)?;
+        std::fs::write(input.join("api/mod.rs"), api)?;
+
+        let mut stats = ProcessingStats::default();
+        stats.record(FileStats::new("lib.rs", 100, 80).with_items(collect_items("app", None, lib)));
+        stats.record(
+            FileStats::new("api/mod.rs", 40, 40).with_items(collect_items("app", None, api)),
+        );
+
+        let report = render(input, &stats)?;
+        assert!(report.starts_with("Files: 2, estimated tokens: 30\n\nTree:\n  api/\n    mod.rs  10 tokens\n  lib.rs  20 tokens\n"));
+        assert!(report.contains("\nItems:\n  const: 1\n  fn: 4\n"));
+        assert!(report.contains("  lib.rs\n    pub struct Config\n    impl Config: pub fn load(path: &str) -> Config\n    pub mod api\n"));
+        assert!(report.contains(
+            "  api/mod.rs\n    pub async fn handler() -> u16\n    pub const LIMIT: usize = ..\n"
+        ));
+        assert!(!report.contains("private"));
+        assert!(!report.contains("parse"));
+        Ok(())
+    }
+}
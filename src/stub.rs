@@ -0,0 +1,135 @@
+use crate::git::item_label;
+use crate::script;
+use crate::structure::{item_visibility, public_api};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use syn::{Attribute, Expr, ImplItem, Item, Lit, Meta, Visibility};
+
+/// Renders the stub a module is reduced to when its code is cut: the summary of its docs,
+/// the signatures of its public items, and how many private items of each kind it has
+pub fn render(content: &str) -> String {
+    let Ok(file) = syn::parse_file(script::split_header(content).1) else {
+        return String::new();
+    };
+    let mut stub = String::new();
+    if let Some(summary) = doc_summary(&file.attrs) {
+        let _ = writeln!(stub, "// Summary: {}", summary);
+    }
+    let api = public_api(&file.items);
+    if !api.is_empty() {
+        stub.push_str("// Public items:\n");
+        for line in api {
+            let _ = writeln!(stub, "//   {}", line);
+        }
+    }
+    let mut counts = BTreeMap::new();
+    count_private(&file.items, &mut counts);
+    if !counts.is_empty() {
+        let counts: Vec<_> = counts
+            .into_iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        let _ = writeln!(stub, "// Private items: {}", counts.join(", "));
+    }
+    stub
+}
+
+/// Returns the first paragraph of the inner docs of a module on one line
+fn doc_summary(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<_> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(text) => Some(text.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|text| text.split('\n').map(str::to_string).collect::<Vec<_>>())
+        .map(|line| line.trim().to_string())
+        .skip_while(String::is_empty)
+        .take_while(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// Counts the items left out of the public API by kind, including the private methods of
+/// inherent impls and the items of inline modules; trait impls and imports are not counted
+fn count_private(items: &[Item], counts: &mut BTreeMap<String, usize>) {
+    let is_public = |vis: &Visibility| matches!(vis, Visibility::Public(_));
+    for item in items {
+        match item {
+            Item::Impl(item) if item.trait_.is_none() => {
+                for impl_item in &item.items {
+                    if let ImplItem::Fn(method) = impl_item {
+                        if !is_public(&method.vis) {
+                            *counts.entry("fn".to_string()).or_default() += 1;
+                        }
+                    }
+                }
+            }
+            Item::Impl(_) => {}
+            Item::Mod(module) => {
+                if !is_public(&module.vis) {
+                    *counts.entry("mod".to_string()).or_default() += 1;
+                }
+                if let Some((_, items)) = &module.content {
+                    count_private(items, counts);
+                }
+            }
+            _ if item_visibility(item).is_some_and(is_public) => {}
+            _ => {
+                let kind = item_label(item)
+                    .and_then(|label| label.split_once(' ').map(|(kind, _)| kind.to_string()));
+                if let Some(kind) = kind {
+                    *counts.entry(kind).or_default() += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_stub() {
+        let content = r#"
+//! Parses configuration files.
+//!
+//! Supports TOML and JSON.
+
+pub struct Config;
+
+impl Config {
+    pub fn load() -> Config { Config }
+    fn validate(&self) {}
+}
+
+impl Default for Config {
+    fn default() -> Self { Config }
+}
+
+fn helper() {}
+const LIMIT: usize = 3;
+mod detail {
+    fn inner() {}
+}
+"#;
+        assert_eq!(
+            render(content),
+            "// Summary: Parses configuration files.\n\
+             // Public items:\n\
+             //   pub struct Config\n\
+             //   impl Config: pub fn load() -> Config\n\
+             // Private items: 1 const, 3 fn, 1 mod\n"
+        );
+        assert_eq!(render("fn main() {}\n"), "// Private items: 1 fn\n");
+        assert_eq!(render("fn broken("), "");
+    }
+}
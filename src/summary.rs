@@ -0,0 +1,151 @@
+use crate::{endpoints, features, hierarchy, paths, type_usage};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// Name of the summary file written next to per-file outputs
+pub const SUMMARY_FILE_NAME: &str = "code_context_summary.txt";
+
+/// Crate-wide analyses that can be emitted at the top of the context
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummarySection {
+    /// HTTP endpoints of axum, actix-web, and rocket services
+    Endpoints,
+    /// Public items that require cargo features
+    Features,
+    /// Supertraits, implementors, and blanket impls of traits
+    Traits,
+    /// Types of the crate ranked by how often they appear in signatures
+    Types,
+}
+
+impl SummarySection {
+    fn title(&self) -> &'static str {
+        match self {
+            SummarySection::Endpoints => "HTTP endpoints",
+            SummarySection::Features => "Feature-gated API",
+            SummarySection::Traits => "Trait hierarchy",
+            SummarySection::Types => "Most used types",
+        }
+    }
+
+    fn lines(&self, sources: &[SourceFile]) -> Vec<String> {
+        match self {
+            SummarySection::Endpoints => endpoints::find_endpoints(sources)
+                .iter()
+                .map(endpoints::Endpoint::to_string)
+                .collect(),
+            SummarySection::Features => features::find_gated_items(sources)
+                .iter()
+                .map(features::GatedItem::to_string)
+                .collect(),
+            SummarySection::Traits => hierarchy::describe_traits(sources),
+            SummarySection::Types => type_usage::most_used_types(sources),
+        }
+    }
+}
+
+/// A parsed source file of the processed crate
+pub struct SourceFile {
+    /// Path relative to the input directory
+    pub path: String,
+    pub ast: syn::File,
+}
+
+/// Parses the given Rust files of the input, skipping files that don't parse
+pub fn load_sources(input: &Path, files: &[PathBuf]) -> Result<Vec<SourceFile>> {
+    let mut sources = Vec::new();
+    for file in files {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read file: {}", file.display()))?;
+        let Ok(ast) = syn::parse_file(&content) else {
+            continue;
+        };
+        let path = match file.strip_prefix(input) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => file,
+        };
+        sources.push(SourceFile {
+            path: paths::slash(path),
+            ast,
+        });
+    }
+    Ok(sources)
+}
+
+/// Renders the requested sections as a comment block, omitting empty sections
+pub fn render(sections: &[SummarySection], sources: &[SourceFile]) -> String {
+    let mut summary = String::new();
+    for section in sections {
+        let lines = section.lines(sources);
+        if lines.is_empty() {
+            continue;
+        }
+        summary.push_str(&format!("// {}:\n", section.title()));
+        for line in lines {
+            summary.push_str(&format!("//   {}\n", line));
+        }
+        summary.push('\n');
+    }
+    summary
+}
+
+/// Renders an item without its body on a single line
+pub fn one_line(item: syn::Item) -> String {
+    let file = syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item],
+    };
+    prettyplease::unparse(&file)
+        .trim_end()
+        .trim_end_matches("{}")
+        .trim_end_matches(';')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .trim_end_matches(',')
+        .to_string()
+}
+
+/// Renders a function signature without attributes or body on a single line
+pub fn signature(sig: &syn::Signature) -> String {
+    one_line(syn::parse_quote!(#sig {}))
+}
+
+/// Renders a type or path on a single line
+pub fn type_string(ty: &impl quote::ToTokens) -> String {
+    one_line(syn::parse_quote!(type T = #ty;))
+        .trim_start_matches("type T = ")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_signature() -> Result<()> {
+        let item: syn::ItemFn = syn::parse_str(
+            "#[get(\"/\")] pub async fn index<'a>(name: &'a str) -> Result<String, Error> { todo!() }",
+        )?;
+        assert_eq!(
+            signature(&item.sig),
+            "async fn index<'a>(name: &'a str) -> Result<String, Error>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_skips_empty_sections() -> Result<()> {
+        let sources = vec![SourceFile {
+            path: "lib.rs".to_string(),
+            ast: syn::parse_str("pub fn plain() {}")?,
+        }];
+        assert_eq!(render(&[SummarySection::Endpoints], &sources), "");
+        Ok(())
+    }
+}
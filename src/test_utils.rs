@@ -1,7 +1,11 @@
 #[cfg(test)]
+use crate::compression::{Compression, Encoder};
+#[cfg(test)]
 use crate::transformer::{CodeTransformer, RustAnalyzer};
 #[cfg(test)]
-use anyhow::Result;
+use anyhow::{Context, Result};
+#[cfg(test)]
+use std::io::Write;
 
 #[cfg(test)]
 /// Helper function to process a string of Rust code
@@ -18,3 +22,13 @@ pub fn process_code(code: &str, no_comments: bool, no_function_bodies: bool) ->
 
     Ok(output)
 }
+
+#[cfg(test)]
+/// Helper function to compress bytes the way the combined output is written
+pub fn compress(compression: Compression, data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = Encoder::new(Some(compression), Vec::new())?;
+    encoder
+        .write_all(data)
+        .context("Failed to compress output")?;
+    encoder.finish()
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Span, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Processing phases that are timed separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Walk,
+    Read,
+    Parse,
+    Transform,
+    Print,
+    Write,
+}
+
+impl Phase {
+    const ALL: [Phase; 6] = [
+        Phase::Walk,
+        Phase::Read,
+        Phase::Parse,
+        Phase::Transform,
+        Phase::Print,
+        Phase::Write,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Walk => "walk",
+            Phase::Read => "read",
+            Phase::Parse => "parse",
+            Phase::Transform => "transform",
+            Phase::Print => "print",
+            Phase::Write => "write",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|phase| phase.name() == name)
+    }
+}
+
+/// Runs a closure in the span of a phase, whose duration the [`TimingLayer`] adds up
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    tracing::trace_span!("phase", phase = phase.name()).in_scope(f)
+}
+
+/// Span of the processing of a single file, whose duration the [`TimingLayer`] records
+pub fn file_span(path: &str) -> Span {
+    tracing::trace_span!("file", path)
+}
+
+/// Takes the durations collected by the timing layer of the current subscriber, leaving
+/// none; without one, nothing was timed
+pub fn take() -> Timings {
+    tracing::dispatcher::get_default(|dispatch| {
+        dispatch
+            .downcast_ref::<TimingLayer>()
+            .map(TimingLayer::take)
+            .unwrap_or_default()
+    })
+}
+
+/// Accumulated durations per phase and per file
+#[derive(Default, Clone, Debug)]
+pub struct Timings {
+    pub phases: BTreeMap<Phase, Duration>,
+    pub files: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    /// Renders phase durations and the slowest files
+    pub fn report(&self, slowest: usize) -> String {
+        let mut report = String::new();
+        let total: Duration = self.phases.values().sum();
+        for (phase, duration) in &self.phases {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            let _ = writeln!(
+                report,
+                "  {:<10} {:>10.3} ms ({:.1}%)",
+                phase.name(),
+                duration.as_secs_f64() * 1000.0,
+                share
+            );
+        }
+
+        let mut files = self.files.clone();
+        files.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        if !files.is_empty() {
+            let _ = writeln!(report, "\nSlowest files:");
+            for (path, duration) in files.iter().take(slowest) {
+                let _ = writeln!(
+                    report,
+                    "  {:>10.3} ms  {}",
+                    duration.as_secs_f64() * 1000.0,
+                    path
+                );
+            }
+        }
+        report
+    }
+}
+
+/// What a timed span measures
+enum Timed {
+    Phase(Phase),
+    File(String),
+}
+
+/// Start of a timed span, kept in its extensions until it closes
+struct Started {
+    timed: Timed,
+    at: Instant,
+}
+
+/// Reads the value of the one field of a timed span
+#[derive(Default)]
+struct FieldValue(Option<String>);
+
+impl Visit for FieldValue {
+    fn record_str(&mut self, _field: &Field, value: &str) {
+        self.0 = Some(value.to_string());
+    }
+
+    fn record_debug(&mut self, _field: &Field, value: &dyn std::fmt::Debug) {
+        self.0 = Some(format!("{:?}", value));
+    }
+}
+
+/// Adds up the durations of the phase and file spans of [`time`] and [`file_span`], from
+/// any thread, until they are taken with [`take`]
+#[derive(Clone, Default)]
+pub struct TimingLayer {
+    timings: Arc<Mutex<Timings>>,
+}
+
+impl TimingLayer {
+    fn take(&self) -> Timings {
+        std::mem::take(&mut *self.timings.lock().expect("a timed thread panicked"))
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimingLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let metadata = attrs.metadata();
+        if metadata.target() != module_path!() {
+            return;
+        }
+        let mut value = FieldValue::default();
+        attrs.record(&mut value);
+        let timed = match (metadata.name(), value.0) {
+            ("phase", Some(name)) => Phase::from_name(&name).map(Timed::Phase),
+            ("file", Some(path)) => Some(Timed::File(path)),
+            _ => None,
+        };
+        if let (Some(timed), Some(span)) = (timed, ctx.span(id)) {
+            span.extensions_mut().insert(Started {
+                timed,
+                at: Instant::now(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(started) = ctx
+            .span(&id)
+            .and_then(|span| span.extensions_mut().remove::<Started>())
+        else {
+            return;
+        };
+        let elapsed = started.at.elapsed();
+        let mut timings = self.timings.lock().expect("a timed thread panicked");
+        match started.timed {
+            Timed::Phase(phase) => *timings.phases.entry(phase).or_default() += elapsed,
+            Timed::File(path) => timings.files.push((path, elapsed)),
+        }
+    }
+}
+
+/// Peak resident memory of the process in bytes, where the platform reports it (Linux)
+pub fn peak_memory() -> Option<u64> {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| parse_peak_memory(&status))
+}
+
+/// Reads the `VmHWM` (high water mark) line of `/proc/self/status`, which is in kB
+fn parse_peak_memory(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes = line["VmHWM:".len()..].trim().strip_suffix("kB")?;
+    kilobytes.trim().parse::<u64>().ok().map(|kb| kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_layer() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::registry().with(TimingLayer::default());
+        let timings = tracing::subscriber::with_default(subscriber, || {
+            let file = file_span("src/lib.rs").entered();
+            assert_eq!(time(Phase::Parse, || 42), 42);
+            time(Phase::Print, || {});
+            drop(file);
+            // Spans of other targets aren't timed
+            tracing::info_span!("phase", phase = "parse").in_scope(|| {});
+            take()
+        });
+        assert_eq!(
+            timings.phases.keys().collect::<Vec<_>>(),
+            [&Phase::Parse, &Phase::Print]
+        );
+        assert_eq!(timings.files.len(), 1);
+        assert_eq!(timings.files[0].0, "src/lib.rs");
+        assert!(timings.files[0].1 >= timings.phases[&Phase::Parse]);
+        // Nothing is timed without the layer
+        assert!(take().phases.is_empty());
+    }
+
+    #[test]
+    fn test_timings_report() {
+        let mut timings = Timings::default();
+        timings
+            .phases
+            .insert(Phase::Parse, Duration::from_millis(30));
+        timings
+            .phases
+            .insert(Phase::Print, Duration::from_millis(10));
+        timings
+            .files
+            .push(("fast.rs".to_string(), Duration::from_millis(1)));
+        timings
+            .files
+            .push(("slow.rs".to_string(), Duration::from_millis(20)));
+
+        let report = timings.report(1);
+        assert!(report.contains("parse"));
+        assert!(report.contains("(75.0%)"));
+        assert!(report.contains("slow.rs"));
+        assert!(!report.contains("fast.rs"));
+    }
+
+    #[test]
+    fn test_parse_peak_memory() {
+        let status = "Name:\tcode-context\nVmPeak:\t  20480 kB\nVmHWM:\t    2048 kB\n";
+        assert_eq!(parse_peak_memory(status), Some(2048 * 1024));
+        assert_eq!(parse_peak_memory("Name:\tcode-context\n"), None);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::processor::estimate_tokens;
+use crate::script;
+use crate::stats::format_count;
+use std::collections::BTreeMap;
+use syn::spanned::Spanned;
+use syn::{ImplItem, Item};
+
+/// Appends a trailing `// ~142 tokens` comment to the last line of each item, including the
+/// items of inline modules and impls, counting its doc comments and attributes; imports and
+/// output that doesn't parse are left unchanged
+pub fn annotate(output: &str) -> String {
+    let (header, code) = script::split_header(output);
+    let Ok(file) = syn::parse_file(code) else {
+        return output.to_string();
+    };
+    // The outermost item ending on a line names the cost of the line
+    let mut costs = BTreeMap::new();
+    collect(&file.items, &mut costs);
+
+    let mut annotated = String::with_capacity(output.len());
+    annotated.push_str(header);
+    for (index, line) in code.split_inclusive('\n').enumerate() {
+        match costs.get(&(index + 1)) {
+            Some(tokens) => {
+                let text = line.trim_end_matches(['\n', '\r']);
+                annotated.push_str(text);
+                annotated.push_str(&format!(" // ~{} tokens", format_count(*tokens)));
+                annotated.push_str(&line[text.len()..]);
+            }
+            None => annotated.push_str(line),
+        }
+    }
+    annotated
+}
+
+/// Records the estimated tokens of each item by the line it ends on
+fn collect(items: &[Item], costs: &mut BTreeMap<usize, usize>) {
+    for item in items {
+        match item {
+            Item::Use(_) | Item::ExternCrate(_) | Item::Verbatim(_) => continue,
+            Item::Mod(module) if module.content.is_none() => continue,
+            _ => {}
+        }
+        record(item.span(), costs);
+        match item {
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect(items, costs);
+                }
+            }
+            Item::Impl(item) => {
+                for impl_item in &item.items {
+                    if matches!(
+                        impl_item,
+                        ImplItem::Fn(_) | ImplItem::Const(_) | ImplItem::Type(_)
+                    ) {
+                        record(impl_item.span(), costs);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn record(span: proc_macro2::Span, costs: &mut BTreeMap<usize, usize>) {
+    costs
+        .entry(span.end().line)
+        .or_insert_with(|| estimate_tokens(span.byte_range().len()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_token_costs() {
+        let output = "use std::fmt;\n/// A setting\npub struct Config;\nimpl Config {\n    pub fn load() -> Self {\n        Config\n    }\n}\nmod api;\n";
+        assert_eq!(
+            annotate(output),
+            "use std::fmt;\n/// A setting\npub struct Config; // ~8 tokens\nimpl Config {\n    pub fn load() -> Self {\n        Config\n    } // ~11 tokens\n} // ~16 tokens\nmod api;\n"
+        );
+        assert_eq!(annotate("fn broken("), "fn broken(");
+    }
+}
@@ -1,12 +1,30 @@
+use crate::queries;
+use crate::removals::Removals;
+use crate::visibility::VisibilityLevel;
 use anyhow::{Context, Result};
 use quote::ToTokens;
+use std::collections::HashSet;
+use std::sync::Arc;
+use syn::spanned::Spanned;
 use syn::{
     parse_quote,
     visit_mut::{self, VisitMut},
-    Attribute, File, GenericArgument, ImplItem, Item, ItemMod, ItemTrait, PathArguments,
-    ReturnType, TraitItem, Type, TypePath,
+    AttrStyle, Attribute, File, GenericArgument, ImplItem, Item, ItemImpl, ItemMod, ItemTrait,
+    Meta, PathArguments, ReturnType, TraitItem, Type, TypePath,
 };
 
+/// Comment that stands for the non-public fields of a struct, as rustdoc shows them
+pub(crate) const PRIVATE_FIELDS_MARKER: &str = "/* private fields */";
+
+/// Field or type name that stands for the private fields marker until printing
+const PRIVATE_FIELDS_PLACEHOLDER: &str = "__CodeContextPrivateFields";
+
+/// Macro that stands for an item kept verbatim until printing, with its index as argument
+const VERBATIM_PLACEHOLDER: &str = "__code_context_verbatim";
+
+/// Comment printed before an item syn doesn't parse, such as new or unstable syntax
+pub(crate) const VERBATIM_MARKER: &str = "// Not parsed by code-context, kept verbatim:";
+
 pub struct RustAnalyzer {
     pub ast: File,
 }
@@ -21,7 +39,7 @@ impl RustAnalyzer {
     }
 
     /// Checks if a type is string-like, or a Result/Option containing a string-like type
-    fn is_string_or_json_type(ty: &Type) -> bool {
+    pub(crate) fn is_string_or_json_type(ty: &Type) -> bool {
         match ty {
             Type::Path(type_path) => {
                 let path_str = type_path.path.to_token_stream().to_string();
@@ -62,9 +80,149 @@ impl RustAnalyzer {
     }
 }
 
+/// Default doc line added to required trait methods
+pub(crate) const REQUIRED_METHOD_DOC: &str = "This is a required method";
+/// Default doc line added to trait methods with a default implementation
+pub(crate) const DEFAULT_METHOD_DOC: &str = "There is a default implementation";
+
+/// Doc lines that mark trait methods as required or provided
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraitAnnotations {
+    pub required: String,
+    pub default: String,
+}
+
+impl Default for TraitAnnotations {
+    fn default() -> Self {
+        Self {
+            required: REQUIRED_METHOD_DOC.to_string(),
+            default: DEFAULT_METHOD_DOC.to_string(),
+        }
+    }
+}
+
+impl TraitAnnotations {
+    /// Returns the annotation for a trait method, depending on whether it has a default body
+    pub fn for_method(&self, has_default: bool) -> &str {
+        if has_default {
+            &self.default
+        } else {
+            &self.required
+        }
+    }
+}
+
+/// Attribute macros that only decorate items and are never treated as test markers
+pub(crate) const TRANSPARENT_ATTRIBUTES: &[&str] = &[
+    "async_trait",
+    "async_trait::async_trait",
+    "axum::debug_handler",
+    "tracing::instrument",
+];
+
+/// Decides which items are removed as test or benchmark code, for their visibility, or as
+/// impls of skipped traits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StripRules {
+    pub keep_benches: bool,
+    /// Attribute macros kept as-is and ignored by the test heuristics, by path or name
+    pub transparent_attributes: Vec<String>,
+    /// Lowest visibility of the kept items
+    pub visibility: VisibilityLevel,
+    /// Traits whose impls are dropped, by path or name
+    pub skipped_traits: Vec<String>,
+}
+
+impl Default for StripRules {
+    fn default() -> Self {
+        Self {
+            keep_benches: false,
+            transparent_attributes: TRANSPARENT_ATTRIBUTES
+                .iter()
+                .map(|attr| attr.to_string())
+                .collect(),
+            visibility: VisibilityLevel::All,
+            skipped_traits: Vec::new(),
+        }
+    }
+}
+
+/// Decides which function bodies are kept when function bodies are removed, shared by free
+/// functions, impl methods, and default trait methods
+#[derive(Clone, Debug, Default)]
+pub struct BodyRules {
+    /// Keeps the bodies of proc-macro entry points and `TokenStream` functions
+    pub proc_macro: bool,
+    /// Keeps the bodies of functions that run SQL queries
+    pub keep_queries: bool,
+    /// Type aliases resolved to string-like types, shared by the transformers of all files
+    pub string_aliases: Arc<HashSet<String>>,
+}
+
+impl BodyRules {
+    /// Checks if the body of a function is kept: it returns a string-like type or alias, is
+    /// part of a proc-macro crate's API, or runs a SQL query
+    pub(crate) fn keeps_body(
+        &self,
+        attrs: &[Attribute],
+        sig: &syn::Signature,
+        block: &syn::Block,
+    ) -> bool {
+        CodeTransformer::analyze_return_type(&sig.output)
+            || CodeTransformer::returns_string_alias(&sig.output, &self.string_aliases)
+            || (self.proc_macro && CodeTransformer::is_proc_macro_api(attrs, sig))
+            || (self.keep_queries && queries::contains_query(block))
+    }
+}
+
+impl StripRules {
+    /// Checks if an attribute is listed as transparent, by its full path or its last segment
+    fn is_transparent(&self, attr: &Attribute) -> bool {
+        let path = attr.path();
+        let full_path = quote::quote!(#path).to_string().replace(' ', "");
+        let name = path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string());
+        self.transparent_attributes
+            .iter()
+            .any(|transparent| *transparent == full_path || Some(transparent) == name.as_ref())
+    }
+
+    /// Checks if an impl is of a skipped trait, matching its path without generic arguments
+    /// or its last segment
+    fn skips_impl(&self, item_impl: &ItemImpl) -> bool {
+        let Some((_, path, _)) = &item_impl.trait_ else {
+            return false;
+        };
+        let full_path = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>()
+            .join("::");
+        let name = path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string());
+        self.skipped_traits
+            .iter()
+            .any(|skipped| *skipped == full_path || Some(skipped) == name.as_ref())
+    }
+}
+
 pub struct CodeTransformer {
     no_comments: bool,
     no_function_bodies: bool,
+    trait_annotations: Option<TraitAnnotations>,
+    keep_trait_defaults: bool,
+    strip_rules: StripRules,
+    body_rules: BodyRules,
+    strip_inner_attributes: bool,
+    hide_private_fields: bool,
+    removals: Removals,
+    /// Source of the items syn keeps as verbatim tokens, by placeholder index
+    verbatim_items: Vec<String>,
 }
 
 impl CodeTransformer {
@@ -73,11 +231,209 @@ impl CodeTransformer {
         Self {
             no_comments,
             no_function_bodies,
+            trait_annotations: Some(TraitAnnotations::default()),
+            keep_trait_defaults: false,
+            strip_rules: StripRules::default(),
+            body_rules: BodyRules::default(),
+            strip_inner_attributes: false,
+            hide_private_fields: false,
+            removals: Removals::default(),
+            verbatim_items: Vec::new(),
+        }
+    }
+
+    /// What the transformer removed from the visited file
+    pub fn removals(&self) -> Removals {
+        self.removals
+    }
+
+    /// Keeps the bodies of functions returning type aliases resolved to string-like types,
+    /// shared by the transformers of all files
+    pub fn with_string_aliases(mut self, string_aliases: Arc<HashSet<String>>) -> Self {
+        self.body_rules.string_aliases = string_aliases;
+        self
+    }
+
+    /// Keeps the bodies of functions that run SQL queries
+    pub fn with_keep_queries(mut self, keep_queries: bool) -> Self {
+        self.body_rules.keep_queries = keep_queries;
+        self
+    }
+
+    /// Keeps the bodies of proc-macro entry points and `TokenStream` functions
+    pub fn with_proc_macro(mut self, proc_macro: bool) -> Self {
+        self.body_rules.proc_macro = proc_macro;
+        self
+    }
+
+    /// Removes inner attributes of files and modules other than doc comments
+    pub fn with_strip_inner_attributes(mut self, strip_inner_attributes: bool) -> Self {
+        self.strip_inner_attributes = strip_inner_attributes;
+        self
+    }
+
+    /// Keeps benchmark functions, modules, and criterion harness code
+    pub fn with_keep_benches(mut self, keep_benches: bool) -> Self {
+        self.strip_rules.keep_benches = keep_benches;
+        self
+    }
+
+    /// Adds attribute macros that are kept as-is and never treated as test markers
+    pub fn with_transparent_attributes(mut self, attributes: &[String]) -> Self {
+        self.strip_rules
+            .transparent_attributes
+            .extend_from_slice(attributes);
+        self
+    }
+
+    /// Replaces the non-public fields of structs with a `/* private fields */` marker
+    pub fn with_hide_private_fields(mut self, hide_private_fields: bool) -> Self {
+        self.hide_private_fields = hide_private_fields;
+        self
+    }
+
+    /// Replaces the placeholders left in the printed code by the transformation with
+    /// comments and the source of the items kept verbatim
+    pub fn finish(&self, printed: &str) -> String {
+        let printed = if self.removals.private_fields > 0 {
+            Self::mark_private_fields(printed)
+        } else {
+            printed.to_string()
+        };
+        if self.verbatim_items.is_empty() {
+            return printed;
+        }
+        let mut output = String::with_capacity(printed.len());
+        for line in printed.split_inclusive('\n') {
+            let source = line
+                .trim()
+                .strip_prefix(VERBATIM_PLACEHOLDER)
+                .and_then(|call| call.strip_prefix("!("))
+                .and_then(|call| call.strip_suffix(");"))
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.verbatim_items.get(index));
+            match source {
+                Some(source) => {
+                    let indent = &line[..line.len() - line.trim_start().len()];
+                    output.push_str(&format!(
+                        "{}{}\n{}{}\n",
+                        indent, VERBATIM_MARKER, indent, source
+                    ));
+                }
+                None => output.push_str(line),
+            }
+        }
+        output
+    }
+
+    /// Prints the placeholders of hidden fields as markers: the `name: ()` field inserted on a
+    /// line of its own, or the only field of a tuple struct
+    fn mark_private_fields(printed: &str) -> String {
+        let field = format!("{}: (),", PRIVATE_FIELDS_PLACEHOLDER);
+        let tuple = format!("({})", PRIVATE_FIELDS_PLACEHOLDER);
+        printed
+            .split_inclusive('\n')
+            .map(|line| {
+                if line.trim() == field {
+                    line.replacen(&field, PRIVATE_FIELDS_MARKER, 1)
+                } else {
+                    line.replace(&tuple, &format!("({})", PRIVATE_FIELDS_MARKER))
+                }
+            })
+            .collect()
+    }
+
+    /// Keeps the source of tokens syn couldn't parse into an item, returning the
+    /// placeholder macro call that stands for them until printing
+    fn keep_verbatim<T: syn::parse::Parse>(&mut self, tokens: &proc_macro2::TokenStream) -> T {
+        let source = tokens
+            .span()
+            .source_text()
+            .unwrap_or_else(|| tokens.to_string());
+        self.removals.unknown_items += 1;
+        self.verbatim_items.push(source);
+        let name = syn::Ident::new(VERBATIM_PLACEHOLDER, proc_macro2::Span::call_site());
+        let index = proc_macro2::Literal::usize_unsuffixed(self.verbatim_items.len() - 1);
+        parse_quote!(#name!(#index);)
+    }
+
+    /// Removes items with a visibility below the given level
+    pub fn with_visibility(mut self, visibility: VisibilityLevel) -> Self {
+        self.strip_rules.visibility = visibility;
+        self
+    }
+
+    /// Removes the impls of the given traits, by path or name
+    pub fn with_skipped_trait_impls(mut self, traits: &[String]) -> Self {
+        self.strip_rules.skipped_traits = traits.to_vec();
+        self
+    }
+
+    /// Keeps default trait method bodies even when function bodies are removed
+    pub fn with_keep_trait_defaults(mut self, keep_trait_defaults: bool) -> Self {
+        self.keep_trait_defaults = keep_trait_defaults;
+        self
+    }
+
+    /// Checks if the default body of a trait method should be removed
+    pub(crate) fn should_remove_trait_default(
+        method: &syn::TraitItemFn,
+        no_function_bodies: bool,
+        keep_trait_defaults: bool,
+        body_rules: &BodyRules,
+    ) -> bool {
+        method.default.as_ref().is_some_and(|default| {
+            no_function_bodies
+                && !keep_trait_defaults
+                && !body_rules.keeps_body(&method.attrs, &method.sig, default)
+        })
+    }
+
+    /// Processes trait comments, default method bodies, and method annotations
+    fn transform_trait(&mut self, item_trait: &mut ItemTrait) {
+        // Process trait-level comments
+        self.process_attributes(&mut item_trait.attrs);
+
+        // Process trait methods
+        for trait_item in &mut item_trait.items {
+            if let TraitItem::Verbatim(tokens) = trait_item {
+                *trait_item = self.keep_verbatim(tokens);
+                continue;
+            }
+            if let TraitItem::Fn(method) = trait_item {
+                // First process the attributes
+                self.process_attributes(&mut method.attrs);
+
+                // Then handle the default implementation
+                if Self::should_remove_trait_default(
+                    method,
+                    self.no_function_bodies,
+                    self.keep_trait_defaults,
+                    &self.body_rules,
+                ) {
+                    if let Some(default) = &mut method.default {
+                        self.elide_body(default);
+                    }
+                }
+            }
+
+            // Finally add the trait method comment
+            Self::add_trait_method_comment(
+                trait_item,
+                self.no_comments,
+                self.trait_annotations.as_ref(),
+            );
         }
     }
 
+    /// Sets the doc lines added to trait methods, or disables them with `None`
+    pub fn with_trait_annotations(mut self, trait_annotations: Option<TraitAnnotations>) -> Self {
+        self.trait_annotations = trait_annotations;
+        self
+    }
+
     /// Gets attributes from any Item type
-    fn get_attrs(item: &Item) -> &[Attribute] {
+    pub(crate) fn get_attrs(item: &Item) -> &[Attribute] {
         match item {
             Item::Fn(f) => &f.attrs,
             Item::Mod(m) => &m.attrs,
@@ -98,65 +454,200 @@ impl CodeTransformer {
         }
     }
 
-    /// Gets mutable attributes from any Item type
-    fn get_attrs_mut(item: &mut Item) -> &mut Vec<Attribute> {
+    /// Gets mutable attributes from any Item type, or `None` for kinds syn doesn't parse
+    /// into a syntax tree, such as `Item::Verbatim`
+    fn get_attrs_mut(item: &mut Item) -> Option<&mut Vec<Attribute>> {
         match item {
-            Item::Fn(f) => &mut f.attrs,
-            Item::Mod(m) => &mut m.attrs,
-            Item::Struct(s) => &mut s.attrs,
-            Item::Enum(e) => &mut e.attrs,
-            Item::Trait(t) => &mut t.attrs,
-            Item::Impl(i) => &mut i.attrs,
-            Item::Type(t) => &mut t.attrs,
-            Item::Const(c) => &mut c.attrs,
-            Item::Static(s) => &mut s.attrs,
-            Item::Use(u) => &mut u.attrs,
-            Item::ExternCrate(e) => &mut e.attrs,
-            Item::ForeignMod(f) => &mut f.attrs,
-            Item::Macro(m) => &mut m.attrs,
-            Item::TraitAlias(t) => &mut t.attrs,
-            Item::Union(u) => &mut u.attrs,
-            _ => panic!("Unexpected item type"),
+            Item::Fn(f) => Some(&mut f.attrs),
+            Item::Mod(m) => Some(&mut m.attrs),
+            Item::Struct(s) => Some(&mut s.attrs),
+            Item::Enum(e) => Some(&mut e.attrs),
+            Item::Trait(t) => Some(&mut t.attrs),
+            Item::Impl(i) => Some(&mut i.attrs),
+            Item::Type(t) => Some(&mut t.attrs),
+            Item::Const(c) => Some(&mut c.attrs),
+            Item::Static(s) => Some(&mut s.attrs),
+            Item::Use(u) => Some(&mut u.attrs),
+            Item::ExternCrate(e) => Some(&mut e.attrs),
+            Item::ForeignMod(f) => Some(&mut f.attrs),
+            Item::Macro(m) => Some(&mut m.attrs),
+            Item::TraitAlias(t) => Some(&mut t.attrs),
+            Item::Union(u) => Some(&mut u.attrs),
+            _ => None,
         }
     }
 
-    /// Checks if an item has test-related attributes
-    fn has_test_attribute(attrs: &[Attribute]) -> bool {
+    /// Checks if an item has test-related attributes: `#[test]`, `#[tokio::test]`-style
+    /// test macros, or `#[cfg(test)]`
+    pub(crate) fn has_test_attribute(attrs: &[Attribute], rules: &StripRules) -> bool {
         attrs.iter().any(|attr| {
-            attr.path().is_ident("test")
-                || (attr.path().is_ident("cfg") && Self::is_cfg_test_attribute(attr))
+            !rules.is_transparent(attr)
+                && (attr
+                    .path()
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "test")
+                    || Self::is_cfg_test_attribute(attr))
         })
     }
 
-    /// Checks if an attribute is #[cfg(test)]
+    /// Checks if an attribute is #[cfg(test)], including `test` inside `all`/`any`
+    /// predicates but not inside `not`
     fn is_cfg_test_attribute(attr: &Attribute) -> bool {
         if !attr.path().is_ident("cfg") {
             return false;
         }
 
         match attr.meta {
-            syn::Meta::List(ref list) => list.tokens.to_string().contains("test"),
+            syn::Meta::List(ref list) => Self::cfg_mentions(list.tokens.clone(), "test"),
             _ => false,
         }
     }
 
-    fn should_remove_item(item: &Item) -> bool {
-        let attrs = Self::get_attrs(item);
-        attrs.iter().any(|attr| {
-            attr.path().is_ident("test") || 
-            matches!(attr.meta, syn::Meta::List(ref list) if list.path.is_ident("cfg") && list.tokens.to_string().contains("test"))
-        })
+    /// Checks if a cfg predicate requires the given identifier
+    fn cfg_mentions(tokens: proc_macro2::TokenStream, ident: &str) -> bool {
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                proc_macro2::TokenTree::Ident(name) if name == "not" => {
+                    // Skip the negated predicate
+                    tokens.next();
+                }
+                // `feature = "test"` compares a key to a value, it doesn't require `test`
+                proc_macro2::TokenTree::Ident(name)
+                    if name == ident
+                        && !matches!(tokens.peek(), Some(proc_macro2::TokenTree::Punct(p)) if p.as_char() == '=') =>
+                {
+                    return true;
+                }
+                proc_macro2::TokenTree::Group(group)
+                    if Self::cfg_mentions(group.stream(), ident) =>
+                {
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Gets attributes from any ImplItem type
+    pub(crate) fn get_impl_item_attrs(item: &ImplItem) -> &[Attribute] {
+        match item {
+            ImplItem::Fn(f) => &f.attrs,
+            ImplItem::Const(c) => &c.attrs,
+            ImplItem::Type(t) => &t.attrs,
+            ImplItem::Macro(m) => &m.attrs,
+            _ => &[],
+        }
+    }
+
+    /// Checks if an impl item is test-only code
+    pub(crate) fn is_test_impl_item(item: &ImplItem, rules: &StripRules) -> bool {
+        Self::has_test_attribute(Self::get_impl_item_attrs(item), rules)
+    }
+
+    /// Checks if an item is benchmark code: a `#[bench]` function, a `#[cfg(bench)]` item,
+    /// or part of a criterion harness
+    pub(crate) fn is_bench_item(item: &Item) -> bool {
+        let is_bench_attr = |attr: &Attribute| {
+            attr.path().is_ident("bench")
+                || matches!(attr.meta, syn::Meta::List(ref list) if list.path.is_ident("cfg") && Self::cfg_mentions(list.tokens.clone(), "bench"))
+        };
+        if Self::get_attrs(item).iter().any(is_bench_attr) {
+            return true;
+        }
+
+        match item {
+            Item::Macro(item_macro) => item_macro.mac.path.segments.last().is_some_and(|segment| {
+                segment.ident == "criterion_group" || segment.ident == "criterion_main"
+            }),
+            Item::Use(item_use) => {
+                matches!(&item_use.tree, syn::UseTree::Path(path) if path.ident == "criterion")
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks if an item is test code, or benchmark code unless benchmarks are kept
+    pub(crate) fn is_stripped_item(item: &Item, rules: &StripRules) -> bool {
+        Self::has_test_attribute(Self::get_attrs(item), rules)
+            || (!rules.keep_benches && Self::is_bench_item(item))
+    }
+
+    /// Checks if an item is below the visibility level of the output; imports, impl blocks,
+    /// and macros have no visibility of their own and are kept
+    pub(crate) fn is_hidden_item(item: &Item, rules: &StripRules) -> bool {
+        let vis = match item {
+            Item::Fn(item) => &item.vis,
+            Item::Mod(item) => &item.vis,
+            Item::Struct(item) => &item.vis,
+            Item::Enum(item) => &item.vis,
+            Item::Union(item) => &item.vis,
+            Item::Trait(item) => &item.vis,
+            Item::TraitAlias(item) => &item.vis,
+            Item::Type(item) => &item.vis,
+            Item::Const(item) => &item.vis,
+            Item::Static(item) => &item.vis,
+            _ => return false,
+        };
+        !rules.visibility.keeps(vis)
+    }
+
+    /// Checks if an item is an impl of a trait whose impls are skipped
+    pub(crate) fn is_skipped_impl(item: &Item, rules: &StripRules) -> bool {
+        matches!(item, Item::Impl(item_impl) if rules.skips_impl(item_impl))
+    }
+
+    /// Checks if an item of an inherent impl is below the visibility level of the output;
+    /// items of trait impls are as visible as the trait
+    pub(crate) fn is_hidden_impl_item(
+        item: &ImplItem,
+        item_impl: &ItemImpl,
+        rules: &StripRules,
+    ) -> bool {
+        let vis = match item {
+            ImplItem::Fn(item) => &item.vis,
+            ImplItem::Const(item) => &item.vis,
+            ImplItem::Type(item) => &item.vis,
+            _ => return false,
+        };
+        item_impl.trait_.is_none() && !rules.visibility.keeps(vis)
+    }
+
+    /// Checks if an impl block or module only contains stripped or hidden code, so it would
+    /// be left empty
+    pub(crate) fn becomes_empty(item: &Item, rules: &StripRules) -> bool {
+        match item {
+            Item::Impl(item_impl) => {
+                !item_impl.items.is_empty()
+                    && item_impl.items.iter().all(|item| {
+                        Self::is_test_impl_item(item, rules)
+                            || Self::is_hidden_impl_item(item, item_impl, rules)
+                    })
+            }
+            Item::Mod(item_mod) => item_mod.content.as_ref().is_some_and(|(_, items)| {
+                !items.is_empty()
+                    && items.iter().all(|item| {
+                        Self::is_stripped_item(item, rules)
+                            || Self::is_hidden_item(item, rules)
+                            || Self::is_skipped_impl(item, rules)
+                            || Self::becomes_empty(item, rules)
+                    })
+            }),
+            _ => false,
+        }
     }
 
     /// Checks if an implementation block is derived
-    fn is_derived_implementation(impl_block: &syn::ItemImpl) -> bool {
+    pub(crate) fn is_derived_implementation(impl_block: &syn::ItemImpl) -> bool {
         Self::get_attrs(&Item::Impl(impl_block.clone()))
             .iter()
             .any(|attr| attr.path().is_ident("derive"))
     }
 
     /// Checks if an implementation block is for the Serialize trait
-    fn is_serialize_impl(impl_block: &syn::ItemImpl) -> bool {
+    pub(crate) fn is_serialize_impl(impl_block: &syn::ItemImpl) -> bool {
         if let Some((_, trait_path, _)) = &impl_block.trait_ {
             let trait_str = quote::quote!(#trait_path).to_string();
             trait_str.contains("Serialize")
@@ -165,24 +656,158 @@ impl CodeTransformer {
         }
     }
 
+    /// Checks if a function is part of a proc-macro crate's API: a `#[proc_macro]`,
+    /// `#[proc_macro_derive]`, or `#[proc_macro_attribute]` entry point, or a function
+    /// returning `TokenStream`
+    pub(crate) fn is_proc_macro_api(attrs: &[Attribute], sig: &syn::Signature) -> bool {
+        let is_entry_point = attrs.iter().any(|attr| {
+            attr.path().is_ident("proc_macro")
+                || attr.path().is_ident("proc_macro_derive")
+                || attr.path().is_ident("proc_macro_attribute")
+        });
+        let returns_token_stream = match &sig.output {
+            ReturnType::Type(_, ty) => match ty.as_ref() {
+                Type::Path(TypePath { path, .. }) => path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "TokenStream"),
+                _ => false,
+            },
+            ReturnType::Default => false,
+        };
+        is_entry_point || returns_token_stream
+    }
+
     /// Determines whether a method's body should be preserved
     /// Analyzes return type to determine if it's string-like
-    fn analyze_return_type(ret_type: &ReturnType) -> bool {
+    pub(crate) fn analyze_return_type(ret_type: &ReturnType) -> bool {
         match ret_type {
             ReturnType::Default => false,
             ReturnType::Type(_, ty) => RustAnalyzer::is_string_or_json_type(ty),
         }
     }
 
+    /// Checks if the return type, or the first type argument of a returned
+    /// `Result`/`Option`, is one of the given string-like aliases
+    pub(crate) fn returns_string_alias(ret_type: &ReturnType, aliases: &HashSet<String>) -> bool {
+        fn is_alias(ty: &Type, aliases: &HashSet<String>) -> bool {
+            match ty {
+                Type::Path(TypePath { path, .. }) => {
+                    path.segments.last().is_some_and(|segment| {
+                        aliases.contains(&segment.ident.to_string())
+                            || ((segment.ident == "Result" || segment.ident == "Option")
+                                && matches!(&segment.arguments, PathArguments::AngleBracketed(args)
+                                    if matches!(args.args.first(), Some(GenericArgument::Type(inner)) if is_alias(inner, aliases))))
+                    })
+                }
+                Type::Reference(type_ref) => is_alias(&type_ref.elem, aliases),
+                _ => false,
+            }
+        }
+        match ret_type {
+            ReturnType::Default => false,
+            ReturnType::Type(_, ty) => !aliases.is_empty() && is_alias(ty, aliases),
+        }
+    }
+
     /// Processes attributes based on comment removal flag
-    fn process_attributes(attrs: &mut Vec<Attribute>, no_comments: bool) {
-        if no_comments {
+    fn process_attributes(&mut self, attrs: &mut Vec<Attribute>) {
+        if self.no_comments {
+            let count = attrs.len();
             attrs.retain(|attr| !attr.path().is_ident("doc"));
+            self.removals.doc_comments += count - attrs.len();
+        }
+    }
+
+    /// Checks if an attribute is an inner attribute such as `#![allow(...)]` or
+    /// `#![cfg_attr(...)]`, but not an inner doc comment
+    pub(crate) fn is_stripped_inner_attribute(attr: &Attribute) -> bool {
+        matches!(attr.style, AttrStyle::Inner(_))
+            && !(attr.path().is_ident("doc") && matches!(attr.meta, Meta::NameValue(_)))
+    }
+
+    fn remove_inner_attributes(&mut self, attrs: &mut Vec<Attribute>) {
+        if self.strip_inner_attributes {
+            let count = attrs.len();
+            attrs.retain(|attr| !Self::is_stripped_inner_attribute(attr));
+            self.removals.inner_attributes += count - attrs.len();
+        }
+    }
+
+    /// Checks if the non-public fields of a struct are all replaced by one marker: named
+    /// fields with any non-public one, or tuple fields that are all non-public
+    pub(crate) fn hides_fields(fields: &syn::Fields) -> bool {
+        let is_private = |field: &syn::Field| !matches!(field.vis, syn::Visibility::Public(_));
+        match fields {
+            syn::Fields::Named(fields) => fields.named.iter().any(is_private),
+            syn::Fields::Unnamed(fields) => {
+                !fields.unnamed.is_empty() && fields.unnamed.iter().all(is_private)
+            }
+            syn::Fields::Unit => false,
         }
     }
 
+    /// Replaces the non-public fields of a struct with a placeholder printed as a marker
+    fn hide_fields(&mut self, fields: &mut syn::Fields) {
+        if !Self::hides_fields(fields) {
+            return;
+        }
+        let placeholder =
+            syn::Ident::new(PRIVATE_FIELDS_PLACEHOLDER, proc_macro2::Span::call_site());
+        match fields {
+            syn::Fields::Named(fields) => {
+                let count = fields.named.len();
+                fields.named = std::mem::take(&mut fields.named)
+                    .into_iter()
+                    .filter(|field| matches!(field.vis, syn::Visibility::Public(_)))
+                    .collect();
+                self.removals.private_fields += count - fields.named.len();
+                fields.named.push(parse_quote!(#placeholder: ()));
+            }
+            syn::Fields::Unnamed(fields) => {
+                self.removals.private_fields += fields.unnamed.len();
+                *fields = parse_quote!((#placeholder));
+            }
+            syn::Fields::Unit => {}
+        }
+    }
+
+    /// Replaces a function body with an empty block
+    fn elide_body(&mut self, block: &mut syn::Block) {
+        if !block.stmts.is_empty() {
+            self.removals.bodies += 1;
+        }
+        *block = parse_quote!({});
+    }
+
+    /// Removes test and hidden items, skipped trait impls, and the impl blocks and modules
+    /// they would leave empty
+    fn retain_items(&mut self, items: &mut Vec<Item>) {
+        let rules = &self.strip_rules;
+        let removals = &mut self.removals;
+        items.retain(|item| {
+            if Self::is_stripped_item(item, rules) {
+                removals.test_items += 1;
+            } else if Self::is_hidden_item(item, rules) {
+                removals.hidden_items += 1;
+            } else if Self::is_skipped_impl(item, rules) {
+                removals.skipped_impls += 1;
+            } else if Self::becomes_empty(item, rules) {
+                removals.items += 1;
+            } else {
+                return true;
+            }
+            removals.count_attributes(Self::get_attrs(item));
+            false
+        });
+    }
+
     /// Adds appropriate comments for trait methods
-    fn add_trait_method_comment(trait_item: &mut TraitItem, no_comments: bool) {
+    fn add_trait_method_comment(
+        trait_item: &mut TraitItem,
+        no_comments: bool,
+        annotations: Option<&TraitAnnotations>,
+    ) {
         if let TraitItem::Fn(method) = trait_item {
             if no_comments {
                 // If no_comments is true, remove all doc comments
@@ -190,6 +815,10 @@ impl CodeTransformer {
                 return;
             }
 
+            let Some(annotations) = annotations else {
+                return;
+            };
+
             // First collect all existing doc comments
             let doc_comments = method
                 .attrs
@@ -217,12 +846,8 @@ impl CodeTransformer {
             let mut new_attrs = Vec::new();
 
             // Add the required/default implementation comment first
-            let status_comment = if method.default.is_none() {
-                parse_quote!(#[doc = " This is a required method"])
-            } else {
-                parse_quote!(#[doc = " There is a default implementation"])
-            };
-            new_attrs.push(status_comment);
+            let status = format!(" {}", annotations.for_method(method.default.is_some()));
+            new_attrs.push(parse_quote!(#[doc = #status]));
 
             // Add an empty doc line if there are existing comments
             if !doc_comments.is_empty() {
@@ -244,7 +869,8 @@ impl CodeTransformer {
 impl VisitMut for CodeTransformer {
     fn visit_item_mod_mut(&mut self, node: &mut ItemMod) {
         // Process module attributes
-        Self::process_attributes(&mut node.attrs, self.no_comments);
+        self.process_attributes(&mut node.attrs);
+        self.remove_inner_attributes(&mut node.attrs);
 
         // Process inner items if they exist
         if let Some((_, items)) = &mut node.content {
@@ -256,38 +882,18 @@ impl VisitMut for CodeTransformer {
     }
 
     fn visit_item_trait_mut(&mut self, node: &mut ItemTrait) {
-        // Process trait-level comments if needed
-        if self.no_comments {
-            node.attrs.retain(|attr| !attr.path().is_ident("doc"));
-        }
-
-        // Process trait items
-        for item in &mut node.items {
-            if let TraitItem::Fn(method) = item {
-                // Process method comments if needed
-                if self.no_comments {
-                    method.attrs.retain(|attr| !attr.path().is_ident("doc"));
-                }
-
-                // Clear default implementation bodies
-                if method.default.is_some() {
-                    method.default = Some(parse_quote!({}));
-                }
-            }
-        }
-
+        self.transform_trait(node);
         visit_mut::visit_item_trait_mut(self, node);
     }
 
     /// Visits a file and removes test-related items
     fn visit_file_mut(&mut self, file: &mut syn::File) {
         // Process file-level attributes if no_comments is true
-        if self.no_comments {
-            file.attrs.retain(|attr| !attr.path().is_ident("doc"));
-        }
+        self.process_attributes(&mut file.attrs);
+        self.remove_inner_attributes(&mut file.attrs);
 
         // Remove all test-related items
-        file.items.retain(|item| !Self::should_remove_item(item));
+        self.retain_items(&mut file.items);
 
         // Process remaining items
         for item in &mut file.items {
@@ -297,13 +903,22 @@ impl VisitMut for CodeTransformer {
 
     fn visit_item_mut(&mut self, item: &mut Item) {
         // Skip test-related items
-        if Self::has_test_attribute(Self::get_attrs(item)) {
+        if Self::is_stripped_item(item, &self.strip_rules) {
+            return;
+        }
+        if let Item::Verbatim(tokens) = item {
+            *item = self.keep_verbatim(tokens);
+            return;
+        }
+        // Unknown kinds pass through untouched rather than failing the file
+        if Self::get_attrs_mut(item).is_none() {
+            self.removals.unknown_items += 1;
             return;
         }
 
         match item {
             Item::Mod(item_mod) => {
-                if Self::has_test_attribute(&item_mod.attrs) {
+                if Self::has_test_attribute(&item_mod.attrs, &self.strip_rules) {
                     if let Some((_, items)) = &mut item_mod.content {
                         items.clear();
                     }
@@ -311,55 +926,60 @@ impl VisitMut for CodeTransformer {
                 }
 
                 // Process module attributes
-                Self::process_attributes(&mut item_mod.attrs, self.no_comments);
+                self.process_attributes(&mut item_mod.attrs);
 
                 if let Some((_, items)) = &mut item_mod.content {
                     // Remove test items from the module
-                    items.retain(|item| !Self::has_test_attribute(Self::get_attrs(item)));
+                    self.retain_items(items);
 
                     // Process remaining items
                     for item in items {
                         // Process attributes before visiting the item
-                        Self::process_attributes(Self::get_attrs_mut(item), self.no_comments);
+                        if let Some(attrs) = Self::get_attrs_mut(item) {
+                            self.process_attributes(attrs);
+                        }
                         self.visit_item_mut(item);
                     }
                 }
             }
             Item::Fn(item_fn) => {
                 // Process function-level comments
-                Self::process_attributes(&mut item_fn.attrs, self.no_comments);
+                self.process_attributes(&mut item_fn.attrs);
 
                 // Only replace block if no_function_bodies is true and return type isn't string-like
-                if self.no_function_bodies && !Self::analyze_return_type(&item_fn.sig.output) {
-                    item_fn.block = parse_quote!({});
-                }
-            }
-            Item::Trait(item_trait) => {
-                // Process trait-level comments
-                Self::process_attributes(&mut item_trait.attrs, self.no_comments);
-
-                // Process trait methods
-                for trait_item in &mut item_trait.items {
-                    if let TraitItem::Fn(method) = trait_item {
-                        // First process the attributes
-                        Self::process_attributes(&mut method.attrs, self.no_comments);
-
-                        // Then handle the default implementation
-                        if method.default.is_some()
-                            && self.no_function_bodies
-                            && !Self::analyze_return_type(&method.sig.output)
-                        {
-                            method.default = Some(parse_quote!({}));
-                        }
-                    }
-
-                    // Finally add the trait method comment
-                    Self::add_trait_method_comment(trait_item, self.no_comments);
+                let keeps_body =
+                    self.body_rules
+                        .keeps_body(&item_fn.attrs, &item_fn.sig, &item_fn.block);
+                if self.no_function_bodies && !keeps_body {
+                    self.elide_body(&mut item_fn.block);
                 }
             }
+            Item::Trait(item_trait) => self.transform_trait(item_trait),
             Item::Impl(item_impl) => {
                 // Process impl block comments
-                Self::process_attributes(&mut item_impl.attrs, self.no_comments);
+                self.process_attributes(&mut item_impl.attrs);
+
+                // Remove test-only and hidden methods and constants
+                let rules = &self.strip_rules;
+                let removals = &mut self.removals;
+                let mut hidden = item_impl
+                    .items
+                    .iter()
+                    .map(|item| Self::is_hidden_impl_item(item, item_impl, rules))
+                    .collect::<Vec<_>>()
+                    .into_iter();
+                item_impl.items.retain(|item| {
+                    let is_hidden = hidden.next() == Some(true);
+                    if Self::is_test_impl_item(item, rules) {
+                        removals.test_items += 1;
+                    } else if is_hidden {
+                        removals.hidden_items += 1;
+                    } else {
+                        return true;
+                    }
+                    removals.count_attributes(Self::get_impl_item_attrs(item));
+                    false
+                });
 
                 // Check implementation type before processing methods
                 let is_derived = Self::is_derived_implementation(item_impl);
@@ -367,32 +987,40 @@ impl VisitMut for CodeTransformer {
 
                 // Process implementation methods
                 for impl_item in &mut item_impl.items {
+                    if let ImplItem::Verbatim(tokens) = impl_item {
+                        *impl_item = self.keep_verbatim(tokens);
+                    }
                     if let ImplItem::Fn(method) = impl_item {
-                        Self::process_attributes(&mut method.attrs, self.no_comments);
-
-                        if self.no_function_bodies
-                            && (is_derived
-                                || (!is_serialize
-                                    && !Self::analyze_return_type(&method.sig.output)))
-                        {
-                            method.block = parse_quote!({});
+                        self.process_attributes(&mut method.attrs);
+
+                        let keeps_body = is_serialize
+                            || self.body_rules.keeps_body(
+                                &method.attrs,
+                                &method.sig,
+                                &method.block,
+                            );
+                        if self.no_function_bodies && (is_derived || !keeps_body) {
+                            self.elide_body(&mut method.block);
                         }
                     }
                 }
             }
             Item::Struct(item_struct) => {
                 // Process struct-level comments
-                Self::process_attributes(&mut item_struct.attrs, self.no_comments);
+                self.process_attributes(&mut item_struct.attrs);
 
                 // Process field-level comments
                 for field in &mut item_struct.fields {
-                    Self::process_attributes(&mut field.attrs, self.no_comments);
+                    self.process_attributes(&mut field.attrs);
+                }
+                if self.hide_private_fields {
+                    self.hide_fields(&mut item_struct.fields);
                 }
                 visit_mut::visit_item_struct_mut(self, item_struct);
             }
             Item::Enum(item_enum) => {
                 // Process enum-level comments
-                Self::process_attributes(&mut item_enum.attrs, self.no_comments);
+                self.process_attributes(&mut item_enum.attrs);
                 visit_mut::visit_item_enum_mut(self, item_enum);
             }
             _ => visit_mut::visit_item_mut(self, item),
@@ -1038,4 +1666,464 @@ impl Debug for MyStruct {
         assert_eq!(process_code(input, false, true)?.trim(), expected.trim());
         Ok(())
     }
+
+    #[test]
+    fn test_trait_annotations_configurable() -> Result<()> {
+        use super::{CodeTransformer, TraitAnnotations};
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"trait MyTrait {
+    /// Existing doc comment
+    fn required_method(&self) -> i32;
+    fn default_method(&self) -> i32 {
+        42
+    }
+}"#;
+        let transform = |annotations: Option<TraitAnnotations>| -> Result<String> {
+            let mut ast = syn::parse_file(input)?;
+            CodeTransformer::new(false, false)
+                .with_trait_annotations(annotations)
+                .visit_file_mut(&mut ast);
+            Ok(prettyplease::unparse(&ast))
+        };
+
+        let disabled = transform(None)?;
+        assert!(disabled.contains("/// Existing doc comment\n    fn required_method"));
+        assert!(!disabled.contains("required method"));
+        assert!(!disabled.contains("default implementation"));
+
+        let custom = transform(Some(TraitAnnotations {
+            required: "Required.".to_string(),
+            default: "Provided.".to_string(),
+        }))?;
+        assert!(custom.contains("/// Required.\n    ///\n    /// Existing doc comment"));
+        assert!(custom.contains("/// Provided.\n    fn default_method"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trait_default_bodies_honor_options() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        let transform = |no_function_bodies: bool, keep_trait_defaults: bool| -> Result<String> {
+            let mut item: syn::ItemTrait = syn::parse_str(
+                "trait MyTrait { fn count(&self) -> i32 { 42 } fn name(&self) -> String { String::new() } }",
+            )?;
+            CodeTransformer::new(false, no_function_bodies)
+                .with_trait_annotations(None)
+                .with_keep_trait_defaults(keep_trait_defaults)
+                .visit_item_trait_mut(&mut item);
+            Ok(quote::quote!(#item).to_string())
+        };
+
+        assert!(transform(false, false)?.contains("{ 42 }"));
+        assert!(transform(true, true)?.contains("{ 42 }"));
+
+        let stripped = transform(true, false)?;
+        assert!(!stripped.contains("42"));
+        assert!(stripped.contains("String :: new ()"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trait_default_bodies_keep_like_methods() -> Result<()> {
+        use super::CodeTransformer;
+        use std::collections::HashSet;
+        use std::sync::Arc;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            trait Store {
+                fn key(&self) -> Key { format!("{}", self.id()) }
+                fn tokens(&self) -> TokenStream { quote!(store) }
+                fn load(&self) -> Row { sqlx::query!("SELECT * FROM rows").fetch_one(self.pool()) }
+                fn count(&self) -> usize { 42 }
+            }
+        "#;
+        let mut ast = syn::parse_file(input)?;
+        CodeTransformer::new(false, true)
+            .with_trait_annotations(None)
+            .with_string_aliases(Arc::new(HashSet::from(["Key".to_string()])))
+            .with_proc_macro(true)
+            .with_keep_queries(true)
+            .visit_file_mut(&mut ast);
+        let output = prettyplease::unparse(&ast);
+        assert!(output.contains("format!(\"{}\", self.id())"));
+        assert!(output.contains("quote!(store)"));
+        assert!(output.contains("SELECT * FROM rows"));
+        assert!(output.contains("fn count(&self) -> usize {}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_removes_items_emptied_by_test_stripping() -> Result<()> {
+        let input = r#"
+            #[cfg(test)]
+            use std::collections::HashMap;
+
+            struct Foo;
+            impl Marker for Foo {}
+
+            impl Foo {
+                #[cfg(test)]
+                fn new_for_test() -> Self { Foo }
+            }
+
+            impl Foo {
+                pub fn keep(&self) {}
+                #[cfg(test)]
+                const FIXTURE: u32 = 1;
+            }
+
+            mod helpers {
+                mod nested {
+                    #[test]
+                    fn check() {}
+                }
+            }
+
+            mod empty {}
+        "#;
+        let expected = r#"struct Foo;
+impl Marker for Foo {}
+impl Foo {
+    pub fn keep(&self) {}
+}
+mod empty {}"#;
+        assert_eq!(process_code(input, false, false)?.trim(), expected.trim());
+        Ok(())
+    }
+
+    #[test]
+    fn test_removes_benchmarks() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            use criterion::{criterion_group, criterion_main, Criterion};
+
+            pub fn fib(n: u64) -> u64 { n }
+
+            #[bench]
+            fn bench_fib(b: &mut Bencher) {}
+
+            #[cfg(bench)]
+            mod benches {
+                fn helper() {}
+            }
+
+            mod harness {
+                criterion_group!(benches, bench_fib);
+                criterion_main!(benches);
+            }
+        "#;
+        assert_eq!(
+            process_code(input, false, false)?.trim(),
+            "pub fn fib(n: u64) -> u64 {\n    n\n}"
+        );
+
+        let mut ast = syn::parse_file(input)?;
+        CodeTransformer::new(false, false)
+            .with_keep_benches(true)
+            .visit_file_mut(&mut ast);
+        let kept = prettyplease::unparse(&ast);
+        assert!(kept.contains("fn bench_fib"));
+        assert!(kept.contains("criterion_main!(benches)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_proc_macro_bodies() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            #[proc_macro_derive(Builder)]
+            pub fn derive_builder(input: TokenStream) -> TokenStream { expand(input) }
+            fn expand(input: TokenStream) -> proc_macro2::TokenStream { input.into() }
+            fn helper(input: &str) -> usize { input.len() }
+        "#;
+        let mut ast = syn::parse_file(input)?;
+        CodeTransformer::new(false, true)
+            .with_proc_macro(true)
+            .visit_file_mut(&mut ast);
+        let output = prettyplease::unparse(&ast);
+        assert!(output.contains("expand(input)"));
+        assert!(output.contains("input.into()"));
+        assert!(output.contains("fn helper(input: &str) -> usize {}"));
+
+        assert!(!process_code(input, false, true)?.contains("expand(input)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_attribute_macro_impls() -> Result<()> {
+        let input = r#"
+            #[async_trait]
+            impl Repository for PgRepository {
+                async fn find(&self, id: i64) -> Option<User> {
+                    self.pool.fetch(id).await
+                }
+            }
+
+            #[axum::debug_handler]
+            async fn get_user(Path(id): Path<i64>) -> Json<User> {
+                Json(load(id).await)
+            }
+        "#;
+        let expected = r#"#[async_trait]
+impl Repository for PgRepository {
+    async fn find(&self, id: i64) -> Option<User> {}
+}
+#[axum::debug_handler]
+async fn get_user(Path(id): Path<i64>) -> Json<User> {}"#;
+        assert_eq!(process_code(input, false, true)?.trim(), expected.trim());
+        Ok(())
+    }
+
+    #[test]
+    fn test_test_heuristics() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            #[tokio::test]
+            async fn async_test() {}
+
+            #[cfg(all(test, feature = "slow"))]
+            fn slow_fixture() {}
+
+            #[cfg(not(test))]
+            pub fn production_only() {}
+
+            #[cfg(feature = "test-utils")]
+            pub fn test_utils() {}
+
+            #[my_macros::test]
+            pub fn decorated() {}
+        "#;
+        let output = process_code(input, false, false)?;
+        assert!(!output.contains("async_test"));
+        assert!(!output.contains("slow_fixture"));
+        assert!(output.contains("production_only"));
+        assert!(output.contains("test_utils"));
+        assert!(!output.contains("decorated"));
+
+        let mut ast = syn::parse_file(input)?;
+        CodeTransformer::new(false, false)
+            .with_transparent_attributes(&["my_macros::test".to_string()])
+            .visit_file_mut(&mut ast);
+        assert!(prettyplease::unparse(&ast).contains("#[my_macros::test]\npub fn decorated"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_query_bodies() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            async fn find_user(pool: &PgPool, id: i64) -> Result<User> {
+                sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id).fetch_one(pool).await
+            }
+            fn add(a: i32, b: i32) -> i32 { a + b }
+        "#;
+        let mut ast = syn::parse_file(input)?;
+        CodeTransformer::new(false, true)
+            .with_keep_queries(true)
+            .visit_file_mut(&mut ast);
+        let output = prettyplease::unparse(&ast);
+        assert!(output.contains("SELECT * FROM users WHERE id = $1"));
+        assert!(output.contains("fn add(a: i32, b: i32) -> i32 {}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_visibility_level() -> Result<()> {
+        use super::CodeTransformer;
+        use crate::visibility::VisibilityLevel;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            pub fn api() {}
+            pub(crate) fn internal() {}
+            fn helper() {}
+            impl Config {
+                pub fn load() {}
+                pub(super) fn reload() {}
+                fn check() {}
+            }
+            impl Display for Config {
+                fn fmt(&self) {}
+            }
+            impl Cache {
+                fn evict() {}
+            }
+            mod detail {
+                pub fn exposed() {}
+            }
+        "#;
+        let mut ast = syn::parse_file(input)?;
+        let mut transformer =
+            CodeTransformer::new(false, false).with_visibility(VisibilityLevel::Crate);
+        transformer.visit_file_mut(&mut ast);
+        let expected = r#"pub fn api() {}
+pub(crate) fn internal() {}
+impl Config {
+    pub fn load() {}
+    pub(super) fn reload() {}
+}
+impl Display for Config {
+    fn fmt(&self) {}
+}
+"#;
+        assert_eq!(prettyplease::unparse(&ast), expected);
+        assert_eq!(transformer.removals().hidden_items, 3);
+        assert_eq!(transformer.removals().items, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_trait_impls() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            impl Config {
+                pub fn load() {}
+            }
+            impl Default for Config {
+                fn default() -> Self { todo!() }
+            }
+            impl PartialEq<Other> for Config {
+                fn eq(&self, other: &Other) -> bool { todo!() }
+            }
+            impl Source for Config {}
+            mod boilerplate {
+                impl core::clone::Clone for Config {
+                    fn clone(&self) -> Self { todo!() }
+                }
+            }
+        "#;
+        let mut ast = syn::parse_file(input)?;
+        let skipped = ["Default", "PartialEq", "core::clone::Clone"].map(String::from);
+        let mut transformer = CodeTransformer::new(false, false).with_skipped_trait_impls(&skipped);
+        transformer.visit_file_mut(&mut ast);
+        let expected = r#"impl Config {
+    pub fn load() {}
+}
+impl Source for Config {}
+"#;
+        assert_eq!(prettyplease::unparse(&ast), expected);
+        assert_eq!(transformer.removals().skipped_impls, 2);
+        assert_eq!(transformer.removals().items, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hide_private_fields() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            pub struct Config {
+                pub name: String,
+                cache: Vec<u8>,
+                pub(crate) retries: u32,
+            }
+            pub struct Id(u64);
+            pub struct Point(pub i32, i32);
+            pub struct Public {
+                pub field: u8,
+            }
+        "#;
+        let mut ast = syn::parse_file(input)?;
+        let mut transformer = CodeTransformer::new(false, false).with_hide_private_fields(true);
+        transformer.visit_file_mut(&mut ast);
+        let expected = r#"pub struct Config {
+    pub name: String,
+    /* private fields */
+}
+pub struct Id(/* private fields */);
+pub struct Point(pub i32, i32);
+pub struct Public {
+    pub field: u8,
+}
+"#;
+        assert_eq!(transformer.finish(&prettyplease::unparse(&ast)), expected);
+        assert_eq!(transformer.removals().private_fields, 3);
+
+        // Code that names the placeholder is left alone, with and without hidden fields
+        let input =
+            "pub struct Id(u64);\nconst NAME: &str = \"__CodeContextPrivateFields: (),\";\n";
+        for hide_private_fields in [false, true] {
+            let mut ast = syn::parse_file(input)?;
+            let mut transformer =
+                CodeTransformer::new(false, false).with_hide_private_fields(hide_private_fields);
+            transformer.visit_file_mut(&mut ast);
+            let output = transformer.finish(&prettyplease::unparse(&ast));
+            assert!(output.contains("\"__CodeContextPrivateFields: (),\""));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_removals() -> Result<()> {
+        use super::CodeTransformer;
+        use crate::removals::Removals;
+        use syn::visit_mut::VisitMut;
+
+        let input = r#"
+            /// Adds numbers
+            fn add(a: i32, b: i32) -> i32 { a + b }
+            fn noop() {}
+            #[test]
+            fn test_add() {}
+            mod helpers {
+                #[cfg(test)]
+                fn fixture() {}
+            }
+        "#;
+        let mut ast = syn::parse_file(input)?;
+        let mut transformer = CodeTransformer::new(true, true);
+        transformer.visit_file_mut(&mut ast);
+        assert_eq!(
+            transformer.removals(),
+            Removals {
+                doc_comments: 1,
+                test_items: 1,
+                bodies: 1,
+                attributes: 1,
+                items: 1,
+                license_headers: 0,
+                inner_attributes: 0,
+                hidden_items: 0,
+                private_fields: 0,
+                collapsed_impls: 0,
+                skipped_impls: 0,
+                unknown_items: 0,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_items_pass_through() -> Result<()> {
+        use super::CodeTransformer;
+        use syn::visit_mut::VisitMut;
+
+        // syn keeps macros 2.0 and impl functions without a body as verbatim tokens
+        let input = "mod api {\n    pub macro m($x:expr) {\n        $x\n    }\n    /// Docs\n    pub fn f() {}\n}\nimpl S {\n    fn g();\n}\n";
+        let mut ast = syn::parse_file(input)?;
+        let mut transformer = CodeTransformer::new(true, false);
+        transformer.visit_file_mut(&mut ast);
+        assert_eq!(transformer.removals().unknown_items, 2);
+        assert_eq!(transformer.removals().doc_comments, 1);
+        assert_eq!(
+            transformer.finish(&prettyplease::unparse(&ast)),
+            "mod api {\n    // Not parsed by code-context, kept verbatim:\n    pub macro m($x:expr) {\n        $x\n    }\n    pub fn f() {}\n}\nimpl S {\n    // Not parsed by code-context, kept verbatim:\n    fn g();\n}\n"
+        );
+        Ok(())
+    }
 }
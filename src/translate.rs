@@ -0,0 +1,132 @@
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Doc comment markers, outer before inner
+const DOC_MARKERS: [&str; 2] = ["///", "//!"];
+
+/// Runs every block of consecutive doc comment lines through a shell command, which reads
+/// the comment text on stdin and writes its replacement, e.g. a translation, to stdout
+pub fn translate_docs(output: &str, command: &str) -> Result<String> {
+    map_doc_blocks(output, |text| run(command, text))
+}
+
+/// Replaces the text of each doc comment block, keeping its indentation and marker
+fn map_doc_blocks(output: &str, mut f: impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut result = String::with_capacity(output.len());
+    let mut lines = output.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        let Some((indent, marker, text)) = doc_line(line) else {
+            result.push_str(line);
+            continue;
+        };
+        let mut block = vec![text];
+        while let Some(next) = lines.next_if(|next| {
+            doc_line(next).is_some_and(|(next_indent, next_marker, _)| {
+                next_indent == indent && next_marker == marker
+            })
+        }) {
+            block.extend(doc_line(next).map(|(_, _, text)| text));
+        }
+
+        for text in f(&(block.join("\n") + "\n"))?.lines() {
+            result.push_str(indent);
+            result.push_str(marker);
+            if !text.is_empty() {
+                result.push(' ');
+                result.push_str(text);
+            }
+            result.push('\n');
+        }
+    }
+    Ok(result)
+}
+
+/// Splits a doc comment line into its indentation, marker, and text
+fn doc_line(line: &str) -> Option<(&str, &'static str, &str)> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    DOC_MARKERS.into_iter().find_map(|marker| {
+        let text = trimmed.strip_prefix(marker)?;
+        // `////` is a regular comment
+        if text.starts_with('/') {
+            return None;
+        }
+        Some((indent, marker, text.strip_prefix(' ').unwrap_or(text)))
+    })
+}
+
+/// Runs a command line through the shell with the text on stdin, returning its stdout
+fn run(command: &str, text: &str) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run doc translation command: {}", command))?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open doc translation stdin")?
+        .write_all(text.as_bytes())
+        .context("Failed to write to doc translation command")?;
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for doc translation command")?;
+    if !output.status.success() {
+        bail!(
+            "Doc translation command failed: {}: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("Doc translation command produced invalid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_doc_blocks() -> Result<()> {
+        let output = "//! 設定\n\n/// 設定を読み込む\n///\n/// 詳細\npub fn load() {}\n\nimpl A {\n    /// 保存する\n    fn save() {}\n    //// not a doc\n}\n";
+        let mut blocks = Vec::new();
+        let translated = map_doc_blocks(output, |text| {
+            blocks.push(text.to_string());
+            Ok(format!("[{}]", text.trim_end()))
+        })?;
+        assert_eq!(
+            blocks,
+            vec!["設定\n", "設定を読み込む\n\n詳細\n", "保存する\n"]
+        );
+        assert_eq!(
+            translated,
+            "//! [設定]\n\n/// [設定を読み込む\n///\n/// 詳細]\npub fn load() {}\n\nimpl A {\n    /// [保存する]\n    fn save() {}\n    //// not a doc\n}\n"
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_translate_docs_with_command() -> Result<()> {
+        let output = "/// load the config\npub fn load() {}\n";
+        assert_eq!(
+            translate_docs(output, "tr a-z A-Z")?,
+            "/// LOAD THE CONFIG\npub fn load() {}\n"
+        );
+        assert!(translate_docs(output, "exit 3").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+use crate::compression::read_output;
+use crate::diff::{is_output_file, FILE_HEADER_PREFIX};
+use crate::git::item_label;
+use crate::paths;
+use crate::processor::estimate_tokens;
+use crate::script;
+use crate::stats::format_count;
+use crate::summary::type_string;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::{ImplItem, Item};
+use walkdir::WalkDir;
+
+/// Commands understood by a trimming session, shown by `help`
+const HELP: &str = "\
+list [n]            List the [n] (default 20) most expensive items, excluded ones marked with x
+toggle <n>...       Exclude the listed items, or include them again
+budget <tokens>     Set the token budget
+fit                 Exclude the most expensive items until the context fits the budget
+reset               Include all items again
+write               Write the trimmed context to the output path
+help                Show this help
+quit                Leave the session
+";
+
+/// Items listed by `list` without a count
+const DEFAULT_LIST_LENGTH: usize = 20;
+
+/// A file of the loaded context
+struct ContextFile {
+    /// Path relative to the loaded directory, or the file name of a loaded file
+    path: PathBuf,
+    content: String,
+}
+
+/// An item of the loaded context that can be excluded
+struct TrimItem {
+    /// Index of the file in the context
+    file: usize,
+    /// File and item, e.g. `src/lib.rs: fn Config::load`
+    label: String,
+    /// Bytes of the item in its file, from the start of its first line through its last
+    /// line break
+    range: Range<usize>,
+    tokens: usize,
+    excluded: bool,
+}
+
+/// A generated context loaded for trimming, with its items sorted by token cost
+pub struct TrimSession {
+    files: Vec<ContextFile>,
+    items: Vec<TrimItem>,
+    /// Whether the loaded context is a directory, written as one too
+    directory: bool,
+    output: PathBuf,
+    budget: Option<usize>,
+}
+
+impl TrimSession {
+    /// Loads a generated output file or directory, to be written trimmed to `output`
+    pub fn load(context: &Path, output: &Path, budget: Option<usize>) -> Result<Self> {
+        let mut files = Vec::new();
+        if context.is_dir() {
+            let entries = WalkDir::new(context)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().is_file() && is_output_file(e.path()));
+            for entry in entries {
+                files.push(ContextFile {
+                    path: entry.path().strip_prefix(context)?.to_path_buf(),
+                    content: read_output(entry.path())?,
+                });
+            }
+        } else {
+            files.push(ContextFile {
+                path: PathBuf::from(context.file_name().context("Context has no file name")?),
+                content: read_output(context)?,
+            });
+        }
+
+        let mut items = Vec::new();
+        for (index, file) in files.iter().enumerate() {
+            collect_items(index, file, &mut items);
+        }
+        items.sort_by_key(|item| std::cmp::Reverse(item.tokens));
+        Ok(Self {
+            files,
+            items,
+            directory: context.is_dir(),
+            output: output.to_path_buf(),
+            budget,
+        })
+    }
+
+    /// Number of items that can be excluded
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Estimated tokens of the context without the excluded items
+    pub fn tokens(&self) -> usize {
+        (0..self.files.len())
+            .map(|file| estimate_tokens(self.trimmed(file).len()))
+            .sum()
+    }
+
+    /// Content of a file without its excluded items
+    fn trimmed(&self, file: usize) -> String {
+        let mut ranges: Vec<_> = self
+            .items
+            .iter()
+            .filter(|item| item.file == file && item.excluded)
+            .map(|item| item.range.clone())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        let content = &self.files[file].content;
+        let mut trimmed = String::with_capacity(content.len());
+        let mut position = 0;
+        for range in ranges {
+            // A method inside an excluded impl is already gone
+            if range.start < position {
+                continue;
+            }
+            trimmed.push_str(&content[position..range.start]);
+            position = range.end;
+        }
+        trimmed.push_str(&content[position..]);
+        trimmed
+    }
+
+    /// Runs one command line, returning its output or `None` to quit
+    pub fn execute(&mut self, line: &str) -> Result<Option<String>> {
+        let (command, argument) = line
+            .trim()
+            .split_once(char::is_whitespace)
+            .map_or((line.trim(), ""), |(command, argument)| {
+                (command, argument.trim())
+            });
+        let output = match command {
+            "" => String::new(),
+            "quit" | "exit" => return Ok(None),
+            "help" => HELP.to_string(),
+            "list" => {
+                let count = match argument {
+                    "" => DEFAULT_LIST_LENGTH,
+                    count => count.parse().context("Expected a number of items")?,
+                };
+                self.list(count)
+            }
+            "toggle" => {
+                for number in argument.split_whitespace() {
+                    let item = number
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|number| self.items.get_mut(number.checked_sub(1)?))
+                        .with_context(|| format!("No item numbered {}", number))?;
+                    item.excluded = !item.excluded;
+                }
+                self.status()
+            }
+            "budget" => {
+                self.budget = Some(argument.parse().context("Expected a number of tokens")?);
+                self.status()
+            }
+            "fit" => self.fit()?,
+            "reset" => {
+                for item in &mut self.items {
+                    item.excluded = false;
+                }
+                self.status()
+            }
+            "write" => self.write()?,
+            _ => anyhow::bail!("Unknown command: {} (try `help`)", command),
+        };
+        Ok(Some(output))
+    }
+
+    /// Lists the most expensive items with their numbers
+    fn list(&self, count: usize) -> String {
+        let mut output = String::new();
+        for (index, item) in self.items.iter().enumerate().take(count) {
+            let _ = writeln!(
+                output,
+                "{:>4}  {}  {:>8}  {}",
+                index + 1,
+                if item.excluded { "x" } else { " " },
+                format_count(item.tokens),
+                item.label
+            );
+        }
+        output + &self.status()
+    }
+
+    /// Summarizes the exclusions and the tokens left against the budget
+    fn status(&self) -> String {
+        let excluded = self.items.iter().filter(|item| item.excluded).count();
+        let tokens = self.tokens();
+        let budget = match self.budget {
+            Some(budget) if tokens > budget => format!(
+                ", {} over the budget of {}",
+                format_count(tokens - budget),
+                format_count(budget)
+            ),
+            Some(budget) => format!(", within the budget of {}", format_count(budget)),
+            None => String::new(),
+        };
+        format!(
+            "{} of {} items excluded, {} tokens{}\n",
+            excluded,
+            self.items.len(),
+            format_count(tokens),
+            budget
+        )
+    }
+
+    /// Excludes the most expensive included items until the context fits the budget
+    fn fit(&mut self) -> Result<String> {
+        let budget = self
+            .budget
+            .context("No budget set; use `budget <tokens>` first")?;
+        for index in 0..self.items.len() {
+            if self.tokens() <= budget {
+                break;
+            }
+            self.items[index].excluded = true;
+        }
+        Ok(self.status())
+    }
+
+    /// Writes the trimmed files to the output path
+    fn write(&self) -> Result<String> {
+        for file in 0..self.files.len() {
+            let path = if self.directory {
+                // Compressed outputs are written back as plain text
+                let relative = paths::slash(&self.files[file].path);
+                let relative = relative
+                    .strip_suffix(".gz")
+                    .or_else(|| relative.strip_suffix(".zst"))
+                    .unwrap_or(&relative);
+                self.output.join(relative)
+            } else {
+                self.output.clone()
+            };
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).context("Failed to create output directory")?;
+            }
+            std::fs::write(&path, self.trimmed(file))
+                .with_context(|| format!("Failed to write trimmed context: {}", path.display()))?;
+        }
+        Ok(format!(
+            "Wrote {} tokens to {}\n",
+            format_count(self.tokens()),
+            paths::slash(&self.output)
+        ))
+    }
+
+    /// Reads commands from the input until `quit` or its end, writing prompts and outputs
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            match self.execute(&line?) {
+                Ok(Some(text)) => write!(output, "{}", text)?,
+                Ok(None) => return Ok(()),
+                Err(error) => writeln!(output, "Error: {:#}", error)?,
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        writeln!(output)?;
+        Ok(())
+    }
+}
+
+/// Collects the top-level items and impl methods of a file; a file that doesn't parse, e.g.
+/// because of YAML front matter, has no items to exclude and is kept whole
+fn collect_items(index: usize, file: &ContextFile, items: &mut Vec<TrimItem>) {
+    let (header, code) = script::split_header(&file.content);
+    let Ok(parsed) = syn::parse_file(code) else {
+        tracing::warn!("Failed to parse {}; keeping it whole", file.path.display());
+        return;
+    };
+    // Output files are labelled by their source, as in the headers of the combined output
+    let name = paths::slash(&file.path);
+    let name = match name.split_once(".rs.txt") {
+        Some((stem, _)) => format!("{}.rs", stem),
+        None => name,
+    };
+    let mut record = |label: String, span: proc_macro2::Span| {
+        let range = span.byte_range();
+        let range = line_range(
+            &file.content,
+            header.len() + range.start,
+            header.len() + range.end,
+        );
+        // In the combined output, items belong to the file of the last header before them
+        let section = file.content[..range.start]
+            .lines()
+            .rev()
+            .find_map(|line| line.strip_prefix(FILE_HEADER_PREFIX))
+            .unwrap_or(&name);
+        items.push(TrimItem {
+            file: index,
+            label: format!("{}: {}", section, label),
+            tokens: estimate_tokens(range.len()),
+            range,
+            excluded: false,
+        });
+    };
+    for item in &parsed.items {
+        let Some(label) = item_label(item) else {
+            continue;
+        };
+        record(label, item.span());
+        if let Item::Impl(item) = item {
+            let self_ty = type_string(&item.self_ty);
+            for impl_item in &item.items {
+                if let ImplItem::Fn(method) = impl_item {
+                    record(
+                        format!("fn {}::{}", self_ty, method.sig.ident),
+                        method.span(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Widens a byte range to whole lines, including the last line break
+fn line_range(content: &str, start: usize, end: usize) -> Range<usize> {
+    let start = content[..start].rfind('\n').map_or(0, |index| index + 1);
+    let end = content[end..]
+        .find('\n')
+        .map_or(content.len(), |index| end + index + 1);
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_trim_session() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let context = temp_dir.path().join("app.rs.txt");
+        std::fs::write(
+            &context,
+            "// File: src/lib.rs\n\npub struct Config;\n\n// File: src/store.rs\n\nimpl Store {\n    /// Loads everything\n    pub fn load_all(&self) -> Vec<Item> {\n        self.items.iter().cloned().collect()\n    }\n    pub fn len(&self) -> usize {}\n}\n",
+        )?;
+        let output = temp_dir.path().join("trimmed.rs.txt");
+        let mut session = TrimSession::load(&context, &output, Some(40))?;
+        assert_eq!(session.len(), 4);
+
+        let listed = session.execute("list 2")?.unwrap();
+        assert_eq!(
+            listed,
+            "   1           42  src/store.rs: impl Store\n   2           30  src/store.rs: fn Store::load_all\n0 of 4 items excluded, 58 tokens, 18 over the budget of 40\n"
+        );
+        session.execute("toggle 2")?;
+        assert!(session.execute("toggle 9").is_err());
+        assert_eq!(
+            session.execute("write")?.unwrap(),
+            format!("Wrote 29 tokens to {}\n", paths::slash(&output))
+        );
+        assert_eq!(
+            std::fs::read_to_string(&output)?,
+            "// File: src/lib.rs\n\npub struct Config;\n\n// File: src/store.rs\n\nimpl Store {\n    pub fn len(&self) -> usize {}\n}\n"
+        );
+
+        session.execute("reset")?;
+        session.execute("budget 20")?;
+        assert!(session
+            .execute("fit")?
+            .unwrap()
+            .starts_with("1 of 4 items excluded"));
+
+        let mut transcript = Vec::new();
+        session.run("frobnicate\nquit\nlist\n".as_bytes(), &mut transcript)?;
+        assert_eq!(
+            String::from_utf8(transcript)?,
+            "> Error: Unknown command: frobnicate (try `help`)\n> "
+        );
+        Ok(())
+    }
+}
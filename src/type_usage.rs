@@ -0,0 +1,187 @@
+use crate::summary::SourceFile;
+use std::collections::HashMap;
+use syn::visit::{self, Visit};
+use syn::{ImplItemFn, Item, ItemFn, Signature, TraitItemFn};
+
+/// Number of types listed in the summary
+const MAX_LISTED_TYPES: usize = 20;
+
+/// A type of the crate with the number of its mentions in signatures
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeUsage {
+    pub name: String,
+    /// File defining the type
+    pub path: String,
+    pub count: usize,
+}
+
+impl std::fmt::Display for TypeUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let uses = if self.count == 1 { "use" } else { "uses" };
+        write!(f, "{} ({}): {} {}", self.name, self.path, self.count, uses)
+    }
+}
+
+/// Collects the types defined in a file, including those of inline modules
+fn collect_definitions(items: &[Item], path: &str, types: &mut HashMap<String, String>) {
+    for item in items {
+        let ident = match item {
+            Item::Struct(item) => &item.ident,
+            Item::Enum(item) => &item.ident,
+            Item::Union(item) => &item.ident,
+            Item::Trait(item) => &item.ident,
+            Item::Type(item) => &item.ident,
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    collect_definitions(items, path, types);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        types
+            .entry(ident.to_string())
+            .or_insert_with(|| path.to_string());
+    }
+}
+
+/// Counts mentions of the crate's types in function signatures
+struct UsageCounter<'a> {
+    types: &'a HashMap<String, String>,
+    counts: HashMap<String, usize>,
+}
+
+impl UsageCounter<'_> {
+    fn count_signature(&mut self, sig: &Signature) {
+        let mut paths = PathCollector::default();
+        paths.visit_signature(sig);
+        for name in paths.names {
+            if self.types.contains_key(&name) {
+                *self.counts.entry(name).or_default() += 1;
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UsageCounter<'_> {
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        self.count_signature(&item.sig);
+        visit::visit_item_fn(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        self.count_signature(&item.sig);
+        visit::visit_impl_item_fn(self, item);
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        self.count_signature(&item.sig);
+        visit::visit_trait_item_fn(self, item);
+    }
+}
+
+/// Collects the last segment of every path in a signature
+#[derive(Default)]
+struct PathCollector {
+    names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for PathCollector {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if let Some(segment) = path.segments.last() {
+            self.names.push(segment.ident.to_string());
+        }
+        visit::visit_path(self, path);
+    }
+}
+
+/// Ranks the types defined in the crate by how often they appear in function signatures
+pub fn rank_types(sources: &[SourceFile]) -> Vec<TypeUsage> {
+    let mut types = HashMap::new();
+    for source in sources {
+        collect_definitions(&source.ast.items, &source.path, &mut types);
+    }
+
+    let mut counter = UsageCounter {
+        types: &types,
+        counts: HashMap::new(),
+    };
+    for source in sources {
+        counter.visit_file(&source.ast);
+    }
+
+    let mut ranking: Vec<TypeUsage> = counter
+        .counts
+        .into_iter()
+        .map(|(name, count)| TypeUsage {
+            path: types[&name].clone(),
+            name,
+            count,
+        })
+        .collect();
+    ranking.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    ranking
+}
+
+/// Renders the most used types for the summary
+pub fn most_used_types(sources: &[SourceFile]) -> Vec<String> {
+    rank_types(sources)
+        .iter()
+        .take(MAX_LISTED_TYPES)
+        .map(TypeUsage::to_string)
+        .collect()
+}
+
+/// Scores files by the signature mentions of the types they define
+pub fn file_scores(sources: &[SourceFile]) -> HashMap<String, usize> {
+    let mut scores = HashMap::new();
+    for usage in rank_types(sources) {
+        *scores.entry(usage.path).or_default() += usage.count;
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_rank_types() -> Result<()> {
+        let sources = vec![
+            SourceFile {
+                path: "config.rs".to_string(),
+                ast: syn::parse_str(
+                    r#"
+                    pub struct Config;
+                    pub enum Mode { Fast }
+                    impl Config {
+                        pub fn new(mode: Mode) -> Config { Config }
+                        pub fn mode(&self) -> Option<crate::config::Mode> { None }
+                    }
+                    "#,
+                )?,
+            },
+            SourceFile {
+                path: "app.rs".to_string(),
+                ast: syn::parse_str(
+                    r#"
+                    pub struct App;
+                    pub fn run(config: &Config) -> Result<App, String> { todo!() }
+                    trait Loader { fn load(&self) -> Config; }
+                    "#,
+                )?,
+            },
+        ];
+        assert_eq!(
+            most_used_types(&sources),
+            vec![
+                "Config (config.rs): 3 uses",
+                "Mode (config.rs): 2 uses",
+                "App (app.rs): 1 use",
+            ]
+        );
+        assert_eq!(file_scores(&sources)["config.rs"], 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use crate::compression;
+use crate::item_ids::ItemRecord;
+use crate::manifest::Manifest;
+use crate::removals::Removals;
+use crate::section_template::SectionTemplate;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Hashes source content (64-bit FNV-1a) to detect files changed between runs
+pub fn content_hash(content: &str) -> String {
+    bytes_hash(content.as_bytes())
+}
+
+/// Hashes bytes with 64-bit FNV-1a, as 16 hex digits
+pub fn bytes_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Sections of a previous single-file output that `--update` reuses for unchanged files
+#[derive(Default, Debug)]
+pub struct PreviousOutput {
+    /// Sections by file path
+    sections: HashMap<String, PreviousSection>,
+}
+
+/// The section of a file in a previous output
+#[derive(Debug)]
+pub struct PreviousSection {
+    pub content: String,
+    source_hash: String,
+    /// Items recorded in the manifest for the section
+    pub items: Vec<ItemRecord>,
+    /// What was cut from the file when the section was generated
+    pub removals: Removals,
+}
+
+impl PreviousOutput {
+    /// Loads the previous output and its manifest; nothing is reused if either is missing,
+    /// they don't match, or the output was generated with other options
+    pub fn load(manifest: &Path, output: &Path, template: &SectionTemplate, options: &str) -> Self {
+        let (Ok(manifest), Ok(output)) =
+            (Manifest::load(manifest), compression::read_output(output))
+        else {
+            return Self::default();
+        };
+        if manifest.options != options {
+            tracing::info!("Options changed since the previous output; regenerating all sections");
+            return Self::default();
+        }
+        Self::parse(&manifest, &output, template).unwrap_or_default()
+    }
+
+    /// Splits an output into sections using the headers and footers of the template and the
+    /// section sizes recorded in the manifest
+    fn parse(manifest: &Manifest, output: &str, template: &SectionTemplate) -> Option<Self> {
+        let mut sections = HashMap::new();
+        let first = manifest.files.first()?;
+        let mut cursor = output.find(&template.header(&first.path))?;
+        for file in &manifest.files {
+            let header = template.header(&file.path);
+            if !output[cursor..].starts_with(&header) {
+                return None;
+            }
+            let start = cursor + header.len();
+            let end = start + file.output_size;
+            let content = output.get(start..end)?;
+            if let Some(hash) = &file.source_hash {
+                sections.insert(
+                    file.path.clone(),
+                    PreviousSection {
+                        content: content.to_string(),
+                        source_hash: hash.clone(),
+                        items: file.items.clone(),
+                        removals: file.removals,
+                    },
+                );
+            }
+            let footer = template.footer(&file.path);
+            if !output[end..].starts_with(&footer) {
+                return None;
+            }
+            cursor = end + footer.len();
+        }
+        Some(Self { sections })
+    }
+
+    /// Returns the previous section of a file if its source hasn't changed
+    pub fn reuse(&self, path: &str, hash: &str) -> Option<&PreviousSection> {
+        self.sections
+            .get(path)
+            .filter(|section| section.source_hash == hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{FileStats, ProcessingStats};
+
+    #[test]
+    fn test_parse_previous_output() {
+        let mut stats = ProcessingStats::default();
+        stats.record(FileStats::new("a.rs", 20, 11).with_source_hash(content_hash("a")));
+        stats.record(FileStats::new("b.rs", 20, 12).with_source_hash(content_hash("b")));
+        let manifest = Manifest::from_stats(&stats);
+        let output = "// Summary:\n\n\n// File: a.rs\n\npub fn a();\n\n// File: b.rs\n\npub fn bb();\n\n// Queries:\n";
+
+        let template = SectionTemplate::default();
+        let previous = PreviousOutput::parse(&manifest, output, &template).expect("sections");
+        assert_eq!(
+            previous
+                .reuse("a.rs", &content_hash("a"))
+                .map(|section| section.content.as_str()),
+            Some("pub fn a();")
+        );
+        assert_eq!(
+            previous
+                .reuse("b.rs", &content_hash("b"))
+                .map(|section| section.content.as_str()),
+            Some("pub fn bb();")
+        );
+        assert!(previous.reuse("b.rs", &content_hash("changed")).is_none());
+
+        assert!(PreviousOutput::parse(&manifest, "// File: a.rs\n\n", &template).is_none());
+
+        let template = SectionTemplate::new("<file path=\"{path}\">\\n", "\\n</file>\\n");
+        let output = "<file path=\"a.rs\">\npub fn a();\n</file>\n<file path=\"b.rs\">\npub fn bb();\n</file>\n";
+        let previous = PreviousOutput::parse(&manifest, output, &template).expect("sections");
+        assert!(previous.reuse("b.rs", &content_hash("b")).is_some());
+        assert!(PreviousOutput::parse(&manifest, output, &SectionTemplate::default()).is_none());
+    }
+}
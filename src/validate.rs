@@ -0,0 +1,39 @@
+use crate::errors::{ErrorKind, Failure};
+use crate::script;
+use anyhow::Result;
+use std::path::Path;
+
+/// Re-parses a transformed file with syn, failing with the location of the first error if
+/// the transformations left code that a downstream Rust parser would reject
+pub fn validate_output(path: &Path, output: &str) -> Result<()> {
+    let (header, code) = script::split_header(output);
+    let Err(error) = syn::parse_file(code) else {
+        return Ok(());
+    };
+    let start = error.span().start();
+    Err(Failure::error(
+        ErrorKind::Other,
+        format!(
+            "Output of {} isn't valid Rust at line {}, column {}: {}",
+            path.display(),
+            start.line + header.lines().count(),
+            start.column + 1,
+            error
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_output() {
+        let path = Path::new("src/main.rs");
+        assert!(validate_output(path, "#!/usr/bin/env rust-script\nfn main() {}\n").is_ok());
+        let error = validate_output(path, "pub fn a() {}\npub fn b() -> {}\n").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Output of src/main.rs isn't valid Rust at line 2, column 15: expected"));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::script;
+use clap::ValueEnum;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+use syn::spanned::Spanned;
+use syn::{ImplItem, Item, Visibility};
+
+/// Lowest visibility of the items kept in the output
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VisibilityLevel {
+    /// Only `pub` items
+    #[value(name = "pub")]
+    Pub,
+    /// `pub` items and crate-internal ones such as `pub(crate)` and `pub(super)`
+    #[value(name = "pub(crate)", alias = "crate")]
+    Crate,
+    /// All items, including private ones
+    #[default]
+    All,
+}
+
+impl VisibilityLevel {
+    /// Checks if an item with the given visibility is kept
+    pub fn keeps(self, vis: &Visibility) -> bool {
+        match self {
+            VisibilityLevel::Pub => matches!(vis, Visibility::Public(_)),
+            VisibilityLevel::Crate => !matches!(vis, Visibility::Inherited),
+            VisibilityLevel::All => true,
+        }
+    }
+}
+
+/// A tagged item: the line of its name, its last line, and its tag
+type Declaration = (usize, usize, String);
+
+/// Appends a trailing `// pub`, `// pub(crate)`, or `// private` comment to the declaration
+/// line of each item, including the items of inline modules and inherent impls, or to its
+/// last line if the declaration line ends inside a literal or block comment; output that
+/// doesn't parse is returned unchanged
+pub fn annotate(output: &str) -> String {
+    let (header, code) = script::split_header(output);
+    let Ok(file) = syn::parse_file(code) else {
+        return output.to_string();
+    };
+    let mut declarations = Vec::new();
+    collect(&file.items, &mut declarations);
+    // The first item declared on a line names the tag of the line
+    let open = open_lines(code);
+    let mut tags = BTreeMap::new();
+    for (name_line, end_line, tag) in declarations {
+        if let Some(line) = [name_line, end_line]
+            .into_iter()
+            .find(|line| !open.contains(line))
+        {
+            tags.entry(line).or_insert(tag);
+        }
+    }
+
+    let mut annotated = String::with_capacity(output.len());
+    annotated.push_str(header);
+    for (index, line) in code.split_inclusive('\n').enumerate() {
+        match tags.get(&(index + 1)) {
+            Some(tag) => {
+                let text = line.trim_end_matches(['\n', '\r']);
+                annotated.push_str(text);
+                annotated.push_str(" // ");
+                annotated.push_str(tag);
+                annotated.push_str(&line[text.len()..]);
+            }
+            None => annotated.push_str(line),
+        }
+    }
+    annotated
+}
+
+/// Records the tag of each item with the line of its name and its last line
+fn collect(items: &[Item], declarations: &mut Vec<Declaration>) {
+    for item in items {
+        let (vis, name): (&Visibility, &dyn Spanned) = match item {
+            Item::Fn(item) => (&item.vis, &item.sig.ident),
+            Item::Struct(item) => (&item.vis, &item.ident),
+            Item::Enum(item) => (&item.vis, &item.ident),
+            Item::Union(item) => (&item.vis, &item.ident),
+            Item::Trait(item) => (&item.vis, &item.ident),
+            Item::Type(item) => (&item.vis, &item.ident),
+            Item::Const(item) => (&item.vis, &item.ident),
+            Item::Static(item) => (&item.vis, &item.ident),
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect(items, declarations);
+                }
+                (&module.vis, &module.ident)
+            }
+            Item::Impl(item) if item.trait_.is_none() => {
+                for impl_item in &item.items {
+                    let (vis, name): (&Visibility, &dyn Spanned) = match impl_item {
+                        ImplItem::Fn(item) => (&item.vis, &item.sig.ident),
+                        ImplItem::Const(item) => (&item.vis, &item.ident),
+                        ImplItem::Type(item) => (&item.vis, &item.ident),
+                        _ => continue,
+                    };
+                    declarations.push((
+                        name.span().start().line,
+                        impl_item.span().end().line,
+                        tag(vis),
+                    ));
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        declarations.push((name.span().start().line, item.span().end().line, tag(vis)));
+    }
+}
+
+/// Lines, counted from 1, whose line break is inside a literal or block comment, where an
+/// appended comment would change the code
+fn open_lines(code: &str) -> HashSet<usize> {
+    let Ok(tokens) = code.parse::<TokenStream>() else {
+        return HashSet::new();
+    };
+    let mut ranges = Vec::new();
+    token_ranges(tokens, &mut ranges);
+    ranges.sort_by_key(|(range, _)| range.start);
+
+    // Literals, and the block comments in the gaps between tokens
+    let mut open = Vec::new();
+    let mut position = 0;
+    for (range, literal) in ranges {
+        if range.start > position {
+            open.extend(block_comments(&code[position..range.start], position));
+        }
+        if literal {
+            open.push(range.clone());
+        }
+        position = position.max(range.end);
+    }
+    open.extend(block_comments(&code[position..], position));
+
+    code.match_indices('\n')
+        .enumerate()
+        .filter(|(_, (index, _))| open.iter().any(|range| range.contains(index)))
+        .map(|(line, _)| line + 1)
+        .collect()
+}
+
+/// Collects the byte ranges of the tokens of a stream, marking literals
+fn token_ranges(tokens: TokenStream, ranges: &mut Vec<(Range<usize>, bool)>) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                ranges.push((group.span_open().byte_range(), false));
+                token_ranges(group.stream(), ranges);
+                ranges.push((group.span_close().byte_range(), false));
+            }
+            TokenTree::Literal(literal) => ranges.push((literal.span().byte_range(), true)),
+            TokenTree::Ident(ident) => ranges.push((ident.span().byte_range(), false)),
+            TokenTree::Punct(punct) => ranges.push((punct.span().byte_range(), false)),
+        }
+    }
+}
+
+/// Finds the block comments of the whitespace and comments between two tokens, as byte
+/// ranges offset by the start of the gap
+fn block_comments(gap: &str, offset: usize) -> Vec<Range<usize>> {
+    let mut comments = Vec::new();
+    let mut index = 0;
+    while index < gap.len() {
+        let rest = &gap[index..];
+        if rest.starts_with("//") {
+            index += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            // Block comments nest
+            let (mut depth, mut end) = (0, index);
+            while end < gap.len() {
+                if gap[end..].starts_with("/*") {
+                    depth += 1;
+                    end += 2;
+                } else if gap[end..].starts_with("*/") {
+                    depth -= 1;
+                    end += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    end += gap[end..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+            comments.push(offset + index..offset + end);
+            index = end;
+        } else {
+            index += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    comments
+}
+
+/// Renders a visibility compactly, e.g. `pub(in crate::api)`
+fn tag(vis: &Visibility) -> String {
+    match vis {
+        Visibility::Public(_) => "pub".to_string(),
+        Visibility::Restricted(restricted) => {
+            let path = restricted
+                .path
+                .to_token_stream()
+                .to_string()
+                .replace(' ', "");
+            match restricted.in_token {
+                Some(_) => format!("pub(in {})", path),
+                None => format!("pub({})", path),
+            }
+        }
+        Visibility::Inherited => "private".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_visibility() {
+        let output = "#!/usr/bin/env rust-script\n/// Docs\n#[derive(Debug)]\npub struct Config;\n\nimpl Config {\n    pub(crate) fn load() {}\n    fn check(&self) {}\n}\n\nimpl Default for Config {\n    fn default() -> Self {\n        Config\n    }\n}\n\nmod api {\n    pub(in crate::api) const LIMIT: usize = 3;\n}\n";
+        assert_eq!(
+            annotate(output),
+            "#!/usr/bin/env rust-script\n/// Docs\n#[derive(Debug)]\npub struct Config; // pub\n\nimpl Config {\n    pub(crate) fn load() {} // pub(crate)\n    fn check(&self) {} // private\n}\n\nimpl Default for Config {\n    fn default() -> Self {\n        Config\n    }\n}\n\nmod api { // private\n    pub(in crate::api) const LIMIT: usize = 3; // pub(in crate::api)\n}\n"
+        );
+        assert_eq!(annotate("fn broken("), "fn broken(");
+    }
+
+    #[test]
+    fn test_annotate_outside_literals_and_comments() -> syn::Result<()> {
+        let output = "const HELP: &str = \"\\\nlist  List the items\n\";\npub fn run() /* runs\n   it */ {}\n";
+        let annotated = annotate(output);
+        assert_eq!(
+            annotated,
+            "const HELP: &str = \"\\\nlist  List the items\n\"; // private\npub fn run() /* runs\n   it */ {} // pub\n"
+        );
+        syn::parse_file(&annotated)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+/// Stock keeping unit, the identifier of an item
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Sku(pub String);
+
+impl std::fmt::Display for Sku {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// An item on the shelves
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Item {
+    pub sku: Sku,
+    pub name: String,
+    /// Price in cents
+    pub price: u64,
+    quantity: u32,
+    // Bumped on every change, to detect concurrent updates
+    revision: u64,
+}
+
+impl Item {
+    /// Creates an item with no stock
+    pub fn new(sku: impl Into<String>, name: impl Into<String>, price: u64) -> Self {
+        Item {
+            sku: Sku(sku.into()),
+            name: name.into(),
+            price,
+            quantity: 0,
+            revision: 0,
+        }
+    }
+
+    pub fn quantity(&self) -> u32 {
+        self.quantity
+    }
+
+    /// Adds stock
+    pub fn restock(&mut self, quantity: u32) {
+        self.quantity += quantity;
+        self.revision += 1;
+    }
+
+    /// Removes stock, failing if there isn't enough
+    pub fn take(&mut self, quantity: u32) -> crate::Result<()> {
+        if quantity > self.quantity {
+            return Err(crate::Error::OutOfStock {
+                sku: self.sku.clone(),
+                available: self.quantity,
+            });
+        }
+        self.quantity -= quantity;
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Label printed on the shelf
+    pub fn label(&self) -> String {
+        format!("{} ({}) - {}.{:02}", self.name, self.sku, self.price / 100, self.price % 100)
+    }
+
+    pub(crate) fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take() {
+        let mut item = Item::new("A-1", "Anvil", 4999);
+        item.restock(2);
+        assert!(item.take(3).is_err());
+        assert!(item.take(2).is_ok());
+    }
+}
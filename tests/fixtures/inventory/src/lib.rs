@@ -0,0 +1,33 @@
+//! Stock keeping for small warehouses.
+//!
+//! Items are kept in a [`Store`], which is either in memory or backed by a file.
+
+pub mod item;
+pub mod store;
+
+pub use item::{Item, Sku};
+pub use store::{MemoryStore, Store};
+
+/// Errors returned by the stores
+#[derive(Debug)]
+pub enum Error {
+    /// No item with the given SKU
+    NotFound(Sku),
+    /// Not enough stock to take the requested quantity
+    OutOfStock { sku: Sku, available: u32 },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotFound(sku) => write!(f, "no item {}", sku),
+            Error::OutOfStock { sku, available } => {
+                write!(f, "only {} of {} left", available, sku)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,51 @@
+use super::Store;
+use crate::{Error, Item, Result, Sku};
+use std::collections::HashMap;
+
+/// A store keeping every item in memory
+#[derive(Default)]
+pub struct MemoryStore {
+    items: HashMap<Sku, Item>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of distinct items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn most_revised(&self) -> Option<&Item> {
+        self.items.values().max_by_key(|item| item.revision())
+    }
+}
+
+impl Store for MemoryStore {
+    fn get(&self, sku: &Sku) -> Result<&Item> {
+        self.items.get(sku).ok_or_else(|| Error::NotFound(sku.clone()))
+    }
+
+    fn get_mut(&mut self, sku: &Sku) -> Result<&mut Item> {
+        self.items
+            .get_mut(sku)
+            .ok_or_else(|| Error::NotFound(sku.clone()))
+    }
+
+    fn insert(&mut self, item: Item) {
+        self.items.insert(item.sku.clone(), item);
+    }
+
+    fn value(&self) -> u64 {
+        self.items
+            .values()
+            .map(|item| item.price * u64::from(item.quantity()))
+            .sum()
+    }
+}
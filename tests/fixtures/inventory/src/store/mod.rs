@@ -0,0 +1,23 @@
+mod memory;
+
+pub use memory::MemoryStore;
+
+use crate::{Item, Result, Sku};
+
+/// Storage of items by SKU
+pub trait Store {
+    /// Returns the item with the given SKU
+    fn get(&self, sku: &Sku) -> Result<&Item>;
+
+    fn get_mut(&mut self, sku: &Sku) -> Result<&mut Item>;
+
+    fn insert(&mut self, item: Item);
+
+    /// Takes stock of an item
+    fn take(&mut self, sku: &Sku, quantity: u32) -> Result<()> {
+        self.get_mut(sku)?.take(quantity)
+    }
+
+    /// Total value of the stock in cents
+    fn value(&self) -> u64;
+}
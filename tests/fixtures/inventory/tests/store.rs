@@ -0,0 +1,11 @@
+use inventory::{Item, MemoryStore, Sku, Store};
+
+#[test]
+fn test_store_value() {
+    let mut store = MemoryStore::new();
+    let mut item = Item::new("A-1", "Anvil", 4999);
+    item.restock(2);
+    store.insert(item);
+    assert_eq!(store.value(), 9998);
+    assert!(store.take(&Sku("A-1".into()), 1).is_ok());
+}
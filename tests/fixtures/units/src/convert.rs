@@ -0,0 +1,45 @@
+use crate::{Meters, Seconds};
+
+/// Conversion between units of the same quantity
+pub trait Convert: Sized {
+    /// Number of base units in one of this unit
+    const FACTOR: f64;
+
+    fn to_base(self) -> f64;
+
+    fn from_base(value: f64) -> Self;
+
+    /// Converts into another unit of the same quantity
+    fn convert<T: Convert>(self) -> T {
+        T::from_base(self.to_base())
+    }
+}
+
+impl Convert for Meters {
+    const FACTOR: f64 = 1.0;
+
+    fn to_base(self) -> f64 {
+        self.0
+    }
+
+    fn from_base(value: f64) -> Self {
+        Meters(value)
+    }
+}
+
+impl Convert for Seconds {
+    const FACTOR: f64 = 1.0;
+
+    fn to_base(self) -> f64 {
+        self.0
+    }
+
+    fn from_base(value: f64) -> Self {
+        Seconds(value)
+    }
+}
+
+// Kilometers are only used for display
+pub(crate) fn kilometers(meters: Meters) -> f64 {
+    meters.0 / 1000.0
+}
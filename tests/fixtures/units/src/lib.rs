@@ -0,0 +1,61 @@
+//! Physical quantities with their units
+
+mod convert;
+
+pub use convert::Convert;
+
+/// A length in meters
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Meters(pub f64);
+
+/// A duration in seconds
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Seconds(pub f64);
+
+/// A speed, as a length over a duration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Speed {
+    pub meters_per_second: f64,
+}
+
+impl From<u8> for Meters {
+    fn from(value: u8) -> Self {
+        Meters(f64::from(value))
+    }
+}
+
+impl From<u16> for Meters {
+    fn from(value: u16) -> Self {
+        Meters(f64::from(value))
+    }
+}
+
+impl From<u32> for Meters {
+    fn from(value: u32) -> Self {
+        Meters(f64::from(value))
+    }
+}
+
+impl std::ops::Div<Seconds> for Meters {
+    type Output = Speed;
+
+    fn div(self, duration: Seconds) -> Speed {
+        Speed {
+            meters_per_second: self.0 / duration.0,
+        }
+    }
+}
+
+impl std::fmt::Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} m/s", self.meters_per_second)
+    }
+}
+
+/// Returns the longest of the named lengths
+pub fn longest<'a>(lengths: &'a [(&'a str, Meters)]) -> Option<&'a str> {
+    lengths
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(name, _)| *name)
+}
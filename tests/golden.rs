@@ -0,0 +1,179 @@
+//! Golden-file tests: runs code-context on every crate of `tests/fixtures` with each option
+//! combination of `CASES`, and compares the output with `tests/golden/<crate>/<case>.txt`.
+//!
+//! `cargo test --test golden -- --bless` rewrites the golden files from the current output
+//! instead; review the diff before committing it. Other arguments filter the runs by
+//! `<crate>/<case>`.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use walkdir::WalkDir;
+
+/// Option combinations each fixture crate is run with, by case name
+const CASES: &[(&str, &[&str])] = &[
+    ("default", &[]),
+    ("no-function-bodies", &["--no-function-bodies"]),
+    ("signatures", &["--no-comments", "--no-function-bodies"]),
+    (
+        "public-api",
+        &[
+            "--visibility",
+            "pub",
+            "--hide-private-fields",
+            "--collapse-impls",
+        ],
+    ),
+    (
+        "module-filter",
+        &["--module-filter", "crate::store,convert"],
+    ),
+    ("annotate", &["--annotate"]),
+    (
+        "single-file",
+        &["--single-file", "--no-function-bodies", "--short-std-paths"],
+    ),
+];
+
+/// Output files that differ between runs
+const SKIPPED_OUTPUTS: &[&str] = &["code-context-manifest.json"];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let bless = args.iter().any(|arg| arg == "--bless");
+    let filters: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(root.join("fixtures"))
+        .expect("tests/fixtures exists")
+        .map(|entry| entry.expect("readable fixture").path())
+        .filter(|path| path.is_dir())
+        .collect();
+    fixtures.sort();
+
+    let mut failed = Vec::new();
+    let mut passed = 0;
+    for fixture in &fixtures {
+        let crate_name = fixture.file_name().unwrap().to_string_lossy();
+        for (case, case_args) in CASES {
+            let name = format!("{}/{}", crate_name, case);
+            if !filters.is_empty() && !filters.iter().any(|filter| name.contains(filter.as_str())) {
+                continue;
+            }
+            let actual = match run(fixture, case_args) {
+                Ok(actual) => actual,
+                Err(error) => {
+                    println!("golden {} ... FAILED\n{}", name, error);
+                    failed.push(name);
+                    continue;
+                }
+            };
+            let golden = root.join("golden").join(format!("{}.txt", name));
+            if bless {
+                std::fs::create_dir_all(golden.parent().unwrap()).expect("golden dir created");
+                std::fs::write(&golden, &actual).expect("golden file written");
+                println!("golden {} ... blessed", name);
+                passed += 1;
+                continue;
+            }
+            let expected = std::fs::read_to_string(&golden).unwrap_or_default();
+            if expected == actual {
+                println!("golden {} ... ok", name);
+                passed += 1;
+            } else {
+                println!(
+                    "golden {} ... FAILED\n{}",
+                    name,
+                    first_difference(&expected, &actual)
+                );
+                failed.push(name);
+            }
+        }
+    }
+
+    println!(
+        "\ngolden test result: {} passed; {} failed",
+        passed,
+        failed.len()
+    );
+    if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        println!(
+            "failed: {}\nrun `cargo test --test golden -- --bless` to accept the new output",
+            failed.join(", ")
+        );
+        ExitCode::FAILURE
+    }
+}
+
+/// Runs code-context on a fixture crate into a temporary directory, failing if any output
+/// isn't valid Rust, and renders the output files, each after a `==> <path> <==` line
+fn run(fixture: &Path, args: &[&str]) -> Result<String, String> {
+    let temp_dir = tempfile::TempDir::new().map_err(|error| error.to_string())?;
+    let output_dir = temp_dir.path().join("out");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_code-context"));
+    // Options must come from the case alone
+    for (key, _) in std::env::vars_os() {
+        if key.to_string_lossy().starts_with("CODE_CONTEXT_") {
+            command.env_remove(key);
+        }
+    }
+    let output = command
+        .arg(fixture)
+        .arg("--output-dir")
+        .arg(output_dir.join("{name}"))
+        .arg("--no-stats")
+        .arg("--validate-output")
+        .args(args)
+        .output()
+        .map_err(|error| error.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+
+    let mut rendered = String::new();
+    let mut files: Vec<PathBuf> = WalkDir::new(&output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect();
+    files.sort();
+    for file in files {
+        let relative = file.strip_prefix(&output_dir).unwrap();
+        if SKIPPED_OUTPUTS
+            .iter()
+            .any(|skipped| file.file_name().is_some_and(|name| name == *skipped))
+        {
+            continue;
+        }
+        let content = std::fs::read_to_string(&file).map_err(|error| error.to_string())?;
+        rendered.push_str(&format!("==> {} <==\n", relative.display()));
+        rendered.push_str(&content);
+        if !content.ends_with('\n') {
+            rendered.push('\n');
+        }
+    }
+    Ok(rendered)
+}
+
+/// Describes the first line where two outputs differ
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(left), Some(right)) if left == right => continue,
+            (None, None) => break,
+            (left, right) => {
+                return format!(
+                    "  line {}:\n  expected: {}\n  actual:   {}",
+                    line,
+                    left.unwrap_or("<end of file>"),
+                    right.unwrap_or("<end of file>")
+                )
+            }
+        }
+    }
+    "  outputs differ only in line endings".to_string()
+}